@@ -0,0 +1,516 @@
+use std::{
+    fmt::Debug,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use log::{info, warn};
+use tokio::{
+    fs::{read_to_string, write},
+    time::sleep,
+};
+
+use crate::{
+    githost::{
+        events::{GitEvent, GitEventKind},
+        host::GitHost,
+        model::{FoundIssue, IssueId, IssueQuery, RepoId},
+    },
+    llm::llm_trait::Llm,
+};
+
+use super::{bot_features::FeatureName, gitbot::GitBot};
+
+#[derive(Debug, thiserror::Error)]
+pub enum BackfillError<GE> {
+    #[error("unable to perform Git host action")]
+    GitHostError(#[source] GE),
+
+    #[error("unable to read resume cursor file")]
+    CursorReadError(#[source] std::io::Error),
+
+    #[error("unable to write resume cursor file")]
+    CursorWriteError(#[source] std::io::Error),
+
+    #[error("resume cursor file has an invalid format")]
+    CursorFormatError,
+
+    #[error("more issues were created on {0} than a search returns, narrow the query")]
+    WindowTooLarge(NaiveDate),
+}
+
+pub type Result<T, GE> = std::result::Result<T, BackfillError<GE>>;
+
+pub struct BackfillOptions {
+    pub query: IssueQuery,
+
+    /// Features that should process the issues.
+    pub features: Vec<FeatureName>,
+
+    /// Pause between two processed issues. Used as a rate limit for both the Git host and the LLM.
+    pub delay: Duration,
+
+    /// File that stores the last processed issue, so that an interrupted backfill can be resumed.
+    pub cursor_path: Option<PathBuf>,
+}
+
+/// What the backfill has done.
+#[derive(Default, PartialEq, Debug)]
+pub struct BackfillSummary {
+    /// Issues that all the selected features have processed without errors.
+    pub processed: usize,
+
+    /// Issues on which at least one feature has failed. The cursor moves past them, so they
+    /// should be processed again with the `process` command.
+    pub failed: Vec<IssueId>,
+}
+
+/// Position of the backfill: the last processed issue and when it was created.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Cursor {
+    created_at: DateTime<Utc>,
+    issue_id: IssueId,
+}
+
+impl Cursor {
+    /// Whether the issue comes after the cursor in the search order. The issue numbers alone are
+    /// not enough, as an issue transferred from another repository gets a new number but keeps
+    /// its creation time.
+    fn is_before(&self, found: &FoundIssue) -> bool {
+        (self.created_at, *self.issue_id) < (found.created_at, *found.id)
+    }
+}
+
+/// Sends synthetic `NewIssue` events for every issue matching the query.
+///
+/// A search returns a limited number of results, so the issues are searched in windows. Every
+/// window starts on the day when the last processed issue was created, and the backfill stops
+/// when a window has no new issues. If a window has no new issues but the search was capped, one
+/// day has more issues than a search returns, and an error is returned.
+///
+/// Errors of the features are logged and do not stop the backfill.
+pub async fn backfill<G: GitHost, L: Llm>(
+    githost: &G,
+    bot: &GitBot<G, L>,
+    repo_id: RepoId,
+    options: &BackfillOptions,
+) -> Result<BackfillSummary, G::Error>
+where
+    G::Error: Debug,
+    L::Error: Debug,
+{
+    let mut cursor = match &options.cursor_path {
+        Some(path) => read_cursor(path).await?,
+        None => None,
+    };
+
+    if let Some(cursor) = cursor {
+        info!("Resuming backfill after issue #{}", *cursor.issue_id);
+    }
+
+    let mut summary = BackfillSummary::default();
+
+    loop {
+        let mut query = options.query.clone();
+
+        if let Some(cursor) = cursor {
+            query.created_after = query
+                .created_after
+                .max(Some(cursor.created_at.date_naive()));
+        }
+
+        let before = summary.processed + summary.failed.len();
+
+        let capped = backfill_window(
+            githost,
+            bot,
+            repo_id,
+            &query,
+            options,
+            &mut cursor,
+            &mut summary,
+        )
+        .await?;
+
+        if summary.processed + summary.failed.len() == before {
+            match cursor {
+                Some(cursor) if capped => {
+                    return Err(BackfillError::WindowTooLarge(
+                        cursor.created_at.date_naive(),
+                    ))
+                }
+                _ => break,
+            }
+        }
+    }
+
+    info!("Backfill finished, processed {} issues", summary.processed);
+
+    if !summary.failed.is_empty() {
+        warn!(
+            "Features have failed on {} issues: {}",
+            summary.failed.len(),
+            summary
+                .failed
+                .iter()
+                .map(|issue_id| format!("#{}", **issue_id))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    Ok(summary)
+}
+
+/// Process the new issues of the window. Returns whether the search was capped.
+async fn backfill_window<G: GitHost, L: Llm>(
+    githost: &G,
+    bot: &GitBot<G, L>,
+    repo_id: RepoId,
+    query: &IssueQuery,
+    options: &BackfillOptions,
+    cursor: &mut Option<Cursor>,
+    summary: &mut BackfillSummary,
+) -> Result<bool, G::Error>
+where
+    G::Error: Debug,
+    L::Error: Debug,
+{
+    let mut page_number = 1;
+
+    loop {
+        let page = githost
+            .search_issues(repo_id, query, page_number)
+            .await
+            .map_err(BackfillError::GitHostError)?;
+
+        for found in page.issues {
+            if cursor.is_some_and(|cursor| !cursor.is_before(&found)) {
+                continue;
+            }
+
+            info!("Backfilling issue #{}", *found.id);
+
+            let event = GitEvent {
+                repo_id,
+                issue_id: found.id,
+                kind: GitEventKind::NewIssue,
+            };
+
+            if bot
+                .process_event_for(&event, &options.features)
+                .await
+                .is_empty()
+            {
+                summary.processed += 1;
+            } else {
+                summary.failed.push(found.id);
+            }
+
+            let next = Cursor {
+                created_at: found.created_at,
+                issue_id: found.id,
+            };
+
+            *cursor = Some(next);
+
+            if let Some(path) = &options.cursor_path {
+                write_cursor(path, next).await?;
+            }
+
+            sleep(options.delay).await;
+        }
+
+        if !page.has_next_page {
+            return Ok(page.capped);
+        }
+
+        page_number += 1;
+    }
+}
+
+/// The cursor file has the creation time and the number of the last processed issue, e.g.
+/// `2024-10-01T12:00:00Z 42`.
+async fn read_cursor<GE>(path: &Path) -> Result<Option<Cursor>, GE> {
+    let content = match read_to_string(path).await {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(BackfillError::CursorReadError(e)),
+    };
+
+    let (created_at, issue_id) = content
+        .trim()
+        .split_once(' ')
+        .ok_or(BackfillError::CursorFormatError)?;
+
+    Ok(Some(Cursor {
+        created_at: DateTime::parse_from_rfc3339(created_at)
+            .map_err(|_| BackfillError::CursorFormatError)?
+            .to_utc(),
+        issue_id: IssueId::from(
+            issue_id
+                .parse::<usize>()
+                .map_err(|_| BackfillError::CursorFormatError)?,
+        ),
+    }))
+}
+
+async fn write_cursor<GE>(path: &Path, cursor: Cursor) -> Result<(), GE> {
+    write(
+        path,
+        format!(
+            "{} {}",
+            cursor.created_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            *cursor.issue_id
+        ),
+    )
+    .await
+    .map_err(BackfillError::CursorWriteError)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, sync::Arc, time::Duration};
+
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use mockall::predicate;
+
+    use crate::{
+        bot::{
+            backfill::{backfill, BackfillError, BackfillOptions, BackfillSummary},
+            bot_features::{BotFeatures, FeatureName},
+            features::label_feature::LabelFeature,
+            gitbot::GitBot,
+        },
+        githost::{
+            events::GitEventKindName,
+            host::MockGitHost,
+            model::{
+                AuthorAssociation, FoundIssue, Issue, IssueId, IssueQuery, IssueState, IssuesPage,
+                RepoId, User, UserId, UserKind,
+            },
+        },
+        llm::{
+            agent::LlmAgent,
+            llm_trait::{CompletionParameters, MockLlm},
+            messages::AiMessage,
+        },
+        storage::impls::sqlite_storage::SqliteStorage,
+    };
+
+    #[tokio::test]
+    async fn resumes_from_cursor() {
+        let cursor_path = std::env::temp_dir().join("gib_backfill_resumes_from_cursor");
+        std::fs::write(&cursor_path, "2024-10-01T12:00:00Z 1").unwrap();
+
+        let mut search_mock = MockGitHost::new();
+
+        // Issue #50 was transferred from another repository, so it has a bigger number than the
+        // issues created after it.
+        search_mock
+            .expect_search_issues()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::function(|query: &IssueQuery| {
+                    query.created_after == NaiveDate::from_ymd_opt(2024, 10, 1)
+                }),
+                predicate::eq(1),
+            )
+            .times(1)
+            .returning(|_, _, _| {
+                Ok(IssuesPage {
+                    issues: vec![found(1, 1), found(50, 1), found(3, 2)],
+                    has_next_page: false,
+                    capped: false,
+                })
+            });
+
+        // The next window starts on the day of the last processed issue and has no new issues.
+        search_mock
+            .expect_search_issues()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::function(|query: &IssueQuery| {
+                    query.created_after == NaiveDate::from_ymd_opt(2024, 10, 2)
+                }),
+                predicate::eq(1),
+            )
+            .times(1)
+            .returning(|_, _, _| {
+                Ok(IssuesPage {
+                    issues: vec![found(3, 2)],
+                    has_next_page: false,
+                    capped: false,
+                })
+            });
+
+        let mut feature_mock = MockGitHost::new();
+
+        feature_mock
+            .expect_get_issue()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(IssueId::from(50 as usize)),
+            )
+            .times(1)
+            .returning(move |_, issue_id| Ok(make_issue(issue_id, 1)));
+
+        // The label feature fails on issue #3.
+        feature_mock
+            .expect_get_issue()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(IssueId::from(3 as usize)),
+            )
+            .times(1)
+            .returning(|_, _| Err(()));
+
+        feature_mock.expect_get_user().returning(|id| {
+            Ok(User {
+                id,
+                nickname: "InAnYan".try_into().unwrap(),
+                kind: UserKind::User,
+            })
+        });
+
+        feature_mock
+            .expect_get_repo_labels()
+            .returning(|_| Ok(vec![]));
+
+        let mut llm_mock = MockLlm::new();
+
+        llm_mock
+            .expect_complete()
+            .times(1)
+            .returning(|_, _, _| Ok(AiMessage::from(non_empty("EMPTY"))));
+
+        let bot = make_bot(Some(LabelFeature::new(
+            feature_mock,
+            LlmAgent::build_raw(
+                llm_mock,
+                non_empty("system"),
+                non_empty("{{ context.issue.body }}"),
+                CompletionParameters::default(),
+            )
+            .unwrap(),
+            vec![GitEventKindName::NewIssue],
+        )));
+
+        let summary = backfill(
+            &search_mock,
+            &bot,
+            RepoId::from(1),
+            &make_options(cursor_path.clone()),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            summary,
+            BackfillSummary {
+                processed: 1,
+                failed: vec![IssueId::from(3 as usize)],
+            }
+        );
+        assert_eq!(
+            std::fs::read_to_string(&cursor_path).unwrap(),
+            "2024-10-02T12:00:00Z 3"
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_capped_window_without_new_issues() {
+        let cursor_path =
+            std::env::temp_dir().join("gib_backfill_rejects_capped_window_without_new_issues");
+        std::fs::write(&cursor_path, "2024-10-01T12:00:00Z 2").unwrap();
+
+        let mut search_mock = MockGitHost::new();
+
+        // The rest of the issues of the day are beyond the search limit.
+        search_mock
+            .expect_search_issues()
+            .times(1)
+            .returning(|_, _, _| {
+                Ok(IssuesPage {
+                    issues: vec![found(1, 1), found(2, 1)],
+                    has_next_page: false,
+                    capped: true,
+                })
+            });
+
+        let result = backfill(
+            &search_mock,
+            &make_bot(None),
+            RepoId::from(1),
+            &make_options(cursor_path),
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(BackfillError::WindowTooLarge(day)) if day == NaiveDate::from_ymd_opt(2024, 10, 1).unwrap()
+        ));
+    }
+
+    fn make_bot(
+        label_feature: Option<LabelFeature<MockGitHost, MockLlm>>,
+    ) -> GitBot<MockGitHost, MockLlm> {
+        GitBot::build_raw(
+            BotFeatures {
+                improve_feature: None,
+                label_feature,
+                review_feature: None,
+                summary_feature: None,
+                triage_assign_feature: None,
+                stale_feature: None,
+                digest_feature: None,
+                release_notes_feature: None,
+                moderation_feature: None,
+                spam_feature: None,
+                security_feature: None,
+                template_compliance_feature: None,
+                log_analysis_feature: None,
+                docs_answer_feature: None,
+            },
+            Arc::new(SqliteStorage::in_memory().unwrap()),
+        )
+    }
+
+    fn make_options(cursor_path: PathBuf) -> BackfillOptions {
+        BackfillOptions {
+            query: IssueQuery::default(),
+            features: vec![FeatureName::Label],
+            delay: Duration::ZERO,
+            cursor_path: Some(cursor_path),
+        }
+    }
+
+    fn found(number: usize, day: u32) -> FoundIssue {
+        FoundIssue {
+            id: IssueId::from(number),
+            created_at: Utc.with_ymd_and_hms(2024, 10, day, 12, 0, 0).unwrap(),
+        }
+    }
+
+    fn make_issue(id: IssueId, day: u32) -> Issue {
+        Issue {
+            id,
+            author_user_id: UserId::from(1),
+            title: "Problem with your program".try_into().unwrap(),
+            body: "Hi! I can't run your program".into(),
+            state: IssueState::Open,
+            labels: vec![],
+            assignees: vec![],
+            milestone: None,
+            created_at: Utc.with_ymd_and_hms(2024, 10, day, 12, 0, 0).unwrap(),
+            updated_at: Utc.with_ymd_and_hms(2024, 10, day, 12, 0, 0).unwrap(),
+            author_association: AuthorAssociation::None,
+        }
+    }
+
+    fn non_empty(s: &str) -> non_empty_string::NonEmptyString {
+        s.try_into().unwrap()
+    }
+}
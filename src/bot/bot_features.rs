@@ -1,26 +1,171 @@
-use serde::Deserialize;
+use std::{
+    fmt::{Debug, Display},
+    str::FromStr,
+    sync::Arc,
+};
+
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
 use tracing::instrument;
 
 use super::{
-    errors::Result,
-    features::{improve_feature::ImproveFeature, label_feature::LabelFeature},
+    comment_store::CommentStore,
+    errors::{GitBotError, Result},
+    features::{
+        digest_feature::DigestFeature, docs_answer_feature::DocsAnswerFeature,
+        improve_feature::ImproveFeature, label_feature::LabelFeature,
+        log_analysis_feature::LogAnalysisFeature, moderation_feature::ModerationFeature,
+        release_notes_feature::ReleaseNotesFeature, review_feature::ReviewFeature,
+        security_feature::SecurityFeature, spam_feature::SpamFeature, stale_feature::StaleFeature,
+        summary_feature::SummaryFeature, template_compliance_feature::TemplateComplianceFeature,
+        triage_assign_feature::TriageAssignFeature,
+    },
+    scheduler::ScheduledEvent,
 };
 use crate::{
-    githost::{events::GitEvent, host::GitHost},
-    llm::llm_trait::Llm,
+    githost::{
+        events::GitEvent,
+        host::GitHost,
+        model::{IssueId, RepoId},
+    },
+    llm::{agent::RenderedMessages, llm_trait::Llm},
+    storage::storage_trait::Storage,
+};
+
+use super::features::{
+    digest_feature::DigestFeatureConfig, docs_answer_feature::DocsAnswerFeatureConfig,
+    improve_feature::ImproveFeatureConfig, label_feature::LabelFeatureConfig,
+    log_analysis_feature::LogAnalysisFeatureConfig, moderation_feature::ModerationFeatureConfig,
+    release_notes_feature::ReleaseNotesFeatureConfig, review_feature::ReviewFeatureConfig,
+    security_feature::SecurityFeatureConfig, spam_feature::SpamFeatureConfig,
+    stale_feature::StaleFeatureConfig, summary_feature::SummaryFeatureConfig,
+    template_compliance_feature::TemplateComplianceFeatureConfig,
+    triage_assign_feature::TriageAssignFeatureConfig,
 };
 
-use super::features::{improve_feature::ImproveFeatureConfig, label_feature::LabelFeatureConfig};
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum FeatureName {
+    Improve,
+    Label,
+    Review,
+    Summary,
+    TriageAssign,
+    Stale,
+    Digest,
+    ReleaseNotes,
+    Moderation,
+    Spam,
+    Security,
+    TemplateCompliance,
+    LogAnalysis,
+    DocsAnswer,
+}
+
+impl FeatureName {
+    pub const ALL: &'static [FeatureName] = &[
+        FeatureName::Improve,
+        FeatureName::Label,
+        FeatureName::Review,
+        FeatureName::Summary,
+        FeatureName::TriageAssign,
+        FeatureName::Stale,
+        FeatureName::Digest,
+        FeatureName::ReleaseNotes,
+        FeatureName::Moderation,
+        FeatureName::Spam,
+        FeatureName::Security,
+        FeatureName::TemplateCompliance,
+        FeatureName::LogAnalysis,
+        FeatureName::DocsAnswer,
+    ];
+}
+
+/// Features that have failed on an event, with their errors.
+pub type FeatureFailures<GE, LE> = Vec<(FeatureName, GitBotError<GE, LE>)>;
+
+#[derive(Debug, thiserror::Error)]
+#[error("unknown feature name: {0}")]
+pub struct UnknownFeatureError(String);
+
+impl FromStr for FeatureName {
+    type Err = UnknownFeatureError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "improve" => Ok(FeatureName::Improve),
+            "label" => Ok(FeatureName::Label),
+            "review" => Ok(FeatureName::Review),
+            "summary" => Ok(FeatureName::Summary),
+            "triage_assign" => Ok(FeatureName::TriageAssign),
+            "stale" => Ok(FeatureName::Stale),
+            "digest" => Ok(FeatureName::Digest),
+            "release_notes" => Ok(FeatureName::ReleaseNotes),
+            "moderation" => Ok(FeatureName::Moderation),
+            "spam" => Ok(FeatureName::Spam),
+            "security" => Ok(FeatureName::Security),
+            "template_compliance" => Ok(FeatureName::TemplateCompliance),
+            "log_analysis" => Ok(FeatureName::LogAnalysis),
+            "docs_answer" => Ok(FeatureName::DocsAnswer),
+            _ => Err(UnknownFeatureError(s.to_string())),
+        }
+    }
+}
+
+impl Display for FeatureName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FeatureName::Improve => write!(f, "improve"),
+            FeatureName::Label => write!(f, "label"),
+            FeatureName::Review => write!(f, "review"),
+            FeatureName::Summary => write!(f, "summary"),
+            FeatureName::TriageAssign => write!(f, "triage_assign"),
+            FeatureName::Stale => write!(f, "stale"),
+            FeatureName::Digest => write!(f, "digest"),
+            FeatureName::ReleaseNotes => write!(f, "release_notes"),
+            FeatureName::Moderation => write!(f, "moderation"),
+            FeatureName::Spam => write!(f, "spam"),
+            FeatureName::Security => write!(f, "security"),
+            FeatureName::TemplateCompliance => write!(f, "template_compliance"),
+            FeatureName::LogAnalysis => write!(f, "log_analysis"),
+            FeatureName::DocsAnswer => write!(f, "docs_answer"),
+        }
+    }
+}
 
 #[derive(Deserialize)]
 pub struct FeaturesConfig {
     pub improve_feature: Option<ImproveFeatureConfig>,
     pub label_feature: Option<LabelFeatureConfig>,
+    pub review_feature: Option<ReviewFeatureConfig>,
+    pub summary_feature: Option<SummaryFeatureConfig>,
+    pub triage_assign_feature: Option<TriageAssignFeatureConfig>,
+    pub stale_feature: Option<StaleFeatureConfig>,
+    pub digest_feature: Option<DigestFeatureConfig>,
+    pub release_notes_feature: Option<ReleaseNotesFeatureConfig>,
+    pub moderation_feature: Option<ModerationFeatureConfig>,
+    pub spam_feature: Option<SpamFeatureConfig>,
+    pub security_feature: Option<SecurityFeatureConfig>,
+    pub template_compliance_feature: Option<TemplateComplianceFeatureConfig>,
+    pub log_analysis_feature: Option<LogAnalysisFeatureConfig>,
+    pub docs_answer_feature: Option<DocsAnswerFeatureConfig>,
 }
 
 pub struct BotFeatures<G, L> {
     pub improve_feature: Option<ImproveFeature<G, L>>,
     pub label_feature: Option<LabelFeature<G, L>>,
+    pub review_feature: Option<ReviewFeature<G, L>>,
+    pub summary_feature: Option<SummaryFeature<G, L>>,
+    pub triage_assign_feature: Option<TriageAssignFeature<G, L>>,
+    pub stale_feature: Option<StaleFeature<G, L>>,
+    pub digest_feature: Option<DigestFeature<G, L>>,
+    pub release_notes_feature: Option<ReleaseNotesFeature<G, L>>,
+    pub moderation_feature: Option<ModerationFeature<G, L>>,
+    pub spam_feature: Option<SpamFeature<G, L>>,
+    pub security_feature: Option<SecurityFeature<G, L>>,
+    pub template_compliance_feature: Option<TemplateComplianceFeature<G, L>>,
+    pub log_analysis_feature: Option<LogAnalysisFeature<G, L>>,
+    pub docs_answer_feature: Option<DocsAnswerFeature<G, L>>,
 }
 
 impl<G: GitHost + Clone, L: Llm + Clone> BotFeatures<G, L> {
@@ -28,11 +173,20 @@ impl<G: GitHost + Clone, L: Llm + Clone> BotFeatures<G, L> {
         config: FeaturesConfig,
         githost: G,
         llm: L,
+        storage: Arc<dyn Storage + Send + Sync>,
     ) -> Result<Self, G::Error, L::Error> {
+        let comments = CommentStore::new(storage.clone());
+
         Ok(Self {
             improve_feature: match config.improve_feature {
                 Some(config) => Some(
-                    ImproveFeature::build_from_config(config, githost.clone(), llm.clone()).await?,
+                    ImproveFeature::build_from_config(
+                        config,
+                        githost.clone(),
+                        llm.clone(),
+                        comments.clone(),
+                    )
+                    .await?,
                 ),
                 None => None,
             },
@@ -43,21 +197,482 @@ impl<G: GitHost + Clone, L: Llm + Clone> BotFeatures<G, L> {
                 ),
                 None => None,
             },
+
+            review_feature: match config.review_feature {
+                Some(config) => Some(
+                    ReviewFeature::build_from_config(config, githost.clone(), llm.clone()).await?,
+                ),
+                None => None,
+            },
+
+            summary_feature: match config.summary_feature {
+                Some(config) => Some(
+                    SummaryFeature::build_from_config(
+                        config,
+                        githost.clone(),
+                        llm.clone(),
+                        comments.clone(),
+                    )
+                    .await?,
+                ),
+                None => None,
+            },
+
+            triage_assign_feature: match config.triage_assign_feature {
+                Some(config) => Some(
+                    TriageAssignFeature::build_from_config(
+                        config,
+                        githost.clone(),
+                        llm.clone(),
+                        comments.clone(),
+                        storage.clone(),
+                    )
+                    .await?,
+                ),
+                None => None,
+            },
+
+            stale_feature: match config.stale_feature {
+                Some(config) => Some(
+                    StaleFeature::build_from_config(config, githost.clone(), llm.clone()).await?,
+                ),
+                None => None,
+            },
+
+            digest_feature: match config.digest_feature {
+                Some(config) => Some(
+                    DigestFeature::build_from_config(config, githost.clone(), llm.clone()).await?,
+                ),
+                None => None,
+            },
+
+            release_notes_feature: match config.release_notes_feature {
+                Some(config) => Some(
+                    ReleaseNotesFeature::build_from_config(config, githost.clone(), llm.clone())
+                        .await?,
+                ),
+                None => None,
+            },
+
+            moderation_feature: match config.moderation_feature {
+                Some(config) => Some(
+                    ModerationFeature::build_from_config(config, githost.clone(), llm.clone())
+                        .await?,
+                ),
+                None => None,
+            },
+
+            spam_feature: match config.spam_feature {
+                Some(config) => Some(
+                    SpamFeature::build_from_config(config, githost.clone(), llm.clone()).await?,
+                ),
+                None => None,
+            },
+
+            security_feature: match config.security_feature {
+                Some(config) => Some(
+                    SecurityFeature::build_from_config(config, githost.clone(), llm.clone())
+                        .await?,
+                ),
+                None => None,
+            },
+
+            template_compliance_feature: match config.template_compliance_feature {
+                Some(config) => Some(
+                    TemplateComplianceFeature::build_from_config(
+                        config,
+                        githost.clone(),
+                        llm.clone(),
+                        comments.clone(),
+                        storage.clone(),
+                    )
+                    .await?,
+                ),
+                None => None,
+            },
+
+            log_analysis_feature: match config.log_analysis_feature {
+                Some(config) => Some(
+                    LogAnalysisFeature::build_from_config(
+                        config,
+                        githost.clone(),
+                        llm.clone(),
+                        comments.clone(),
+                    )
+                    .await?,
+                ),
+                None => None,
+            },
+
+            docs_answer_feature: match config.docs_answer_feature {
+                Some(config) => Some(
+                    DocsAnswerFeature::build_from_config(
+                        config,
+                        githost.clone(),
+                        llm.clone(),
+                        comments.clone(),
+                    )
+                    .await?,
+                ),
+                None => None,
+            },
         })
     }
 }
 
 impl<G: GitHost, L: Llm> BotFeatures<G, L> {
     #[instrument(skip(self))]
-    pub async fn process_event(&self, event: &GitEvent) -> Result<(), G::Error, L::Error> {
+    pub async fn process_event(&self, event: &GitEvent) -> FeatureFailures<G::Error, L::Error>
+    where
+        G::Error: Debug,
+        L::Error: Debug,
+    {
+        self.process_event_for(event, FeatureName::ALL).await
+    }
+
+    /// Process the event only with the selected features. Returns the features that have failed.
+    ///
+    /// An error of a feature is logged and does not stop the other features. The security, spam
+    /// and moderation features go first, and the others are skipped if they closed, locked or
+    /// hid something, or if they failed, as the issue may be something that should be taken down.
+    #[instrument(skip(self))]
+    pub async fn process_event_for(
+        &self,
+        event: &GitEvent,
+        selected: &[FeatureName],
+    ) -> FeatureFailures<G::Error, L::Error>
+    where
+        G::Error: Debug,
+        L::Error: Debug,
+    {
+        let mut failures = Vec::new();
+        let mut taken_down = false;
+
+        // Goes first, so that the other features get the issue with the secrets masked.
+        if let Some(security_feature) = &self.security_feature {
+            if selected.contains(&FeatureName::Security) {
+                let result = security_feature.process_event(event).await;
+                taken_down |= keep_failure(FeatureName::Security, event, result, &mut failures)
+                    .unwrap_or(false);
+            }
+        }
+
+        if let Some(spam_feature) = &self.spam_feature {
+            if selected.contains(&FeatureName::Spam) {
+                let result = spam_feature.process_event(event).await;
+                taken_down |=
+                    keep_failure(FeatureName::Spam, event, result, &mut failures).unwrap_or(false);
+            }
+        }
+
+        if let Some(moderation_feature) = &self.moderation_feature {
+            if selected.contains(&FeatureName::Moderation) {
+                let result = moderation_feature.process_event(event).await;
+                taken_down |= keep_failure(FeatureName::Moderation, event, result, &mut failures)
+                    .unwrap_or(false);
+            }
+        }
+
+        if taken_down {
+            info!(
+                "Issue {} was taken down, skipping the other features",
+                *event.issue_id
+            );
+            return failures;
+        }
+
+        if !failures.is_empty() {
+            warn!(
+                "Gatekeeping features failed on issue {}, skipping the other features",
+                *event.issue_id
+            );
+            return failures;
+        }
+
         if let Some(improve_feature) = &self.improve_feature {
-            improve_feature.process_event(event).await?;
+            if selected.contains(&FeatureName::Improve) {
+                let result = improve_feature.process_event(event).await;
+                keep_failure(FeatureName::Improve, event, result, &mut failures);
+            }
         }
 
         if let Some(label_feature) = &self.label_feature {
-            label_feature.process_event(event).await?;
+            if selected.contains(&FeatureName::Label) {
+                let result = label_feature.process_event(event).await;
+                keep_failure(FeatureName::Label, event, result, &mut failures);
+            }
+        }
+
+        if let Some(review_feature) = &self.review_feature {
+            if selected.contains(&FeatureName::Review) {
+                let result = review_feature.process_event(event).await;
+                keep_failure(FeatureName::Review, event, result, &mut failures);
+            }
+        }
+
+        if let Some(summary_feature) = &self.summary_feature {
+            if selected.contains(&FeatureName::Summary) {
+                let result = summary_feature.process_event(event).await;
+                keep_failure(FeatureName::Summary, event, result, &mut failures);
+            }
+        }
+
+        if let Some(triage_assign_feature) = &self.triage_assign_feature {
+            if selected.contains(&FeatureName::TriageAssign) {
+                let result = triage_assign_feature.process_event(event).await;
+                keep_failure(FeatureName::TriageAssign, event, result, &mut failures);
+            }
+        }
+
+        if let Some(template_compliance_feature) = &self.template_compliance_feature {
+            if selected.contains(&FeatureName::TemplateCompliance) {
+                let result = template_compliance_feature.process_event(event).await;
+                keep_failure(
+                    FeatureName::TemplateCompliance,
+                    event,
+                    result,
+                    &mut failures,
+                );
+            }
+        }
+
+        if let Some(log_analysis_feature) = &self.log_analysis_feature {
+            if selected.contains(&FeatureName::LogAnalysis) {
+                let result = log_analysis_feature.process_event(event).await;
+                keep_failure(FeatureName::LogAnalysis, event, result, &mut failures);
+            }
+        }
+
+        if let Some(docs_answer_feature) = &self.docs_answer_feature {
+            if selected.contains(&FeatureName::DocsAnswer) {
+                let result = docs_answer_feature.process_event(event).await;
+                keep_failure(FeatureName::DocsAnswer, event, result, &mut failures);
+            }
+        }
+
+        failures
+    }
+
+    /// Enabled features that have periodic jobs.
+    pub fn scheduled_features(&self) -> Vec<FeatureName> {
+        let mut features = Vec::new();
+
+        if self.stale_feature.is_some() {
+            features.push(FeatureName::Stale);
+        }
+
+        if self.digest_feature.is_some() {
+            features.push(FeatureName::Digest);
+        }
+
+        // These jobs rebuild the indexes, so that they follow the repository.
+        if self
+            .improve_feature
+            .as_ref()
+            .is_some_and(|improve_feature| improve_feature.has_code_index())
+        {
+            features.push(FeatureName::Improve);
+        }
+
+        if self.docs_answer_feature.is_some() {
+            features.push(FeatureName::DocsAnswer);
+        }
+
+        features
+    }
+
+    /// Process a time-based event with the feature it is addressed to.
+    #[instrument(skip(self))]
+    pub async fn process_scheduled_event(
+        &self,
+        event: &ScheduledEvent,
+    ) -> Result<(), G::Error, L::Error>
+    where
+        G::Error: Debug,
+        L::Error: Debug,
+    {
+        match event.feature {
+            FeatureName::Stale => {
+                if let Some(stale_feature) = &self.stale_feature {
+                    stale_feature.process_scheduled(event.repo_id).await?;
+                }
+            }
+
+            FeatureName::Digest => {
+                if let Some(digest_feature) = &self.digest_feature {
+                    digest_feature.process_scheduled(event.repo_id).await?;
+                }
+            }
+
+            FeatureName::Improve => {
+                if let Some(improve_feature) = &self.improve_feature {
+                    improve_feature.rebuild_index(event.repo_id).await?;
+                }
+            }
+
+            FeatureName::DocsAnswer => {
+                if let Some(docs_answer_feature) = &self.docs_answer_feature {
+                    docs_answer_feature.rebuild_index(event.repo_id).await?;
+                }
+            }
+
+            feature => warn!("Feature {} has no scheduled jobs", feature),
         }
 
         Ok(())
     }
+
+    /// Write release notes for the pull requests merged between the refs.
+    ///
+    /// Returns `None` if the feature is not enabled.
+    pub async fn release_notes(
+        &self,
+        repo_id: RepoId,
+        base: String,
+        head: String,
+    ) -> Result<Option<String>, G::Error, L::Error> {
+        Ok(match &self.release_notes_feature {
+            Some(release_notes_feature) => Some(
+                release_notes_feature
+                    .write_notes(repo_id, base, head)
+                    .await?,
+            ),
+            None => None,
+        })
+    }
+
+    /// Rebuild the code and docs indexes of the repository that the enabled features use.
+    pub async fn rebuild_indexes(&self, repo_id: RepoId) -> Result<(), G::Error, L::Error> {
+        if let Some(improve_feature) = &self.improve_feature {
+            improve_feature.rebuild_index(repo_id).await?;
+        }
+
+        if let Some(docs_answer_feature) = &self.docs_answer_feature {
+            docs_answer_feature.rebuild_index(repo_id).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Render the messages that the feature would send to the LLM for the issue.
+    ///
+    /// Returns `None` if the feature is not enabled.
+    pub async fn render(
+        &self,
+        feature: FeatureName,
+        repo_id: RepoId,
+        issue_id: IssueId,
+    ) -> Result<Option<RenderedMessages>, G::Error, L::Error> {
+        Ok(match feature {
+            FeatureName::Improve => match &self.improve_feature {
+                Some(improve_feature) => Some(improve_feature.render(repo_id, issue_id).await?),
+                None => None,
+            },
+
+            FeatureName::Label => match &self.label_feature {
+                Some(label_feature) => Some(label_feature.render(repo_id, issue_id).await?),
+                None => None,
+            },
+
+            FeatureName::Review => match &self.review_feature {
+                Some(review_feature) => Some(review_feature.render(repo_id, issue_id).await?),
+                None => None,
+            },
+
+            FeatureName::Summary => match &self.summary_feature {
+                Some(summary_feature) => Some(summary_feature.render(repo_id, issue_id).await?),
+                None => None,
+            },
+
+            FeatureName::TriageAssign => match &self.triage_assign_feature {
+                Some(triage_assign_feature) => {
+                    Some(triage_assign_feature.render(repo_id, issue_id).await?)
+                }
+                None => None,
+            },
+
+            FeatureName::Stale => match &self.stale_feature {
+                Some(stale_feature) => Some(stale_feature.render(repo_id, issue_id).await?),
+                None => None,
+            },
+
+            FeatureName::Digest => match &self.digest_feature {
+                Some(digest_feature) => Some(digest_feature.render(repo_id).await?),
+                None => None,
+            },
+
+            FeatureName::ReleaseNotes => match &self.release_notes_feature {
+                Some(release_notes_feature) => {
+                    Some(release_notes_feature.render(repo_id, issue_id).await?)
+                }
+                None => None,
+            },
+
+            FeatureName::Moderation => match &self.moderation_feature {
+                Some(moderation_feature) => {
+                    Some(moderation_feature.render(repo_id, issue_id).await?)
+                }
+                None => None,
+            },
+
+            FeatureName::Spam => match &self.spam_feature {
+                Some(spam_feature) => Some(spam_feature.render(repo_id, issue_id).await?),
+                None => None,
+            },
+
+            FeatureName::Security => match &self.security_feature {
+                Some(security_feature) => Some(security_feature.render(repo_id, issue_id).await?),
+                None => None,
+            },
+
+            FeatureName::TemplateCompliance => match &self.template_compliance_feature {
+                Some(template_compliance_feature) => Some(
+                    template_compliance_feature
+                        .render(repo_id, issue_id)
+                        .await?,
+                ),
+                None => None,
+            },
+
+            FeatureName::LogAnalysis => match &self.log_analysis_feature {
+                Some(log_analysis_feature) => {
+                    Some(log_analysis_feature.render(repo_id, issue_id).await?)
+                }
+                None => None,
+            },
+
+            FeatureName::DocsAnswer => match &self.docs_answer_feature {
+                Some(docs_answer_feature) => {
+                    Some(docs_answer_feature.render(repo_id, issue_id).await?)
+                }
+                None => None,
+            },
+        })
+    }
+}
+
+/// Log the error of a feature and add it to the failures, so that it doesn't stop the other
+/// features.
+fn keep_failure<T, E: Debug, GE, LE>(
+    feature: FeatureName,
+    event: &GitEvent,
+    result: std::result::Result<T, E>,
+    failures: &mut FeatureFailures<GE, LE>,
+) -> Option<T>
+where
+    GitBotError<GE, LE>: From<E>,
+{
+    match result {
+        Ok(value) => Some(value),
+        Err(e) => {
+            error!(
+                "Feature {} failed on issue {}: {:?}",
+                feature, *event.issue_id, e
+            );
+
+            failures.push((feature, e.into()));
+
+            None
+        }
+    }
 }
@@ -0,0 +1,132 @@
+use std::sync::Arc;
+
+use log::warn;
+use non_empty_string::NonEmptyString;
+
+use crate::{
+    githost::{
+        host::GitHost,
+        model::{Comment, CommentId, IssueId, RepoId},
+    },
+    storage::{
+        impls::sqlite_storage::SqliteStorage,
+        storage_trait::{IssueKey, Storage},
+    },
+};
+
+use super::bot_features::FeatureName;
+
+/// Whether the comment was made by the bot, and not e.g. by a user quoting the bot's comment.
+pub async fn is_own_comment<G: GitHost>(githost: &G, comment: &Comment) -> Result<bool, G::Error> {
+    let author = githost.get_user(comment.user_id).await?;
+
+    Ok(author.nickname == *githost.get_self_name())
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct CommentKey {
+    pub repo_id: RepoId,
+    pub issue_id: IssueId,
+    pub feature: FeatureName,
+}
+
+impl From<CommentKey> for IssueKey {
+    fn from(key: CommentKey) -> Self {
+        Self {
+            repo_id: key.repo_id,
+            issue_id: key.issue_id,
+            feature: key.feature.to_string(),
+        }
+    }
+}
+
+/// Remembers the comment that a feature has made on an issue, so that the feature can keep one
+/// living comment per issue instead of making a new one on every event.
+///
+/// The store is shared between features and keeps the comments in the `Storage`. If the storage
+/// fails, the error is logged and the comment is treated as unknown, so at worst the feature makes
+/// a new comment.
+#[derive(Clone)]
+pub struct CommentStore {
+    storage: Arc<dyn Storage + Send + Sync>,
+}
+
+impl Default for CommentStore {
+    /// A store that lives in memory only.
+    fn default() -> Self {
+        Self::new(Arc::new(
+            SqliteStorage::in_memory().expect("in-memory database can always be opened"),
+        ))
+    }
+}
+
+impl CommentStore {
+    pub fn new(storage: Arc<dyn Storage + Send + Sync>) -> Self {
+        Self { storage }
+    }
+
+    pub async fn get(&self, key: &CommentKey) -> Option<CommentId> {
+        match self.storage.get_comment(&(*key).into()).await {
+            Ok(comment_id) => comment_id,
+            Err(e) => {
+                warn!("Unable to read the comment from the storage: {}", e);
+                None
+            }
+        }
+    }
+
+    pub async fn set(&self, key: CommentKey, comment_id: CommentId) {
+        if let Err(e) = self.storage.set_comment(&key.into(), comment_id).await {
+            warn!("Unable to save the comment to the storage: {}", e);
+        }
+    }
+
+    pub async fn remove(&self, key: &CommentKey) {
+        if let Err(e) = self.storage.remove_comment(&(*key).into()).await {
+            warn!("Unable to remove the comment from the storage: {}", e);
+        }
+    }
+
+    /// Edit the stored comment, or make a new one if there is none yet.
+    pub async fn upsert_comment<G: GitHost>(
+        &self,
+        githost: &G,
+        key: CommentKey,
+        message: NonEmptyString,
+    ) -> Result<CommentId, G::Error> {
+        match self.get(&key).await {
+            Some(comment_id) => {
+                githost
+                    .edit_comment(key.repo_id, comment_id, message)
+                    .await?;
+
+                Ok(comment_id)
+            }
+
+            None => {
+                let comment_id = githost
+                    .make_comment(key.repo_id, key.issue_id, message)
+                    .await?;
+
+                self.set(key, comment_id).await;
+
+                Ok(comment_id)
+            }
+        }
+    }
+
+    /// Delete the stored comment, if there is one.
+    pub async fn retract_comment<G: GitHost>(
+        &self,
+        githost: &G,
+        key: &CommentKey,
+    ) -> Result<(), G::Error> {
+        if let Some(comment_id) = self.get(key).await {
+            githost.delete_comment(key.repo_id, comment_id).await?;
+
+            self.remove(key).await;
+        }
+
+        Ok(())
+    }
+}
@@ -1,4 +1,18 @@
-use super::features::{improve_feature::ImproveFeatureError, label_feature::LabelFeatureError};
+use crate::storage::storage_trait::StorageError;
+
+use super::{
+    features::{
+        digest_feature::DigestFeatureError, docs_answer_feature::DocsAnswerFeatureError,
+        improve_feature::ImproveFeatureError, label_feature::LabelFeatureError,
+        log_analysis_feature::LogAnalysisFeatureError, moderation_feature::ModerationFeatureError,
+        release_notes_feature::ReleaseNotesFeatureError, review_feature::ReviewFeatureError,
+        security_feature::SecurityFeatureError, spam_feature::SpamFeatureError,
+        stale_feature::StaleFeatureError, summary_feature::SummaryFeatureError,
+        template_compliance_feature::TemplateComplianceFeatureError,
+        triage_assign_feature::TriageAssignFeatureError,
+    },
+    scheduler::SchedulerError,
+};
 
 #[derive(Debug, thiserror::Error)]
 pub enum GitBotError<GE, LE> {
@@ -7,6 +21,48 @@ pub enum GitBotError<GE, LE> {
 
     #[error("issue-label feature returned an error")]
     LabelFeatureError(#[from] LabelFeatureError<GE, LE>),
+
+    #[error("pull-request-review feature returned an error")]
+    ReviewFeatureError(#[from] ReviewFeatureError<GE, LE>),
+
+    #[error("pull-request-summary feature returned an error")]
+    SummaryFeatureError(#[from] SummaryFeatureError<GE, LE>),
+
+    #[error("issue-triage-assign feature returned an error")]
+    TriageAssignFeatureError(#[from] TriageAssignFeatureError<GE, LE>),
+
+    #[error("stale-issue feature returned an error")]
+    StaleFeatureError(#[from] StaleFeatureError<GE, LE>),
+
+    #[error("repository-digest feature returned an error")]
+    DigestFeatureError(#[from] DigestFeatureError<GE, LE>),
+
+    #[error("release-notes feature returned an error")]
+    ReleaseNotesFeatureError(#[from] ReleaseNotesFeatureError<GE, LE>),
+
+    #[error("moderation feature returned an error")]
+    ModerationFeatureError(#[from] ModerationFeatureError<GE, LE>),
+
+    #[error("spam feature returned an error")]
+    SpamFeatureError(#[from] SpamFeatureError<GE, LE>),
+
+    #[error("security feature returned an error")]
+    SecurityFeatureError(#[from] SecurityFeatureError<GE, LE>),
+
+    #[error("template compliance feature returned an error")]
+    TemplateComplianceFeatureError(#[from] TemplateComplianceFeatureError<GE, LE>),
+
+    #[error("log analysis feature returned an error")]
+    LogAnalysisFeatureError(#[from] LogAnalysisFeatureError<GE, LE>),
+
+    #[error("docs answer feature returned an error")]
+    DocsAnswerFeatureError(#[from] DocsAnswerFeatureError<GE, LE>),
+
+    #[error("unable to setup scheduler")]
+    SchedulerError(#[from] SchedulerError<GE>),
+
+    #[error("unable to open the storage")]
+    StorageError(#[source] StorageError),
 }
 
 pub type Result<T, GE, LE> = std::result::Result<T, GitBotError<GE, LE>>;
@@ -0,0 +1,842 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, HashSet},
+    path::PathBuf,
+};
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use log::{info, warn};
+use non_empty_string::NonEmptyString;
+use serde::{Deserialize, Serialize};
+use tokio::fs::{create_dir_all, write};
+
+use crate::{
+    githost::{
+        host::GitHost,
+        model::{Issue, IssueId, IssueQuery, IssueState, IssueStateFilter, RepoId, RepoName},
+    },
+    llm::{
+        agent::{LlmAgent, LlmAgentConfig, LlmAgentError, RenderedMessages},
+        llm_trait::{Embedding, Llm},
+    },
+    vector_store::vector_store_trait::cosine_similarity,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum DigestFeatureError<GE, LE> {
+    #[error("error from LLM agent")]
+    LlmAgentError(#[source] LlmAgentError<LE>),
+
+    #[error("unable to perform Git host action")]
+    GitHostError(#[from] GE),
+
+    #[error("unable to embed the titles")]
+    LlmError(#[source] LE),
+
+    #[error("unable to write digest file")]
+    FileWriteError(#[source] std::io::Error),
+
+    #[error("digest should cover at least one day, got {0}")]
+    InvalidDays(i64),
+}
+
+pub type Result<T, GE, LE> = std::result::Result<T, DigestFeatureError<GE, LE>>;
+
+/// Where the digest is published.
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum DigestTarget {
+    /// A new issue. Posted in the digested repository if `repo` is absent.
+    Issue { repo: Option<RepoName> },
+
+    /// A new discussion in the category. Posted in the digested repository if `repo` is absent.
+    Discussion {
+        repo: Option<RepoName>,
+        category: NonEmptyString,
+    },
+
+    /// A Markdown file in the directory, named after the repository and the date.
+    File { directory: PathBuf },
+}
+
+#[derive(Deserialize)]
+pub struct DigestFeatureConfig {
+    agent: LlmAgentConfig,
+
+    /// Number of past days covered by the digest. Should be positive.
+    #[serde(default = "default_days")]
+    days: i64,
+
+    target: DigestTarget,
+
+    /// How similar the titles of unlabeled items should be to share a topic, from -1.0 to 1.0.
+    /// Needs `embedding_model_name` in the LLM configuration, otherwise unlabeled items are not
+    /// grouped.
+    #[serde(default = "default_topic_similarity")]
+    topic_similarity: f32,
+}
+
+fn default_days() -> i64 {
+    7
+}
+
+fn default_topic_similarity() -> f32 {
+    0.8
+}
+
+pub struct DigestFeature<G, L> {
+    githost: G,
+    agent: LlmAgent<L, DigestFeatureContext>,
+
+    /// Embeds the titles of unlabeled items to group them by topic.
+    llm: L,
+    days: i64,
+    target: DigestTarget,
+    topic_similarity: f32,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DigestItemTemplate {
+    pub number: IssueId,
+    pub title: NonEmptyString,
+    pub state: IssueState,
+    pub labels: Vec<NonEmptyString>,
+    pub comments: usize,
+}
+
+#[derive(Serialize, Debug)]
+pub struct LabelGroupTemplate {
+    pub label: String,
+    pub items: Vec<DigestItemTemplate>,
+}
+
+/// Unlabeled items with similar titles. The topic is not named, the LLM names it from the titles.
+#[derive(Serialize, Debug)]
+pub struct TopicGroupTemplate {
+    pub items: Vec<DigestItemTemplate>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DigestFeatureContext {
+    /// Repository in the `owner/name` format.
+    pub repo: String,
+
+    /// First day of the period.
+    pub since: NaiveDate,
+
+    /// Last day of the period.
+    pub until: NaiveDate,
+
+    pub issues_opened: Vec<DigestItemTemplate>,
+    pub issues_closed: Vec<DigestItemTemplate>,
+    pub pull_requests_opened: Vec<DigestItemTemplate>,
+    pub pull_requests_closed: Vec<DigestItemTemplate>,
+
+    /// Issues and pull requests of the period grouped by label, the largest groups first. An item
+    /// with several labels is in several groups.
+    pub label_groups: Vec<LabelGroupTemplate>,
+
+    /// Unlabeled issues and pull requests of the period grouped by the similarity of their titles,
+    /// the largest groups first. Items that are alike to no other are in no group.
+    pub topic_groups: Vec<TopicGroupTemplate>,
+
+    /// Issues opened in the period that are still open and have no comments from other people.
+    pub unanswered: Vec<DigestItemTemplate>,
+}
+
+impl<G: GitHost, L: Llm> DigestFeature<G, L> {
+    pub async fn build_from_config(
+        config: DigestFeatureConfig,
+        githost: G,
+        llm: L,
+    ) -> Result<Self, G::Error, L::Error>
+    where
+        L: Clone,
+    {
+        if config.days <= 0 {
+            return Err(DigestFeatureError::InvalidDays(config.days));
+        }
+
+        let agent = LlmAgent::build_from_config(llm.clone(), config.agent)
+            .map_err(DigestFeatureError::LlmAgentError)?;
+
+        Ok(Self::new(
+            githost,
+            agent,
+            llm,
+            config.days,
+            config.target,
+            config.topic_similarity,
+        ))
+    }
+
+    pub fn new(
+        githost: G,
+        agent: LlmAgent<L, DigestFeatureContext>,
+        llm: L,
+        days: i64,
+        target: DigestTarget,
+        topic_similarity: f32,
+    ) -> Self {
+        Self {
+            githost,
+            agent,
+            llm,
+            days,
+            target,
+            topic_similarity,
+        }
+    }
+
+    /// Write a digest of the repository for the past days and publish it.
+    pub async fn process_scheduled(&self, repo_id: RepoId) -> Result<(), G::Error, L::Error> {
+        self.process_scheduled_at(repo_id, Utc::now()).await
+    }
+
+    async fn process_scheduled_at(
+        &self,
+        repo_id: RepoId,
+        now: DateTime<Utc>,
+    ) -> Result<(), G::Error, L::Error> {
+        let context = self.make_context(repo_id, now).await?;
+
+        let digest = self
+            .agent
+            .process(&context)
+            .await
+            .map_err(DigestFeatureError::LlmAgentError)?;
+
+        let title: NonEmptyString = format!(
+            "Digest of {} from {} to {}",
+            context.repo, context.since, context.until
+        )
+        .try_into()
+        .expect("digest title is never empty");
+
+        info!("Publishing {}", title);
+
+        match &self.target {
+            DigestTarget::Issue { repo } => {
+                let target_repo_id = self.find_target_repo(repo_id, repo).await?;
+
+                self.githost
+                    .create_issue(target_repo_id, title, digest.to_string())
+                    .await?;
+            }
+
+            DigestTarget::Discussion { repo, category } => {
+                let target_repo_id = self.find_target_repo(repo_id, repo).await?;
+
+                self.githost
+                    .create_discussion(target_repo_id, category.clone(), title, digest.to_string())
+                    .await?;
+            }
+
+            DigestTarget::File { directory } => {
+                create_dir_all(directory)
+                    .await
+                    .map_err(DigestFeatureError::FileWriteError)?;
+
+                let file_name = format!(
+                    "{}-{}.md",
+                    context.repo.replace('/', "-"),
+                    context.until.format("%Y-%m-%d")
+                );
+
+                write(
+                    directory.join(file_name),
+                    format!("# {}\n\n{}\n", title, digest),
+                )
+                .await
+                .map_err(DigestFeatureError::FileWriteError)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn render(&self, repo_id: RepoId) -> Result<RenderedMessages, G::Error, L::Error> {
+        let context = self.make_context(repo_id, Utc::now()).await?;
+
+        self.agent
+            .render(&context)
+            .map_err(DigestFeatureError::LlmAgentError)
+    }
+
+    async fn find_target_repo(
+        &self,
+        repo_id: RepoId,
+        repo: &Option<RepoName>,
+    ) -> Result<RepoId, G::Error, L::Error> {
+        Ok(match repo {
+            Some(repo) => self.githost.find_repo(repo).await?.id,
+            None => repo_id,
+        })
+    }
+
+    async fn make_context(
+        &self,
+        repo_id: RepoId,
+        now: DateTime<Utc>,
+    ) -> Result<DigestFeatureContext, G::Error, L::Error> {
+        let repo = self.githost.get_repo(repo_id).await?;
+
+        // Today is not included, so that the digest covers only whole days.
+        let until = now.date_naive();
+        let since = until - Duration::days(self.days);
+
+        let opened = IssueQuery {
+            state: IssueStateFilter::All,
+            created_after: Some(since),
+            created_before: Some(until),
+            ..Default::default()
+        };
+
+        let closed = IssueQuery {
+            state: IssueStateFilter::All,
+            closed_after: Some(since),
+            closed_before: Some(until),
+            ..Default::default()
+        };
+
+        // An issue can be found by several queries, e.g. when it's both opened and closed in the
+        // period.
+        let mut cache = HashMap::new();
+
+        let issues_opened = self.collect_items(repo_id, &opened, &mut cache).await?;
+        let issues_closed = self.collect_items(repo_id, &closed, &mut cache).await?;
+
+        let pull_requests_opened = self
+            .collect_items(
+                repo_id,
+                &IssueQuery {
+                    pull_requests: true,
+                    ..opened
+                },
+                &mut cache,
+            )
+            .await?;
+
+        let pull_requests_closed = self
+            .collect_items(
+                repo_id,
+                &IssueQuery {
+                    pull_requests: true,
+                    ..closed
+                },
+                &mut cache,
+            )
+            .await?;
+
+        let unanswered = issues_opened
+            .iter()
+            .filter(|(item, answered)| item.state == IssueState::Open && !answered)
+            .map(|(item, _)| item.clone())
+            .collect();
+
+        let issues_opened: Vec<_> = issues_opened.into_iter().map(|(item, _)| item).collect();
+        let issues_closed: Vec<_> = issues_closed.into_iter().map(|(item, _)| item).collect();
+        let pull_requests_opened: Vec<_> = pull_requests_opened
+            .into_iter()
+            .map(|(item, _)| item)
+            .collect();
+        let pull_requests_closed: Vec<_> = pull_requests_closed
+            .into_iter()
+            .map(|(item, _)| item)
+            .collect();
+
+        let mut seen = HashSet::new();
+
+        // An issue can be both opened and closed in the period.
+        let items: Vec<&DigestItemTemplate> = issues_opened
+            .iter()
+            .chain(&issues_closed)
+            .chain(&pull_requests_opened)
+            .chain(&pull_requests_closed)
+            .filter(|item| seen.insert(item.number))
+            .collect();
+
+        let label_groups = group_by_label(&items);
+        let topic_groups = self.group_by_topic(&items).await?;
+
+        Ok(DigestFeatureContext {
+            repo: format!("{}/{}", repo.owner, repo.name),
+            since,
+            until: until - Duration::days(1),
+            issues_opened,
+            issues_closed,
+            pull_requests_opened,
+            pull_requests_closed,
+            label_groups,
+            topic_groups,
+            unanswered,
+        })
+    }
+
+    async fn group_by_topic(
+        &self,
+        items: &[&DigestItemTemplate],
+    ) -> Result<Vec<TopicGroupTemplate>, G::Error, L::Error> {
+        let unlabeled: Vec<&DigestItemTemplate> = items
+            .iter()
+            .copied()
+            .filter(|item| item.labels.is_empty())
+            .collect();
+
+        if unlabeled.len() < 2 {
+            return Ok(Vec::new());
+        }
+
+        let titles = unlabeled
+            .iter()
+            .map(|item| item.title.to_string())
+            .collect();
+
+        let Some(embeddings) = self
+            .llm
+            .embed(titles)
+            .await
+            .map_err(DigestFeatureError::LlmError)?
+        else {
+            warn!("The LLM has no embedding model, so unlabeled items are not grouped by topic");
+            return Ok(Vec::new());
+        };
+
+        Ok(cluster_by_similarity(
+            unlabeled.into_iter().zip(embeddings),
+            self.topic_similarity,
+        ))
+    }
+
+    /// Items found by the query, each with a flag whether someone besides the author commented.
+    /// Items already in the cache are not fetched again.
+    async fn collect_items(
+        &self,
+        repo_id: RepoId,
+        query: &IssueQuery,
+        cache: &mut HashMap<IssueId, (DigestItemTemplate, bool)>,
+    ) -> Result<Vec<(DigestItemTemplate, bool)>, G::Error, L::Error> {
+        let mut items = Vec::new();
+
+        for page_number in 1.. {
+            let page = self
+                .githost
+                .search_issues(repo_id, query, page_number)
+                .await?;
+
+            for found in page.issues {
+                let issue_id = found.id;
+
+                if let Some(item) = cache.get(&issue_id) {
+                    items.push(item.clone());
+                    continue;
+                }
+
+                let issue = self.githost.get_issue(repo_id, issue_id).await?;
+                let comments = self.githost.list_comments(repo_id, issue_id).await?;
+
+                let answered = comments
+                    .iter()
+                    .any(|comment| comment.user_id != issue.author_user_id);
+
+                let item = (make_item(issue, comments.len()), answered);
+                cache.insert(issue_id, item.clone());
+                items.push(item);
+            }
+
+            if !page.has_next_page {
+                break;
+            }
+        }
+
+        Ok(items)
+    }
+}
+
+fn make_item(issue: Issue, comments: usize) -> DigestItemTemplate {
+    DigestItemTemplate {
+        number: issue.id,
+        title: issue.title,
+        state: issue.state,
+        labels: issue.labels,
+        comments,
+    }
+}
+
+fn group_by_label(items: &[&DigestItemTemplate]) -> Vec<LabelGroupTemplate> {
+    let mut groups: BTreeMap<String, Vec<DigestItemTemplate>> = BTreeMap::new();
+
+    for &item in items {
+        for label in &item.labels {
+            groups
+                .entry(label.to_string())
+                .or_default()
+                .push(item.clone());
+        }
+    }
+
+    let mut groups: Vec<_> = groups
+        .into_iter()
+        .map(|(label, items)| LabelGroupTemplate { label, items })
+        .collect();
+
+    groups.sort_by_key(|group| Reverse(group.items.len()));
+
+    groups
+}
+
+/// An item joins the group whose first item is the most similar to it, or starts a new group.
+/// Groups of a single item are dropped.
+fn cluster_by_similarity<'a>(
+    items: impl Iterator<Item = (&'a DigestItemTemplate, Embedding)>,
+    min_similarity: f32,
+) -> Vec<TopicGroupTemplate> {
+    let mut groups: Vec<(Embedding, Vec<DigestItemTemplate>)> = Vec::new();
+
+    for (item, embedding) in items {
+        let closest = groups
+            .iter_mut()
+            .map(|group| (cosine_similarity(&embedding, &group.0), group))
+            .filter(|(similarity, _)| *similarity >= min_similarity)
+            .max_by(|a, b| a.0.total_cmp(&b.0));
+
+        match closest {
+            Some((_, group)) => group.1.push(item.clone()),
+            None => groups.push((embedding, vec![item.clone()])),
+        }
+    }
+
+    let mut groups: Vec<_> = groups
+        .into_iter()
+        .filter(|(_, items)| items.len() > 1)
+        .map(|(_, items)| TopicGroupTemplate { items })
+        .collect();
+
+    groups.sort_by_key(|group| Reverse(group.items.len()));
+
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chrono::{DateTime, NaiveDate, Utc};
+    use mockall::predicate;
+    use non_empty_string::NonEmptyString;
+
+    use crate::{
+        bot::features::digest_feature::{DigestFeature, DigestTarget},
+        githost::{
+            host::MockGitHost,
+            model::{
+                AuthorAssociation, Comment, CommentId, FoundIssue, Issue, IssueId, IssueQuery,
+                IssueState, IssueStateFilter, IssuesPage, Repo, RepoId, UserId,
+            },
+        },
+        llm::{
+            agent::LlmAgent,
+            llm_trait::{CompletionParameters, MockLlm},
+            messages::AiMessage,
+        },
+    };
+
+    const TEST_SYSTEM_MESSAGE: &str = "You are a bot that writes weekly digests of repositories.";
+    const TEST_USER_MESSAGE: &str =
+        "{% for item in context.issues_opened %}#{{ item.number }} {{ item.title }}{% endfor %}";
+
+    #[tokio::test]
+    async fn posts_digest_issue() {
+        let mut githost_mock = make_githost_mock(true);
+
+        githost_mock
+            .expect_create_issue()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(non_empty(
+                    "Digest of InAnYan/rust-gib from 2024-09-23 to 2024-09-29",
+                )),
+                predicate::eq("Two bugs were reported this week.".to_string()),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(IssueId::from(10 as usize)));
+
+        let feature = make_feature(
+            githost_mock,
+            DigestTarget::Issue { repo: None },
+            true,
+            MockLlm::new(),
+        );
+
+        feature
+            .process_scheduled_at(RepoId::from(1), make_now())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn writes_digest_file() {
+        let directory = std::env::temp_dir().join("gib_digest_writes_digest_file");
+
+        let feature = make_feature(
+            make_githost_mock(true),
+            DigestTarget::File {
+                directory: directory.clone(),
+            },
+            true,
+            MockLlm::new(),
+        );
+
+        feature
+            .process_scheduled_at(RepoId::from(1), make_now())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(directory.join("InAnYan-rust-gib-2024-09-29.md")).unwrap(),
+            "# Digest of InAnYan/rust-gib from 2024-09-23 to 2024-09-29\n\nTwo bugs were reported this week.\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn collects_activity() {
+        let feature = make_feature(
+            make_githost_mock(true),
+            DigestTarget::Issue { repo: None },
+            false,
+            MockLlm::new(),
+        );
+
+        let context = feature
+            .make_context(RepoId::from(1), make_now())
+            .await
+            .unwrap();
+
+        assert_eq!(context.issues_opened.len(), 2);
+        assert_eq!(context.issues_closed.len(), 1);
+        assert!(context.pull_requests_opened.is_empty());
+
+        assert_eq!(context.label_groups.len(), 1);
+        assert_eq!(context.label_groups[0].label, "bug");
+        assert_eq!(context.label_groups[0].items.len(), 2);
+
+        assert!(context.topic_groups.is_empty());
+
+        assert_eq!(context.unanswered.len(), 1);
+        assert_eq!(context.unanswered[0].number, IssueId::from(1 as usize));
+    }
+
+    #[tokio::test]
+    async fn groups_unlabeled_items_by_topic() {
+        let mut embedding_llm_mock = MockLlm::new();
+
+        embedding_llm_mock
+            .expect_embed()
+            .with(predicate::eq(vec![
+                "Program crashes".to_string(),
+                "Program crashes".to_string(),
+            ]))
+            .times(1)
+            .returning(|_| Ok(Some(vec![vec![1.0, 0.1], vec![0.9, 0.0]])));
+
+        let feature = make_feature(
+            make_githost_mock(false),
+            DigestTarget::Issue { repo: None },
+            false,
+            embedding_llm_mock,
+        );
+
+        let context = feature
+            .make_context(RepoId::from(1), make_now())
+            .await
+            .unwrap();
+
+        assert!(context.label_groups.is_empty());
+
+        assert_eq!(context.topic_groups.len(), 1);
+        assert_eq!(
+            context.topic_groups[0]
+                .items
+                .iter()
+                .map(|item| item.number)
+                .collect::<Vec<_>>(),
+            vec![IssueId::from(1 as usize), IssueId::from(2 as usize)]
+        );
+    }
+
+    fn make_feature(
+        githost_mock: MockGitHost,
+        target: DigestTarget,
+        expect_llm: bool,
+        embedding_llm_mock: MockLlm,
+    ) -> DigestFeature<MockGitHost, MockLlm> {
+        let mut llm_mock = MockLlm::new();
+
+        if expect_llm {
+            llm_mock.expect_complete().times(1).returning(|_, _, _| {
+                Ok(AiMessage::from(non_empty(
+                    "Two bugs were reported this week.",
+                )))
+            });
+        } else {
+            llm_mock.expect_complete().never();
+        }
+
+        DigestFeature::new(
+            githost_mock,
+            LlmAgent::build_raw(
+                llm_mock,
+                TEST_SYSTEM_MESSAGE.try_into().unwrap(),
+                TEST_USER_MESSAGE.try_into().unwrap(),
+                CompletionParameters::default(),
+            )
+            .unwrap(),
+            embedding_llm_mock,
+            7,
+            target,
+            0.8,
+        )
+    }
+
+    /// Issue #1 is opened and unanswered, issue #2 is opened and closed, no pull requests. Both
+    /// issues are labeled as bugs if `labeled`.
+    fn make_githost_mock(labeled: bool) -> MockGitHost {
+        let mut githost_mock = MockGitHost::new();
+
+        githost_mock.expect_get_repo().returning(|id| {
+            Ok(Repo {
+                id,
+                owner: "InAnYan".into(),
+                name: "rust-gib".into(),
+            })
+        });
+
+        let since = NaiveDate::from_ymd_opt(2024, 9, 23);
+        let until = NaiveDate::from_ymd_opt(2024, 9, 30);
+
+        for (query, issues) in [
+            (
+                IssueQuery {
+                    state: IssueStateFilter::All,
+                    created_after: since,
+                    created_before: until,
+                    ..Default::default()
+                },
+                vec![1, 2],
+            ),
+            (
+                IssueQuery {
+                    state: IssueStateFilter::All,
+                    closed_after: since,
+                    closed_before: until,
+                    ..Default::default()
+                },
+                vec![2],
+            ),
+            (
+                IssueQuery {
+                    state: IssueStateFilter::All,
+                    pull_requests: true,
+                    created_after: since,
+                    created_before: until,
+                    ..Default::default()
+                },
+                vec![],
+            ),
+            (
+                IssueQuery {
+                    state: IssueStateFilter::All,
+                    pull_requests: true,
+                    closed_after: since,
+                    closed_before: until,
+                    ..Default::default()
+                },
+                vec![],
+            ),
+        ] {
+            githost_mock
+                .expect_search_issues()
+                .with(
+                    predicate::eq(RepoId::from(1)),
+                    predicate::eq(query),
+                    predicate::eq(1),
+                )
+                .times(1)
+                .returning(move |_, _, _| {
+                    Ok(IssuesPage {
+                        issues: issues
+                            .iter()
+                            .map(|id| FoundIssue {
+                                id: IssueId::from(*id as usize),
+                                created_at: make_now(),
+                            })
+                            .collect(),
+                        has_next_page: false,
+                        capped: false,
+                    })
+                });
+        }
+
+        // Issue #2 is found by two queries, but fetched once.
+        githost_mock
+            .expect_get_issue()
+            .times(2)
+            .returning(move |_, issue_id| {
+                let created_at = make_now() - chrono::Duration::days(3);
+
+                Ok(Issue {
+                    id: issue_id,
+                    author_user_id: UserId::from(1),
+                    title: non_empty("Program crashes"),
+                    body: "It crashes on start".into(),
+                    state: if *issue_id == 1 {
+                        IssueState::Open
+                    } else {
+                        IssueState::Closed
+                    },
+                    labels: if labeled {
+                        vec![non_empty("bug")]
+                    } else {
+                        vec![]
+                    },
+                    assignees: vec![],
+                    milestone: None,
+                    created_at,
+                    updated_at: created_at,
+                    author_association: AuthorAssociation::None,
+                })
+            });
+
+        githost_mock
+            .expect_list_comments()
+            .times(2)
+            .returning(|_, issue_id| {
+                Ok(if *issue_id == 1 {
+                    vec![]
+                } else {
+                    let created_at = make_now() - chrono::Duration::days(2);
+
+                    vec![Comment {
+                        id: CommentId::from(1),
+                        user_id: UserId::from(2),
+                        body: non_empty("Fixed in #3"),
+                        created_at,
+                        updated_at: created_at,
+                        author_association: AuthorAssociation::Member,
+                    }]
+                })
+            });
+
+        githost_mock
+    }
+
+    fn make_now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-09-30T09:00:00Z")
+            .unwrap()
+            .to_utc()
+    }
+
+    fn non_empty(s: &str) -> NonEmptyString {
+        NonEmptyString::from_str(s).unwrap()
+    }
+}
@@ -0,0 +1,408 @@
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bot::{
+        bot_features::FeatureName,
+        comment_store::{CommentKey, CommentStore},
+    },
+    githost::{
+        events::{GitEvent, GitEventKindName},
+        host::GitHost,
+        model::{Issue, IssueId, RepoId},
+    },
+    llm::{
+        agent::{LlmAgent, LlmAgentConfig, LlmAgentError, RenderedMessages},
+        llm_trait::Llm,
+    },
+    retrieval::docs_index::{DocsIndex, DocsIndexConfig, DocsIndexError, ScoredDocPassage},
+};
+
+use super::templates::IssueTemplate;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DocsAnswerFeatureError<GE, LE> {
+    #[error("error from LLM agent")]
+    LlmAgentError(#[source] LlmAgentError<LE>),
+
+    #[error("unable to perform Git host action")]
+    GitHostError(#[from] GE),
+
+    #[error("unable to retrieve docs")]
+    DocsIndexError(#[source] DocsIndexError<GE, LE>),
+}
+
+pub type Result<T, GE, LE> = std::result::Result<T, DocsAnswerFeatureError<GE, LE>>;
+
+#[derive(Deserialize)]
+pub struct DocsAnswerFeatureConfig {
+    agent: LlmAgentConfig,
+
+    #[serde(default = "GitEventKindName::default_subscription")]
+    events: Vec<GitEventKindName>,
+
+    docs: DocsIndexConfig,
+}
+
+pub struct DocsAnswerFeature<G, L> {
+    githost: G,
+    agent: LlmAgent<L, DocsAnswerFeatureContext>,
+    events: Vec<GitEventKindName>,
+    docs_index: DocsIndex<G, L>,
+    comments: CommentStore,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DocsAnswerFeatureContext {
+    pub issue: IssueTemplate,
+
+    /// Passages of the docs that are similar to the issue, most similar first.
+    pub passages: Vec<ScoredDocPassage>,
+}
+
+impl<G: GitHost, L: Llm> DocsAnswerFeature<G, L> {
+    pub async fn build_from_config(
+        config: DocsAnswerFeatureConfig,
+        githost: G,
+        llm: L,
+        comments: CommentStore,
+    ) -> Result<Self, G::Error, L::Error>
+    where
+        G: Clone,
+        L: Clone,
+    {
+        let docs_index = DocsIndex::build_from_config(config.docs, githost.clone(), llm.clone())
+            .map_err(DocsAnswerFeatureError::DocsIndexError)?;
+
+        let agent = LlmAgent::build_from_config(llm, config.agent)
+            .map_err(DocsAnswerFeatureError::LlmAgentError)?;
+
+        Ok(Self::new(
+            githost,
+            agent,
+            config.events,
+            docs_index,
+            comments,
+        ))
+    }
+
+    pub fn new(
+        githost: G,
+        agent: LlmAgent<L, DocsAnswerFeatureContext>,
+        events: Vec<GitEventKindName>,
+        docs_index: DocsIndex<G, L>,
+        comments: CommentStore,
+    ) -> Self {
+        Self {
+            githost,
+            agent,
+            events,
+            docs_index,
+            comments,
+        }
+    }
+
+    pub async fn process_event(&self, event: &GitEvent) -> Result<(), G::Error, L::Error> {
+        if !self.events.contains(&event.kind.name()) {
+            return Ok(());
+        }
+
+        let issue = self
+            .githost
+            .get_issue(event.repo_id, event.issue_id)
+            .await?;
+
+        let context = self.make_context(event.repo_id, issue).await?;
+
+        // It's better to stay silent than to make up an answer that is not in the docs.
+        if context.passages.is_empty() {
+            info!(
+                "No docs are relevant to issue {}, not answering",
+                *event.issue_id
+            );
+
+            return Ok(());
+        }
+
+        let ai_message = self
+            .agent
+            .process(&context)
+            .await
+            .map_err(DocsAnswerFeatureError::LlmAgentError)?;
+
+        let key = CommentKey {
+            repo_id: event.repo_id,
+            issue_id: event.issue_id,
+            feature: FeatureName::DocsAnswer,
+        };
+
+        if ai_message
+            .as_str()
+            .trim()
+            .trim_matches('`')
+            .starts_with("EMPTY")
+        {
+            self.comments.retract_comment(&self.githost, &key).await?;
+        } else {
+            self.comments
+                .upsert_comment(&self.githost, key, ai_message)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Index the docs again.
+    pub async fn rebuild_index(&self, repo_id: RepoId) -> Result<(), G::Error, L::Error> {
+        self.docs_index
+            .rebuild(repo_id)
+            .await
+            .map_err(DocsAnswerFeatureError::DocsIndexError)
+    }
+
+    pub async fn render(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+    ) -> Result<RenderedMessages, G::Error, L::Error> {
+        let issue = self.githost.get_issue(repo_id, issue_id).await?;
+
+        let context = self.make_context(repo_id, issue).await?;
+
+        self.agent
+            .render(&context)
+            .map_err(DocsAnswerFeatureError::LlmAgentError)
+    }
+
+    async fn make_context(
+        &self,
+        repo_id: RepoId,
+        issue: Issue,
+    ) -> Result<DocsAnswerFeatureContext, G::Error, L::Error> {
+        let passages = self
+            .docs_index
+            .retrieve(
+                repo_id,
+                &self
+                    .agent
+                    .redact(&format!("{}\n\n{}", issue.title, issue.body)),
+            )
+            .await
+            .map_err(DocsAnswerFeatureError::DocsIndexError)?;
+
+        let author = self.githost.get_user(issue.author_user_id).await?;
+
+        Ok(DocsAnswerFeatureContext {
+            issue: (issue, author).into(),
+            passages,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, str::FromStr, sync::Arc};
+
+    use chrono::{DateTime, Utc};
+    use mockall::predicate;
+    use non_empty_string::NonEmptyString;
+
+    use crate::{
+        bot::{comment_store::CommentStore, features::docs_answer_feature::DocsAnswerFeature},
+        githost::{
+            events::{GitEvent, GitEventKind, GitEventKindName},
+            host::MockGitHost,
+            model::{
+                AuthorAssociation, CommentId, Issue, IssueId, IssueState, RepoId, User, UserId,
+                UserKind,
+            },
+        },
+        llm::{
+            agent::LlmAgent,
+            llm_trait::{CompletionParameters, MockLlm},
+            messages::{AiMessage, ChatMessage, UserMessage},
+        },
+        retrieval::docs_index::{DocsIndex, DocsIndexOptions, DocsSource},
+        vector_store::impls::file_vector_store::FileVectorStore,
+    };
+
+    const TEST_SYSTEM_MESSAGE: &str = "You are a bot that answers questions with the docs.";
+    const TEST_USER_MESSAGE: &str = "{% for passage in context.passages %}\
+                                     {{ passage.url }}: {{ passage.text }}\
+                                     {% endfor %}";
+
+    #[tokio::test]
+    async fn answers_with_docs() {
+        let docs_dir = make_docs_dir("gib_docs_answer_answers_with_docs");
+
+        let mut githost_mock = make_githost_mock();
+
+        let answer =
+            non_empty("> Download the key.\n\nSee https://docs.example.com/setup.md#private-key");
+
+        githost_mock
+            .expect_make_comment()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(IssueId::from(1 as usize)),
+                predicate::eq(answer.clone()),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(CommentId::from(1)));
+
+        let mut llm_mock = MockLlm::new();
+
+        llm_mock
+            .expect_complete()
+            .with(
+                predicate::always(),
+                predicate::eq(vec![ChatMessage::from(UserMessage::from(non_empty(
+                    "https://docs.example.com/setup.md#private-key: Download the key.",
+                )))]),
+                predicate::always(),
+            )
+            .times(1)
+            .returning(move |_, _, _| Ok(AiMessage::from(answer.clone())));
+
+        let feature = make_feature(githost_mock, llm_mock, &docs_dir, vec![0.2, 1.0]).await;
+
+        feature.process_event(&make_event()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn refuses_without_relevant_docs() {
+        let docs_dir = make_docs_dir("gib_docs_answer_refuses_without_relevant_docs");
+
+        // Neither the agent is asked, nor a comment is made.
+        let feature = make_feature(
+            make_githost_mock(),
+            MockLlm::new(),
+            &docs_dir,
+            vec![-1.0, -1.0],
+        )
+        .await;
+
+        feature.process_event(&make_event()).await.unwrap();
+    }
+
+    async fn make_feature(
+        githost_mock: MockGitHost,
+        llm_mock: MockLlm,
+        docs_dir: &Path,
+        query_embedding: Vec<f32>,
+    ) -> DocsAnswerFeature<MockGitHost, MockLlm> {
+        let mut embedding_llm_mock = MockLlm::new();
+
+        embedding_llm_mock
+            .expect_embed()
+            .with(predicate::eq(vec![
+                "Setup\nRun the bot.".to_string(),
+                "Private key\nDownload the key.".to_string(),
+            ]))
+            .returning(|_| Ok(Some(vec![vec![1.0, 0.0], vec![0.0, 1.0]])));
+
+        embedding_llm_mock
+            .expect_embed()
+            .with(predicate::eq(vec![
+                "Where is the key?\n\nI can't find the private key.".to_string(),
+            ]))
+            .returning(move |_| Ok(Some(vec![query_embedding.clone()])));
+
+        let feature = DocsAnswerFeature::new(
+            githost_mock,
+            LlmAgent::build_raw(
+                llm_mock,
+                TEST_SYSTEM_MESSAGE.try_into().unwrap(),
+                TEST_USER_MESSAGE.try_into().unwrap(),
+                CompletionParameters::default(),
+            )
+            .unwrap(),
+            vec![GitEventKindName::NewIssue],
+            DocsIndex::new(
+                MockGitHost::new(),
+                embedding_llm_mock,
+                Arc::new(FileVectorStore::new(docs_dir.join("index"))),
+                DocsIndexOptions {
+                    source: DocsSource::Local {
+                        path: docs_dir.join("docs"),
+                        base_url: "https://docs.example.com/".try_into().unwrap(),
+                    },
+                    max_chunk_chars: 2000,
+                    max_passages: 3,
+                    min_score: 0.5,
+                },
+            ),
+            CommentStore::default(),
+        );
+
+        feature.rebuild_index(RepoId::from(1)).await.unwrap();
+
+        feature
+    }
+
+    fn make_docs_dir(name: &str) -> std::path::PathBuf {
+        let docs_dir = std::env::temp_dir().join(name);
+
+        let _ = std::fs::remove_dir_all(&docs_dir);
+
+        std::fs::create_dir_all(docs_dir.join("docs")).unwrap();
+
+        std::fs::write(
+            docs_dir.join("docs").join("setup.md"),
+            "# Setup\n\nRun the bot.\n\n## Private key\n\nDownload the key.",
+        )
+        .unwrap();
+
+        docs_dir
+    }
+
+    fn make_githost_mock() -> MockGitHost {
+        let mut githost_mock = MockGitHost::new();
+
+        githost_mock.expect_get_issue().returning(|_, issue_id| {
+            let created_at = make_now();
+
+            Ok(Issue {
+                id: issue_id,
+                author_user_id: UserId::from(2),
+                title: non_empty("Where is the key?"),
+                body: "I can't find the private key.".into(),
+                state: IssueState::Open,
+                labels: vec![],
+                assignees: vec![],
+                milestone: None,
+                created_at,
+                updated_at: created_at,
+                author_association: AuthorAssociation::None,
+            })
+        });
+
+        githost_mock.expect_get_user().returning(|id| {
+            Ok(User {
+                id,
+                nickname: non_empty("reporter"),
+                kind: UserKind::User,
+            })
+        });
+
+        githost_mock
+    }
+
+    fn make_event() -> GitEvent {
+        GitEvent {
+            repo_id: RepoId::from(1),
+            issue_id: IssueId::from(1 as usize),
+            kind: GitEventKind::NewIssue,
+        }
+    }
+
+    fn make_now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-10-01T12:00:00Z")
+            .unwrap()
+            .to_utc()
+    }
+
+    fn non_empty(s: &str) -> NonEmptyString {
+        NonEmptyString::from_str(s).unwrap()
+    }
+}
@@ -1,12 +1,18 @@
 use crate::{
+    bot::{
+        bot_features::FeatureName,
+        comment_store::{CommentKey, CommentStore},
+    },
     githost::{
-        events::{GitEvent, GitEventKind},
+        events::{GitEvent, GitEventKindName},
         host::GitHost,
+        model::{IssueId, RepoId},
     },
     llm::{
-        agent::{LlmAgent, LlmAgentConfig, LlmAgentError},
+        agent::{LlmAgent, LlmAgentConfig, LlmAgentError, RenderedMessages},
         llm_trait::Llm,
     },
+    retrieval::code_index::{CodeIndex, CodeIndexConfig, CodeIndexError, CodeSnippet},
 };
 use serde::{Deserialize, Serialize};
 
@@ -19,6 +25,9 @@ pub enum ImproveFeatureError<GE, LE> {
 
     #[error("unable to perform Git host action")]
     GitHostError(#[from] GE),
+
+    #[error("unable to retrieve code context")]
+    CodeIndexError(#[source] CodeIndexError<GE, LE>),
 }
 
 pub type Result<T, GE, LE> = std::result::Result<T, ImproveFeatureError<GE, LE>>;
@@ -26,16 +35,29 @@ pub type Result<T, GE, LE> = std::result::Result<T, ImproveFeatureError<GE, LE>>
 #[derive(Deserialize)]
 pub struct ImproveFeatureConfig {
     agent: LlmAgentConfig,
+
+    #[serde(default = "GitEventKindName::default_subscription")]
+    events: Vec<GitEventKindName>,
+
+    /// Give the relevant parts of the repository's code to the template as `code_snippets`.
+    #[serde(default)]
+    code_context: Option<CodeIndexConfig>,
 }
 
 pub struct ImproveFeature<G, L> {
     githost: G,
     agent: LlmAgent<L, ImproveFeatureContext>,
+    events: Vec<GitEventKindName>,
+    comments: CommentStore,
+    code_index: Option<CodeIndex<G, L>>,
 }
 
 #[derive(Serialize, Debug)]
 pub struct ImproveFeatureContext {
     pub issue: IssueTemplate,
+
+    /// Empty if the code context is not configured.
+    pub code_snippets: Vec<CodeSnippet>,
 }
 
 impl<G: GitHost, L: Llm> ImproveFeature<G, L> {
@@ -43,29 +65,51 @@ impl<G: GitHost, L: Llm> ImproveFeature<G, L> {
         config: ImproveFeatureConfig,
         githost: G,
         llm: L,
-    ) -> Result<Self, G::Error, L::Error> {
+        comments: CommentStore,
+    ) -> Result<Self, G::Error, L::Error>
+    where
+        G: Clone,
+        L: Clone,
+    {
+        let code_index = config
+            .code_context
+            .map(|code_context| {
+                CodeIndex::build_from_config(code_context, githost.clone(), llm.clone())
+            })
+            .transpose()
+            .map_err(ImproveFeatureError::CodeIndexError)?;
+
         let agent = LlmAgent::build_from_config(llm, config.agent)
             .map_err(ImproveFeatureError::LlmAgentError)?;
 
-        Ok(Self::new(githost, agent))
+        Ok(Self::new(
+            githost,
+            agent,
+            config.events,
+            comments,
+            code_index,
+        ))
     }
 
-    pub fn new(githost: G, agent: LlmAgent<L, ImproveFeatureContext>) -> Self {
-        Self { githost, agent }
+    pub fn new(
+        githost: G,
+        agent: LlmAgent<L, ImproveFeatureContext>,
+        events: Vec<GitEventKindName>,
+        comments: CommentStore,
+        code_index: Option<CodeIndex<G, L>>,
+    ) -> Self {
+        Self {
+            githost,
+            agent,
+            events,
+            comments,
+            code_index,
+        }
     }
 
     pub async fn process_event(&self, event: &GitEvent) -> Result<(), G::Error, L::Error> {
-        if let GitEventKind::NewIssue = event.kind {
-            let issue = self
-                .githost
-                .get_issue(event.repo_id, event.issue_id)
-                .await?;
-
-            let author = self.githost.get_user(issue.author_user_id).await?;
-
-            let context = ImproveFeatureContext {
-                issue: (issue, author).into(),
-            };
+        if self.events.contains(&event.kind.name()) {
+            let context = self.make_context(event.repo_id, event.issue_id).await?;
 
             let ai_message = self
                 .agent
@@ -73,28 +117,108 @@ impl<G: GitHost, L: Llm> ImproveFeature<G, L> {
                 .await
                 .map_err(ImproveFeatureError::LlmAgentError)?;
 
-            if !ai_message.as_str().starts_with("EMPTY") {
-                self.githost
-                    .make_comment(event.repo_id, event.issue_id, ai_message)
+            let key = CommentKey {
+                repo_id: event.repo_id,
+                issue_id: event.issue_id,
+                feature: FeatureName::Improve,
+            };
+
+            // The comment is updated when the issue changes, and removed once the issue is good.
+            if ai_message
+                .as_str()
+                .trim()
+                .trim_matches('`')
+                .starts_with("EMPTY")
+            {
+                self.comments.retract_comment(&self.githost, &key).await?;
+            } else {
+                self.comments
+                    .upsert_comment(&self.githost, key, ai_message)
                     .await?;
             }
         }
 
         Ok(())
     }
+
+    /// Whether the code context is enabled, so there is an index to rebuild.
+    pub fn has_code_index(&self) -> bool {
+        self.code_index.is_some()
+    }
+
+    /// Index the code of the repository again. Does nothing if the code context is disabled.
+    pub async fn rebuild_index(&self, repo_id: RepoId) -> Result<(), G::Error, L::Error> {
+        if let Some(code_index) = &self.code_index {
+            code_index
+                .rebuild(repo_id)
+                .await
+                .map_err(ImproveFeatureError::CodeIndexError)?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn render(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+    ) -> Result<RenderedMessages, G::Error, L::Error> {
+        let context = self.make_context(repo_id, issue_id).await?;
+
+        self.agent
+            .render(&context)
+            .map_err(ImproveFeatureError::LlmAgentError)
+    }
+
+    async fn make_context(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+    ) -> Result<ImproveFeatureContext, G::Error, L::Error> {
+        let issue = self.githost.get_issue(repo_id, issue_id).await?;
+
+        let author = self.githost.get_user(issue.author_user_id).await?;
+
+        let code_snippets = match &self.code_index {
+            Some(code_index) => code_index
+                .retrieve(
+                    repo_id,
+                    &self
+                        .agent
+                        .redact(&format!("{}\n\n{}", issue.title, issue.body)),
+                )
+                .await
+                .map_err(ImproveFeatureError::CodeIndexError)?,
+
+            None => Vec::new(),
+        };
+
+        Ok(ImproveFeatureContext {
+            issue: (issue, author).into(),
+            code_snippets,
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use chrono::Utc;
     use mockall::predicate;
     use non_empty_string::NonEmptyString;
 
     use crate::{
-        bot::features::improve_feature::ImproveFeature,
+        bot::{
+            bot_features::FeatureName,
+            comment_store::{CommentKey, CommentStore},
+            features::improve_feature::ImproveFeature,
+        },
         githost::{
-            events::{GitEvent, GitEventKind},
+            events::{GitEvent, GitEventKind, GitEventKindName},
             host::MockGitHost,
-            model::{Issue, IssueId, RepoId, User, UserId},
+            model::{
+                AuthorAssociation, CommentId, Issue, IssueId, IssueState, RepoId, User, UserId,
+                UserKind,
+            },
         },
         llm::{
             agent::LlmAgent,
@@ -132,7 +256,7 @@ mod tests {
                 predicate::eq(git_event.issue_id),
                 predicate::eq(llm_output.clone()),
             )
-            .returning(|_, _, _| Ok(()));
+            .returning(|_, _, _| Ok(CommentId::from(1)));
 
         githost_mock
             .expect_get_user()
@@ -154,6 +278,9 @@ mod tests {
                 CompletionParameters::default(),
             )
             .unwrap(),
+            vec![GitEventKindName::NewIssue],
+            CommentStore::default(),
+            None,
         );
 
         feature.process_event(&git_event).await.unwrap();
@@ -197,11 +324,119 @@ mod tests {
                 CompletionParameters::default(),
             )
             .unwrap(),
+            vec![GitEventKindName::NewIssue],
+            CommentStore::default(),
+            None,
         );
 
         feature.process_event(&git_event).await.unwrap();
     }
 
+    #[tokio::test]
+    async fn updates_previous_comment() {
+        let (git_event, issue, author) = make_test_data();
+
+        let llm_output: NonEmptyString = "Please state your Rust version".try_into().unwrap();
+
+        let mut githost_mock = make_githost_mock(issue, author);
+
+        githost_mock
+            .expect_edit_comment()
+            .with(
+                predicate::eq(git_event.repo_id),
+                predicate::eq(CommentId::from(5)),
+                predicate::eq(llm_output.clone()),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        githost_mock.expect_make_comment().never();
+
+        let comments = CommentStore::default();
+        comments
+            .set(make_comment_key(&git_event), CommentId::from(5))
+            .await;
+
+        let feature = make_feature(githost_mock, llm_output, comments);
+
+        feature.process_event(&git_event).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn retracts_comment_on_empty() {
+        let (git_event, issue, author) = make_test_data();
+
+        let mut githost_mock = make_githost_mock(issue, author);
+
+        githost_mock
+            .expect_delete_comment()
+            .with(
+                predicate::eq(git_event.repo_id),
+                predicate::eq(CommentId::from(5)),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let comments = CommentStore::default();
+        comments
+            .set(make_comment_key(&git_event), CommentId::from(5))
+            .await;
+
+        let feature = make_feature(githost_mock, "EMPTY".try_into().unwrap(), comments.clone());
+
+        feature.process_event(&git_event).await.unwrap();
+
+        assert_eq!(comments.get(&make_comment_key(&git_event)).await, None);
+    }
+
+    fn make_githost_mock(issue: Issue, author: User) -> MockGitHost {
+        let mut githost_mock = MockGitHost::new();
+
+        githost_mock
+            .expect_get_issue()
+            .returning(move |_, _| Ok(issue.clone()));
+
+        githost_mock
+            .expect_get_user()
+            .returning(move |_| Ok(author.clone()));
+
+        githost_mock
+    }
+
+    fn make_feature(
+        githost_mock: MockGitHost,
+        llm_output: NonEmptyString,
+        comments: CommentStore,
+    ) -> ImproveFeature<MockGitHost, MockLlm> {
+        let mut llm_mock = MockLlm::new();
+
+        llm_mock
+            .expect_complete()
+            .returning(move |_, _, _| Ok(AiMessage::from(llm_output.clone())));
+
+        ImproveFeature::new(
+            githost_mock,
+            LlmAgent::build_raw(
+                llm_mock,
+                TEST_SYSTEM_MESSAGE.try_into().unwrap(),
+                TEST_USER_MESSAGE.try_into().unwrap(),
+                CompletionParameters::default(),
+            )
+            .unwrap(),
+            vec![GitEventKindName::NewIssue],
+            comments,
+            None,
+        )
+    }
+
+    fn make_comment_key(git_event: &GitEvent) -> CommentKey {
+        CommentKey {
+            repo_id: git_event.repo_id,
+            issue_id: git_event.issue_id,
+            feature: FeatureName::Improve,
+        }
+    }
+
     fn make_test_data() -> (GitEvent, Issue, User) {
         let repo_id = RepoId::from(1);
         let issue_id = IssueId::from(1 as usize);
@@ -218,11 +453,19 @@ mod tests {
             author_user_id: user_id,
             title: "Problem with your program".try_into().unwrap(),
             body: "Hi! I can't run your program".into(),
+            state: IssueState::Open,
+            labels: vec![],
+            assignees: vec![],
+            milestone: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            author_association: AuthorAssociation::None,
         };
 
         let author = User {
             id: user_id,
             nickname: "InAnYan".try_into().unwrap(),
+            kind: UserKind::User,
         };
 
         (git_event, issue, author)
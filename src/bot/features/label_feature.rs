@@ -2,11 +2,12 @@ use std::str::FromStr;
 
 use crate::{
     githost::{
-        events::{GitEvent, GitEventKind},
+        events::{GitEvent, GitEventKindName},
         host::GitHost,
+        model::{IssueId, RepoId},
     },
     llm::{
-        agent::{LlmAgent, LlmAgentConfig, LlmAgentError},
+        agent::{LlmAgent, LlmAgentConfig, LlmAgentError, RenderedMessages},
         llm_trait::Llm,
     },
 };
@@ -30,11 +31,15 @@ pub type Result<T, GE, LE> = std::result::Result<T, LabelFeatureError<GE, LE>>;
 #[derive(Deserialize)]
 pub struct LabelFeatureConfig {
     agent: LlmAgentConfig,
+
+    #[serde(default = "GitEventKindName::default_subscription")]
+    events: Vec<GitEventKindName>,
 }
 
 pub struct LabelFeature<G, L> {
     githost: G,
     agent: LlmAgent<L, LabelFeatureContext>,
+    events: Vec<GitEventKindName>,
 }
 
 #[derive(Serialize, Debug)]
@@ -52,28 +57,24 @@ impl<G: GitHost, L: Llm> LabelFeature<G, L> {
         let agent = LlmAgent::build_from_config(llm, config.agent)
             .map_err(LabelFeatureError::LlmAgentError)?;
 
-        Ok(Self::new(githost, agent))
+        Ok(Self::new(githost, agent, config.events))
     }
 
-    pub fn new(githost: G, agent: LlmAgent<L, LabelFeatureContext>) -> Self {
-        Self { githost, agent }
+    pub fn new(
+        githost: G,
+        agent: LlmAgent<L, LabelFeatureContext>,
+        events: Vec<GitEventKindName>,
+    ) -> Self {
+        Self {
+            githost,
+            agent,
+            events,
+        }
     }
 
     pub async fn process_event(&self, event: &GitEvent) -> Result<(), G::Error, L::Error> {
-        if let GitEventKind::NewIssue = event.kind {
-            let issue = self
-                .githost
-                .get_issue(event.repo_id, event.issue_id)
-                .await?;
-
-            let author = self.githost.get_user(issue.author_user_id).await?;
-
-            let labels = self.githost.get_repo_labels(event.repo_id).await?;
-
-            let context = LabelFeatureContext {
-                issue: (issue, author).into(),
-                labels: labels.into_iter().map(|l| l.into()).collect(),
-            };
+        if self.events.contains(&event.kind.name()) {
+            let context = self.make_context(event.repo_id, event.issue_id).await?;
 
             let ai_message = self
                 .agent
@@ -100,24 +101,58 @@ impl<G: GitHost, L: Llm> LabelFeature<G, L> {
 
         Ok(())
     }
+
+    pub async fn render(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+    ) -> Result<RenderedMessages, G::Error, L::Error> {
+        let context = self.make_context(repo_id, issue_id).await?;
+
+        self.agent
+            .render(&context)
+            .map_err(LabelFeatureError::LlmAgentError)
+    }
+
+    async fn make_context(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+    ) -> Result<LabelFeatureContext, G::Error, L::Error> {
+        let issue = self.githost.get_issue(repo_id, issue_id).await?;
+
+        let author = self.githost.get_user(issue.author_user_id).await?;
+
+        let labels = self.githost.get_repo_labels(repo_id).await?;
+
+        Ok(LabelFeatureContext {
+            issue: (issue, author).into(),
+            labels: labels.into_iter().map(|l| l.into()).collect(),
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
 
+    use chrono::Utc;
     use mockall::predicate;
     use non_empty_string::NonEmptyString;
 
     use crate::{
         bot::features::label_feature::LabelFeature,
         githost::{
-            events::{GitEvent, GitEventKind},
+            events::{GitEvent, GitEventKind, GitEventKindName},
             host::MockGitHost,
-            model::{Issue, IssueId, RepoId, User, UserId},
+            model::{
+                AuthorAssociation, Issue, IssueId, IssueState, Label, LabelId, RepoId, User,
+                UserId, UserKind,
+            },
         },
         llm::{
             agent::LlmAgent,
+            impls::recording_llm::{RecordingLlm, RecordingMode},
             llm_trait::{CompletionParameters, MockLlm},
             messages::AiMessage,
         },
@@ -182,6 +217,7 @@ mod tests {
                 CompletionParameters::default(),
             )
             .unwrap(),
+            vec![GitEventKindName::NewIssue],
         );
 
         feature.process_event(&git_event).await.unwrap();
@@ -230,6 +266,78 @@ mod tests {
                 CompletionParameters::default(),
             )
             .unwrap(),
+            vec![GitEventKindName::NewIssue],
+        );
+
+        feature.process_event(&git_event).await.unwrap();
+    }
+
+    /// Runs the example templates against an answer recorded in a cassette. Once the templates
+    /// change, the request is not found in the cassette, and the answer should be recorded again
+    /// with `RecordingMode::Record` and a real LLM.
+    #[tokio::test]
+    async fn labels_issue_with_example_templates() {
+        let (git_event, issue, author) = make_test_data();
+
+        let mut githost_mock = MockGitHost::new();
+
+        githost_mock
+            .expect_get_issue()
+            .returning(move |_, _| Ok(issue.clone()));
+
+        githost_mock
+            .expect_get_user()
+            .returning(move |_| Ok(author.clone()));
+
+        githost_mock.expect_get_repo_labels().returning(|_| {
+            Ok(vec![
+                Label {
+                    id: LabelId::from(1),
+                    name: "bug".try_into().unwrap(),
+                    description: "something isn't working".into(),
+                },
+                Label {
+                    id: LabelId::from(2),
+                    name: "enhancement".try_into().unwrap(),
+                    description: "the issue asks for a new feature".into(),
+                },
+            ])
+        });
+
+        githost_mock
+            .expect_assign_label()
+            .with(
+                predicate::eq(git_event.repo_id),
+                predicate::eq(git_event.issue_id),
+                predicate::eq(NonEmptyString::from_str("bug").unwrap()),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        // The wrapped LLM has no expectations, so the answer can come only from the cassette.
+        let llm = RecordingLlm::build_raw(
+            MockLlm::new(),
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/cassettes/label_feature.json"),
+            RecordingMode::Replay,
+        )
+        .await
+        .unwrap();
+
+        let feature = LabelFeature::new(
+            githost_mock,
+            LlmAgent::build_raw(
+                llm,
+                include_str!("../../../examples/rust-gib/templates/label/system_message.tmpl")
+                    .try_into()
+                    .unwrap(),
+                include_str!("../../../examples/rust-gib/templates/label/user_message.tmpl")
+                    .try_into()
+                    .unwrap(),
+                CompletionParameters { temperature: 0.7 },
+            )
+            .unwrap(),
+            vec![GitEventKindName::NewIssue],
         );
 
         feature.process_event(&git_event).await.unwrap();
@@ -251,11 +359,19 @@ mod tests {
             author_user_id: user_id,
             title: "Problem with your program".try_into().unwrap(),
             body: "Hi! I can't run your program".into(),
+            state: IssueState::Open,
+            labels: vec![],
+            assignees: vec![],
+            milestone: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            author_association: AuthorAssociation::None,
         };
 
         let author = User {
             id: user_id,
             nickname: "InAnYan".try_into().unwrap(),
+            kind: UserKind::User,
         };
 
         (git_event, issue, author)
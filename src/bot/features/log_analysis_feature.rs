@@ -0,0 +1,513 @@
+use log::{info, warn};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bot::{
+        bot_features::FeatureName,
+        comment_store::{CommentKey, CommentStore},
+    },
+    githost::{
+        events::{GitEvent, GitEventKindName},
+        host::GitHost,
+        model::{Issue, IssueId, RepoId},
+    },
+    llm::{
+        agent::{LlmAgent, LlmAgentConfig, LlmAgentError, RenderedMessages},
+        llm_trait::Llm,
+    },
+    utils::log_extraction::{LogExtractor, LogReport, Suspect},
+};
+
+use super::templates::IssueTemplate;
+
+#[derive(Debug, thiserror::Error)]
+pub enum LogAnalysisFeatureError<GE, LE> {
+    #[error("error from LLM agent")]
+    LlmAgentError(#[source] LlmAgentError<LE>),
+
+    #[error("unable to perform Git host action")]
+    GitHostError(#[from] GE),
+
+    #[error("attachment pattern is not a valid regular expression")]
+    AttachmentPatternError(#[source] regex::Error),
+}
+
+pub type Result<T, GE, LE> = std::result::Result<T, LogAnalysisFeatureError<GE, LE>>;
+
+#[derive(Deserialize)]
+pub struct LogAnalysisFeatureConfig {
+    agent: LlmAgentConfig,
+
+    #[serde(default = "default_events")]
+    events: Vec<GitEventKindName>,
+
+    /// Download the log files that are attached to the issue.
+    #[serde(default = "default_fetch_attachments")]
+    fetch_attachments: bool,
+
+    /// Links in the issue body that are treated as log attachments.
+    #[serde(default = "default_attachment_pattern")]
+    attachment_pattern: String,
+
+    /// Only the beginning of a bigger attachment is analyzed.
+    #[serde(default = "default_max_attachment_bytes")]
+    max_attachment_bytes: usize,
+}
+
+fn default_events() -> Vec<GitEventKindName> {
+    vec![GitEventKindName::NewIssue, GitEventKindName::IssueEdited]
+}
+
+fn default_fetch_attachments() -> bool {
+    true
+}
+
+fn default_attachment_pattern() -> String {
+    r"https://github\.com/[\w./-]+/files/\d+/[\w.-]+\.(?:log|txt)".into()
+}
+
+fn default_max_attachment_bytes() -> usize {
+    1_000_000
+}
+
+pub struct AttachmentOptions {
+    pub fetch: bool,
+    pub pattern: Regex,
+    pub max_bytes: usize,
+}
+
+pub struct LogAnalysisFeature<G, L> {
+    githost: G,
+    agent: LlmAgent<L, LogAnalysisFeatureContext>,
+    events: Vec<GitEventKindName>,
+    attachments: AttachmentOptions,
+    comments: CommentStore,
+    extractor: LogExtractor,
+    client: reqwest::Client,
+}
+
+#[derive(Serialize, Debug)]
+pub struct LogAnalysisFeatureContext {
+    pub issue: IssueTemplate,
+    pub logs: LogReport,
+
+    /// Files of the project where the problem probably is, most likely first.
+    pub suspects: Vec<Suspect>,
+}
+
+impl<G: GitHost, L: Llm> LogAnalysisFeature<G, L> {
+    pub async fn build_from_config(
+        config: LogAnalysisFeatureConfig,
+        githost: G,
+        llm: L,
+        comments: CommentStore,
+    ) -> Result<Self, G::Error, L::Error> {
+        let agent = LlmAgent::build_from_config(llm, config.agent)
+            .map_err(LogAnalysisFeatureError::LlmAgentError)?;
+
+        Ok(Self::new(
+            githost,
+            agent,
+            config.events,
+            AttachmentOptions {
+                fetch: config.fetch_attachments,
+                pattern: Regex::new(&config.attachment_pattern)
+                    .map_err(LogAnalysisFeatureError::AttachmentPatternError)?,
+                max_bytes: config.max_attachment_bytes,
+            },
+            comments,
+        ))
+    }
+
+    pub fn new(
+        githost: G,
+        agent: LlmAgent<L, LogAnalysisFeatureContext>,
+        events: Vec<GitEventKindName>,
+        attachments: AttachmentOptions,
+        comments: CommentStore,
+    ) -> Self {
+        Self {
+            githost,
+            agent,
+            events,
+            attachments,
+            comments,
+            extractor: LogExtractor::default(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub async fn process_event(&self, event: &GitEvent) -> Result<(), G::Error, L::Error> {
+        if !self.events.contains(&event.kind.name()) {
+            return Ok(());
+        }
+
+        let issue = self
+            .githost
+            .get_issue(event.repo_id, event.issue_id)
+            .await?;
+
+        let context = self.make_context(issue).await?;
+
+        // Most issues have no logs, so the LLM is not asked about them.
+        if !context.logs.has_failures() {
+            return Ok(());
+        }
+
+        info!(
+            "Issue {} has {} panics, {} backtraces and {} error lines",
+            *event.issue_id,
+            context.logs.panics.len(),
+            context.logs.backtraces.len(),
+            context.logs.errors.len()
+        );
+
+        let ai_message = self
+            .agent
+            .process(&context)
+            .await
+            .map_err(LogAnalysisFeatureError::LlmAgentError)?;
+
+        let key = CommentKey {
+            repo_id: event.repo_id,
+            issue_id: event.issue_id,
+            feature: FeatureName::LogAnalysis,
+        };
+
+        if ai_message
+            .as_str()
+            .trim()
+            .trim_matches('`')
+            .starts_with("EMPTY")
+        {
+            self.comments.retract_comment(&self.githost, &key).await?;
+        } else {
+            self.comments
+                .upsert_comment(&self.githost, key, ai_message)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn render(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+    ) -> Result<RenderedMessages, G::Error, L::Error> {
+        let issue = self.githost.get_issue(repo_id, issue_id).await?;
+
+        let context = self.make_context(issue).await?;
+
+        self.agent
+            .render(&context)
+            .map_err(LogAnalysisFeatureError::LlmAgentError)
+    }
+
+    async fn make_context(
+        &self,
+        issue: Issue,
+    ) -> Result<LogAnalysisFeatureContext, G::Error, L::Error> {
+        let mut logs = self.extractor.extract(&issue.body);
+
+        if self.attachments.fetch {
+            for link in self.attachments.pattern.find_iter(&issue.body) {
+                // A broken attachment shouldn't stop the analysis of the rest.
+                match self.fetch_attachment(link.as_str()).await {
+                    Ok(text) => logs.extend(self.extractor.extract(&text)),
+                    Err(e) => warn!("Unable to download attachment {}: {}", link.as_str(), e),
+                }
+            }
+        }
+
+        let suspects = logs.suspects();
+
+        let author = self.githost.get_user(issue.author_user_id).await?;
+
+        Ok(LogAnalysisFeatureContext {
+            issue: (issue, author).into(),
+            logs,
+            suspects,
+        })
+    }
+
+    /// Download the beginning of the attachment. The rest of a bigger attachment is never
+    /// downloaded.
+    async fn fetch_attachment(&self, url: &str) -> std::result::Result<String, reqwest::Error> {
+        let max_bytes = self.attachments.max_bytes;
+
+        let mut response = self.client.get(url).send().await?.error_for_status()?;
+
+        let length = response.content_length();
+
+        if let Some(length) = length.filter(|length| *length > max_bytes as u64) {
+            info!(
+                "Attachment {} has {} bytes, only the first {} are analyzed",
+                url, length, max_bytes
+            );
+        }
+
+        let mut bytes =
+            Vec::with_capacity(length.map_or(0, |length| length.min(max_bytes as u64) as usize));
+
+        while bytes.len() < max_bytes {
+            let Some(chunk) = response.chunk().await? else {
+                break;
+            };
+
+            let end = chunk.len().min(max_bytes - bytes.len());
+            bytes.extend_from_slice(&chunk[..end]);
+        }
+
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chrono::{DateTime, Utc};
+    use mockall::predicate;
+    use non_empty_string::NonEmptyString;
+    use regex::Regex;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use crate::{
+        bot::{
+            comment_store::CommentStore,
+            features::log_analysis_feature::{AttachmentOptions, LogAnalysisFeature},
+        },
+        githost::{
+            events::{GitEvent, GitEventKind, GitEventKindName},
+            host::MockGitHost,
+            model::{
+                AuthorAssociation, CommentId, Issue, IssueId, IssueState, RepoId, User, UserId,
+                UserKind,
+            },
+        },
+        llm::{
+            agent::LlmAgent,
+            llm_trait::{CompletionParameters, MockLlm},
+            messages::AiMessage,
+        },
+    };
+
+    const TEST_SYSTEM_MESSAGE: &str = "You are a bot that analyzes logs.";
+    const TEST_USER_MESSAGE: &str = "Panic: {{ context.logs.panics.0.message }}. \
+                                     Suspects:{% for suspect in context.suspects %} \
+                                     {{ suspect.file }}:{{ suspect.line }}{% endfor %}";
+
+    const ATTACHED_LOG: &str = "\
+thread 'main' panicked at src/bot/bot.rs:42:9:
+called `Option::unwrap()` on a `None` value
+stack backtrace:
+   0: core::option::unwrap_failed
+             at /rustc/eeb90cda1/library/core/src/option.rs:2015:5
+   1: gib::bot::features::label_feature::LabelFeature::process_event
+             at ./src/bot/features/label_feature.rs:120:13
+";
+
+    #[tokio::test]
+    async fn analyzes_attached_log() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/files/1/output.log"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(ATTACHED_LOG))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let body = format!(
+            "The bot crashes on every new issue. Log: {}/files/1/output.log",
+            server.uri()
+        );
+
+        let feature = make_feature(
+            make_githost_mock(&body),
+            MockLlm::new(),
+            &format!(r"{}/files/\d+/\S+\.log", regex::escape(&server.uri())),
+        );
+
+        let rendered = feature
+            .render(RepoId::from(1), IssueId::from(1 as usize))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            rendered.user_message.as_str(),
+            "Panic: called `Option::unwrap()` on a `None` value. Suspects: src/bot/bot.rs:42 \
+             src/bot/features/label_feature.rs:120"
+        );
+    }
+
+    #[tokio::test]
+    async fn reads_only_beginning_of_big_attachment() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/files/1/output.log"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(ATTACHED_LOG))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut feature = make_feature(make_githost_mock(""), MockLlm::new(), "$^");
+        feature.attachments.max_bytes = 20;
+
+        let text = feature
+            .fetch_attachment(&format!("{}/files/1/output.log", server.uri()))
+            .await
+            .unwrap();
+
+        assert_eq!(text, "thread 'main' panick");
+    }
+
+    #[tokio::test]
+    async fn comments_analysis() {
+        let mut githost_mock = make_githost_mock(ATTACHED_LOG);
+
+        let analysis = non_empty("The problem is probably in `src/bot/bot.rs`.");
+
+        githost_mock
+            .expect_make_comment()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(IssueId::from(1 as usize)),
+                predicate::eq(analysis.clone()),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(CommentId::from(1)));
+
+        let mut llm_mock = MockLlm::new();
+
+        llm_mock
+            .expect_complete()
+            .times(1)
+            .returning(move |_, _, _| Ok(AiMessage::from(analysis.clone())));
+
+        let feature = make_feature(githost_mock, llm_mock, "$^");
+
+        feature.process_event(&make_event()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn ignores_empty_analysis() {
+        let mut llm_mock = MockLlm::new();
+
+        // Neither a comment is made, nor a retracted one is looked for on the Git host.
+        llm_mock
+            .expect_complete()
+            .times(1)
+            .returning(|_, _, _| Ok(AiMessage::from(non_empty("\n`EMPTY`\n"))));
+
+        let feature = make_feature(make_githost_mock(ATTACHED_LOG), llm_mock, "$^");
+
+        feature.process_event(&make_event()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn skips_issue_without_logs() {
+        let feature = make_feature(
+            make_githost_mock("Please add a dark theme."),
+            MockLlm::new(),
+            "$^",
+        );
+
+        feature.process_event(&make_event()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn skips_issue_with_only_versions() {
+        let feature = make_feature(
+            make_githost_mock("The theme is too bright.\n\nrustc 1.82.0, gib version 0.3.1"),
+            MockLlm::new(),
+            "$^",
+        );
+
+        feature.process_event(&make_event()).await.unwrap();
+    }
+
+    fn make_feature(
+        githost_mock: MockGitHost,
+        llm_mock: MockLlm,
+        attachment_pattern: &str,
+    ) -> LogAnalysisFeature<MockGitHost, MockLlm> {
+        LogAnalysisFeature::new(
+            githost_mock,
+            LlmAgent::build_raw(
+                llm_mock,
+                TEST_SYSTEM_MESSAGE.try_into().unwrap(),
+                TEST_USER_MESSAGE.try_into().unwrap(),
+                CompletionParameters::default(),
+            )
+            .unwrap(),
+            vec![GitEventKindName::NewIssue],
+            AttachmentOptions {
+                fetch: true,
+                pattern: Regex::new(attachment_pattern).unwrap(),
+                max_bytes: 1_000_000,
+            },
+            CommentStore::default(),
+        )
+    }
+
+    fn make_githost_mock(body: &str) -> MockGitHost {
+        let mut githost_mock = MockGitHost::new();
+
+        let body = body.to_string();
+
+        githost_mock
+            .expect_get_issue()
+            .returning(move |_, issue_id| {
+                let created_at = make_now();
+
+                Ok(Issue {
+                    id: issue_id,
+                    author_user_id: UserId::from(2),
+                    title: non_empty("The bot crashes"),
+                    body: body.clone(),
+                    state: IssueState::Open,
+                    labels: vec![],
+                    assignees: vec![],
+                    milestone: None,
+                    created_at,
+                    updated_at: created_at,
+                    author_association: AuthorAssociation::None,
+                })
+            });
+
+        githost_mock.expect_get_user().returning(|id| {
+            Ok(User {
+                id,
+                nickname: non_empty("reporter"),
+                kind: UserKind::User,
+            })
+        });
+
+        githost_mock
+    }
+
+    fn make_event() -> GitEvent {
+        GitEvent {
+            repo_id: RepoId::from(1),
+            issue_id: IssueId::from(1 as usize),
+            kind: GitEventKind::NewIssue,
+        }
+    }
+
+    fn make_now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-10-01T12:00:00Z")
+            .unwrap()
+            .to_utc()
+    }
+
+    fn non_empty(s: &str) -> NonEmptyString {
+        NonEmptyString::from_str(s).unwrap()
+    }
+}
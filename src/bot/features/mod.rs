@@ -1,3 +1,15 @@
+pub mod digest_feature;
+pub mod docs_answer_feature;
 pub mod improve_feature;
 pub mod label_feature;
+pub mod log_analysis_feature;
+pub mod moderation_feature;
+pub mod release_notes_feature;
+pub mod review_feature;
+pub mod security_feature;
+pub mod spam_feature;
+pub mod stale_feature;
+pub mod summary_feature;
+pub mod template_compliance_feature;
 pub mod templates;
+pub mod triage_assign_feature;
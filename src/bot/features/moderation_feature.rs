@@ -0,0 +1,690 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use non_empty_string::NonEmptyString;
+use serde::{Deserialize, Serialize};
+use tokio::{fs::OpenOptions, io::AsyncWriteExt};
+
+use crate::{
+    githost::{
+        events::{GitEvent, GitEventKind, GitEventKindName},
+        host::GitHost,
+        model::{
+            AuthorAssociation, CommentId, HideReason, IssueId, LockReason, RepoId, User, UserKind,
+        },
+    },
+    llm::{
+        agent::{LlmAgent, LlmAgentConfig, LlmAgentError, RenderedMessages},
+        llm_trait::{Llm, ModerationScores},
+    },
+};
+
+use super::templates::AuthorTemplate;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ModerationFeatureError<GE, LE> {
+    #[error("error from LLM agent")]
+    LlmAgentError(#[source] LlmAgentError<LE>),
+
+    #[error("unable to perform Git host action")]
+    GitHostError(#[from] GE),
+
+    #[error("LLM provider has no moderation endpoint")]
+    ModerationNotSupported,
+
+    #[error("unable to write to the audit log")]
+    AuditLogError(#[source] std::io::Error),
+}
+
+pub type Result<T, GE, LE> = std::result::Result<T, ModerationFeatureError<GE, LE>>;
+
+/// Who gives the scores to the moderation categories.
+#[derive(Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationClassifier {
+    /// The LLM agent answers with a JSON object of category scores.
+    #[default]
+    Llm,
+
+    /// The moderation endpoint of the LLM provider. Category names should be the ones of the
+    /// provider, e.g. `harassment`.
+    Provider,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationAction {
+    /// Assign a label to the issue.
+    Label { name: NonEmptyString },
+
+    /// Hide the comment. Issues cannot be hidden, so the action is skipped for them.
+    Hide {
+        #[serde(default = "default_hide_reason")]
+        reason: HideReason,
+    },
+
+    /// Lock the conversation of the issue.
+    Lock {
+        #[serde(default)]
+        reason: Option<LockReason>,
+    },
+
+    /// Write a comment that mentions the maintainers.
+    Notify,
+}
+
+fn default_hide_reason() -> HideReason {
+    HideReason::Abuse
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ModerationCategory {
+    pub name: NonEmptyString,
+
+    /// Explanation for the LLM of what content belongs to the category.
+    #[serde(default)]
+    pub description: String,
+
+    /// Minimal score, from 0.0 to 1.0, at which the actions are applied.
+    pub threshold: f32,
+
+    pub actions: Vec<ModerationAction>,
+}
+
+#[derive(Deserialize)]
+pub struct ModerationFeatureConfig {
+    agent: LlmAgentConfig,
+
+    #[serde(default = "default_events")]
+    events: Vec<GitEventKindName>,
+
+    #[serde(default)]
+    classifier: ModerationClassifier,
+
+    categories: Vec<ModerationCategory>,
+
+    /// Nicknames of the people to mention in the `notify` action, without the leading `@`.
+    #[serde(default)]
+    maintainers: Vec<NonEmptyString>,
+
+    /// JSON Lines file where every flagged issue or comment is recorded for appeals.
+    #[serde(default)]
+    audit_log_path: Option<PathBuf>,
+}
+
+fn default_events() -> Vec<GitEventKindName> {
+    vec![GitEventKindName::NewIssue, GitEventKindName::NewComment]
+}
+
+pub struct ModerationOptions {
+    pub classifier: ModerationClassifier,
+    pub categories: Vec<ModerationCategory>,
+    pub maintainers: Vec<NonEmptyString>,
+    pub audit_log_path: Option<PathBuf>,
+}
+
+pub struct ModerationFeature<G, L> {
+    githost: G,
+    agent: LlmAgent<L, ModerationFeatureContext>,
+    events: Vec<GitEventKindName>,
+    options: ModerationOptions,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ModerationFeatureContext {
+    pub text: String,
+    pub author: AuthorTemplate,
+    pub categories: Vec<CategoryTemplate>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CategoryTemplate {
+    pub name: NonEmptyString,
+    pub description: String, // Can be empty.
+}
+
+/// An issue or a comment that is being moderated.
+struct Subject {
+    repo_id: RepoId,
+    issue_id: IssueId,
+    comment_id: Option<CommentId>,
+    text: String,
+    author: User,
+    author_association: AuthorAssociation,
+}
+
+#[derive(Serialize, Debug)]
+struct AuditRecord<'a> {
+    time: DateTime<Utc>,
+    repo_id: usize,
+    issue_id: usize,
+    comment_id: Option<usize>,
+    author: &'a NonEmptyString,
+    text: &'a str,
+    categories: Vec<FlaggedCategory<'a>>,
+    actions: &'a [ModerationAction],
+}
+
+#[derive(Serialize, Debug)]
+struct FlaggedCategory<'a> {
+    name: &'a NonEmptyString,
+    score: f32,
+    threshold: f32,
+}
+
+impl<G: GitHost, L: Llm> ModerationFeature<G, L> {
+    pub async fn build_from_config(
+        config: ModerationFeatureConfig,
+        githost: G,
+        llm: L,
+    ) -> Result<Self, G::Error, L::Error> {
+        let agent = LlmAgent::build_from_config(llm, config.agent)
+            .map_err(ModerationFeatureError::LlmAgentError)?;
+
+        Ok(Self::new(
+            githost,
+            agent,
+            config.events,
+            ModerationOptions {
+                classifier: config.classifier,
+                categories: config.categories,
+                maintainers: config.maintainers,
+                audit_log_path: config.audit_log_path,
+            },
+        ))
+    }
+
+    pub fn new(
+        githost: G,
+        agent: LlmAgent<L, ModerationFeatureContext>,
+        events: Vec<GitEventKindName>,
+        options: ModerationOptions,
+    ) -> Self {
+        Self {
+            githost,
+            agent,
+            events,
+            options,
+        }
+    }
+
+    /// Returns whether the comment was hidden or the issue was locked, so that the other features
+    /// should leave them alone.
+    pub async fn process_event(&self, event: &GitEvent) -> Result<bool, G::Error, L::Error> {
+        if !self.events.contains(&event.kind.name()) {
+            return Ok(false);
+        }
+
+        let subject = match event.kind {
+            GitEventKind::NewIssue => self.issue_subject(event.repo_id, event.issue_id).await?,
+
+            GitEventKind::NewComment(comment_id) => {
+                let comment = self
+                    .githost
+                    .get_comment(event.repo_id, event.issue_id, comment_id)
+                    .await?;
+
+                Subject {
+                    repo_id: event.repo_id,
+                    issue_id: event.issue_id,
+                    comment_id: Some(comment_id),
+                    text: comment.body.to_string(),
+                    author: self.githost.get_user(comment.user_id).await?,
+                    author_association: comment.author_association,
+                }
+            }
+
+            _ => return Ok(false),
+        };
+
+        // Maintainers and bots (including this one) are trusted.
+        if matches!(
+            subject.author_association,
+            AuthorAssociation::Owner | AuthorAssociation::Member | AuthorAssociation::Collaborator
+        ) || subject.author.kind == UserKind::Bot
+        {
+            return Ok(false);
+        }
+
+        let scores = self.classify(&subject).await?;
+
+        let flagged: Vec<(&ModerationCategory, f32)> = self
+            .options
+            .categories
+            .iter()
+            .filter_map(|category| {
+                scores
+                    .get(category.name.as_str())
+                    .filter(|score| **score >= category.threshold)
+                    .map(|score| (category, *score))
+            })
+            .collect();
+
+        if flagged.is_empty() {
+            return Ok(false);
+        }
+
+        // Several categories may ask for the same action, but it is applied only once.
+        let mut actions: Vec<ModerationAction> = Vec::new();
+
+        for (category, _) in &flagged {
+            for action in &category.actions {
+                if !actions.contains(action) {
+                    actions.push(action.clone());
+                }
+            }
+        }
+
+        let category_names: Vec<&str> = flagged
+            .iter()
+            .map(|(category, _)| category.name.as_str())
+            .collect();
+
+        info!(
+            "Issue {} comment {:?} is flagged as {}",
+            *subject.issue_id,
+            subject.comment_id.map(|id| *id),
+            category_names.join(", ")
+        );
+
+        // The record is written first, so that a failed action still leaves a trace.
+        self.write_audit_record(&subject, &flagged, &actions)
+            .await?;
+
+        for action in &actions {
+            self.apply_action(&subject, action, &category_names).await?;
+        }
+
+        Ok(actions.iter().any(|action| match action {
+            ModerationAction::Hide { .. } => subject.comment_id.is_some(),
+            ModerationAction::Lock { .. } => true,
+            _ => false,
+        }))
+    }
+
+    pub async fn render(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+    ) -> Result<RenderedMessages, G::Error, L::Error> {
+        let subject = self.issue_subject(repo_id, issue_id).await?;
+
+        self.agent
+            .render(&self.make_context(&subject))
+            .map_err(ModerationFeatureError::LlmAgentError)
+    }
+
+    async fn issue_subject(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+    ) -> Result<Subject, G::Error, L::Error> {
+        let issue = self.githost.get_issue(repo_id, issue_id).await?;
+
+        Ok(Subject {
+            repo_id,
+            issue_id,
+            comment_id: None,
+            text: format!("{}\n\n{}", issue.title, issue.body),
+            author: self.githost.get_user(issue.author_user_id).await?,
+            author_association: issue.author_association,
+        })
+    }
+
+    fn make_context(&self, subject: &Subject) -> ModerationFeatureContext {
+        ModerationFeatureContext {
+            text: subject.text.clone(),
+            author: subject.author.clone().into(),
+            categories: self
+                .options
+                .categories
+                .iter()
+                .map(|category| CategoryTemplate {
+                    name: category.name.clone(),
+                    description: category.description.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    async fn classify(&self, subject: &Subject) -> Result<ModerationScores, G::Error, L::Error> {
+        match self.options.classifier {
+            ModerationClassifier::Llm => {
+                let ai_message = self
+                    .agent
+                    .process(&self.make_context(subject))
+                    .await
+                    .map_err(ModerationFeatureError::LlmAgentError)?;
+
+                Ok(parse_scores(ai_message.as_str()))
+            }
+
+            ModerationClassifier::Provider => self
+                .agent
+                .moderate(&subject.text)
+                .await
+                .map_err(ModerationFeatureError::LlmAgentError)?
+                .ok_or(ModerationFeatureError::ModerationNotSupported),
+        }
+    }
+
+    async fn apply_action(
+        &self,
+        subject: &Subject,
+        action: &ModerationAction,
+        category_names: &[&str],
+    ) -> Result<(), G::Error, L::Error> {
+        match action {
+            ModerationAction::Label { name } => {
+                self.githost
+                    .assign_label(subject.repo_id, subject.issue_id, name.clone())
+                    .await?
+            }
+
+            ModerationAction::Hide { reason } => match subject.comment_id {
+                Some(comment_id) => {
+                    self.githost
+                        .hide_comment(subject.repo_id, comment_id, *reason)
+                        .await?
+                }
+
+                None => warn!("Issues cannot be hidden. Skipping."),
+            },
+
+            ModerationAction::Lock { reason } => {
+                self.githost
+                    .lock_issue(subject.repo_id, subject.issue_id, *reason)
+                    .await?
+            }
+
+            ModerationAction::Notify => {
+                if self.options.maintainers.is_empty() {
+                    warn!("There are no maintainers to notify");
+                    return Ok(());
+                }
+
+                let mentions = self
+                    .options
+                    .maintainers
+                    .iter()
+                    .map(|maintainer| format!("@{}", maintainer))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                let what = if subject.comment_id.is_some() {
+                    "A comment in this thread"
+                } else {
+                    "This issue"
+                };
+
+                let message = format!(
+                    "{} {} was flagged by moderation as `{}`. Could you take a look?",
+                    mentions,
+                    what,
+                    category_names.join("`, `")
+                );
+
+                self.githost
+                    .make_comment(
+                        subject.repo_id,
+                        subject.issue_id,
+                        message
+                            .try_into()
+                            .expect("message always contains mentions"),
+                    )
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn write_audit_record(
+        &self,
+        subject: &Subject,
+        flagged: &[(&ModerationCategory, f32)],
+        actions: &[ModerationAction],
+    ) -> Result<(), G::Error, L::Error> {
+        let Some(path) = &self.options.audit_log_path else {
+            return Ok(());
+        };
+
+        let record = AuditRecord {
+            time: Utc::now(),
+            repo_id: *subject.repo_id,
+            issue_id: *subject.issue_id,
+            comment_id: subject.comment_id.map(|id| *id),
+            author: &subject.author.nickname,
+            text: &subject.text,
+            categories: flagged
+                .iter()
+                .map(|(category, score)| FlaggedCategory {
+                    name: &category.name,
+                    score: *score,
+                    threshold: category.threshold,
+                })
+                .collect(),
+            actions,
+        };
+
+        let mut line = serde_json::to_string(&record).expect("audit record is always serializable");
+        line.push('\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .map_err(ModerationFeatureError::AuditLogError)?;
+
+        file.write_all(line.as_bytes())
+            .await
+            .map_err(ModerationFeatureError::AuditLogError)
+    }
+}
+
+/// Parse the LLM answer. The LLM should answer with a JSON object that maps category names to
+/// scores.
+fn parse_scores(ai_message: &str) -> ModerationScores {
+    let text = ai_message.trim();
+
+    // LLMs like to wrap JSON into Markdown code blocks.
+    let text = text
+        .strip_prefix("```json")
+        .or_else(|| text.strip_prefix("```"))
+        .and_then(|text| text.strip_suffix("```"))
+        .unwrap_or(text);
+
+    match serde_json::from_str(text) {
+        Ok(scores) => scores,
+        Err(e) => {
+            error!("AI has generated malformed result: {:?}. Skipping.", e);
+            HashMap::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, str::FromStr};
+
+    use chrono::{DateTime, Utc};
+    use mockall::predicate;
+    use non_empty_string::NonEmptyString;
+
+    use crate::{
+        bot::features::moderation_feature::{
+            default_events, parse_scores, ModerationAction, ModerationCategory,
+            ModerationClassifier, ModerationFeature, ModerationOptions,
+        },
+        githost::{
+            events::{GitEvent, GitEventKind},
+            host::MockGitHost,
+            model::{
+                AuthorAssociation, Comment, CommentId, HideReason, IssueId, RepoId, User, UserId,
+                UserKind,
+            },
+        },
+        llm::{
+            agent::LlmAgent,
+            llm_trait::{CompletionParameters, MockLlm},
+            messages::AiMessage,
+        },
+    };
+
+    const TEST_SYSTEM_MESSAGE: &str = "You are a bot that moderates comments.";
+    const TEST_USER_MESSAGE: &str = "The text is: {{ context.text }}";
+
+    #[tokio::test]
+    async fn hides_and_labels_flagged_comment() {
+        let mut githost_mock = make_githost_mock(AuthorAssociation::None);
+
+        githost_mock
+            .expect_hide_comment()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(CommentId::from(1)),
+                predicate::eq(HideReason::Abuse),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        githost_mock
+            .expect_assign_label()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(IssueId::from(1 as usize)),
+                predicate::eq(non_empty("moderated")),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        let mut llm_mock = MockLlm::new();
+
+        llm_mock.expect_complete().times(1).returning(|_, _, _| {
+            Ok(AiMessage::from(non_empty(
+                "```json\n{\"harassment\": 0.9, \"off-topic\": 0.1}\n```",
+            )))
+        });
+
+        let feature = make_feature(githost_mock, llm_mock, ModerationClassifier::Llm);
+
+        assert!(feature.process_event(&make_comment_event()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn ignores_scores_below_threshold() {
+        let githost_mock = make_githost_mock(AuthorAssociation::FirstTimer);
+
+        let mut llm_mock = MockLlm::new();
+
+        llm_mock
+            .expect_moderate()
+            .times(1)
+            .returning(|_| Ok(Some(HashMap::from([("harassment".to_string(), 0.3)]))));
+
+        let feature = make_feature(githost_mock, llm_mock, ModerationClassifier::Provider);
+
+        feature.process_event(&make_comment_event()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn trusts_maintainers() {
+        let githost_mock = make_githost_mock(AuthorAssociation::Member);
+
+        let llm_mock = MockLlm::new();
+
+        let feature = make_feature(githost_mock, llm_mock, ModerationClassifier::Llm);
+
+        feature.process_event(&make_comment_event()).await.unwrap();
+    }
+
+    #[test]
+    fn parses_malformed_scores_as_empty() {
+        assert!(parse_scores("I think it is fine").is_empty());
+    }
+
+    fn make_feature(
+        githost_mock: MockGitHost,
+        llm_mock: MockLlm,
+        classifier: ModerationClassifier,
+    ) -> ModerationFeature<MockGitHost, MockLlm> {
+        ModerationFeature::new(
+            githost_mock,
+            LlmAgent::build_raw(
+                llm_mock,
+                TEST_SYSTEM_MESSAGE.try_into().unwrap(),
+                TEST_USER_MESSAGE.try_into().unwrap(),
+                CompletionParameters::default(),
+            )
+            .unwrap(),
+            default_events(),
+            ModerationOptions {
+                classifier,
+                categories: vec![ModerationCategory {
+                    name: non_empty("harassment"),
+                    description: String::new(),
+                    threshold: 0.8,
+                    actions: vec![
+                        ModerationAction::Hide {
+                            reason: HideReason::Abuse,
+                        },
+                        ModerationAction::Label {
+                            name: non_empty("moderated"),
+                        },
+                    ],
+                }],
+                maintainers: vec![],
+                audit_log_path: None,
+            },
+        )
+    }
+
+    fn make_githost_mock(author_association: AuthorAssociation) -> MockGitHost {
+        let mut githost_mock = MockGitHost::new();
+
+        githost_mock
+            .expect_get_comment()
+            .returning(move |_, _, comment_id| {
+                let created_at = make_now();
+
+                Ok(Comment {
+                    id: comment_id,
+                    user_id: UserId::from(2),
+                    body: non_empty("You are an idiot"),
+                    created_at,
+                    updated_at: created_at,
+                    author_association,
+                })
+            });
+
+        githost_mock.expect_get_user().returning(|id| {
+            Ok(User {
+                id,
+                nickname: non_empty("troll"),
+                kind: UserKind::User,
+            })
+        });
+
+        githost_mock
+    }
+
+    fn make_comment_event() -> GitEvent {
+        GitEvent {
+            repo_id: RepoId::from(1),
+            issue_id: IssueId::from(1 as usize),
+            kind: GitEventKind::NewComment(CommentId::from(1)),
+        }
+    }
+
+    fn make_now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-10-01T12:00:00Z")
+            .unwrap()
+            .to_utc()
+    }
+
+    fn non_empty(s: &str) -> NonEmptyString {
+        NonEmptyString::from_str(s).unwrap()
+    }
+}
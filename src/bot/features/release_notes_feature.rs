@@ -0,0 +1,379 @@
+use std::{collections::HashSet, str::FromStr};
+
+use chrono::NaiveDate;
+use log::info;
+use non_empty_string::NonEmptyString;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    githost::{
+        host::GitHost,
+        model::{Issue, IssueId, RepoId},
+    },
+    llm::{
+        agent::{LlmAgent, LlmAgentConfig, LlmAgentError, RenderedMessages},
+        llm_trait::Llm,
+    },
+};
+
+use super::templates::IssueTemplate;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReleaseNotesFeatureError<GE, LE> {
+    #[error("error from LLM agent")]
+    LlmAgentError(#[source] LlmAgentError<LE>),
+
+    #[error("unable to perform Git host action")]
+    GitHostError(#[from] GE),
+}
+
+pub type Result<T, GE, LE> = std::result::Result<T, ReleaseNotesFeatureError<GE, LE>>;
+
+/// A section of the release notes. A pull request goes to the first section that has one of
+/// its labels.
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+pub struct ReleaseSection {
+    pub title: NonEmptyString,
+    pub labels: Vec<NonEmptyString>,
+}
+
+#[derive(Deserialize)]
+pub struct ReleaseNotesFeatureConfig {
+    agent: LlmAgentConfig,
+
+    #[serde(default = "default_sections")]
+    sections: Vec<ReleaseSection>,
+
+    /// Section for pull requests that don't match any other section.
+    #[serde(default = "default_other_section")]
+    other_section: NonEmptyString,
+}
+
+fn default_sections() -> Vec<ReleaseSection> {
+    let section = |title: &str, labels: &[&str]| ReleaseSection {
+        title: NonEmptyString::from_str(title).unwrap(),
+        labels: labels
+            .iter()
+            .map(|label| NonEmptyString::from_str(label).unwrap())
+            .collect(),
+    };
+
+    vec![
+        section("Breaking changes", &["breaking", "breaking-change"]),
+        section("Features", &["feature", "enhancement"]),
+        section("Fixes", &["bug", "fix"]),
+    ]
+}
+
+fn default_other_section() -> NonEmptyString {
+    NonEmptyString::from_str("Other changes").unwrap()
+}
+
+pub struct ReleaseNotesFeature<G, L> {
+    githost: G,
+    agent: LlmAgent<L, ReleaseNotesFeatureContext>,
+    sections: Vec<ReleaseSection>,
+    other_section: NonEmptyString,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ReleaseNotesFeatureContext {
+    pub pull_request: IssueTemplate,
+
+    /// Title of the section that the pull request goes to.
+    pub section: NonEmptyString,
+}
+
+impl<G: GitHost, L: Llm> ReleaseNotesFeature<G, L> {
+    pub async fn build_from_config(
+        config: ReleaseNotesFeatureConfig,
+        githost: G,
+        llm: L,
+    ) -> Result<Self, G::Error, L::Error> {
+        let agent = LlmAgent::build_from_config(llm, config.agent)
+            .map_err(ReleaseNotesFeatureError::LlmAgentError)?;
+
+        Ok(Self::new(
+            githost,
+            agent,
+            config.sections,
+            config.other_section,
+        ))
+    }
+
+    pub fn new(
+        githost: G,
+        agent: LlmAgent<L, ReleaseNotesFeatureContext>,
+        sections: Vec<ReleaseSection>,
+        other_section: NonEmptyString,
+    ) -> Self {
+        Self {
+            githost,
+            agent,
+            sections,
+            other_section,
+        }
+    }
+
+    /// Write the sections of the release notes for the pull requests merged between the refs.
+    /// Every pull request title is rewritten by the LLM into a user-facing bullet point.
+    pub async fn write_notes(
+        &self,
+        repo_id: RepoId,
+        base: String,
+        head: String,
+    ) -> Result<String, G::Error, L::Error> {
+        let commits = self.githost.compare_refs(repo_id, base, head).await?;
+
+        let mut seen = HashSet::new();
+        let mut pull_request_ids = Vec::new();
+
+        for commit in commits {
+            for pull_request_id in self
+                .githost
+                .list_merged_pull_requests(repo_id, commit)
+                .await?
+            {
+                if seen.insert(pull_request_id) {
+                    pull_request_ids.push(pull_request_id);
+                }
+            }
+        }
+
+        info!(
+            "Writing release notes for {} pull requests",
+            pull_request_ids.len()
+        );
+
+        let mut section_titles: Vec<_> = self
+            .sections
+            .iter()
+            .map(|section| section.title.clone())
+            .collect();
+        section_titles.push(self.other_section.clone());
+
+        let mut bullets: Vec<Vec<String>> = vec![Vec::new(); section_titles.len()];
+
+        for pull_request_id in pull_request_ids {
+            let context = self.make_context(repo_id, pull_request_id).await?;
+
+            let ai_message = self
+                .agent
+                .process(&context)
+                .await
+                .map_err(ReleaseNotesFeatureError::LlmAgentError)?;
+
+            let section_index = section_titles
+                .iter()
+                .position(|title| *title == context.section)
+                .expect("section is always one of the titles");
+
+            bullets[section_index].push(format!(
+                "- {} (#{})",
+                ai_message.as_str().trim().trim_start_matches("- "),
+                *pull_request_id
+            ));
+        }
+
+        Ok(section_titles
+            .into_iter()
+            .zip(bullets)
+            .filter(|(_, bullets)| !bullets.is_empty())
+            .map(|(title, bullets)| format!("### {}\n\n{}\n", title, bullets.join("\n")))
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    pub async fn render(
+        &self,
+        repo_id: RepoId,
+        pull_request_id: IssueId,
+    ) -> Result<RenderedMessages, G::Error, L::Error> {
+        let context = self.make_context(repo_id, pull_request_id).await?;
+
+        self.agent
+            .render(&context)
+            .map_err(ReleaseNotesFeatureError::LlmAgentError)
+    }
+
+    async fn make_context(
+        &self,
+        repo_id: RepoId,
+        pull_request_id: IssueId,
+    ) -> Result<ReleaseNotesFeatureContext, G::Error, L::Error> {
+        // Pull requests are issues too, and only the issue model has labels.
+        let issue = self.githost.get_issue(repo_id, pull_request_id).await?;
+        let author = self.githost.get_user(issue.author_user_id).await?;
+
+        let section = self.choose_section(&issue);
+
+        Ok(ReleaseNotesFeatureContext {
+            pull_request: (issue, author).into(),
+            section,
+        })
+    }
+
+    fn choose_section(&self, issue: &Issue) -> NonEmptyString {
+        self.sections
+            .iter()
+            .find(|section| {
+                section
+                    .labels
+                    .iter()
+                    .any(|label| issue.labels.contains(label))
+            })
+            .map(|section| section.title.clone())
+            .unwrap_or_else(|| self.other_section.clone())
+    }
+}
+
+/// Format the release notes as a section of a changelog in the Keep a Changelog format.
+pub fn format_changelog(version: &str, date: NaiveDate, notes: &str) -> String {
+    format!(
+        "## [{}] - {}\n\n{}",
+        version,
+        date.format("%Y-%m-%d"),
+        notes
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chrono::{DateTime, NaiveDate, Utc};
+    use mockall::predicate;
+    use non_empty_string::NonEmptyString;
+
+    use crate::{
+        bot::features::release_notes_feature::{
+            default_other_section, default_sections, format_changelog, ReleaseNotesFeature,
+        },
+        githost::{
+            host::MockGitHost,
+            model::{
+                AuthorAssociation, Issue, IssueId, IssueState, RepoId, User, UserId, UserKind,
+            },
+        },
+        llm::{
+            agent::LlmAgent,
+            llm_trait::{CompletionParameters, MockLlm},
+            messages::AiMessage,
+        },
+    };
+
+    const TEST_SYSTEM_MESSAGE: &str =
+        "You are a bot that rewrites pull request titles into release notes.";
+    const TEST_USER_MESSAGE: &str = "The title is: {{ context.pull_request.title }}";
+
+    #[tokio::test]
+    async fn groups_pull_requests_by_label() {
+        let mut githost_mock = MockGitHost::new();
+
+        githost_mock
+            .expect_compare_refs()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq("v0.1.0".to_string()),
+                predicate::eq("v0.2.0".to_string()),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(vec!["a".into(), "b".into(), "c".into()]));
+
+        // Commits `a` and `b` belong to the same pull request.
+        githost_mock
+            .expect_list_merged_pull_requests()
+            .returning(|_, commit| {
+                Ok(match commit.as_str() {
+                    "a" | "b" => vec![IssueId::from(1 as usize)],
+                    _ => vec![IssueId::from(2 as usize)],
+                })
+            });
+
+        githost_mock
+            .expect_get_issue()
+            .times(2)
+            .returning(|_, issue_id| {
+                let created_at = make_now();
+
+                Ok(Issue {
+                    id: issue_id,
+                    author_user_id: UserId::from(1),
+                    title: non_empty("Fix crash"),
+                    body: String::new(),
+                    state: IssueState::Closed,
+                    labels: if *issue_id == 1 {
+                        vec![non_empty("bug")]
+                    } else {
+                        vec![non_empty("documentation")]
+                    },
+                    assignees: vec![],
+                    milestone: None,
+                    created_at,
+                    updated_at: created_at,
+                    author_association: AuthorAssociation::Member,
+                })
+            });
+
+        githost_mock.expect_get_user().returning(|id| {
+            Ok(User {
+                id,
+                nickname: non_empty("InAnYan"),
+                kind: UserKind::User,
+            })
+        });
+
+        let mut llm_mock = MockLlm::new();
+
+        llm_mock.expect_complete().times(2).returning(|_, _, _| {
+            Ok(AiMessage::from(non_empty(
+                "- The program no longer crashes",
+            )))
+        });
+
+        let feature = ReleaseNotesFeature::new(
+            githost_mock,
+            LlmAgent::build_raw(
+                llm_mock,
+                TEST_SYSTEM_MESSAGE.try_into().unwrap(),
+                TEST_USER_MESSAGE.try_into().unwrap(),
+                CompletionParameters::default(),
+            )
+            .unwrap(),
+            default_sections(),
+            default_other_section(),
+        );
+
+        let notes = feature
+            .write_notes(RepoId::from(1), "v0.1.0".into(), "v0.2.0".into())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            notes,
+            "### Fixes\n\n- The program no longer crashes (#1)\n\n### Other changes\n\n- The program no longer crashes (#2)\n"
+        );
+    }
+
+    #[test]
+    fn formats_changelog() {
+        assert_eq!(
+            format_changelog(
+                "0.2.0",
+                NaiveDate::from_ymd_opt(2024, 10, 1).unwrap(),
+                "### Fixes\n\n- Fix crash (#1)\n"
+            ),
+            "## [0.2.0] - 2024-10-01\n\n### Fixes\n\n- Fix crash (#1)\n"
+        );
+    }
+
+    fn make_now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-10-01T12:00:00Z")
+            .unwrap()
+            .to_utc()
+    }
+
+    fn non_empty(s: &str) -> NonEmptyString {
+        NonEmptyString::from_str(s).unwrap()
+    }
+}
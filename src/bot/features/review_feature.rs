@@ -0,0 +1,676 @@
+use std::{cmp::Reverse, collections::HashSet};
+
+use glob::Pattern;
+use log::error;
+use non_empty_string::NonEmptyString;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    githost::{
+        events::{GitEvent, GitEventKindName},
+        host::GitHost,
+        model::{ChangedFile, IssueId, RepoId, Review, ReviewComment, ReviewVerdict},
+    },
+    llm::{
+        agent::{LlmAgent, LlmAgentConfig, LlmAgentError, RenderedMessages},
+        llm_trait::Llm,
+    },
+};
+
+use super::templates::{ChangedFileTemplate, PullRequestTemplate};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReviewFeatureError<GE, LE> {
+    #[error("error from LLM agent")]
+    LlmAgentError(#[source] LlmAgentError<LE>),
+
+    #[error("unable to perform Git host action")]
+    GitHostError(#[from] GE),
+
+    #[error("invalid path glob pattern")]
+    GlobPatternError(#[source] glob::PatternError),
+
+    #[error("pull request has no files that can be reviewed")]
+    NoReviewableFiles,
+}
+
+pub type Result<T, GE, LE> = std::result::Result<T, ReviewFeatureError<GE, LE>>;
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    #[default]
+    Warning,
+    Error,
+}
+
+#[derive(Deserialize)]
+pub struct ReviewFeatureConfig {
+    agent: LlmAgentConfig,
+
+    #[serde(default = "default_events")]
+    events: Vec<GitEventKindName>,
+
+    /// Glob patterns of files that should be reviewed. All files are reviewed if empty.
+    #[serde(default)]
+    include_paths: Vec<String>,
+
+    /// Glob patterns of files that should never be reviewed.
+    #[serde(default)]
+    exclude_paths: Vec<String>,
+
+    #[serde(default = "default_max_findings")]
+    max_findings: usize,
+
+    #[serde(default)]
+    min_severity: Severity,
+
+    /// Maximum size of a diff chunk (in characters) that is sent to the LLM at once.
+    #[serde(default = "default_max_chunk_size")]
+    max_chunk_size: usize,
+}
+
+fn default_events() -> Vec<GitEventKindName> {
+    vec![
+        GitEventKindName::NewPullRequest,
+        GitEventKindName::PullRequestSynchronized,
+    ]
+}
+
+fn default_max_findings() -> usize {
+    10
+}
+
+fn default_max_chunk_size() -> usize {
+    12000
+}
+
+pub struct ReviewOptions {
+    pub include_paths: Vec<Pattern>,
+    pub exclude_paths: Vec<Pattern>,
+    pub max_findings: usize,
+    pub min_severity: Severity,
+    pub max_chunk_size: usize,
+}
+
+impl Default for ReviewOptions {
+    fn default() -> Self {
+        Self {
+            include_paths: Vec::new(),
+            exclude_paths: Vec::new(),
+            max_findings: default_max_findings(),
+            min_severity: Severity::default(),
+            max_chunk_size: default_max_chunk_size(),
+        }
+    }
+}
+
+pub struct ReviewFeature<G, L> {
+    githost: G,
+    agent: LlmAgent<L, ReviewFeatureContext>,
+    events: Vec<GitEventKindName>,
+    options: ReviewOptions,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ReviewFeatureContext {
+    pub pull_request: PullRequestTemplate,
+
+    /// The file under review. Its patch may be only a part of the whole file diff.
+    pub file: ChangedFileTemplate,
+}
+
+/// A single problem found by the LLM, in the format the LLM is asked to answer with.
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+pub struct Finding {
+    pub file: String,
+    pub line: usize,
+    pub severity: Severity,
+    pub message: NonEmptyString,
+}
+
+impl<G: GitHost, L: Llm> ReviewFeature<G, L> {
+    pub async fn build_from_config(
+        config: ReviewFeatureConfig,
+        githost: G,
+        llm: L,
+    ) -> Result<Self, G::Error, L::Error> {
+        let agent = LlmAgent::build_from_config(llm, config.agent)
+            .map_err(ReviewFeatureError::LlmAgentError)?;
+
+        let options = ReviewOptions {
+            include_paths: compile_patterns::<G::Error, L::Error>(&config.include_paths)?,
+            exclude_paths: compile_patterns::<G::Error, L::Error>(&config.exclude_paths)?,
+            max_findings: config.max_findings,
+            min_severity: config.min_severity,
+            max_chunk_size: config.max_chunk_size,
+        };
+
+        Ok(Self::new(githost, agent, config.events, options))
+    }
+
+    pub fn new(
+        githost: G,
+        agent: LlmAgent<L, ReviewFeatureContext>,
+        events: Vec<GitEventKindName>,
+        options: ReviewOptions,
+    ) -> Self {
+        Self {
+            githost,
+            agent,
+            events,
+            options,
+        }
+    }
+
+    pub async fn process_event(&self, event: &GitEvent) -> Result<(), G::Error, L::Error> {
+        if !self.events.contains(&event.kind.name()) {
+            return Ok(());
+        }
+
+        let (pull_request, files) = self.get_pull_request(event.repo_id, event.issue_id).await?;
+
+        let mut findings = Vec::new();
+        let mut commentable_lines = HashSet::new();
+
+        for file in files {
+            let Some(patch) = &file.patch else {
+                continue;
+            };
+
+            commentable_lines.extend(
+                changed_lines(patch)
+                    .into_iter()
+                    .map(|line| (file.path.clone(), line)),
+            );
+
+            for chunk in chunk_patch(patch, self.options.max_chunk_size) {
+                let context = ReviewFeatureContext {
+                    pull_request: pull_request.clone(),
+                    file: ChangedFileTemplate {
+                        path: file.path.clone(),
+                        status: file.status,
+                        patch: annotate_patch(&chunk),
+                    },
+                };
+
+                let ai_message = self
+                    .agent
+                    .process(&context)
+                    .await
+                    .map_err(ReviewFeatureError::LlmAgentError)?;
+
+                findings.extend(
+                    parse_findings(ai_message.as_str())
+                        .into_iter()
+                        .filter(|finding| finding.file == file.path),
+                );
+            }
+        }
+
+        findings.retain(|finding| finding.severity >= self.options.min_severity);
+
+        // Stable sort keeps the order of files for findings of the same severity.
+        findings.sort_by_key(|finding| Reverse(finding.severity));
+        findings.truncate(self.options.max_findings);
+
+        if findings.is_empty() {
+            return Ok(());
+        }
+
+        self.githost
+            .make_pull_request_review(
+                event.repo_id,
+                event.issue_id,
+                make_review(findings, &commentable_lines),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Render the messages for the first chunk of the first reviewable file.
+    pub async fn render(
+        &self,
+        repo_id: RepoId,
+        pull_request_id: IssueId,
+    ) -> Result<RenderedMessages, G::Error, L::Error> {
+        let (pull_request, files) = self.get_pull_request(repo_id, pull_request_id).await?;
+
+        let (file, chunk) = files
+            .into_iter()
+            .find_map(|file| {
+                let chunk = chunk_patch(file.patch.as_ref()?, self.options.max_chunk_size)
+                    .into_iter()
+                    .next()?;
+
+                Some((file, chunk))
+            })
+            .ok_or(ReviewFeatureError::NoReviewableFiles)?;
+
+        let context = ReviewFeatureContext {
+            pull_request,
+            file: ChangedFileTemplate {
+                path: file.path,
+                status: file.status,
+                patch: annotate_patch(&chunk),
+            },
+        };
+
+        self.agent
+            .render(&context)
+            .map_err(ReviewFeatureError::LlmAgentError)
+    }
+
+    async fn get_pull_request(
+        &self,
+        repo_id: RepoId,
+        pull_request_id: IssueId,
+    ) -> Result<(PullRequestTemplate, Vec<ChangedFile>), G::Error, L::Error> {
+        let pull_request = self
+            .githost
+            .get_pull_request(repo_id, pull_request_id)
+            .await?;
+
+        let author = self.githost.get_user(pull_request.author_user_id).await?;
+
+        let files = self
+            .githost
+            .get_pull_request_files(repo_id, pull_request_id)
+            .await?
+            .into_iter()
+            .filter(|file| self.is_path_selected(&file.path))
+            .collect();
+
+        Ok(((pull_request, author).into(), files))
+    }
+
+    fn is_path_selected(&self, path: &str) -> bool {
+        let included = self.options.include_paths.is_empty()
+            || self
+                .options
+                .include_paths
+                .iter()
+                .any(|pattern| pattern.matches(path));
+
+        let excluded = self
+            .options
+            .exclude_paths
+            .iter()
+            .any(|pattern| pattern.matches(path));
+
+        included && !excluded
+    }
+}
+
+fn compile_patterns<GE, LE>(patterns: &[String]) -> Result<Vec<Pattern>, GE, LE> {
+    patterns
+        .iter()
+        .map(|pattern| Pattern::new(pattern).map_err(ReviewFeatureError::GlobPatternError))
+        .collect()
+}
+
+fn make_review(findings: Vec<Finding>, commentable_lines: &HashSet<(String, usize)>) -> Review {
+    let mut body = format!(
+        "I have reviewed this pull request and found {} potential issue(s).",
+        findings.len()
+    );
+
+    let mut comments = Vec::new();
+
+    for finding in findings {
+        let message = format!(
+            "**{}**: {}",
+            severity_name(finding.severity),
+            finding.message
+        );
+
+        // GitHub rejects the whole review if a comment points outside of the diff, so such
+        // findings are listed in the review body instead.
+        if commentable_lines.contains(&(finding.file.clone(), finding.line)) {
+            comments.push(ReviewComment {
+                path: finding.file,
+                line: finding.line,
+                body: message
+                    .try_into()
+                    .expect("message always contains severity"),
+            });
+        } else {
+            body.push_str(&format!(
+                "\n\n- `{}:{}` {}",
+                finding.file, finding.line, message
+            ));
+        }
+    }
+
+    Review {
+        body,
+        verdict: ReviewVerdict::Comment,
+        comments,
+    }
+}
+
+fn severity_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Info => "Info",
+        Severity::Warning => "Warning",
+        Severity::Error => "Error",
+    }
+}
+
+/// Parse the LLM answer. The LLM should answer with a JSON array of findings, or with the special
+/// word "EMPTY".
+fn parse_findings(ai_message: &str) -> Vec<Finding> {
+    let text = ai_message.trim();
+
+    if text.starts_with("EMPTY") {
+        return Vec::new();
+    }
+
+    // LLMs like to wrap JSON into Markdown code blocks.
+    let text = text
+        .strip_prefix("```json")
+        .or_else(|| text.strip_prefix("```"))
+        .and_then(|text| text.strip_suffix("```"))
+        .unwrap_or(text);
+
+    match serde_json::from_str(text) {
+        Ok(findings) => findings,
+        Err(e) => {
+            error!("AI has generated malformed result: {:?}. Skipping.", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Split a file patch into chunks at hunk boundaries, so that each chunk is smaller than
+/// `max_size`. A single hunk that is larger than `max_size` becomes a chunk on its own.
+fn chunk_patch(patch: &str, max_size: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+
+    for hunk in split_hunks(patch) {
+        if !current.is_empty() && current.len() + hunk.len() > max_size {
+            chunks.push(std::mem::take(&mut current));
+        }
+
+        current.push_str(hunk);
+    }
+
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+fn split_hunks(patch: &str) -> Vec<&str> {
+    let mut starts: Vec<usize> = patch
+        .match_indices("\n@@")
+        .map(|(index, _)| index + 1)
+        .collect();
+
+    starts.insert(0, 0);
+    starts.push(patch.len());
+
+    starts
+        .windows(2)
+        .map(|window| &patch[window[0]..window[1]])
+        .filter(|hunk| !hunk.is_empty())
+        .collect()
+}
+
+/// Parse the start line of the new file from a hunk header like `@@ -1,5 +10,7 @@`.
+fn parse_hunk_start(header: &str) -> Option<usize> {
+    let new_range = header
+        .split_whitespace()
+        .find(|part| part.starts_with('+'))?;
+
+    new_range[1..].split(',').next()?.parse().ok()
+}
+
+/// Walk over the patch lines, calling `f` with the new-file line number (if the line exists in
+/// the new file) and the line itself.
+fn walk_patch(patch: &str, mut f: impl FnMut(Option<usize>, &str)) {
+    let mut line_number = None;
+
+    for line in patch.lines() {
+        if line.starts_with("@@") {
+            line_number = parse_hunk_start(line);
+            f(None, line);
+        } else if line.starts_with('-') || line.starts_with('\\') {
+            f(None, line);
+        } else {
+            f(line_number, line);
+            line_number = line_number.map(|n| n + 1);
+        }
+    }
+}
+
+/// Lines of the new file that are present in the patch and so can be commented on.
+fn changed_lines(patch: &str) -> Vec<usize> {
+    let mut lines = Vec::new();
+
+    walk_patch(patch, |line_number, _| {
+        if let Some(line_number) = line_number {
+            lines.push(line_number);
+        }
+    });
+
+    lines
+}
+
+/// Prefix every line of the patch with its line number in the new file, so that the LLM doesn't
+/// have to count lines by itself.
+fn annotate_patch(patch: &str) -> String {
+    let mut result = String::new();
+
+    walk_patch(patch, |line_number, line| {
+        match line_number {
+            Some(line_number) => result.push_str(&format!("{:>5} {}\n", line_number, line)),
+            None => result.push_str(&format!("{:>5} {}\n", "", line)),
+        };
+    });
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use mockall::predicate;
+    use non_empty_string::NonEmptyString;
+
+    use crate::{
+        bot::features::review_feature::{
+            annotate_patch, changed_lines, chunk_patch, ReviewFeature, ReviewOptions,
+        },
+        githost::{
+            events::{GitEvent, GitEventKind, GitEventKindName},
+            host::MockGitHost,
+            model::{
+                ChangedFile, ChangedFileStatus, IssueId, PullRequest, RepoId, Review,
+                ReviewComment, ReviewVerdict, User, UserId, UserKind,
+            },
+        },
+        llm::{
+            agent::LlmAgent,
+            llm_trait::{CompletionParameters, MockLlm},
+            messages::AiMessage,
+        },
+    };
+
+    const TEST_SYSTEM_MESSAGE: &str = "You are a bot that reviews pull requests. Answer with a JSON array of findings or write EMPTY";
+    const TEST_USER_MESSAGE: &str =
+        "Review the file {{ context.file.path }}:\n{{ context.file.patch }}";
+
+    const TEST_PATCH: &str = "@@ -1,3 +1,4 @@\n fn main() {\n-    println!(\"Hello\");\n+    let x = 1 / 0;\n+    println!(\"{}\", x);\n }";
+
+    #[tokio::test]
+    async fn posts_review() {
+        let llm_output = r#"```json
+[
+  {"file": "src/main.rs", "line": 2, "severity": "error", "message": "Division by zero"},
+  {"file": "src/main.rs", "line": 3, "severity": "info", "message": "Consider using a logger"},
+  {"file": "src/main.rs", "line": 42, "severity": "warning", "message": "Missing tests"}
+]
+```"#;
+
+        let mut githost_mock = make_githost_mock();
+
+        githost_mock
+            .expect_make_pull_request_review()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(IssueId::from(2 as usize)),
+                predicate::eq(Review {
+                    body: "I have reviewed this pull request and found 2 potential issue(s).\n\n- `src/main.rs:42` **Warning**: Missing tests".into(),
+                    verdict: ReviewVerdict::Comment,
+                    comments: vec![ReviewComment {
+                        path: "src/main.rs".into(),
+                        line: 2,
+                        body: "**Error**: Division by zero".try_into().unwrap(),
+                    }],
+                }),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        let feature = make_feature(githost_mock, llm_output, ReviewOptions::default());
+
+        feature.process_event(&make_event()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn doesnt_review_on_empty() {
+        let feature = make_feature(make_githost_mock(), "EMPTY", ReviewOptions::default());
+
+        feature.process_event(&make_event()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn skips_excluded_files() {
+        let mut llm_mock = MockLlm::new();
+
+        llm_mock.expect_complete().never();
+
+        let feature = ReviewFeature::new(
+            make_githost_mock(),
+            LlmAgent::build_raw(
+                llm_mock,
+                TEST_SYSTEM_MESSAGE.try_into().unwrap(),
+                TEST_USER_MESSAGE.try_into().unwrap(),
+                CompletionParameters::default(),
+            )
+            .unwrap(),
+            vec![GitEventKindName::NewPullRequest],
+            ReviewOptions {
+                exclude_paths: vec![glob::Pattern::new("src/**").unwrap()],
+                ..Default::default()
+            },
+        );
+
+        feature.process_event(&make_event()).await.unwrap();
+    }
+
+    #[test]
+    fn annotates_patch() {
+        assert_eq!(
+            annotate_patch(TEST_PATCH),
+            "      @@ -1,3 +1,4 @@\n    1  fn main() {\n      -    println!(\"Hello\");\n    2 +    let x = 1 / 0;\n    3 +    println!(\"{}\", x);\n    4  }\n"
+        );
+
+        assert_eq!(changed_lines(TEST_PATCH), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn chunks_patch_by_hunks() {
+        let patch = "@@ -1 +1 @@\n-a\n+b\n@@ -10 +10 @@\n-c\n+d";
+
+        assert_eq!(
+            chunk_patch(patch, 20),
+            vec!["@@ -1 +1 @@\n-a\n+b\n", "@@ -10 +10 @@\n-c\n+d"]
+        );
+
+        assert_eq!(chunk_patch(patch, 1000), vec![patch]);
+    }
+
+    fn make_feature(
+        githost_mock: MockGitHost,
+        llm_output: &'static str,
+        options: ReviewOptions,
+    ) -> ReviewFeature<MockGitHost, MockLlm> {
+        let mut llm_mock = MockLlm::new();
+
+        llm_mock.expect_complete().returning(move |_, _, _| {
+            Ok(AiMessage::from(
+                NonEmptyString::try_from(llm_output).unwrap(),
+            ))
+        });
+
+        ReviewFeature::new(
+            githost_mock,
+            LlmAgent::build_raw(
+                llm_mock,
+                TEST_SYSTEM_MESSAGE.try_into().unwrap(),
+                TEST_USER_MESSAGE.try_into().unwrap(),
+                CompletionParameters::default(),
+            )
+            .unwrap(),
+            vec![GitEventKindName::NewPullRequest],
+            options,
+        )
+    }
+
+    fn make_githost_mock() -> MockGitHost {
+        let mut githost_mock = MockGitHost::new();
+
+        githost_mock
+            .expect_get_pull_request()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(IssueId::from(2 as usize)),
+            )
+            .returning(|_, id| {
+                Ok(PullRequest {
+                    id,
+                    author_user_id: UserId::from(1),
+                    title: "Print a number".try_into().unwrap(),
+                    body: String::new(),
+                    base_ref: "main".into(),
+                    head_ref: "feature".into(),
+                    head_sha: "6dcb09b5b57875f334f61aebed695e2e4193db5e".into(),
+                })
+            });
+
+        githost_mock.expect_get_user().returning(|id| {
+            Ok(User {
+                id,
+                nickname: "InAnYan".try_into().unwrap(),
+                kind: UserKind::User,
+            })
+        });
+
+        githost_mock
+            .expect_get_pull_request_files()
+            .returning(|_, _| {
+                Ok(vec![ChangedFile {
+                    path: "src/main.rs".into(),
+                    status: ChangedFileStatus::Modified,
+                    additions: 2,
+                    deletions: 1,
+                    patch: Some(TEST_PATCH.into()),
+                }])
+            });
+
+        githost_mock
+    }
+
+    fn make_event() -> GitEvent {
+        GitEvent {
+            repo_id: RepoId::from(1),
+            issue_id: IssueId::from(2 as usize),
+            kind: GitEventKind::NewPullRequest,
+        }
+    }
+}
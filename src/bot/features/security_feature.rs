@@ -0,0 +1,490 @@
+use std::{str::FromStr, sync::Arc};
+
+use log::{info, warn};
+use non_empty_string::NonEmptyString;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    githost::{
+        events::{GitEvent, GitEventKindName},
+        host::GitHost,
+        model::{AuthorAssociation, IssueId, RepoId},
+    },
+    llm::{
+        agent::{LlmAgent, LlmAgentConfig, LlmAgentError, RenderedMessages},
+        llm_trait::Llm,
+    },
+    notifier::notifier_trait::{build_notifier, Notifier, NotifierChoice, NotifierError},
+    utils::redaction::Redactor,
+};
+
+use super::templates::IssueTemplate;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SecurityFeatureError<GE, LE> {
+    #[error("error from LLM agent")]
+    LlmAgentError(#[source] LlmAgentError<LE>),
+
+    #[error("unable to perform Git host action")]
+    GitHostError(#[from] GE),
+
+    #[error("unable to notify maintainers")]
+    NotifierError(#[source] NotifierError),
+}
+
+pub type Result<T, GE, LE> = std::result::Result<T, SecurityFeatureError<GE, LE>>;
+
+#[derive(Deserialize)]
+pub struct SecurityFeatureConfig {
+    agent: LlmAgentConfig,
+
+    #[serde(default = "GitEventKindName::default_subscription")]
+    events: Vec<GitEventKindName>,
+
+    /// Comment that points the reporter to the private disclosure.
+    #[serde(default = "default_comment")]
+    comment: NonEmptyString,
+
+    /// Where to tell the maintainers about the report.
+    #[serde(default)]
+    notifier: Option<NotifierChoice>,
+
+    #[serde(default)]
+    lock: bool,
+
+    /// Replace the body of the issue with a notice. The original body is sent to the notifier.
+    #[serde(default)]
+    hide_body: bool,
+
+    /// Mask tokens, keys and passwords in the body of every new issue, not only of security
+    /// reports. The original body stays in the edit history of the issue, so the maintainers are
+    /// asked to delete it there.
+    #[serde(default)]
+    redact_secrets: bool,
+}
+
+fn default_comment() -> NonEmptyString {
+    NonEmptyString::from_str(
+        "Thank you for the report! It looks like it describes a security vulnerability. Please \
+         don't disclose the details in public, and report them privately as described in \
+         `SECURITY.md`, or through the private vulnerability reporting of this repository.",
+    )
+    .unwrap()
+}
+
+const HIDDEN_BODY_NOTICE: &str = "The body of this issue was hidden because it may contain \
+                                  details of a security vulnerability.";
+
+pub struct SecurityOptions {
+    pub comment: NonEmptyString,
+    pub lock: bool,
+    pub hide_body: bool,
+    pub redact_secrets: bool,
+}
+
+pub struct SecurityFeature<G, L> {
+    githost: G,
+    agent: LlmAgent<L, SecurityFeatureContext>,
+    events: Vec<GitEventKindName>,
+    notifier: Option<Arc<dyn Notifier + Send + Sync>>,
+    options: SecurityOptions,
+    redactor: Redactor,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SecurityFeatureContext {
+    pub issue: IssueTemplate,
+}
+
+impl<G: GitHost, L: Llm> SecurityFeature<G, L> {
+    pub async fn build_from_config(
+        config: SecurityFeatureConfig,
+        githost: G,
+        llm: L,
+    ) -> Result<Self, G::Error, L::Error> {
+        let agent = LlmAgent::build_from_config(llm, config.agent)
+            .map_err(SecurityFeatureError::LlmAgentError)?;
+
+        let notifier = config
+            .notifier
+            .map(build_notifier)
+            .transpose()
+            .map_err(SecurityFeatureError::NotifierError)?;
+
+        Ok(Self::new(
+            githost,
+            agent,
+            config.events,
+            notifier,
+            SecurityOptions {
+                comment: config.comment,
+                lock: config.lock,
+                hide_body: config.hide_body,
+                redact_secrets: config.redact_secrets,
+            },
+        ))
+    }
+
+    pub fn new(
+        githost: G,
+        agent: LlmAgent<L, SecurityFeatureContext>,
+        events: Vec<GitEventKindName>,
+        notifier: Option<Arc<dyn Notifier + Send + Sync>>,
+        options: SecurityOptions,
+    ) -> Self {
+        Self {
+            githost,
+            agent,
+            events,
+            notifier,
+            options,
+            redactor: Redactor::secrets(),
+        }
+    }
+
+    /// Returns whether the body was hidden or the issue was locked, so that the other features
+    /// should leave the issue alone.
+    pub async fn process_event(&self, event: &GitEvent) -> Result<bool, G::Error, L::Error> {
+        if !self.events.contains(&event.kind.name()) {
+            return Ok(false);
+        }
+
+        let mut issue = self
+            .githost
+            .get_issue(event.repo_id, event.issue_id)
+            .await?;
+
+        // Secrets are masked first, so that they are not sent to the LLM either.
+        if self.options.redact_secrets {
+            let masked = self.redactor.mask(&issue.body);
+
+            if masked != issue.body {
+                info!("Masking secrets in issue {}", *event.issue_id);
+
+                self.githost
+                    .edit_issue_body(event.repo_id, event.issue_id, masked.clone())
+                    .await?;
+
+                issue.body = masked;
+
+                let repo = self.githost.get_repo(event.repo_id).await?;
+
+                self.notify(&format!(
+                    "Secrets were masked in {}/{}#{}, but the original body is still in the edit \
+                     history of the issue. Please delete that revision.",
+                    repo.owner, repo.name, *event.issue_id
+                ))
+                .await?;
+            }
+        }
+
+        // Maintainers know where to report vulnerabilities.
+        if matches!(
+            issue.author_association,
+            AuthorAssociation::Owner | AuthorAssociation::Member | AuthorAssociation::Collaborator
+        ) {
+            return Ok(false);
+        }
+
+        let author = self.githost.get_user(issue.author_user_id).await?;
+
+        let title = issue.title.clone();
+        let body = issue.body.clone();
+
+        let context = SecurityFeatureContext {
+            issue: (issue, author).into(),
+        };
+
+        let ai_message = self
+            .agent
+            .process(&context)
+            .await
+            .map_err(SecurityFeatureError::LlmAgentError)?;
+
+        if !ai_message.as_str().trim().starts_with("SECURITY") {
+            return Ok(false);
+        }
+
+        info!("Issue {} looks like a security report", *event.issue_id);
+
+        self.githost
+            .make_comment(event.repo_id, event.issue_id, self.options.comment.clone())
+            .await?;
+
+        if self.notifier.is_some() {
+            let repo = self.githost.get_repo(event.repo_id).await?;
+
+            let mut message = format!(
+                "Probable security report in {}/{}#{}: {}",
+                repo.owner, repo.name, *event.issue_id, title
+            );
+
+            // The body is about to disappear from the issue, so the maintainers get it here.
+            if self.options.hide_body {
+                message = format!("{}\n\n{}", message, body);
+            }
+
+            self.notify(&message).await?;
+        }
+
+        if self.options.hide_body {
+            self.githost
+                .edit_issue_body(event.repo_id, event.issue_id, HIDDEN_BODY_NOTICE.into())
+                .await?;
+        }
+
+        if self.options.lock {
+            self.githost
+                .lock_issue(event.repo_id, event.issue_id, None)
+                .await?;
+        }
+
+        Ok(self.options.hide_body || self.options.lock)
+    }
+
+    /// Tell the maintainers, if there is a notifier.
+    async fn notify(&self, message: &str) -> Result<(), G::Error, L::Error> {
+        match &self.notifier {
+            Some(notifier) => notifier
+                .notify(message)
+                .await
+                .map_err(SecurityFeatureError::NotifierError),
+
+            None => {
+                warn!("There is no notifier to tell the maintainers: {}", message);
+                Ok(())
+            }
+        }
+    }
+
+    pub async fn render(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+    ) -> Result<RenderedMessages, G::Error, L::Error> {
+        let mut issue = self.githost.get_issue(repo_id, issue_id).await?;
+        let author = self.githost.get_user(issue.author_user_id).await?;
+
+        if self.options.redact_secrets {
+            issue.body = self.redactor.mask(&issue.body);
+        }
+
+        let context = SecurityFeatureContext {
+            issue: (issue, author).into(),
+        };
+
+        self.agent
+            .render(&context)
+            .map_err(SecurityFeatureError::LlmAgentError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Arc};
+
+    use chrono::{DateTime, Utc};
+    use mockall::predicate;
+    use non_empty_string::NonEmptyString;
+
+    use crate::{
+        bot::features::security_feature::{
+            default_comment, SecurityFeature, SecurityOptions, HIDDEN_BODY_NOTICE,
+        },
+        githost::{
+            events::{GitEvent, GitEventKind, GitEventKindName},
+            host::MockGitHost,
+            model::{
+                AuthorAssociation, CommentId, Issue, IssueId, IssueState, Repo, RepoId, User,
+                UserId, UserKind,
+            },
+        },
+        llm::{
+            agent::LlmAgent,
+            llm_trait::{CompletionParameters, MockLlm},
+            messages::AiMessage,
+        },
+        notifier::notifier_trait::MockNotifier,
+    };
+
+    const TEST_SYSTEM_MESSAGE: &str = "You are a bot that detects security reports.";
+    const TEST_USER_MESSAGE: &str = "The issue body is: {{ context.issue.body }}";
+
+    #[tokio::test]
+    async fn redirects_security_report() {
+        let mut githost_mock =
+            make_githost_mock("SQL injection in the login form, token: abcdefgh12345678");
+
+        // First the secret is masked, then the whole body is hidden.
+        githost_mock
+            .expect_edit_issue_body()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(IssueId::from(1 as usize)),
+                predicate::eq("SQL injection in the login form, [REDACTED credential]".to_string()),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        githost_mock
+            .expect_edit_issue_body()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(IssueId::from(1 as usize)),
+                predicate::eq(HIDDEN_BODY_NOTICE.to_string()),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        githost_mock
+            .expect_make_comment()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(IssueId::from(1 as usize)),
+                predicate::eq(default_comment()),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(CommentId::from(1)));
+
+        githost_mock.expect_get_repo().returning(|id| {
+            Ok(Repo {
+                id,
+                owner: "InAnYan".into(),
+                name: "rust-gib".into(),
+            })
+        });
+
+        let mut notifier_mock = MockNotifier::new();
+
+        notifier_mock
+            .expect_notify()
+            .with(predicate::eq(
+                "Secrets were masked in InAnYan/rust-gib#1, but the original body is still in the edit history of the issue. Please delete that revision.",
+            ))
+            .times(1)
+            .returning(|_| Ok(()));
+
+        notifier_mock
+            .expect_notify()
+            .with(predicate::eq(
+                "Probable security report in InAnYan/rust-gib#1: Login is broken\n\nSQL injection in the login form, [REDACTED credential]",
+            ))
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let mut llm_mock = MockLlm::new();
+
+        llm_mock
+            .expect_complete()
+            .times(1)
+            .returning(|_, _, _| Ok(AiMessage::from(non_empty("SECURITY"))));
+
+        let feature = SecurityFeature::new(
+            githost_mock,
+            LlmAgent::build_raw(
+                llm_mock,
+                TEST_SYSTEM_MESSAGE.try_into().unwrap(),
+                TEST_USER_MESSAGE.try_into().unwrap(),
+                CompletionParameters::default(),
+            )
+            .unwrap(),
+            vec![GitEventKindName::NewIssue],
+            Some(Arc::new(notifier_mock)),
+            SecurityOptions {
+                comment: default_comment(),
+                lock: false,
+                hide_body: true,
+                redact_secrets: true,
+            },
+        );
+
+        assert!(feature.process_event(&make_event()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn ignores_ordinary_issue() {
+        let githost_mock = make_githost_mock("The program crashes on start");
+
+        let mut llm_mock = MockLlm::new();
+
+        llm_mock
+            .expect_complete()
+            .times(1)
+            .returning(|_, _, _| Ok(AiMessage::from(non_empty("EMPTY"))));
+
+        let feature = SecurityFeature::new(
+            githost_mock,
+            LlmAgent::build_raw(
+                llm_mock,
+                TEST_SYSTEM_MESSAGE.try_into().unwrap(),
+                TEST_USER_MESSAGE.try_into().unwrap(),
+                CompletionParameters::default(),
+            )
+            .unwrap(),
+            vec![GitEventKindName::NewIssue],
+            None,
+            SecurityOptions {
+                comment: default_comment(),
+                lock: true,
+                hide_body: true,
+                redact_secrets: true,
+            },
+        );
+
+        assert!(!feature.process_event(&make_event()).await.unwrap());
+    }
+
+    fn make_githost_mock(body: &str) -> MockGitHost {
+        let mut githost_mock = MockGitHost::new();
+
+        let body = body.to_string();
+
+        githost_mock
+            .expect_get_issue()
+            .returning(move |_, issue_id| {
+                let created_at = make_now();
+
+                Ok(Issue {
+                    id: issue_id,
+                    author_user_id: UserId::from(2),
+                    title: non_empty("Login is broken"),
+                    body: body.clone(),
+                    state: IssueState::Open,
+                    labels: vec![],
+                    assignees: vec![],
+                    milestone: None,
+                    created_at,
+                    updated_at: created_at,
+                    author_association: AuthorAssociation::None,
+                })
+            });
+
+        githost_mock.expect_get_user().returning(|id| {
+            Ok(User {
+                id,
+                nickname: non_empty("reporter"),
+                kind: UserKind::User,
+            })
+        });
+
+        githost_mock
+    }
+
+    fn make_event() -> GitEvent {
+        GitEvent {
+            repo_id: RepoId::from(1),
+            issue_id: IssueId::from(1 as usize),
+            kind: GitEventKind::NewIssue,
+        }
+    }
+
+    fn make_now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-10-01T12:00:00Z")
+            .unwrap()
+            .to_utc()
+    }
+
+    fn non_empty(s: &str) -> NonEmptyString {
+        NonEmptyString::from_str(s).unwrap()
+    }
+}
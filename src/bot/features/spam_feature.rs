@@ -0,0 +1,528 @@
+use std::str::FromStr;
+
+use log::{error, info};
+use non_empty_string::NonEmptyString;
+use serde::{Deserialize, Serialize};
+use smart_default::SmartDefault;
+
+use crate::{
+    githost::{
+        events::{GitEvent, GitEventKindName},
+        host::GitHost,
+        model::{AuthorAssociation, CloseReason, Issue, IssueId, RepoId},
+    },
+    llm::{
+        agent::{LlmAgent, LlmAgentConfig, LlmAgentError, RenderedMessages},
+        llm_trait::Llm,
+    },
+};
+
+use super::templates::IssueTemplate;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SpamFeatureError<GE, LE> {
+    #[error("error from LLM agent")]
+    LlmAgentError(#[source] LlmAgentError<LE>),
+
+    #[error("unable to perform Git host action")]
+    GitHostError(#[from] GE),
+}
+
+pub type Result<T, GE, LE> = std::result::Result<T, SpamFeatureError<GE, LE>>;
+
+/// How much every heuristic adds to the heuristic score. The sum is capped at 1.0.
+#[derive(SmartDefault, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct SpamHeuristicWeights {
+    /// Links make up a large part of the text.
+    #[default(0.3)]
+    pub link_density: f32,
+
+    /// The author has never contributed to any repository on the Git host. The Git host doesn't
+    /// tell the age of an account, so this is the closest sign of a new account.
+    #[default(0.2)]
+    pub new_account: f32,
+
+    /// The body is empty or contains only the headings and comments of the issue template.
+    #[default(0.3)]
+    pub empty_template: f32,
+
+    /// The title or the body contains one of the known spam phrases.
+    #[default(0.4)]
+    pub spam_phrase: f32,
+}
+
+#[derive(Deserialize)]
+pub struct SpamFeatureConfig {
+    agent: LlmAgentConfig,
+
+    #[serde(default = "GitEventKindName::default_subscription")]
+    events: Vec<GitEventKindName>,
+
+    /// Issues with the spam score at least this are closed.
+    #[serde(default = "default_threshold")]
+    threshold: f32,
+
+    /// Share of the LLM verdict in the spam score. The rest is the heuristic score. With `0.0`
+    /// the LLM is not called.
+    #[serde(default = "default_llm_weight")]
+    llm_weight: f32,
+
+    #[serde(default)]
+    heuristic_weights: SpamHeuristicWeights,
+
+    /// Case-insensitive phrases that are found only in spam.
+    #[serde(default = "default_phrases")]
+    phrases: Vec<String>,
+
+    #[serde(default = "default_label")]
+    label: NonEmptyString,
+
+    #[serde(default = "default_comment")]
+    comment: NonEmptyString,
+}
+
+fn default_threshold() -> f32 {
+    0.7
+}
+
+fn default_llm_weight() -> f32 {
+    0.5
+}
+
+fn default_phrases() -> Vec<String> {
+    [
+        "buy now",
+        "casino",
+        "escort",
+        "seo services",
+        "backlinks",
+        "customer care number",
+        "test issue",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
+fn default_label() -> NonEmptyString {
+    NonEmptyString::from_str("spam").unwrap()
+}
+
+fn default_comment() -> NonEmptyString {
+    NonEmptyString::from_str(
+        "This issue looks like spam, so it was closed automatically. If this is a mistake, please \
+         leave a comment and a maintainer will reopen it.",
+    )
+    .unwrap()
+}
+
+pub struct SpamOptions {
+    pub threshold: f32,
+    pub llm_weight: f32,
+    pub heuristic_weights: SpamHeuristicWeights,
+    pub phrases: Vec<String>,
+    pub label: NonEmptyString,
+    pub comment: NonEmptyString,
+}
+
+pub struct SpamFeature<G, L> {
+    githost: G,
+    agent: LlmAgent<L, SpamFeatureContext>,
+    events: Vec<GitEventKindName>,
+    options: SpamOptions,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SpamFeatureContext {
+    pub issue: IssueTemplate,
+
+    /// Names of the heuristics that matched the issue.
+    pub signals: Vec<&'static str>,
+}
+
+impl<G: GitHost, L: Llm> SpamFeature<G, L> {
+    pub async fn build_from_config(
+        config: SpamFeatureConfig,
+        githost: G,
+        llm: L,
+    ) -> Result<Self, G::Error, L::Error> {
+        let agent = LlmAgent::build_from_config(llm, config.agent)
+            .map_err(SpamFeatureError::LlmAgentError)?;
+
+        Ok(Self::new(
+            githost,
+            agent,
+            config.events,
+            SpamOptions {
+                threshold: config.threshold,
+                llm_weight: config.llm_weight,
+                heuristic_weights: config.heuristic_weights,
+                phrases: config.phrases,
+                label: config.label,
+                comment: config.comment,
+            },
+        ))
+    }
+
+    pub fn new(
+        githost: G,
+        agent: LlmAgent<L, SpamFeatureContext>,
+        events: Vec<GitEventKindName>,
+        options: SpamOptions,
+    ) -> Self {
+        Self {
+            githost,
+            agent,
+            events,
+            options,
+        }
+    }
+
+    /// Returns whether the issue was closed as spam, so that the other features should leave it
+    /// alone.
+    pub async fn process_event(&self, event: &GitEvent) -> Result<bool, G::Error, L::Error> {
+        if !self.events.contains(&event.kind.name()) {
+            return Ok(false);
+        }
+
+        let issue = self
+            .githost
+            .get_issue(event.repo_id, event.issue_id)
+            .await?;
+
+        // People of the project are never treated as spammers.
+        if matches!(
+            issue.author_association,
+            AuthorAssociation::Owner | AuthorAssociation::Member | AuthorAssociation::Collaborator
+        ) {
+            return Ok(false);
+        }
+
+        let (heuristic_score, signals) = self.heuristic_score(&issue);
+
+        let llm_score = if self.options.llm_weight > 0.0 {
+            let author = self.githost.get_user(issue.author_user_id).await?;
+
+            let context = SpamFeatureContext {
+                issue: (issue, author).into(),
+                signals,
+            };
+
+            let ai_message = self
+                .agent
+                .process(&context)
+                .await
+                .map_err(SpamFeatureError::LlmAgentError)?;
+
+            parse_llm_score(ai_message.as_str())
+        } else {
+            0.0
+        };
+
+        let score =
+            (1.0 - self.options.llm_weight) * heuristic_score + self.options.llm_weight * llm_score;
+
+        info!(
+            "Spam score of issue {} is {:.2} (heuristics {:.2}, LLM {:.2})",
+            *event.issue_id, score, heuristic_score, llm_score
+        );
+
+        if score < self.options.threshold {
+            return Ok(false);
+        }
+
+        self.githost
+            .make_comment(event.repo_id, event.issue_id, self.options.comment.clone())
+            .await?;
+
+        self.githost
+            .assign_label(event.repo_id, event.issue_id, self.options.label.clone())
+            .await?;
+
+        self.githost
+            .close_issue(event.repo_id, event.issue_id, CloseReason::NotPlanned)
+            .await?;
+
+        Ok(true)
+    }
+
+    pub async fn render(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+    ) -> Result<RenderedMessages, G::Error, L::Error> {
+        let issue = self.githost.get_issue(repo_id, issue_id).await?;
+        let author = self.githost.get_user(issue.author_user_id).await?;
+
+        let (_, signals) = self.heuristic_score(&issue);
+
+        let context = SpamFeatureContext {
+            issue: (issue, author).into(),
+            signals,
+        };
+
+        self.agent
+            .render(&context)
+            .map_err(SpamFeatureError::LlmAgentError)
+    }
+
+    /// Score of the cheap checks, from 0.0 to 1.0, and the names of the checks that matched.
+    fn heuristic_score(&self, issue: &Issue) -> (f32, Vec<&'static str>) {
+        let weights = &self.options.heuristic_weights;
+
+        let mut score = 0.0;
+        let mut signals = Vec::new();
+
+        if has_high_link_density(&issue.body) {
+            score += weights.link_density;
+            signals.push("link_density");
+        }
+
+        if issue.author_association == AuthorAssociation::FirstTimer {
+            score += weights.new_account;
+            signals.push("new_account");
+        }
+
+        if is_empty_template(&issue.body) {
+            score += weights.empty_template;
+            signals.push("empty_template");
+        }
+
+        let text = format!("{}\n{}", issue.title, issue.body).to_lowercase();
+
+        if self
+            .options
+            .phrases
+            .iter()
+            .any(|phrase| text.contains(&phrase.to_lowercase()))
+        {
+            score += weights.spam_phrase;
+            signals.push("spam_phrase");
+        }
+
+        (f32::min(score, 1.0), signals)
+    }
+}
+
+/// At least two links, and at least one link per ten words.
+fn has_high_link_density(body: &str) -> bool {
+    let words = body.split_whitespace().count();
+
+    let links = body
+        .split_whitespace()
+        .filter(|word| word.contains("http://") || word.contains("https://"))
+        .count();
+
+    links >= 2 && links * 10 >= words
+}
+
+/// Nothing is left after removing Markdown headings and HTML comments.
+fn is_empty_template(body: &str) -> bool {
+    let mut rest = body.to_string();
+
+    while let Some(start) = rest.find("<!--") {
+        let end = rest[start..]
+            .find("-->")
+            .map(|end| start + end + "-->".len())
+            .unwrap_or(rest.len());
+
+        rest.replace_range(start..end, "");
+    }
+
+    rest.lines()
+        .map(str::trim)
+        .all(|line| line.is_empty() || line.starts_with('#'))
+}
+
+/// Parse the LLM answer. The LLM should answer with a single number from 0.0 to 1.0.
+fn parse_llm_score(ai_message: &str) -> f32 {
+    match ai_message.trim().parse::<f32>() {
+        Ok(score) => score.clamp(0.0, 1.0),
+        Err(e) => {
+            error!("AI has generated malformed result: {:?}. Skipping.", e);
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chrono::{DateTime, Utc};
+    use mockall::predicate;
+    use non_empty_string::NonEmptyString;
+
+    use crate::{
+        bot::features::spam_feature::{
+            default_comment, default_label, default_phrases, is_empty_template, SpamFeature,
+            SpamHeuristicWeights, SpamOptions,
+        },
+        githost::{
+            events::{GitEvent, GitEventKind, GitEventKindName},
+            host::MockGitHost,
+            model::{
+                AuthorAssociation, CloseReason, CommentId, Issue, IssueId, IssueState, RepoId,
+                User, UserId, UserKind,
+            },
+        },
+        llm::{
+            agent::LlmAgent,
+            llm_trait::{CompletionParameters, MockLlm},
+            messages::AiMessage,
+        },
+    };
+
+    const TEST_SYSTEM_MESSAGE: &str = "You are a bot that detects spam.";
+    const TEST_USER_MESSAGE: &str = "The issue body is: {{ context.issue.body }}";
+
+    const SPAM_BODY: &str =
+        "Best SEO services https://example.com/seo https://example.com/backlinks";
+
+    #[tokio::test]
+    async fn closes_spam() {
+        let mut githost_mock = make_githost_mock(AuthorAssociation::FirstTimer, SPAM_BODY);
+
+        githost_mock
+            .expect_make_comment()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(IssueId::from(1 as usize)),
+                predicate::eq(default_comment()),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(CommentId::from(1)));
+
+        githost_mock
+            .expect_assign_label()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(IssueId::from(1 as usize)),
+                predicate::eq(default_label()),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        githost_mock
+            .expect_close_issue()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(IssueId::from(1 as usize)),
+                predicate::eq(CloseReason::NotPlanned),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        let mut llm_mock = MockLlm::new();
+
+        llm_mock
+            .expect_complete()
+            .times(1)
+            .returning(|_, _, _| Ok(AiMessage::from(non_empty("0.95"))));
+
+        assert!(make_feature(githost_mock, llm_mock)
+            .process_event(&make_event())
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn keeps_issues_of_members() {
+        let githost_mock = make_githost_mock(AuthorAssociation::Member, SPAM_BODY);
+
+        let llm_mock = MockLlm::new();
+
+        assert!(!make_feature(githost_mock, llm_mock)
+            .process_event(&make_event())
+            .await
+            .unwrap());
+    }
+
+    #[test]
+    fn detects_empty_template() {
+        assert!(is_empty_template(
+            "### Describe the bug\n<!-- A clear description. -->\n\n### Steps\n"
+        ));
+        assert!(!is_empty_template("### Describe the bug\nIt crashes."));
+    }
+
+    fn make_feature(
+        githost_mock: MockGitHost,
+        llm_mock: MockLlm,
+    ) -> SpamFeature<MockGitHost, MockLlm> {
+        SpamFeature::new(
+            githost_mock,
+            LlmAgent::build_raw(
+                llm_mock,
+                TEST_SYSTEM_MESSAGE.try_into().unwrap(),
+                TEST_USER_MESSAGE.try_into().unwrap(),
+                CompletionParameters::default(),
+            )
+            .unwrap(),
+            vec![GitEventKindName::NewIssue],
+            SpamOptions {
+                threshold: 0.7,
+                llm_weight: 0.5,
+                heuristic_weights: SpamHeuristicWeights::default(),
+                phrases: default_phrases(),
+                label: default_label(),
+                comment: default_comment(),
+            },
+        )
+    }
+
+    fn make_githost_mock(author_association: AuthorAssociation, body: &str) -> MockGitHost {
+        let mut githost_mock = MockGitHost::new();
+
+        let body = body.to_string();
+
+        githost_mock
+            .expect_get_issue()
+            .returning(move |_, issue_id| {
+                let created_at = make_now();
+
+                Ok(Issue {
+                    id: issue_id,
+                    author_user_id: UserId::from(2),
+                    title: non_empty("Great offer"),
+                    body: body.clone(),
+                    state: IssueState::Open,
+                    labels: vec![],
+                    assignees: vec![],
+                    milestone: None,
+                    created_at,
+                    updated_at: created_at,
+                    author_association,
+                })
+            });
+
+        githost_mock.expect_get_user().returning(|id| {
+            Ok(User {
+                id,
+                nickname: non_empty("spammer"),
+                kind: UserKind::User,
+            })
+        });
+
+        githost_mock
+    }
+
+    fn make_event() -> GitEvent {
+        GitEvent {
+            repo_id: RepoId::from(1),
+            issue_id: IssueId::from(1 as usize),
+            kind: GitEventKind::NewIssue,
+        }
+    }
+
+    fn make_now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-10-01T12:00:00Z")
+            .unwrap()
+            .to_utc()
+    }
+
+    fn non_empty(s: &str) -> NonEmptyString {
+        NonEmptyString::from_str(s).unwrap()
+    }
+}
@@ -0,0 +1,707 @@
+use std::{collections::HashMap, fmt::Debug, str::FromStr};
+
+use chrono::{DateTime, Duration, Utc};
+use log::{error, info};
+use non_empty_string::NonEmptyString;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bot::comment_store::is_own_comment,
+    githost::{
+        host::GitHost,
+        model::{CloseReason, Comment, Issue, IssueId, IssueQuery, RepoId, User, UserId},
+    },
+    llm::{
+        agent::{LlmAgent, LlmAgentConfig, LlmAgentError, RenderedMessages},
+        llm_trait::Llm,
+    },
+};
+
+use super::templates::{CommentTemplate, IssueTemplate};
+
+/// Hidden marker that identifies the nudge comments of the bot.
+const NUDGE_MARKER: &str = "<!-- gib:stale-nudge -->";
+
+#[derive(Debug, thiserror::Error)]
+pub enum StaleFeatureError<GE, LE> {
+    #[error("error from LLM agent")]
+    LlmAgentError(#[source] LlmAgentError<LE>),
+
+    #[error("unable to perform Git host action")]
+    GitHostError(#[from] GE),
+}
+
+pub type Result<T, GE, LE> = std::result::Result<T, StaleFeatureError<GE, LE>>;
+
+#[derive(Deserialize)]
+pub struct StaleFeatureConfig {
+    agent: LlmAgentConfig,
+
+    /// Only issues with this label are checked.
+    #[serde(default = "default_label")]
+    label: NonEmptyString,
+
+    /// Days without activity of the reporter before the nudge.
+    #[serde(default = "default_nudge_after_days")]
+    nudge_after_days: i64,
+
+    /// Days after the nudge before the issue is closed.
+    #[serde(default = "default_close_after_days")]
+    close_after_days: i64,
+
+    #[serde(default)]
+    exempt_labels: Vec<NonEmptyString>,
+
+    /// Titles of milestones.
+    #[serde(default)]
+    exempt_milestones: Vec<NonEmptyString>,
+
+    /// Nicknames of assignees.
+    #[serde(default)]
+    exempt_assignees: Vec<NonEmptyString>,
+}
+
+fn default_label() -> NonEmptyString {
+    NonEmptyString::from_str("needs-info").unwrap()
+}
+
+fn default_nudge_after_days() -> i64 {
+    14
+}
+
+fn default_close_after_days() -> i64 {
+    7
+}
+
+pub struct StaleOptions {
+    pub label: NonEmptyString,
+    pub nudge_after: Duration,
+    pub close_after: Duration,
+    pub exempt_labels: Vec<NonEmptyString>,
+    pub exempt_milestones: Vec<NonEmptyString>,
+    pub exempt_assignees: Vec<NonEmptyString>,
+}
+
+pub struct StaleFeature<G, L> {
+    githost: G,
+    agent: LlmAgent<L, StaleFeatureContext>,
+    options: StaleOptions,
+}
+
+#[derive(Serialize, Debug)]
+pub struct StaleFeatureContext {
+    pub issue: IssueTemplate,
+    pub comments: Vec<CommentTemplate>,
+
+    /// Days since the last activity of the reporter.
+    pub days_inactive: i64,
+
+    /// Days until the issue will be closed if the reporter doesn't answer.
+    pub close_after_days: i64,
+}
+
+/// What should be done with a stale issue.
+#[derive(PartialEq, Debug)]
+enum StaleAction {
+    Nothing,
+    Nudge,
+    Close,
+}
+
+impl<G: GitHost, L: Llm> StaleFeature<G, L> {
+    pub async fn build_from_config(
+        config: StaleFeatureConfig,
+        githost: G,
+        llm: L,
+    ) -> Result<Self, G::Error, L::Error> {
+        let agent = LlmAgent::build_from_config(llm, config.agent)
+            .map_err(StaleFeatureError::LlmAgentError)?;
+
+        Ok(Self::new(
+            githost,
+            agent,
+            StaleOptions {
+                label: config.label,
+                nudge_after: Duration::days(config.nudge_after_days),
+                close_after: Duration::days(config.close_after_days),
+                exempt_labels: config.exempt_labels,
+                exempt_milestones: config.exempt_milestones,
+                exempt_assignees: config.exempt_assignees,
+            },
+        ))
+    }
+
+    pub fn new(githost: G, agent: LlmAgent<L, StaleFeatureContext>, options: StaleOptions) -> Self {
+        Self {
+            githost,
+            agent,
+            options,
+        }
+    }
+
+    /// Check all open issues with the label in the repository.
+    ///
+    /// Errors of single issues are logged and do not stop the check of the other issues.
+    pub async fn process_scheduled(&self, repo_id: RepoId) -> Result<(), G::Error, L::Error>
+    where
+        G::Error: Debug,
+        L::Error: Debug,
+    {
+        self.process_scheduled_at(repo_id, Utc::now()).await
+    }
+
+    async fn process_scheduled_at(
+        &self,
+        repo_id: RepoId,
+        now: DateTime<Utc>,
+    ) -> Result<(), G::Error, L::Error>
+    where
+        G::Error: Debug,
+        L::Error: Debug,
+    {
+        let query = IssueQuery {
+            labels: vec![self.options.label.clone()],
+            ..Default::default()
+        };
+
+        let mut issue_ids = Vec::new();
+
+        for page_number in 1.. {
+            let page = self
+                .githost
+                .search_issues(repo_id, &query, page_number)
+                .await?;
+
+            issue_ids.extend(page.issues.into_iter().map(|found| found.id));
+
+            if !page.has_next_page {
+                break;
+            }
+        }
+
+        for issue_id in issue_ids {
+            if let Err(e) = self.process_issue(repo_id, issue_id, now).await {
+                error!("Unable to process stale issue #{}: {:?}", *issue_id, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn process_issue(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+        now: DateTime<Utc>,
+    ) -> Result<(), G::Error, L::Error> {
+        let issue = self.githost.get_issue(repo_id, issue_id).await?;
+
+        if self.is_exempt(&issue) {
+            return Ok(());
+        }
+
+        let comments = self.githost.list_comments(repo_id, issue_id).await?;
+
+        let mut last_nudge = None;
+
+        for comment in &comments {
+            if comment.body.as_str().contains(NUDGE_MARKER)
+                && is_own_comment(&self.githost, comment).await?
+            {
+                last_nudge = last_nudge.max(Some(comment.created_at));
+            }
+        }
+
+        match self.decide(&issue, &comments, last_nudge, now) {
+            StaleAction::Nothing => {}
+
+            StaleAction::Nudge => {
+                info!("Nudging the reporter of stale issue #{}", *issue_id);
+
+                let context = self.make_context(issue, comments, now).await?;
+
+                let ai_message = self
+                    .agent
+                    .process(&context)
+                    .await
+                    .map_err(StaleFeatureError::LlmAgentError)?;
+
+                self.githost
+                    .make_comment(
+                        repo_id,
+                        issue_id,
+                        format!("{}\n{}", NUDGE_MARKER, ai_message)
+                            .try_into()
+                            .expect("nudge always contains the marker"),
+                    )
+                    .await?;
+            }
+
+            StaleAction::Close => {
+                info!("Closing stale issue #{}", *issue_id);
+
+                self.githost
+                    .close_issue(repo_id, issue_id, CloseReason::NotPlanned)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Render the nudge for the issue as if it was stale now.
+    pub async fn render(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+    ) -> Result<RenderedMessages, G::Error, L::Error> {
+        let issue = self.githost.get_issue(repo_id, issue_id).await?;
+        let comments = self.githost.list_comments(repo_id, issue_id).await?;
+
+        let context = self.make_context(issue, comments, Utc::now()).await?;
+
+        self.agent
+            .render(&context)
+            .map_err(StaleFeatureError::LlmAgentError)
+    }
+
+    fn is_exempt(&self, issue: &Issue) -> bool {
+        issue
+            .labels
+            .iter()
+            .any(|label| self.options.exempt_labels.contains(label))
+            || issue
+                .milestone
+                .as_ref()
+                .is_some_and(|milestone| self.options.exempt_milestones.contains(&milestone.title))
+            || issue
+                .assignees
+                .iter()
+                .any(|assignee| self.options.exempt_assignees.contains(assignee))
+    }
+
+    fn decide(
+        &self,
+        issue: &Issue,
+        comments: &[Comment],
+        last_nudge: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> StaleAction {
+        let last_activity = last_reporter_activity(issue, comments);
+
+        match last_nudge {
+            // The reporter hasn't answered the nudge.
+            Some(nudged_at) if nudged_at > last_activity => {
+                if now - nudged_at >= self.options.close_after {
+                    StaleAction::Close
+                } else {
+                    StaleAction::Nothing
+                }
+            }
+
+            _ => {
+                if now - last_activity >= self.options.nudge_after {
+                    StaleAction::Nudge
+                } else {
+                    StaleAction::Nothing
+                }
+            }
+        }
+    }
+
+    async fn make_context(
+        &self,
+        issue: Issue,
+        comments: Vec<Comment>,
+        now: DateTime<Utc>,
+    ) -> Result<StaleFeatureContext, G::Error, L::Error> {
+        let days_inactive = (now - last_reporter_activity(&issue, &comments)).num_days();
+
+        let mut users: HashMap<usize, User> = HashMap::new();
+
+        let mut comment_templates = Vec::new();
+
+        for comment in comments {
+            let user = self.get_user_cached(&mut users, comment.user_id).await?;
+            comment_templates.push((comment, user).into());
+        }
+
+        let author = self
+            .get_user_cached(&mut users, issue.author_user_id)
+            .await?;
+
+        Ok(StaleFeatureContext {
+            issue: (issue, author).into(),
+            comments: comment_templates,
+            days_inactive,
+            close_after_days: self.options.close_after.num_days(),
+        })
+    }
+
+    async fn get_user_cached(
+        &self,
+        users: &mut HashMap<usize, User>,
+        user_id: UserId,
+    ) -> Result<User, G::Error, L::Error> {
+        if let Some(user) = users.get(&*user_id) {
+            return Ok(user.clone());
+        }
+
+        let user = self.githost.get_user(user_id).await?;
+
+        users.insert(*user_id, user.clone());
+
+        Ok(user)
+    }
+}
+
+fn last_reporter_activity(issue: &Issue, comments: &[Comment]) -> DateTime<Utc> {
+    comments
+        .iter()
+        .filter(|comment| comment.user_id == issue.author_user_id)
+        .map(|comment| comment.created_at)
+        .chain(std::iter::once(issue.created_at))
+        .max()
+        .expect("there is always the issue creation time")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chrono::{DateTime, Duration, Utc};
+    use mockall::predicate;
+    use non_empty_string::NonEmptyString;
+
+    use crate::{
+        bot::features::stale_feature::{StaleFeature, StaleOptions, NUDGE_MARKER},
+        githost::{
+            host::MockGitHost,
+            model::{
+                AuthorAssociation, CloseReason, Comment, CommentId, FoundIssue, Issue, IssueId,
+                IssueQuery, IssueState, IssuesPage, RepoId, User, UserId, UserKind,
+            },
+        },
+        llm::{
+            agent::LlmAgent,
+            llm_trait::{CompletionParameters, MockLlm},
+            messages::AiMessage,
+        },
+    };
+
+    const TEST_SYSTEM_MESSAGE: &str =
+        "You are a bot that politely reminds users to answer questions in their issues.";
+    const TEST_USER_MESSAGE: &str = "The issue is: {{ context.issue.body }}. It is inactive for {{ context.days_inactive }} days.";
+
+    #[tokio::test]
+    async fn nudges_inactive_reporter() {
+        let now = make_now();
+
+        let mut githost_mock = make_githost_mock(vec![], now - Duration::days(20), vec![]);
+
+        githost_mock
+            .expect_make_comment()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(IssueId::from(1 as usize)),
+                predicate::eq(non_empty(&format!(
+                    "{}\nCould you share your OS version?",
+                    NUDGE_MARKER
+                ))),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(CommentId::from(2)));
+
+        let feature = make_feature(githost_mock, true);
+
+        feature
+            .process_scheduled_at(RepoId::from(1), now)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn closes_after_nudge() {
+        let now = make_now();
+
+        let comments = vec![make_comment(
+            UserId::from(2),
+            &format!("{}\nCould you share your OS version?", NUDGE_MARKER),
+            now - Duration::days(8),
+        )];
+
+        let mut githost_mock = make_githost_mock(vec![], now - Duration::days(30), comments);
+
+        githost_mock
+            .expect_close_issue()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(IssueId::from(1 as usize)),
+                predicate::eq(CloseReason::NotPlanned),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        let feature = make_feature(githost_mock, false);
+
+        feature
+            .process_scheduled_at(RepoId::from(1), now)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn waits_after_reporter_answer() {
+        let now = make_now();
+
+        let comments = vec![
+            make_comment(
+                UserId::from(2),
+                &format!("{}\nCould you share your OS version?", NUDGE_MARKER),
+                now - Duration::days(10),
+            ),
+            make_comment(UserId::from(1), "I use Linux", now - Duration::days(9)),
+        ];
+
+        let mut githost_mock = make_githost_mock(vec![], now - Duration::days(30), comments);
+
+        githost_mock.expect_make_comment().never();
+        githost_mock.expect_close_issue().never();
+
+        let feature = make_feature(githost_mock, false);
+
+        feature
+            .process_scheduled_at(RepoId::from(1), now)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn ignores_quoted_nudge() {
+        let now = make_now();
+
+        let comments = vec![make_comment(
+            UserId::from(3),
+            &format!("> {}\n> Could you share your OS version?", NUDGE_MARKER),
+            now - Duration::days(8),
+        )];
+
+        let mut githost_mock = make_githost_mock(vec![], now - Duration::days(30), comments);
+
+        githost_mock
+            .expect_make_comment()
+            .times(1)
+            .returning(|_, _, _| Ok(CommentId::from(2)));
+
+        githost_mock.expect_close_issue().never();
+
+        let feature = make_feature(githost_mock, true);
+
+        feature
+            .process_scheduled_at(RepoId::from(1), now)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn continues_after_failed_issue() {
+        let now = make_now();
+
+        let mut githost_mock = MockGitHost::new();
+
+        githost_mock.expect_search_issues().returning(|_, _, _| {
+            Ok(IssuesPage {
+                issues: vec![found(1), found(2)],
+                has_next_page: false,
+                capped: false,
+            })
+        });
+
+        githost_mock
+            .expect_get_issue()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(IssueId::from(1 as usize)),
+            )
+            .times(1)
+            .returning(|_, _| Err(()));
+
+        githost_mock
+            .expect_get_issue()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(IssueId::from(2 as usize)),
+            )
+            .times(1)
+            .returning(move |_, issue_id| {
+                Ok(Issue {
+                    id: issue_id,
+                    author_user_id: UserId::from(1),
+                    title: non_empty("Program crashes"),
+                    body: "It crashes on start".into(),
+                    state: IssueState::Open,
+                    labels: vec![non_empty("pinned")],
+                    assignees: vec![],
+                    milestone: None,
+                    created_at: now,
+                    updated_at: now,
+                    author_association: AuthorAssociation::None,
+                })
+            });
+
+        let feature = make_feature(githost_mock, false);
+
+        feature
+            .process_scheduled_at(RepoId::from(1), now)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn skips_exempt_issue() {
+        let now = make_now();
+
+        let mut githost_mock = make_githost_mock(
+            vec![non_empty("needs-info"), non_empty("pinned")],
+            now - Duration::days(100),
+            vec![],
+        );
+
+        githost_mock.expect_make_comment().never();
+        githost_mock.expect_close_issue().never();
+
+        let feature = make_feature(githost_mock, false);
+
+        feature
+            .process_scheduled_at(RepoId::from(1), now)
+            .await
+            .unwrap();
+    }
+
+    fn make_feature(
+        githost_mock: MockGitHost,
+        expect_llm: bool,
+    ) -> StaleFeature<MockGitHost, MockLlm> {
+        let mut llm_mock = MockLlm::new();
+
+        if expect_llm {
+            llm_mock.expect_complete().times(1).returning(|_, _, _| {
+                Ok(AiMessage::from(non_empty(
+                    "Could you share your OS version?",
+                )))
+            });
+        } else {
+            llm_mock.expect_complete().never();
+        }
+
+        StaleFeature::new(
+            githost_mock,
+            LlmAgent::build_raw(
+                llm_mock,
+                TEST_SYSTEM_MESSAGE.try_into().unwrap(),
+                TEST_USER_MESSAGE.try_into().unwrap(),
+                CompletionParameters::default(),
+            )
+            .unwrap(),
+            StaleOptions {
+                label: non_empty("needs-info"),
+                nudge_after: Duration::days(14),
+                close_after: Duration::days(7),
+                exempt_labels: vec![non_empty("pinned")],
+                exempt_milestones: vec![],
+                exempt_assignees: vec![],
+            },
+        )
+    }
+
+    fn make_githost_mock(
+        labels: Vec<NonEmptyString>,
+        created_at: DateTime<Utc>,
+        comments: Vec<Comment>,
+    ) -> MockGitHost {
+        let mut githost_mock = MockGitHost::new();
+
+        githost_mock
+            .expect_search_issues()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(IssueQuery {
+                    labels: vec![non_empty("needs-info")],
+                    ..Default::default()
+                }),
+                predicate::eq(1),
+            )
+            .returning(|_, _, _| {
+                Ok(IssuesPage {
+                    issues: vec![found(1)],
+                    has_next_page: false,
+                    capped: false,
+                })
+            });
+
+        githost_mock
+            .expect_get_issue()
+            .returning(move |_, issue_id| {
+                Ok(Issue {
+                    id: issue_id,
+                    author_user_id: UserId::from(1),
+                    title: non_empty("Program crashes"),
+                    body: "It crashes on start".into(),
+                    state: IssueState::Open,
+                    labels: labels.clone(),
+                    assignees: vec![],
+                    milestone: None,
+                    created_at,
+                    updated_at: created_at,
+                    author_association: AuthorAssociation::None,
+                })
+            });
+
+        githost_mock
+            .expect_list_comments()
+            .returning(move |_, _| Ok(comments.clone()));
+
+        // User 2 is the bot.
+        githost_mock.expect_get_user().returning(|id| {
+            Ok(User {
+                id,
+                nickname: non_empty(if *id == 2 { "gib[bot]" } else { "InAnYan" }),
+                kind: UserKind::User,
+            })
+        });
+
+        githost_mock
+            .expect_get_self_name()
+            .return_const(non_empty("gib[bot]"));
+
+        githost_mock
+    }
+
+    fn make_comment(user_id: UserId, body: &str, created_at: DateTime<Utc>) -> Comment {
+        Comment {
+            id: CommentId::from(1),
+            user_id,
+            body: non_empty(body),
+            created_at,
+            updated_at: created_at,
+            author_association: AuthorAssociation::None,
+        }
+    }
+
+    fn found(id: usize) -> FoundIssue {
+        FoundIssue {
+            id: IssueId::from(id),
+            created_at: make_now(),
+        }
+    }
+
+    fn make_now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-10-01T12:00:00Z")
+            .unwrap()
+            .to_utc()
+    }
+
+    fn non_empty(s: &str) -> NonEmptyString {
+        NonEmptyString::from_str(s).unwrap()
+    }
+}
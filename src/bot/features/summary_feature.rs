@@ -0,0 +1,513 @@
+use non_empty_string::NonEmptyString;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bot::{
+        bot_features::FeatureName,
+        comment_store::{is_own_comment, CommentKey, CommentStore},
+    },
+    githost::{
+        events::{GitEvent, GitEventKindName},
+        host::GitHost,
+        model::{IssueId, RepoId},
+    },
+    llm::{
+        agent::{LlmAgent, LlmAgentConfig, LlmAgentError, RenderedMessages},
+        llm_trait::Llm,
+    },
+};
+
+use super::templates::PullRequestTemplate;
+
+/// Hidden marker that identifies the summary in a comment or in the pull request body.
+const SUMMARY_START_MARKER: &str = "<!-- gib:summary:start -->";
+const SUMMARY_END_MARKER: &str = "<!-- gib:summary:end -->";
+
+#[derive(Debug, thiserror::Error)]
+pub enum SummaryFeatureError<GE, LE> {
+    #[error("error from LLM agent")]
+    LlmAgentError(#[source] LlmAgentError<LE>),
+
+    #[error("unable to perform Git host action")]
+    GitHostError(#[from] GE),
+}
+
+pub type Result<T, GE, LE> = std::result::Result<T, SummaryFeatureError<GE, LE>>;
+
+/// Where the summary is written to.
+#[derive(Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SummaryTarget {
+    /// A comment on the pull request.
+    #[default]
+    Comment,
+
+    /// A marked section at the end of the pull request description.
+    Body,
+}
+
+#[derive(Deserialize)]
+pub struct SummaryFeatureConfig {
+    agent: LlmAgentConfig,
+
+    #[serde(default = "default_events")]
+    events: Vec<GitEventKindName>,
+
+    #[serde(default)]
+    target: SummaryTarget,
+
+    /// Diffs longer than this number of characters are truncated before sending them to the LLM.
+    #[serde(default = "default_max_diff_size")]
+    max_diff_size: usize,
+}
+
+fn default_events() -> Vec<GitEventKindName> {
+    vec![
+        GitEventKindName::NewPullRequest,
+        GitEventKindName::PullRequestSynchronized,
+    ]
+}
+
+fn default_max_diff_size() -> usize {
+    30000
+}
+
+pub struct SummaryFeature<G, L> {
+    githost: G,
+    agent: LlmAgent<L, SummaryFeatureContext>,
+    events: Vec<GitEventKindName>,
+    target: SummaryTarget,
+    max_diff_size: usize,
+    comments: CommentStore,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SummaryFeatureContext {
+    pub pull_request: PullRequestTemplate,
+    pub diff: String,
+    pub is_diff_truncated: bool,
+}
+
+impl<G: GitHost, L: Llm> SummaryFeature<G, L> {
+    pub async fn build_from_config(
+        config: SummaryFeatureConfig,
+        githost: G,
+        llm: L,
+        comments: CommentStore,
+    ) -> Result<Self, G::Error, L::Error> {
+        let agent = LlmAgent::build_from_config(llm, config.agent)
+            .map_err(SummaryFeatureError::LlmAgentError)?;
+
+        Ok(Self::new(
+            githost,
+            agent,
+            config.events,
+            config.target,
+            config.max_diff_size,
+            comments,
+        ))
+    }
+
+    pub fn new(
+        githost: G,
+        agent: LlmAgent<L, SummaryFeatureContext>,
+        events: Vec<GitEventKindName>,
+        target: SummaryTarget,
+        max_diff_size: usize,
+        comments: CommentStore,
+    ) -> Self {
+        Self {
+            githost,
+            agent,
+            events,
+            target,
+            max_diff_size,
+            comments,
+        }
+    }
+
+    pub async fn process_event(&self, event: &GitEvent) -> Result<(), G::Error, L::Error> {
+        if !self.events.contains(&event.kind.name()) {
+            return Ok(());
+        }
+
+        let context = self.make_context(event.repo_id, event.issue_id).await?;
+
+        let ai_message = self
+            .agent
+            .process(&context)
+            .await
+            .map_err(SummaryFeatureError::LlmAgentError)?;
+
+        match self.target {
+            SummaryTarget::Comment => {
+                self.write_comment(event.repo_id, event.issue_id, &ai_message)
+                    .await?
+            }
+
+            SummaryTarget::Body => {
+                self.write_body(
+                    event.repo_id,
+                    event.issue_id,
+                    &context.pull_request.body,
+                    &ai_message,
+                )
+                .await?
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn render(
+        &self,
+        repo_id: RepoId,
+        pull_request_id: IssueId,
+    ) -> Result<RenderedMessages, G::Error, L::Error> {
+        let context = self.make_context(repo_id, pull_request_id).await?;
+
+        self.agent
+            .render(&context)
+            .map_err(SummaryFeatureError::LlmAgentError)
+    }
+
+    /// Update the previous summary comment, so that new pushes don't produce more comments.
+    async fn write_comment(
+        &self,
+        repo_id: RepoId,
+        pull_request_id: IssueId,
+        summary: &NonEmptyString,
+    ) -> Result<(), G::Error, L::Error> {
+        let message: NonEmptyString = wrap_summary(summary)
+            .try_into()
+            .expect("summary with markers is never empty");
+
+        let key = CommentKey {
+            repo_id,
+            issue_id: pull_request_id,
+            feature: FeatureName::Summary,
+        };
+
+        // Without a persistent storage the store is empty after a restart, so the comment is also
+        // searched by the marker.
+        if self.comments.get(&key).await.is_none() {
+            let comments = self.githost.list_comments(repo_id, pull_request_id).await?;
+
+            for comment in comments {
+                if comment.body.as_str().contains(SUMMARY_START_MARKER)
+                    && is_own_comment(&self.githost, &comment).await?
+                {
+                    self.comments.set(key, comment.id).await;
+                    break;
+                }
+            }
+        }
+
+        self.comments
+            .upsert_comment(&self.githost, key, message)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn write_body(
+        &self,
+        repo_id: RepoId,
+        pull_request_id: IssueId,
+        body: &str,
+        summary: &NonEmptyString,
+    ) -> Result<(), G::Error, L::Error> {
+        self.githost
+            .edit_pull_request_body(repo_id, pull_request_id, replace_summary(body, summary))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn make_context(
+        &self,
+        repo_id: RepoId,
+        pull_request_id: IssueId,
+    ) -> Result<SummaryFeatureContext, G::Error, L::Error> {
+        let pull_request = self
+            .githost
+            .get_pull_request(repo_id, pull_request_id)
+            .await?;
+
+        let author = self.githost.get_user(pull_request.author_user_id).await?;
+
+        let mut diff = self
+            .githost
+            .get_pull_request_diff(repo_id, pull_request_id)
+            .await?;
+
+        let is_diff_truncated = diff.len() > self.max_diff_size;
+
+        if is_diff_truncated {
+            let mut end = self.max_diff_size;
+
+            while !diff.is_char_boundary(end) {
+                end -= 1;
+            }
+
+            diff.truncate(end);
+        }
+
+        Ok(SummaryFeatureContext {
+            pull_request: (pull_request, author).into(),
+            diff,
+            is_diff_truncated,
+        })
+    }
+}
+
+fn wrap_summary(summary: &NonEmptyString) -> String {
+    format!(
+        "{}\n{}\n{}",
+        SUMMARY_START_MARKER, summary, SUMMARY_END_MARKER
+    )
+}
+
+/// Replace the marked summary section of the body, or append it if there is none yet.
+fn replace_summary(body: &str, summary: &NonEmptyString) -> String {
+    let section = wrap_summary(summary);
+
+    if let Some(start) = body.find(SUMMARY_START_MARKER) {
+        if let Some(end) = body[start..].find(SUMMARY_END_MARKER) {
+            let end = start + end + SUMMARY_END_MARKER.len();
+
+            return format!("{}{}{}", &body[..start], section, &body[end..]);
+        }
+    }
+
+    if body.trim().is_empty() {
+        section
+    } else {
+        format!("{}\n\n{}", body.trim_end(), section)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use mockall::predicate;
+    use non_empty_string::NonEmptyString;
+
+    use crate::{
+        bot::{
+            comment_store::CommentStore,
+            features::summary_feature::{
+                replace_summary, SummaryFeature, SummaryTarget, SUMMARY_END_MARKER,
+                SUMMARY_START_MARKER,
+            },
+        },
+        githost::{
+            events::{GitEvent, GitEventKind, GitEventKindName},
+            host::MockGitHost,
+            model::{
+                AuthorAssociation, Comment, CommentId, IssueId, PullRequest, RepoId, User, UserId,
+                UserKind,
+            },
+        },
+        llm::{
+            agent::LlmAgent,
+            llm_trait::{CompletionParameters, MockLlm},
+            messages::AiMessage,
+        },
+    };
+
+    const TEST_SYSTEM_MESSAGE: &str = "You are a bot that summarizes pull requests on GitHub.";
+    const TEST_USER_MESSAGE: &str = "Summarize this diff: {{ context.diff }}";
+
+    #[tokio::test]
+    async fn makes_new_comment() {
+        let mut githost_mock = make_githost_mock();
+
+        githost_mock
+            .expect_list_comments()
+            .returning(|_, _| Ok(vec![]));
+
+        githost_mock
+            .expect_make_comment()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(IssueId::from(2 as usize)),
+                predicate::eq(expected_message()),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(CommentId::from(12)));
+
+        let feature = make_feature(githost_mock, SummaryTarget::Comment);
+
+        feature.process_event(&make_event()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn updates_previous_comment() {
+        let mut githost_mock = make_githost_mock();
+
+        githost_mock.expect_list_comments().returning(|_, _| {
+            Ok(vec![
+                // A user quoting the bot's comment.
+                Comment {
+                    id: CommentId::from(10),
+                    user_id: UserId::from(2),
+                    body: format!("> {}\n> Old summary\n\nNice work!", SUMMARY_START_MARKER)
+                        .try_into()
+                        .unwrap(),
+                    created_at: Utc::now(),
+                    updated_at: Utc::now(),
+                    author_association: AuthorAssociation::None,
+                },
+                Comment {
+                    id: CommentId::from(11),
+                    user_id: UserId::from(3),
+                    body: format!(
+                        "{}\nOld summary\n{}",
+                        SUMMARY_START_MARKER, SUMMARY_END_MARKER
+                    )
+                    .try_into()
+                    .unwrap(),
+                    created_at: Utc::now(),
+                    updated_at: Utc::now(),
+                    author_association: AuthorAssociation::None,
+                },
+            ])
+        });
+
+        githost_mock
+            .expect_edit_comment()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(CommentId::from(11)),
+                predicate::eq(expected_message()),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        githost_mock.expect_make_comment().never();
+
+        let feature = make_feature(githost_mock, SummaryTarget::Comment);
+
+        feature.process_event(&make_event()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn fills_body_section() {
+        let mut githost_mock = make_githost_mock();
+
+        githost_mock
+            .expect_edit_pull_request_body()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(IssueId::from(2 as usize)),
+                predicate::eq(format!("Fixes #1\n\n{}", expected_message())),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        let feature = make_feature(githost_mock, SummaryTarget::Body);
+
+        feature.process_event(&make_event()).await.unwrap();
+    }
+
+    #[test]
+    fn replaces_summary_section() {
+        let body = format!(
+            "Before\n{}\nOld summary\n{}\nAfter",
+            SUMMARY_START_MARKER, SUMMARY_END_MARKER
+        );
+
+        assert_eq!(
+            replace_summary(&body, &"New summary".try_into().unwrap()),
+            format!(
+                "Before\n{}\nNew summary\n{}\nAfter",
+                SUMMARY_START_MARKER, SUMMARY_END_MARKER
+            )
+        );
+    }
+
+    fn expected_message() -> non_empty_string::NonEmptyString {
+        format!(
+            "{}\nThis PR adds a greeting.\n{}",
+            SUMMARY_START_MARKER, SUMMARY_END_MARKER
+        )
+        .try_into()
+        .unwrap()
+    }
+
+    fn make_feature(
+        githost_mock: MockGitHost,
+        target: SummaryTarget,
+    ) -> SummaryFeature<MockGitHost, MockLlm> {
+        let mut llm_mock = MockLlm::new();
+
+        llm_mock.expect_complete().times(1).returning(|_, _, _| {
+            Ok(AiMessage::from(
+                NonEmptyString::try_from("This PR adds a greeting.").unwrap(),
+            ))
+        });
+
+        SummaryFeature::new(
+            githost_mock,
+            LlmAgent::build_raw(
+                llm_mock,
+                TEST_SYSTEM_MESSAGE.try_into().unwrap(),
+                TEST_USER_MESSAGE.try_into().unwrap(),
+                CompletionParameters::default(),
+            )
+            .unwrap(),
+            vec![GitEventKindName::NewPullRequest],
+            target,
+            1000,
+            CommentStore::default(),
+        )
+    }
+
+    fn make_githost_mock() -> MockGitHost {
+        let mut githost_mock = MockGitHost::new();
+
+        githost_mock.expect_get_pull_request().returning(|_, id| {
+            Ok(PullRequest {
+                id,
+                author_user_id: UserId::from(1),
+                title: "Add greeting".try_into().unwrap(),
+                body: "Fixes #1".into(),
+                base_ref: "main".into(),
+                head_ref: "greeting".into(),
+                head_sha: "6dcb09b5b57875f334f61aebed695e2e4193db5e".into(),
+            })
+        });
+
+        // User 3 is the bot.
+        githost_mock.expect_get_user().returning(|id| {
+            Ok(User {
+                id,
+                nickname: if *id == 3 { "gib[bot]" } else { "InAnYan" }
+                    .try_into()
+                    .unwrap(),
+                kind: UserKind::User,
+            })
+        });
+
+        githost_mock
+            .expect_get_self_name()
+            .return_const(NonEmptyString::try_from("gib[bot]").unwrap());
+
+        githost_mock
+            .expect_get_pull_request_diff()
+            .returning(|_, _| {
+                Ok("diff --git a/src/main.rs b/src/main.rs\n+println!(\"Hello\");".into())
+            });
+
+        githost_mock
+    }
+
+    fn make_event() -> GitEvent {
+        GitEvent {
+            repo_id: RepoId::from(1),
+            issue_id: IssueId::from(2 as usize),
+            kind: GitEventKind::NewPullRequest,
+        }
+    }
+}
@@ -0,0 +1,772 @@
+use std::{str::FromStr, sync::Arc};
+
+use chrono::{Duration, Utc};
+use log::{info, warn};
+use non_empty_string::NonEmptyString;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bot::{
+        bot_features::FeatureName,
+        comment_store::{CommentKey, CommentStore},
+    },
+    githost::{
+        events::{GitEvent, GitEventKindName},
+        host::GitHost,
+        model::{Issue, IssueId, RepoId},
+    },
+    llm::{
+        agent::{LlmAgent, LlmAgentConfig, LlmAgentError, RenderedMessages},
+        llm_trait::Llm,
+    },
+    storage::storage_trait::{CachedRepoSettings, Storage},
+};
+
+use super::templates::IssueTemplate;
+
+/// What GitHub writes into the issue body for an optional field that was left empty.
+const NO_RESPONSE: &str = "_No response_";
+
+/// How long the parsed issue forms of a repository are used before they are fetched again.
+const FORMS_CACHE_MINUTES: i64 = 60;
+
+/// Kind of the cached repository settings that holds the issue forms.
+const FORMS_CACHE_KIND: &str = "issue_forms";
+
+#[derive(Debug, thiserror::Error)]
+pub enum TemplateComplianceFeatureError<GE, LE> {
+    #[error("error from LLM agent")]
+    LlmAgentError(#[source] LlmAgentError<LE>),
+
+    #[error("unable to perform Git host action")]
+    GitHostError(#[from] GE),
+}
+
+pub type Result<T, GE, LE> = std::result::Result<T, TemplateComplianceFeatureError<GE, LE>>;
+
+#[derive(Deserialize)]
+pub struct TemplateComplianceFeatureConfig {
+    agent: LlmAgentConfig,
+
+    #[serde(default = "default_events")]
+    events: Vec<GitEventKindName>,
+
+    /// Directory of the issue forms in the repository.
+    #[serde(default = "default_templates_path")]
+    templates_path: String,
+
+    /// Label for issues with missing information.
+    #[serde(default = "default_label")]
+    label: NonEmptyString,
+}
+
+fn default_events() -> Vec<GitEventKindName> {
+    vec![GitEventKindName::NewIssue, GitEventKindName::IssueEdited]
+}
+
+fn default_templates_path() -> String {
+    ".github/ISSUE_TEMPLATE".into()
+}
+
+fn default_label() -> NonEmptyString {
+    NonEmptyString::from_str("needs-info").unwrap()
+}
+
+/// A field of an issue form that the rules can check.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct FormField {
+    pub label: String,
+    pub required: bool,
+
+    /// Texts that mean the field was not filled in: the placeholder and the default value.
+    pub placeholders: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct IssueForm {
+    pub name: String,
+    pub labels: Vec<String>,
+    pub fields: Vec<FormField>,
+}
+
+/// The YAML schema of GitHub issue forms, only the parts that are needed here.
+#[derive(Deserialize)]
+struct RawIssueForm {
+    name: String,
+
+    #[serde(default)]
+    labels: Option<RawLabels>,
+
+    body: Vec<RawFormElement>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawLabels {
+    /// Comma-separated.
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Deserialize)]
+struct RawFormElement {
+    #[serde(rename = "type")]
+    kind: String,
+
+    #[serde(default)]
+    attributes: RawFormAttributes,
+
+    #[serde(default)]
+    validations: RawFormValidations,
+}
+
+#[derive(Deserialize, Default)]
+struct RawFormAttributes {
+    #[serde(default)]
+    label: String,
+
+    #[serde(default)]
+    placeholder: String,
+
+    #[serde(default)]
+    value: Option<serde_yaml::Value>,
+}
+
+#[derive(Deserialize, Default)]
+struct RawFormValidations {
+    #[serde(default)]
+    required: bool,
+}
+
+/// Why a field is in the checklist.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FieldProblem {
+    Missing,
+    Empty,
+    Placeholder,
+}
+
+pub struct TemplateComplianceFeature<G, L> {
+    githost: G,
+    agent: LlmAgent<L, TemplateComplianceFeatureContext>,
+    events: Vec<GitEventKindName>,
+    templates_path: String,
+    label: NonEmptyString,
+    comments: CommentStore,
+
+    /// Caches the issue forms of the repositories.
+    storage: Arc<dyn Storage + Send + Sync>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TemplateComplianceFeatureContext {
+    pub issue: IssueTemplate,
+    pub forms: Vec<IssueFormTemplate>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct IssueFormTemplate {
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+impl<G: GitHost, L: Llm> TemplateComplianceFeature<G, L> {
+    pub async fn build_from_config(
+        config: TemplateComplianceFeatureConfig,
+        githost: G,
+        llm: L,
+        comments: CommentStore,
+        storage: Arc<dyn Storage + Send + Sync>,
+    ) -> Result<Self, G::Error, L::Error> {
+        let agent = LlmAgent::build_from_config(llm, config.agent)
+            .map_err(TemplateComplianceFeatureError::LlmAgentError)?;
+
+        Ok(Self::new(
+            githost,
+            agent,
+            config.events,
+            config.templates_path,
+            config.label,
+            comments,
+            storage,
+        ))
+    }
+
+    pub fn new(
+        githost: G,
+        agent: LlmAgent<L, TemplateComplianceFeatureContext>,
+        events: Vec<GitEventKindName>,
+        templates_path: String,
+        label: NonEmptyString,
+        comments: CommentStore,
+        storage: Arc<dyn Storage + Send + Sync>,
+    ) -> Self {
+        Self {
+            githost,
+            agent,
+            events,
+            templates_path,
+            label,
+            comments,
+            storage,
+        }
+    }
+
+    pub async fn process_event(&self, event: &GitEvent) -> Result<(), G::Error, L::Error> {
+        if !self.events.contains(&event.kind.name()) {
+            return Ok(());
+        }
+
+        let forms = self.fetch_forms(event.repo_id).await?;
+
+        if forms.is_empty() {
+            return Ok(());
+        }
+
+        let issue = self
+            .githost
+            .get_issue(event.repo_id, event.issue_id)
+            .await?;
+
+        let form = match choose_form(&forms, &issue) {
+            Some(form) => form,
+
+            // Every section was deleted, so only the LLM can tell what the issue is about.
+            None => {
+                let (context, forms) = self.make_context(issue.clone(), forms).await?;
+
+                let ai_message = self
+                    .agent
+                    .process(&context)
+                    .await
+                    .map_err(TemplateComplianceFeatureError::LlmAgentError)?;
+
+                let answer = ai_message.as_str().trim().trim_matches('`');
+
+                if answer.starts_with("EMPTY") {
+                    return Ok(());
+                }
+
+                match forms.into_iter().find(|form| form.name == answer) {
+                    Some(form) => form,
+                    None => {
+                        warn!("AI has chosen an unknown template: {}. Skipping.", answer);
+                        return Ok(());
+                    }
+                }
+            }
+        };
+
+        let problems = check_form(&form, &issue.body);
+
+        let key = CommentKey {
+            repo_id: event.repo_id,
+            issue_id: event.issue_id,
+            feature: FeatureName::TemplateCompliance,
+        };
+
+        if problems.is_empty() {
+            // The reporter has fixed the issue after the checklist.
+            if self.comments.get(&key).await.is_some() {
+                self.comments.retract_comment(&self.githost, &key).await?;
+
+                if issue.labels.contains(&self.label) {
+                    self.githost
+                        .remove_label(event.repo_id, event.issue_id, self.label.clone())
+                        .await?;
+                }
+            }
+
+            return Ok(());
+        }
+
+        info!(
+            "Issue {} misses {} fields of template {}",
+            *event.issue_id,
+            problems.len(),
+            form.name
+        );
+
+        self.comments
+            .upsert_comment(&self.githost, key, format_checklist(&form.name, &problems))
+            .await?;
+
+        if !issue.labels.contains(&self.label) {
+            self.githost
+                .assign_label(event.repo_id, event.issue_id, self.label.clone())
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn render(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+    ) -> Result<RenderedMessages, G::Error, L::Error> {
+        let forms = self.fetch_forms(repo_id).await?;
+        let issue = self.githost.get_issue(repo_id, issue_id).await?;
+
+        let (context, _) = self.make_context(issue, forms).await?;
+
+        self.agent
+            .render(&context)
+            .map_err(TemplateComplianceFeatureError::LlmAgentError)
+    }
+
+    async fn make_context(
+        &self,
+        issue: Issue,
+        forms: Vec<IssueForm>,
+    ) -> Result<(TemplateComplianceFeatureContext, Vec<IssueForm>), G::Error, L::Error> {
+        let author = self.githost.get_user(issue.author_user_id).await?;
+
+        let context = TemplateComplianceFeatureContext {
+            issue: (issue, author).into(),
+            forms: forms
+                .iter()
+                .map(|form| IssueFormTemplate {
+                    name: form.name.clone(),
+                    fields: form
+                        .fields
+                        .iter()
+                        .map(|field| field.label.clone())
+                        .collect(),
+                })
+                .collect(),
+        };
+
+        Ok((context, forms))
+    }
+
+    /// The forms are fetched again only when the cached ones are older than
+    /// `FORMS_CACHE_MINUTES`, so that the files are not read on every event.
+    async fn fetch_forms(&self, repo_id: RepoId) -> Result<Vec<IssueForm>, G::Error, L::Error> {
+        if let Some(forms) = self.cached_forms(repo_id).await {
+            return Ok(forms);
+        }
+
+        let mut forms = Vec::new();
+
+        for path in self
+            .githost
+            .list_directory(repo_id, self.templates_path.clone())
+            .await?
+        {
+            if !is_form_path(&path) {
+                continue;
+            }
+
+            let Some(content) = self.githost.get_file_content(repo_id, path.clone()).await? else {
+                continue;
+            };
+
+            match parse_issue_form(&content) {
+                Ok(form) => forms.push(form),
+                Err(e) => warn!("Unable to parse issue form {}: {}", path, e),
+            }
+        }
+
+        let cached = CachedRepoSettings {
+            settings: serde_json::to_string(&forms).expect("issue forms are serializable"),
+            fetched_at: Utc::now(),
+        };
+
+        if let Err(e) = self
+            .storage
+            .set_repo_settings(repo_id, FORMS_CACHE_KIND, cached)
+            .await
+        {
+            warn!("Unable to save the issue forms to the storage: {}", e);
+        }
+
+        Ok(forms)
+    }
+
+    async fn cached_forms(&self, repo_id: RepoId) -> Option<Vec<IssueForm>> {
+        let cached = match self
+            .storage
+            .get_repo_settings(repo_id, FORMS_CACHE_KIND)
+            .await
+        {
+            Ok(cached) => cached?,
+            Err(e) => {
+                warn!("Unable to read the issue forms from the storage: {}", e);
+                return None;
+            }
+        };
+
+        if Utc::now() - cached.fetched_at > Duration::minutes(FORMS_CACHE_MINUTES) {
+            return None;
+        }
+
+        match serde_json::from_str(&cached.settings) {
+            Ok(forms) => Some(forms),
+            Err(e) => {
+                warn!("Cached issue forms are invalid: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// Markdown templates have no structure to check, and the template chooser config is not a form.
+fn is_form_path(path: &str) -> bool {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+
+    (file_name.ends_with(".yml") || file_name.ends_with(".yaml"))
+        && file_name != "config.yml"
+        && file_name != "config.yaml"
+}
+
+fn parse_issue_form(content: &str) -> serde_yaml::Result<IssueForm> {
+    let raw: RawIssueForm = serde_yaml::from_str(content)?;
+
+    let labels = match raw.labels {
+        Some(RawLabels::One(labels)) => labels
+            .split(',')
+            .map(|label| label.trim().to_string())
+            .filter(|label| !label.is_empty())
+            .collect(),
+        Some(RawLabels::Many(labels)) => labels,
+        None => Vec::new(),
+    };
+
+    let fields = raw
+        .body
+        .into_iter()
+        // Markdown elements are not shown in the issue, and checkboxes have no free text.
+        .filter(|element| matches!(element.kind.as_str(), "input" | "textarea" | "dropdown"))
+        .map(|element| {
+            let mut placeholders = Vec::new();
+
+            if !element.attributes.placeholder.trim().is_empty() {
+                placeholders.push(element.attributes.placeholder.trim().to_string());
+            }
+
+            if let Some(serde_yaml::Value::String(value)) = element.attributes.value {
+                if !value.trim().is_empty() {
+                    placeholders.push(value.trim().to_string());
+                }
+            }
+
+            FormField {
+                label: element.attributes.label.trim().to_string(),
+                required: element.validations.required,
+                placeholders,
+            }
+        })
+        .filter(|field| !field.label.is_empty())
+        .collect();
+
+    Ok(IssueForm {
+        name: raw.name,
+        labels,
+        fields,
+    })
+}
+
+/// Split the issue body into the `### Heading` sections that GitHub makes from a form.
+fn parse_sections(body: &str) -> Vec<(String, String)> {
+    let mut sections: Vec<(String, String)> = Vec::new();
+
+    for line in body.lines() {
+        match line.strip_prefix("### ") {
+            Some(heading) => sections.push((heading.trim().to_string(), String::new())),
+            None => {
+                if let Some((_, content)) = sections.last_mut() {
+                    content.push_str(line);
+                    content.push('\n');
+                }
+            }
+        }
+    }
+
+    sections
+        .into_iter()
+        .map(|(heading, content)| (heading, content.trim().to_string()))
+        .collect()
+}
+
+/// The form that the issue follows: the one with the labels of the issue, otherwise the one with
+/// the most sections in the body. `None` if no section of any form is found.
+fn choose_form(forms: &[IssueForm], issue: &Issue) -> Option<IssueForm> {
+    let sections = parse_sections(&issue.body);
+
+    forms
+        .iter()
+        .map(|form| {
+            let labeled = !form.labels.is_empty()
+                && form
+                    .labels
+                    .iter()
+                    .all(|label| issue.labels.iter().any(|l| l.as_str() == label));
+
+            let matched = form
+                .fields
+                .iter()
+                .filter(|field| sections.iter().any(|(heading, _)| *heading == field.label))
+                .count();
+
+            (form, (labeled, matched))
+        })
+        .filter(|(_, (_, matched))| *matched > 0)
+        .max_by_key(|(_, score)| *score)
+        .map(|(form, _)| form.clone())
+}
+
+/// Required fields of the form that are missing, empty or still have the placeholder.
+fn check_form(form: &IssueForm, body: &str) -> Vec<(String, FieldProblem)> {
+    let sections = parse_sections(body);
+
+    form.fields
+        .iter()
+        .filter(|field| field.required)
+        .filter_map(|field| {
+            let problem = match sections.iter().find(|(heading, _)| *heading == field.label) {
+                None => FieldProblem::Missing,
+                Some((_, content)) if content.is_empty() || content == NO_RESPONSE => {
+                    FieldProblem::Empty
+                }
+                Some((_, content)) if field.placeholders.contains(content) => {
+                    FieldProblem::Placeholder
+                }
+                Some(_) => return None,
+            };
+
+            Some((field.label.clone(), problem))
+        })
+        .collect()
+}
+
+fn format_checklist(form_name: &str, problems: &[(String, FieldProblem)]) -> NonEmptyString {
+    let items = problems
+        .iter()
+        .map(|(label, problem)| {
+            let reason = match problem {
+                FieldProblem::Missing => "the section is missing",
+                FieldProblem::Empty => "the section is empty",
+                FieldProblem::Placeholder => "the section still has the example text",
+            };
+
+            format!("- [ ] **{}**: {}", label, reason)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        "Thank you for the issue! It seems to follow the **{}** template, but some required \
+         information is missing. Please edit the issue and fill in:\n\n{}",
+        form_name, items
+    )
+    .try_into()
+    .expect("checklist is never empty")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Arc};
+
+    use chrono::{DateTime, Utc};
+    use mockall::predicate;
+    use non_empty_string::NonEmptyString;
+
+    use crate::{
+        bot::{
+            comment_store::CommentStore,
+            features::template_compliance_feature::{
+                check_form, default_events, default_label, parse_issue_form, FieldProblem,
+                TemplateComplianceFeature,
+            },
+        },
+        githost::{
+            events::{GitEvent, GitEventKind},
+            host::MockGitHost,
+            model::{
+                AuthorAssociation, CommentId, Issue, IssueId, IssueState, RepoId, User, UserId,
+                UserKind,
+            },
+        },
+        llm::{
+            agent::LlmAgent,
+            llm_trait::{CompletionParameters, MockLlm},
+        },
+        storage::impls::sqlite_storage::SqliteStorage,
+    };
+
+    const BUG_REPORT_FORM: &str = r#"
+name: Bug report
+labels: ["bug"]
+body:
+  - type: markdown
+    attributes:
+      value: Thanks for taking the time to fill out this bug report!
+  - type: textarea
+    id: what-happened
+    attributes:
+      label: What happened?
+      placeholder: Tell us what you see!
+    validations:
+      required: true
+  - type: input
+    id: version
+    attributes:
+      label: Version
+      value: "0.0.0"
+    validations:
+      required: true
+  - type: textarea
+    id: logs
+    attributes:
+      label: Logs
+"#;
+
+    const TEST_SYSTEM_MESSAGE: &str = "You are a bot that finds the template of an issue.";
+    const TEST_USER_MESSAGE: &str = "The issue body is: {{ context.issue.body }}";
+
+    #[test]
+    fn checks_required_fields() {
+        let form = parse_issue_form(BUG_REPORT_FORM).unwrap();
+
+        assert_eq!(form.fields.len(), 3);
+
+        let problems = check_form(
+            &form,
+            "### What happened?\n\nIt crashes.\n\n### Version\n\n0.0.0\n\n### Logs\n\n_No response_",
+        );
+
+        assert_eq!(
+            problems,
+            vec![("Version".to_string(), FieldProblem::Placeholder)]
+        );
+
+        let problems = check_form(&form, "### What happened?\n\n_No response_\n");
+
+        assert_eq!(
+            problems,
+            vec![
+                ("What happened?".to_string(), FieldProblem::Empty),
+                ("Version".to_string(), FieldProblem::Missing)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn posts_checklist() {
+        let mut githost_mock = MockGitHost::new();
+
+        // The forms are fetched once and then taken from the storage.
+        githost_mock
+            .expect_list_directory()
+            .times(1)
+            .returning(|_, _| {
+                Ok(vec![
+                    ".github/ISSUE_TEMPLATE/bug_report.yml".into(),
+                    ".github/ISSUE_TEMPLATE/config.yml".into(),
+                    ".github/ISSUE_TEMPLATE/config.yaml".into(),
+                    ".github/ISSUE_TEMPLATE/question.md".into(),
+                ])
+            });
+
+        // Only the form is read.
+        githost_mock
+            .expect_get_file_content()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(".github/ISSUE_TEMPLATE/bug_report.yml".to_string()),
+            )
+            .times(1)
+            .returning(|_, _| Ok(Some(BUG_REPORT_FORM.into())));
+
+        githost_mock.expect_get_issue().returning(|_, issue_id| {
+            let created_at = make_now();
+
+            Ok(Issue {
+                id: issue_id,
+                author_user_id: UserId::from(2),
+                title: non_empty("It crashes"),
+                body: "### What happened?\n\nIt crashes.\n".into(),
+                state: IssueState::Open,
+                labels: vec![non_empty("bug")],
+                assignees: vec![],
+                milestone: None,
+                created_at,
+                updated_at: created_at,
+                author_association: AuthorAssociation::None,
+            })
+        });
+
+        githost_mock.expect_get_user().returning(|id| {
+            Ok(User {
+                id,
+                nickname: non_empty("reporter"),
+                kind: UserKind::User,
+            })
+        });
+
+        githost_mock
+            .expect_make_comment()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(IssueId::from(1 as usize)),
+                predicate::eq(non_empty(
+                    "Thank you for the issue! It seems to follow the **Bug report** template, but \
+                     some required information is missing. Please edit the issue and fill \
+                     in:\n\n- [ ] **Version**: the section is missing",
+                )),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(CommentId::from(1)));
+
+        githost_mock
+            .expect_assign_label()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(IssueId::from(1 as usize)),
+                predicate::eq(default_label()),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        let feature = TemplateComplianceFeature::new(
+            githost_mock,
+            LlmAgent::build_raw(
+                MockLlm::new(),
+                TEST_SYSTEM_MESSAGE.try_into().unwrap(),
+                TEST_USER_MESSAGE.try_into().unwrap(),
+                CompletionParameters::default(),
+            )
+            .unwrap(),
+            default_events(),
+            ".github/ISSUE_TEMPLATE".into(),
+            default_label(),
+            CommentStore::default(),
+            Arc::new(SqliteStorage::in_memory().unwrap()),
+        );
+
+        feature
+            .process_event(&GitEvent {
+                repo_id: RepoId::from(1),
+                issue_id: IssueId::from(1 as usize),
+                kind: GitEventKind::NewIssue,
+            })
+            .await
+            .unwrap();
+
+        feature
+            .render(RepoId::from(1), IssueId::from(1 as usize))
+            .await
+            .unwrap();
+    }
+
+    fn make_now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-10-01T12:00:00Z")
+            .unwrap()
+            .to_utc()
+    }
+
+    fn non_empty(s: &str) -> NonEmptyString {
+        NonEmptyString::from_str(s).unwrap()
+    }
+}
@@ -1,14 +1,28 @@
+use chrono::{DateTime, Utc};
 use non_empty_string::NonEmptyString;
 use serde::Serialize;
 
-use crate::githost::model::{Issue, IssueId, Label, User};
+use crate::githost::model::{
+    AuthorAssociation, ChangedFileStatus, Comment, Issue, IssueId, IssueState, Label, PullRequest,
+    User, UserKind,
+};
 
 #[derive(Serialize, Debug)]
 pub struct IssueTemplate {
     pub number: IssueId,
     pub author: AuthorTemplate,
+    pub author_association: AuthorAssociation,
     pub title: NonEmptyString,
     pub body: String, // Can be empty.
+    pub state: IssueState,
+    pub labels: Vec<NonEmptyString>,
+    pub assignees: Vec<NonEmptyString>,
+    pub milestone: Option<NonEmptyString>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+
+    /// Number of whole days since the issue was created.
+    pub age_days: i64,
 }
 
 impl From<(Issue, User)> for IssueTemplate {
@@ -16,21 +30,31 @@ impl From<(Issue, User)> for IssueTemplate {
         IssueTemplate {
             number: issue.id,
             author: user.into(),
+            author_association: issue.author_association,
             title: issue.title,
             body: issue.body,
+            state: issue.state,
+            labels: issue.labels,
+            assignees: issue.assignees,
+            milestone: issue.milestone.map(|milestone| milestone.title),
+            created_at: issue.created_at,
+            updated_at: issue.updated_at,
+            age_days: (Utc::now() - issue.created_at).num_days(),
         }
     }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Clone, Debug)]
 pub struct AuthorTemplate {
     pub nickname: NonEmptyString,
+    pub kind: UserKind,
 }
 
 impl From<User> for AuthorTemplate {
     fn from(value: User) -> Self {
         AuthorTemplate {
             nickname: value.nickname,
+            kind: value.kind,
         }
     }
 }
@@ -49,3 +73,50 @@ impl From<Label> for LabelTemplate {
         }
     }
 }
+
+#[derive(Serialize, Clone, Debug)]
+pub struct PullRequestTemplate {
+    pub number: IssueId,
+    pub author: AuthorTemplate,
+    pub title: NonEmptyString,
+    pub body: String, // Can be empty.
+    pub base_ref: String,
+    pub head_ref: String,
+}
+
+impl From<(PullRequest, User)> for PullRequestTemplate {
+    fn from((pull_request, user): (PullRequest, User)) -> Self {
+        PullRequestTemplate {
+            number: pull_request.id,
+            author: user.into(),
+            title: pull_request.title,
+            body: pull_request.body,
+            base_ref: pull_request.base_ref,
+            head_ref: pull_request.head_ref,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ChangedFileTemplate {
+    pub path: String,
+    pub status: ChangedFileStatus,
+    pub patch: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CommentTemplate {
+    pub author: AuthorTemplate,
+    pub body: NonEmptyString,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<(Comment, User)> for CommentTemplate {
+    fn from((comment, user): (Comment, User)) -> Self {
+        CommentTemplate {
+            author: user.into(),
+            body: comment.body,
+            created_at: comment.created_at,
+        }
+    }
+}
@@ -0,0 +1,679 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use non_empty_string::NonEmptyString;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bot::{
+        bot_features::FeatureName,
+        comment_store::{CommentKey, CommentStore},
+    },
+    githost::{
+        events::{GitEvent, GitEventKindName},
+        host::GitHost,
+        model::{IssueId, RepoId},
+    },
+    llm::{
+        agent::{LlmAgent, LlmAgentConfig, LlmAgentError, RenderedMessages},
+        llm_trait::Llm,
+    },
+    storage::storage_trait::{Assignment, Storage},
+};
+
+use super::templates::IssueTemplate;
+
+/// How many recent assignments are shown to the LLM.
+const RECENT_ASSIGNMENTS_LIMIT: usize = 20;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TriageAssignFeatureError<GE, LE> {
+    #[error("error from LLM agent")]
+    LlmAgentError(#[source] LlmAgentError<LE>),
+
+    #[error("unable to perform Git host action")]
+    GitHostError(#[from] GE),
+}
+
+pub type Result<T, GE, LE> = std::result::Result<T, TriageAssignFeatureError<GE, LE>>;
+
+/// What to do with the owners of the chosen area.
+#[derive(Deserialize, Clone, Copy, PartialEq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum TriageMode {
+    /// Assign the owners to the issue.
+    #[default]
+    Assign,
+
+    /// Write a comment that mentions the owners.
+    Mention,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Area {
+    pub name: NonEmptyString,
+
+    /// Explanation for the LLM of what issues belong to the area.
+    #[serde(default)]
+    pub description: String,
+
+    /// Nicknames of the owners, without the leading `@`.
+    pub owners: Vec<NonEmptyString>,
+
+    /// Pick only one owner in turn instead of all of them.
+    #[serde(default = "default_round_robin")]
+    pub round_robin: bool,
+}
+
+fn default_round_robin() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+pub struct TriageAssignFeatureConfig {
+    agent: LlmAgentConfig,
+
+    #[serde(default = "GitEventKindName::default_subscription")]
+    events: Vec<GitEventKindName>,
+
+    #[serde(default)]
+    mode: TriageMode,
+
+    #[serde(default)]
+    areas: Vec<Area>,
+
+    /// Path to a CODEOWNERS-style file in the repository. Every line of it becomes an area named
+    /// after its path pattern.
+    #[serde(default)]
+    codeowners_path: Option<String>,
+
+    /// Nicknames of people that should not get new issues now.
+    #[serde(default)]
+    out_of_office: Vec<NonEmptyString>,
+}
+
+pub struct TriageAssignOptions {
+    pub mode: TriageMode,
+    pub areas: Vec<Area>,
+    pub codeowners_path: Option<String>,
+    pub out_of_office: Vec<NonEmptyString>,
+}
+
+pub struct TriageAssignFeature<G, L> {
+    githost: G,
+    agent: LlmAgent<L, TriageAssignFeatureContext>,
+    events: Vec<GitEventKindName>,
+    options: TriageAssignOptions,
+    comments: CommentStore,
+
+    /// Remembers when the owners were chosen for the round-robin.
+    storage: Arc<dyn Storage + Send + Sync>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TriageAssignFeatureContext {
+    pub issue: IssueTemplate,
+    pub areas: Vec<AreaTemplate>,
+
+    /// The last assignment of every owner in every area, the newest first.
+    pub recent_assignments: Vec<AssignmentTemplate>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct AreaTemplate {
+    pub name: NonEmptyString,
+    pub description: String, // Can be empty.
+}
+
+#[derive(Serialize, Debug)]
+pub struct AssignmentTemplate {
+    pub area: String,
+    pub nickname: String,
+    pub assigned_at: DateTime<Utc>,
+}
+
+impl From<Assignment> for AssignmentTemplate {
+    fn from(value: Assignment) -> Self {
+        AssignmentTemplate {
+            area: value.area,
+            nickname: value.nickname,
+            assigned_at: value.assigned_at,
+        }
+    }
+}
+
+impl<G: GitHost, L: Llm> TriageAssignFeature<G, L> {
+    pub async fn build_from_config(
+        config: TriageAssignFeatureConfig,
+        githost: G,
+        llm: L,
+        comments: CommentStore,
+        storage: Arc<dyn Storage + Send + Sync>,
+    ) -> Result<Self, G::Error, L::Error> {
+        let agent = LlmAgent::build_from_config(llm, config.agent)
+            .map_err(TriageAssignFeatureError::LlmAgentError)?;
+
+        Ok(Self::new(
+            githost,
+            agent,
+            config.events,
+            TriageAssignOptions {
+                mode: config.mode,
+                areas: config.areas,
+                codeowners_path: config.codeowners_path,
+                out_of_office: config.out_of_office,
+            },
+            comments,
+            storage,
+        ))
+    }
+
+    pub fn new(
+        githost: G,
+        agent: LlmAgent<L, TriageAssignFeatureContext>,
+        events: Vec<GitEventKindName>,
+        options: TriageAssignOptions,
+        comments: CommentStore,
+        storage: Arc<dyn Storage + Send + Sync>,
+    ) -> Self {
+        Self {
+            githost,
+            agent,
+            events,
+            options,
+            comments,
+            storage,
+        }
+    }
+
+    pub async fn process_event(&self, event: &GitEvent) -> Result<(), G::Error, L::Error> {
+        if !self.events.contains(&event.kind.name()) {
+            return Ok(());
+        }
+
+        let (context, areas) = self.make_context(event.repo_id, event.issue_id).await?;
+
+        // Somebody has already taken care of the issue.
+        if !context.issue.assignees.is_empty() || areas.is_empty() {
+            return Ok(());
+        }
+
+        let ai_message = self
+            .agent
+            .process(&context)
+            .await
+            .map_err(TriageAssignFeatureError::LlmAgentError)?;
+
+        let answer = ai_message.as_str().trim().trim_matches('`');
+
+        if answer.starts_with("EMPTY") {
+            return Ok(());
+        }
+
+        let Some(area) = areas.iter().find(|area| area.name.as_str() == answer) else {
+            warn!("AI has chosen an unknown area: {}. Skipping.", answer);
+            return Ok(());
+        };
+
+        let owners = self.choose_owners(area).await;
+
+        if owners.is_empty() {
+            info!("All owners of area {} are out of office", area.name);
+            return Ok(());
+        }
+
+        match self.options.mode {
+            TriageMode::Assign => {
+                self.githost
+                    .set_assignees(event.repo_id, event.issue_id, owners)
+                    .await?
+            }
+
+            TriageMode::Mention => {
+                let mentions = owners
+                    .iter()
+                    .map(|owner| format!("@{}", owner))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                let message = format!(
+                    "{} this issue seems to belong to the `{}` area. Could you take a look?",
+                    mentions, area.name
+                );
+
+                self.comments
+                    .upsert_comment(
+                        &self.githost,
+                        CommentKey {
+                            repo_id: event.repo_id,
+                            issue_id: event.issue_id,
+                            feature: FeatureName::TriageAssign,
+                        },
+                        message
+                            .try_into()
+                            .expect("message always contains mentions"),
+                    )
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn render(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+    ) -> Result<RenderedMessages, G::Error, L::Error> {
+        let (context, _) = self.make_context(repo_id, issue_id).await?;
+
+        self.agent
+            .render(&context)
+            .map_err(TriageAssignFeatureError::LlmAgentError)
+    }
+
+    async fn make_context(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+    ) -> Result<(TriageAssignFeatureContext, Vec<Area>), G::Error, L::Error> {
+        let issue = self.githost.get_issue(repo_id, issue_id).await?;
+
+        let author = self.githost.get_user(issue.author_user_id).await?;
+
+        let mut areas = self.options.areas.clone();
+
+        if let Some(path) = &self.options.codeowners_path {
+            match self.githost.get_file_content(repo_id, path.clone()).await? {
+                Some(content) => areas.extend(parse_codeowners(&content)),
+                None => warn!("There is no {} file in the repository", path),
+            }
+        }
+
+        let recent_assignments = match self
+            .storage
+            .recent_assignments(RECENT_ASSIGNMENTS_LIMIT)
+            .await
+        {
+            Ok(assignments) => assignments.into_iter().map(Into::into).collect(),
+            Err(e) => {
+                warn!(
+                    "Unable to read the recent assignments from the storage: {}",
+                    e
+                );
+                Vec::new()
+            }
+        };
+
+        let context = TriageAssignFeatureContext {
+            issue: (issue, author).into(),
+            areas: areas
+                .iter()
+                .map(|area| AreaTemplate {
+                    name: area.name.clone(),
+                    description: area.description.clone(),
+                })
+                .collect(),
+            recent_assignments,
+        };
+
+        Ok((context, areas))
+    }
+
+    async fn choose_owners(&self, area: &Area) -> Vec<NonEmptyString> {
+        let available: Vec<NonEmptyString> = area
+            .owners
+            .iter()
+            .filter(|owner| !self.options.out_of_office.contains(owner))
+            .cloned()
+            .collect();
+
+        let owners = if !area.round_robin || available.is_empty() {
+            available
+        } else {
+            // The owner that got an issue of the area least recently (or never) goes next. Ties
+            // are resolved by the order in the config.
+            let mut next: Option<(NonEmptyString, Option<DateTime<Utc>>)> = None;
+
+            for owner in available {
+                let assigned_at = match self
+                    .storage
+                    .last_assignment(area.name.as_str(), owner.as_str())
+                    .await
+                {
+                    Ok(assigned_at) => assigned_at,
+                    Err(e) => {
+                        warn!("Unable to read the last assignment from the storage: {}", e);
+                        None
+                    }
+                };
+
+                if next
+                    .as_ref()
+                    .is_none_or(|(_, earliest)| assigned_at < *earliest)
+                {
+                    next = Some((owner, assigned_at));
+                }
+            }
+
+            let (owner, _) = next.expect("available owners are not empty");
+
+            vec![owner]
+        };
+
+        for owner in &owners {
+            if let Err(e) = self
+                .storage
+                .set_assignment(area.name.as_str(), owner.as_str(), Utc::now())
+                .await
+            {
+                warn!("Unable to save the assignment to the storage: {}", e);
+            }
+        }
+
+        owners
+    }
+}
+
+/// Parse a CODEOWNERS file. Team owners (`@org/team`) and emails are skipped, as they can't be
+/// assigned to issues.
+fn parse_codeowners(content: &str) -> Vec<Area> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+
+            let pattern = parts.next()?;
+
+            let owners: Vec<NonEmptyString> = parts
+                .filter_map(|owner| owner.strip_prefix('@'))
+                .filter(|owner| !owner.contains('/'))
+                .filter_map(|owner| owner.to_string().try_into().ok())
+                .collect();
+
+            if owners.is_empty() {
+                return None;
+            }
+
+            Some(Area {
+                name: pattern.to_string().try_into().ok()?,
+                description: format!("Code at `{}`", pattern),
+                owners,
+                round_robin: true,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Arc};
+
+    use chrono::Utc;
+    use mockall::predicate;
+    use non_empty_string::NonEmptyString;
+
+    use crate::{
+        bot::{
+            comment_store::CommentStore,
+            features::triage_assign_feature::{
+                parse_codeowners, Area, TriageAssignFeature, TriageAssignFeatureContext,
+                TriageAssignOptions, TriageMode,
+            },
+        },
+        githost::{
+            events::{GitEvent, GitEventKind, GitEventKindName},
+            host::MockGitHost,
+            model::{
+                AuthorAssociation, CommentId, Issue, IssueId, IssueState, RepoId, User, UserId,
+                UserKind,
+            },
+        },
+        llm::{
+            agent::LlmAgent,
+            llm_trait::{CompletionParameters, MockLlm},
+            messages::AiMessage,
+        },
+        storage::{impls::sqlite_storage::SqliteStorage, storage_trait::Storage},
+    };
+
+    const TEST_SYSTEM_MESSAGE: &str =
+        "You are a bot that routes issues to areas. Answer with the area name or EMPTY";
+    const TEST_USER_MESSAGE: &str = "{% for area in context.areas %}{{ area.name }}: {{ area.description }}\n{% endfor %}Recent:{% for assignment in context.recent_assignments %} {{ assignment.nickname }} in {{ assignment.area }}{% endfor %}\nIssue: {{ context.issue.body }}";
+
+    #[tokio::test]
+    async fn assigns_in_turn() {
+        let storage: Arc<dyn Storage + Send + Sync> = Arc::new(SqliteStorage::in_memory().unwrap());
+
+        // `bob` is out of office, so `alice` and `carol` take turns.
+        let feature = make_feature(
+            expect_assignees(&[("alice", 1), ("carol", 1)]),
+            "webhooks",
+            TriageMode::Assign,
+            storage.clone(),
+        );
+
+        for _ in 0..2 {
+            feature.process_event(&make_event()).await.unwrap();
+        }
+
+        // The turns are remembered after a restart.
+        let feature = make_feature(
+            expect_assignees(&[("alice", 1)]),
+            "webhooks",
+            TriageMode::Assign,
+            storage,
+        );
+
+        feature.process_event(&make_event()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn keeps_turns_per_area() {
+        let storage: Arc<dyn Storage + Send + Sync> = Arc::new(SqliteStorage::in_memory().unwrap());
+
+        // An issue of another area doesn't take the turn of `alice` in `webhooks`.
+        storage
+            .set_assignment("llm", "alice", Utc::now() - chrono::Duration::hours(1))
+            .await
+            .unwrap();
+
+        let feature = make_feature(
+            expect_assignees(&[("alice", 1)]),
+            "webhooks",
+            TriageMode::Assign,
+            storage,
+        );
+
+        feature.process_event(&make_event()).await.unwrap();
+
+        let rendered = feature
+            .render(RepoId::from(1), IssueId::from(1 as usize))
+            .await
+            .unwrap();
+
+        assert!(rendered
+            .user_message
+            .contains("Recent: alice in webhooks alice in llm\n"));
+    }
+
+    #[tokio::test]
+    async fn mentions_owners() {
+        let mut githost_mock = make_githost_mock(vec![]);
+
+        githost_mock
+            .expect_make_comment()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq(IssueId::from(1 as usize)),
+                predicate::eq(non_empty(
+                    "@alice this issue seems to belong to the `webhooks` area. Could you take a look?",
+                )),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(CommentId::from(1)));
+
+        let feature = make_feature(
+            githost_mock,
+            "webhooks",
+            TriageMode::Mention,
+            Arc::new(SqliteStorage::in_memory().unwrap()),
+        );
+
+        feature.process_event(&make_event()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn skips_assigned_issue() {
+        let mut githost_mock = make_githost_mock(vec![non_empty("dave")]);
+
+        githost_mock.expect_set_assignees().never();
+
+        let mut llm_mock = MockLlm::new();
+
+        llm_mock.expect_complete().never();
+
+        let feature = TriageAssignFeature::new(
+            githost_mock,
+            make_agent(llm_mock),
+            vec![GitEventKindName::NewIssue],
+            make_options(TriageMode::Assign),
+            CommentStore::default(),
+            Arc::new(SqliteStorage::in_memory().unwrap()),
+        );
+
+        feature.process_event(&make_event()).await.unwrap();
+    }
+
+    #[test]
+    fn parses_codeowners() {
+        let areas = parse_codeowners(
+            "# Owners\n\n*.rs @alice @org/rust-team\n/docs/ @bob docs@example.com\n/ci/ @org/ci",
+        );
+
+        assert_eq!(areas.len(), 2);
+        assert_eq!(areas[0].name.as_str(), "*.rs");
+        assert_eq!(areas[0].owners, vec![non_empty("alice")]);
+        assert_eq!(areas[1].name.as_str(), "/docs/");
+        assert_eq!(areas[1].owners, vec![non_empty("bob")]);
+    }
+
+    fn make_feature(
+        githost_mock: MockGitHost,
+        llm_output: &'static str,
+        mode: TriageMode,
+        storage: Arc<dyn Storage + Send + Sync>,
+    ) -> TriageAssignFeature<MockGitHost, MockLlm> {
+        let mut llm_mock = MockLlm::new();
+
+        llm_mock
+            .expect_complete()
+            .returning(move |_, _, _| Ok(AiMessage::from(non_empty(llm_output))));
+
+        TriageAssignFeature::new(
+            githost_mock,
+            make_agent(llm_mock),
+            vec![GitEventKindName::NewIssue],
+            make_options(mode),
+            CommentStore::default(),
+            storage,
+        )
+    }
+
+    fn make_agent(llm_mock: MockLlm) -> LlmAgent<MockLlm, TriageAssignFeatureContext> {
+        LlmAgent::build_raw(
+            llm_mock,
+            TEST_SYSTEM_MESSAGE.try_into().unwrap(),
+            TEST_USER_MESSAGE.try_into().unwrap(),
+            CompletionParameters::default(),
+        )
+        .unwrap()
+    }
+
+    fn make_options(mode: TriageMode) -> TriageAssignOptions {
+        TriageAssignOptions {
+            mode,
+            areas: vec![
+                Area {
+                    name: non_empty("webhooks"),
+                    description: "Receiving events from GitHub".into(),
+                    owners: vec![non_empty("alice"), non_empty("bob"), non_empty("carol")],
+                    round_robin: true,
+                },
+                Area {
+                    name: non_empty("llm"),
+                    description: "Calling LLMs".into(),
+                    owners: vec![non_empty("dave")],
+                    round_robin: true,
+                },
+            ],
+            codeowners_path: None,
+            out_of_office: vec![non_empty("bob")],
+        }
+    }
+
+    fn make_githost_mock(assignees: Vec<NonEmptyString>) -> MockGitHost {
+        let mut githost_mock = MockGitHost::new();
+
+        githost_mock
+            .expect_get_issue()
+            .returning(move |_, issue_id| {
+                Ok(Issue {
+                    id: issue_id,
+                    author_user_id: UserId::from(1),
+                    title: non_empty("Webhook is not received"),
+                    body: "The bot doesn't react to new issues".into(),
+                    state: IssueState::Open,
+                    labels: vec![],
+                    assignees: assignees.clone(),
+                    milestone: None,
+                    created_at: Utc::now(),
+                    updated_at: Utc::now(),
+                    author_association: AuthorAssociation::None,
+                })
+            });
+
+        githost_mock.expect_get_user().returning(|id| {
+            Ok(User {
+                id,
+                nickname: non_empty("InAnYan"),
+                kind: UserKind::User,
+            })
+        });
+
+        githost_mock
+    }
+
+    fn expect_assignees(owners: &[(&'static str, usize)]) -> MockGitHost {
+        let mut githost_mock = make_githost_mock(vec![]);
+
+        for (owner, times) in owners {
+            githost_mock
+                .expect_set_assignees()
+                .with(
+                    predicate::eq(RepoId::from(1)),
+                    predicate::eq(IssueId::from(1 as usize)),
+                    predicate::eq(vec![non_empty(owner)]),
+                )
+                .times(*times)
+                .returning(|_, _, _| Ok(()));
+        }
+
+        githost_mock
+    }
+
+    fn make_event() -> GitEvent {
+        GitEvent {
+            repo_id: RepoId::from(1),
+            issue_id: IssueId::from(1 as usize),
+            kind: GitEventKind::NewIssue,
+        }
+    }
+
+    fn non_empty(s: &str) -> NonEmptyString {
+        NonEmptyString::from_str(s).unwrap()
+    }
+}
@@ -1,23 +1,40 @@
+use std::{fmt::Debug, sync::Arc};
+
 use serde::Deserialize;
 use tracing::instrument;
 
 use crate::{
-    githost::{events::GitEvent, host::GitHost},
-    llm::llm_trait::Llm,
+    githost::{
+        events::GitEvent,
+        host::GitHost,
+        model::{IssueId, RepoId},
+    },
+    llm::{agent::RenderedMessages, llm_trait::Llm},
+    storage::storage_trait::{build_storage, Storage, StorageChoice},
 };
 
 use super::{
-    bot_features::{BotFeatures, FeaturesConfig},
-    errors::Result,
+    bot_features::{BotFeatures, FeatureFailures, FeatureName, FeaturesConfig},
+    errors::{GitBotError, Result},
+    scheduler::{ScheduledEvent, Scheduler, SchedulerConfig},
 };
 
 #[derive(Deserialize)]
 pub struct GitBotConfig {
     features: FeaturesConfig,
+
+    /// Where the bot remembers its comments, processed deliveries and scheduler runs. Everything
+    /// is forgotten on restart if absent.
+    storage: Option<StorageChoice>,
+
+    /// Periodic jobs of the features. No jobs are run if absent.
+    scheduler: Option<SchedulerConfig>,
 }
 
 pub struct GitBot<G, L> {
     features: BotFeatures<G, L>,
+    scheduler: Option<Scheduler>,
+    storage: Arc<dyn Storage + Send + Sync>,
 }
 
 impl<G: GitHost + Clone, L: Llm + Clone> GitBot<G, L> {
@@ -26,17 +43,100 @@ impl<G: GitHost + Clone, L: Llm + Clone> GitBot<G, L> {
         githost: G,
         llm: L,
     ) -> Result<Self, G::Error, L::Error> {
+        let storage = build_storage(config.storage).map_err(GitBotError::StorageError)?;
+
+        let features =
+            BotFeatures::build_from_config(config.features, githost, llm, storage.clone()).await?;
+
+        let scheduler = match config.scheduler {
+            Some(config) => Some(Scheduler::build::<G::Error>(
+                config,
+                &features.scheduled_features(),
+                storage.clone(),
+            )?),
+            None => None,
+        };
+
         Ok(Self {
-            features: BotFeatures::build_from_config(config.features, githost, llm).await?,
+            features,
+            scheduler,
+            storage,
         })
     }
+}
+
+impl<G: GitHost, L: Llm> GitBot<G, L> {
+    pub fn build_raw(features: BotFeatures<G, L>, storage: Arc<dyn Storage + Send + Sync>) -> Self {
+        Self {
+            features,
+            scheduler: None,
+            storage,
+        }
+    }
 
-    pub fn build_raw(features: BotFeatures<G, L>) -> Self {
-        Self { features }
+    /// The storage that the features use, to share it with the webhook server.
+    pub fn storage(&self) -> Arc<dyn Storage + Send + Sync> {
+        self.storage.clone()
+    }
+
+    /// Take the scheduler out of the bot, so that it can be run alongside the event loop.
+    pub fn take_scheduler(&mut self) -> Option<Scheduler> {
+        self.scheduler.take()
     }
 
     #[instrument(skip(self))]
-    pub async fn process_event(&self, event: &GitEvent) -> Result<(), G::Error, L::Error> {
+    pub async fn process_event(&self, event: &GitEvent) -> FeatureFailures<G::Error, L::Error>
+    where
+        G::Error: Debug,
+        L::Error: Debug,
+    {
         self.features.process_event(event).await
     }
+
+    #[instrument(skip(self))]
+    pub async fn process_event_for(
+        &self,
+        event: &GitEvent,
+        selected: &[FeatureName],
+    ) -> FeatureFailures<G::Error, L::Error>
+    where
+        G::Error: Debug,
+        L::Error: Debug,
+    {
+        self.features.process_event_for(event, selected).await
+    }
+
+    #[instrument(skip(self))]
+    pub async fn process_scheduled_event(
+        &self,
+        event: &ScheduledEvent,
+    ) -> Result<(), G::Error, L::Error>
+    where
+        G::Error: Debug,
+        L::Error: Debug,
+    {
+        self.features.process_scheduled_event(event).await
+    }
+
+    pub async fn release_notes(
+        &self,
+        repo_id: RepoId,
+        base: String,
+        head: String,
+    ) -> Result<Option<String>, G::Error, L::Error> {
+        self.features.release_notes(repo_id, base, head).await
+    }
+
+    pub async fn rebuild_indexes(&self, repo_id: RepoId) -> Result<(), G::Error, L::Error> {
+        self.features.rebuild_indexes(repo_id).await
+    }
+
+    pub async fn render(
+        &self,
+        feature: FeatureName,
+        repo_id: RepoId,
+        issue_id: IssueId,
+    ) -> Result<Option<RenderedMessages>, G::Error, L::Error> {
+        self.features.render(feature, repo_id, issue_id).await
+    }
 }
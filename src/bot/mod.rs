@@ -1,4 +1,7 @@
+pub mod backfill;
 pub mod bot_features;
+pub mod comment_store;
 pub mod errors;
 pub mod features;
 pub mod gitbot;
+pub mod scheduler;
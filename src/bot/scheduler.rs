@@ -0,0 +1,592 @@
+use std::{str::FromStr, sync::Arc};
+
+use chrono::{DateTime, Duration, Utc};
+use log::{error, info, warn};
+use rand::Rng;
+use serde::Deserialize;
+use tokio::{sync::mpsc::Sender, time::Instant};
+
+use crate::{
+    githost::{
+        host::GitHost,
+        model::{RepoId, RepoName},
+    },
+    storage::storage_trait::{Storage, StorageError},
+};
+
+use super::bot_features::FeatureName;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SchedulerError<GE> {
+    #[error("unable to perform Git host action")]
+    GitHostError(#[source] GE),
+
+    #[error("invalid cron expression `{0}`")]
+    CronError(String, #[source] cron::error::Error),
+
+    #[error("feature `{0}` has no scheduled jobs or is not enabled")]
+    NotScheduledFeature(FeatureName),
+
+    #[error("job of feature `{0}` should run at most once a minute")]
+    IntervalTooShort(FeatureName),
+
+    #[error("unable to read the last runs from the storage")]
+    StorageError(#[source] StorageError),
+}
+
+pub type Result<T, GE> = std::result::Result<T, SchedulerError<GE>>;
+
+/// How many next runs of a cron schedule are checked for the minimal interval.
+const CRON_RUNS_TO_CHECK: usize = 10;
+
+/// A time-based event. Unlike `GitEvent`, it is not bound to an issue, so the feature decides by
+/// itself what to process in the repository.
+#[derive(Clone, Debug)]
+pub struct ScheduledEvent {
+    pub repo_id: RepoId,
+    pub feature: FeatureName,
+
+    /// Held while the event is alive, so that the scheduler knows that the run is not over yet.
+    _run_token: Option<Arc<()>>,
+}
+
+impl ScheduledEvent {
+    pub fn new(repo_id: RepoId, feature: FeatureName) -> Self {
+        Self {
+            repo_id,
+            feature,
+            _run_token: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SchedulerConfig {
+    /// Repositories that the scheduled jobs are run on.
+    pub repos: Vec<RepoName>,
+
+    pub jobs: Vec<JobConfig>,
+}
+
+/// A job is identified by its feature, schedule and repositories, so that several jobs of a feature
+/// have their own last runs. A changed job is run as a new one.
+#[derive(Deserialize)]
+pub struct JobConfig {
+    pub feature: FeatureName,
+    pub schedule: ScheduleConfig,
+
+    /// Repositories of this job. Defaults to the repositories of the scheduler.
+    #[serde(default)]
+    pub repos: Vec<RepoName>,
+
+    /// Every run is delayed by a random number of seconds up to this one.
+    #[serde(default)]
+    pub jitter_seconds: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleConfig {
+    IntervalMinutes(u64),
+
+    /// Cron expression with seconds, e.g. `0 0 9 * * Mon-Fri`.
+    Cron(String),
+}
+
+enum Schedule {
+    Interval(Duration),
+    Cron(Box<cron::Schedule>),
+}
+
+impl Schedule {
+    /// The first run after the last one. Missed runs are run once as soon as possible.
+    fn next_run(
+        &self,
+        last_run: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        let next_run = match (self, last_run) {
+            (Schedule::Interval(_), None) => now,
+            (Schedule::Interval(interval), Some(last_run)) => last_run + *interval,
+            (Schedule::Cron(schedule), last_run) => {
+                schedule.after(&last_run.unwrap_or(now)).next()?
+            }
+        };
+
+        Some(next_run.max(now))
+    }
+}
+
+struct Job {
+    /// Identifies the job in the storage.
+    key: String,
+    feature: FeatureName,
+    repos: Vec<RepoName>,
+    repo_ids: Vec<RepoId>,
+    schedule: Schedule,
+    jitter_seconds: u64,
+    next_run: Option<DateTime<Utc>>,
+
+    /// Cloned into every event of a run. The run is over when only the job holds it.
+    run_token: Arc<()>,
+}
+
+/// Wall clock that follows the Tokio clock, so that the scheduler can be tested with paused time.
+struct Clock {
+    start_utc: DateTime<Utc>,
+    start_instant: Instant,
+}
+
+impl Clock {
+    fn new() -> Self {
+        Self {
+            start_utc: Utc::now(),
+            start_instant: Instant::now(),
+        }
+    }
+
+    fn now(&self) -> DateTime<Utc> {
+        self.start_utc
+            + Duration::from_std(self.start_instant.elapsed())
+                .expect("scheduler runs for less than a few centuries")
+    }
+
+    fn instant_at(&self, time: DateTime<Utc>) -> Instant {
+        self.start_instant + (time - self.start_utc).to_std().unwrap_or_default()
+    }
+}
+
+pub struct Scheduler {
+    jobs: Vec<Job>,
+
+    /// Keeps the last run times, so that restarts don't run the jobs again.
+    storage: Arc<dyn Storage + Send + Sync>,
+}
+
+impl Scheduler {
+    /// Build the scheduler. Only the `scheduled_features` can have jobs.
+    pub fn build<GE>(
+        config: SchedulerConfig,
+        scheduled_features: &[FeatureName],
+        storage: Arc<dyn Storage + Send + Sync>,
+    ) -> Result<Self, GE> {
+        let mut jobs = Vec::new();
+
+        for job in config.jobs {
+            if !scheduled_features.contains(&job.feature) {
+                return Err(SchedulerError::NotScheduledFeature(job.feature));
+            }
+
+            let repos = if job.repos.is_empty() {
+                config.repos.clone()
+            } else {
+                job.repos
+            };
+
+            let key = job_key(job.feature, &job.schedule, &repos);
+
+            let schedule = match job.schedule {
+                // A zero interval would make the scheduler run the job in a busy loop.
+                ScheduleConfig::IntervalMinutes(0) => {
+                    return Err(SchedulerError::IntervalTooShort(job.feature))
+                }
+
+                ScheduleConfig::IntervalMinutes(minutes) => {
+                    Schedule::Interval(Duration::minutes(minutes as i64))
+                }
+
+                ScheduleConfig::Cron(expression) => {
+                    let schedule = cron::Schedule::from_str(&expression)
+                        .map_err(|e| SchedulerError::CronError(expression, e))?;
+
+                    // The expression has seconds, so `* * * * * *` would run the job every second.
+                    if fires_too_often(&schedule) {
+                        return Err(SchedulerError::IntervalTooShort(job.feature));
+                    }
+
+                    Schedule::Cron(Box::new(schedule))
+                }
+            };
+
+            jobs.push(Job {
+                key,
+                feature: job.feature,
+                repos,
+                repo_ids: Vec::new(),
+                schedule,
+                jitter_seconds: job.jitter_seconds,
+                next_run: None,
+                run_token: Arc::new(()),
+            });
+        }
+
+        Ok(Self { jobs, storage })
+    }
+
+    /// Send scheduled events until the receiver is closed.
+    ///
+    /// A job is not run again while the events of its previous run are still being processed.
+    pub async fn run<G: GitHost>(
+        mut self,
+        githost: G,
+        sender: Sender<ScheduledEvent>,
+    ) -> Result<(), G::Error> {
+        for job in &mut self.jobs {
+            for repo in &job.repos {
+                job.repo_ids.push(
+                    githost
+                        .find_repo(repo)
+                        .await
+                        .map_err(SchedulerError::GitHostError)?
+                        .id,
+                );
+            }
+        }
+
+        let mut last_runs = Vec::new();
+
+        for job in &self.jobs {
+            last_runs.push(
+                self.storage
+                    .last_scheduler_run(&job.key)
+                    .await
+                    .map_err(SchedulerError::StorageError)?,
+            );
+        }
+
+        let clock = Clock::new();
+
+        for (job, last_run) in self.jobs.iter_mut().zip(last_runs) {
+            job.next_run = job
+                .schedule
+                .next_run(last_run, clock.now())
+                .map(|next_run| next_run + random_jitter(job.jitter_seconds));
+        }
+
+        loop {
+            let Some(next_run) = self.jobs.iter().filter_map(|job| job.next_run).min() else {
+                return Ok(());
+            };
+
+            tokio::time::sleep_until(clock.instant_at(next_run)).await;
+
+            let now = clock.now();
+
+            for job in &mut self.jobs {
+                let is_due = job.next_run.is_some_and(|next_run| next_run <= now);
+
+                if !is_due {
+                    continue;
+                }
+
+                if Arc::strong_count(&job.run_token) > 1 {
+                    warn!(
+                        "Skipping scheduled job of feature {}, the previous run is not finished",
+                        job.feature
+                    );
+                } else {
+                    info!("Running scheduled job of feature {}", job.feature);
+
+                    // The run is saved before it starts, so that a restart doesn't repeat it.
+                    if let Err(e) = self.storage.set_scheduler_run(&job.key, now).await {
+                        error!("Unable to save the scheduler run: {}", e);
+                    }
+
+                    for repo_id in &job.repo_ids {
+                        let event = ScheduledEvent {
+                            repo_id: *repo_id,
+                            feature: job.feature,
+                            _run_token: Some(job.run_token.clone()),
+                        };
+
+                        if sender.send(event).await.is_err() {
+                            return Ok(());
+                        }
+                    }
+                }
+
+                job.next_run = job
+                    .schedule
+                    .next_run(Some(now), now)
+                    .map(|next_run| next_run + random_jitter(job.jitter_seconds));
+            }
+        }
+    }
+}
+
+fn job_key(feature: FeatureName, schedule: &ScheduleConfig, repos: &[RepoName]) -> String {
+    let schedule = match schedule {
+        ScheduleConfig::IntervalMinutes(minutes) => format!("every {} minutes", minutes),
+        ScheduleConfig::Cron(expression) => format!("cron `{}`", expression),
+    };
+
+    let repos: Vec<String> = repos
+        .iter()
+        .map(|repo| format!("{}/{}", repo.owner, repo.name))
+        .collect();
+
+    format!("{} {} on {}", feature, schedule, repos.join(", "))
+}
+
+/// Whether some of the next runs are less than a minute apart. A schedule with several seconds
+/// runs twice in the same minute, so a few runs are enough to tell.
+fn fires_too_often(schedule: &cron::Schedule) -> bool {
+    let runs: Vec<DateTime<Utc>> = schedule.upcoming(Utc).take(CRON_RUNS_TO_CHECK).collect();
+
+    runs.windows(2)
+        .any(|pair| pair[1] - pair[0] < Duration::minutes(1))
+}
+
+fn random_jitter(max_seconds: u64) -> Duration {
+    Duration::seconds(rand::thread_rng().gen_range(0..=max_seconds) as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use chrono::{DateTime, Utc};
+    use tokio::{sync::mpsc::channel, time::Instant};
+
+    use crate::{
+        bot::{
+            bot_features::FeatureName,
+            scheduler::{JobConfig, ScheduleConfig, Scheduler, SchedulerConfig, SchedulerError},
+        },
+        githost::{
+            host::MockGitHost,
+            model::{Repo, RepoId},
+        },
+        storage::storage_trait::MockStorage,
+    };
+
+    fn make_githost_mock() -> MockGitHost {
+        let mut githost_mock = MockGitHost::new();
+
+        githost_mock.expect_find_repo().returning(|name| {
+            Ok(Repo {
+                id: RepoId::from(1),
+                owner: name.owner.to_string(),
+                name: name.name.to_string(),
+            })
+        });
+
+        githost_mock
+    }
+
+    fn make_config(schedule: ScheduleConfig) -> SchedulerConfig {
+        SchedulerConfig {
+            repos: vec!["InAnYan/rust-gib".parse().unwrap()],
+            jobs: vec![JobConfig {
+                feature: FeatureName::Stale,
+                schedule,
+                repos: Vec::new(),
+                jitter_seconds: 0,
+            }],
+        }
+    }
+
+    /// Storage that has the given last run and collects the saved runs.
+    fn make_storage_mock(
+        last_run: Option<DateTime<Utc>>,
+    ) -> (Arc<MockStorage>, Arc<Mutex<Vec<String>>>) {
+        let mut storage_mock = MockStorage::new();
+
+        storage_mock
+            .expect_last_scheduler_run()
+            .returning(move |_| Ok(last_run));
+
+        let saved_runs = Arc::new(Mutex::new(Vec::new()));
+        let saved_runs_clone = saved_runs.clone();
+
+        storage_mock
+            .expect_set_scheduler_run()
+            .returning(move |job, _| {
+                saved_runs_clone.lock().unwrap().push(job.to_string());
+                Ok(())
+            });
+
+        (Arc::new(storage_mock), saved_runs)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sends_events_periodically() {
+        let scheduler = Scheduler::build::<()>(
+            make_config(ScheduleConfig::IntervalMinutes(60)),
+            &[FeatureName::Stale],
+            make_storage_mock(None).0,
+        )
+        .unwrap();
+
+        let (sender, mut receiver) = channel(1);
+
+        let handle = tokio::spawn(scheduler.run(make_githost_mock(), sender));
+
+        let start = Instant::now();
+
+        for _ in 0..2 {
+            let event = receiver.recv().await.unwrap();
+
+            assert_eq!(event.repo_id, RepoId::from(1));
+            assert_eq!(event.feature, FeatureName::Stale);
+        }
+
+        assert_eq!(start.elapsed(), Duration::from_secs(60 * 60));
+
+        drop(receiver);
+
+        handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn skips_job_while_previous_run_in_progress() {
+        let scheduler = Scheduler::build::<()>(
+            make_config(ScheduleConfig::IntervalMinutes(60)),
+            &[FeatureName::Stale],
+            make_storage_mock(None).0,
+        )
+        .unwrap();
+
+        let (sender, mut receiver) = channel(1);
+
+        tokio::spawn(scheduler.run(make_githost_mock(), sender));
+
+        let start = Instant::now();
+
+        let event = receiver.recv().await.unwrap();
+
+        tokio::time::sleep(Duration::from_secs(90 * 60)).await;
+
+        assert!(receiver.try_recv().is_err());
+
+        drop(event);
+
+        receiver.recv().await.unwrap();
+
+        assert_eq!(start.elapsed(), Duration::from_secs(120 * 60));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn continues_from_saved_state() {
+        let (storage_mock, saved_runs) =
+            make_storage_mock(Some(Utc::now() - chrono::Duration::minutes(20)));
+
+        let scheduler = Scheduler::build::<()>(
+            make_config(ScheduleConfig::IntervalMinutes(60)),
+            &[FeatureName::Stale],
+            storage_mock,
+        )
+        .unwrap();
+
+        let (sender, mut receiver) = channel(1);
+
+        tokio::spawn(scheduler.run(make_githost_mock(), sender));
+
+        let start = Instant::now();
+
+        receiver.recv().await.unwrap();
+
+        let elapsed = start.elapsed();
+        assert!(elapsed > Duration::from_secs(39 * 60) && elapsed <= Duration::from_secs(40 * 60));
+
+        assert_eq!(
+            *saved_runs.lock().unwrap(),
+            vec!["stale every 60 minutes on InAnYan/rust-gib".to_string()]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn keeps_last_runs_per_job() {
+        let mut storage_mock = MockStorage::new();
+
+        // Only the daily job has run recently.
+        storage_mock.expect_last_scheduler_run().returning(|job| {
+            Ok((job == "stale every 1440 minutes on InAnYan/rust-gib")
+                .then(|| Utc::now() - chrono::Duration::minutes(20)))
+        });
+
+        storage_mock
+            .expect_set_scheduler_run()
+            .returning(|_, _| Ok(()));
+
+        let mut config = make_config(ScheduleConfig::IntervalMinutes(60));
+
+        config.jobs.push(JobConfig {
+            feature: FeatureName::Stale,
+            schedule: ScheduleConfig::IntervalMinutes(24 * 60),
+            repos: Vec::new(),
+            jitter_seconds: 0,
+        });
+
+        let scheduler =
+            Scheduler::build::<()>(config, &[FeatureName::Stale], Arc::new(storage_mock)).unwrap();
+
+        let (sender, mut receiver) = channel(1);
+
+        tokio::spawn(scheduler.run(make_githost_mock(), sender));
+
+        let start = Instant::now();
+
+        receiver.recv().await.unwrap();
+
+        assert_eq!(start.elapsed(), Duration::ZERO);
+
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn rejects_not_scheduled_feature() {
+        assert!(Scheduler::build::<()>(
+            make_config(ScheduleConfig::IntervalMinutes(60)),
+            &[FeatureName::Label],
+            Arc::new(MockStorage::new()),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn rejects_zero_interval() {
+        assert!(matches!(
+            Scheduler::build::<()>(
+                make_config(ScheduleConfig::IntervalMinutes(0)),
+                &[FeatureName::Stale],
+                Arc::new(MockStorage::new()),
+            ),
+            Err(SchedulerError::IntervalTooShort(FeatureName::Stale))
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_cron() {
+        assert!(Scheduler::build::<()>(
+            make_config(ScheduleConfig::Cron("every monday".to_string())),
+            &[FeatureName::Stale],
+            Arc::new(MockStorage::new()),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn rejects_cron_more_often_than_every_minute() {
+        for expression in ["* * * * * *", "0,30 * * * * *"] {
+            assert!(matches!(
+                Scheduler::build::<()>(
+                    make_config(ScheduleConfig::Cron(expression.to_string())),
+                    &[FeatureName::Stale],
+                    Arc::new(MockStorage::new()),
+                ),
+                Err(SchedulerError::IntervalTooShort(FeatureName::Stale))
+            ));
+        }
+
+        assert!(Scheduler::build::<()>(
+            make_config(ScheduleConfig::Cron("0 * * * * *".to_string())),
+            &[FeatureName::Stale],
+            Arc::new(MockStorage::new()),
+        )
+        .is_ok());
+    }
+}
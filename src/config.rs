@@ -5,8 +5,9 @@ use serde::Deserialize;
 use tracing::instrument;
 
 use crate::{
-    bot::gitbot::GitBotConfig, githost::impls::github::github_host::GithubConfig,
-    llm::impls::openai_llm::OpenAiLlmConfig,
+    bot::gitbot::GitBotConfig,
+    githost::impls::github::github_host::GithubConfig,
+    llm::impls::{openai_llm::OpenAiLlmConfig, recording_llm::RecordingLlmConfig},
 };
 
 #[derive(Deserialize)]
@@ -14,6 +15,11 @@ pub struct Config {
     pub githost: GitHostChoice,
     pub webhook_server: Option<WebhookServerConfig>,
     pub llm: LlmChoice,
+
+    /// Record the requests to the LLM into a cassette, or answer them from one.
+    #[serde(default)]
+    pub llm_recording: Option<RecordingLlmConfig>,
+
     pub bot: GitBotConfig,
 }
 
@@ -1,9 +1,15 @@
-use super::model::{CommentId, IssueId, RepoId};
+use non_empty_string::NonEmptyString;
+use serde::Deserialize;
+
+use super::model::{CloseReason, CommentId, IssueId, RepoId, UserId};
 
 #[derive(Debug, PartialEq)]
 pub struct GitEvent {
     pub repo_id: RepoId,
+
+    /// Number of the issue or the pull request.
     pub issue_id: IssueId,
+
     pub kind: GitEventKind,
 }
 
@@ -11,4 +17,78 @@ pub struct GitEvent {
 pub enum GitEventKind {
     NewIssue,
     NewComment(CommentId),
+    IssueEdited(IssueEdit),
+    IssueReopened,
+    IssueClosed(Option<CloseReason>),
+    IssueLabeled(LabelChange),
+    IssueUnlabeled(LabelChange),
+    IssueAssigned(UserId),
+    IssueTransferred,
+    NewPullRequest,
+    PullRequestSynchronized,
+
+    /// A comment on a line of the pull request diff. These comments are different from the issue
+    /// comments.
+    PullRequestReviewComment(CommentId),
+}
+
+/// Title and body of an issue before and after an edit.
+///
+/// Old values are present only for the parts that were actually changed.
+#[derive(Debug, PartialEq)]
+pub struct IssueEdit {
+    pub old_title: Option<NonEmptyString>,
+    pub new_title: NonEmptyString,
+    pub old_body: Option<String>,
+    pub new_body: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct LabelChange {
+    pub label: NonEmptyString,
+    pub actor: UserId,
+}
+
+/// Kind of a `GitEvent` without its data. Used by features to declare which events they handle.
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum GitEventKindName {
+    NewIssue,
+    NewComment,
+    IssueEdited,
+    IssueReopened,
+    IssueClosed,
+    IssueLabeled,
+    IssueUnlabeled,
+    IssueAssigned,
+    IssueTransferred,
+    NewPullRequest,
+    PullRequestSynchronized,
+    PullRequestReviewComment,
+}
+
+impl GitEventKindName {
+    /// Events handled by a feature if its configuration does not say otherwise.
+    pub fn default_subscription() -> Vec<GitEventKindName> {
+        vec![GitEventKindName::NewIssue]
+    }
+}
+
+impl GitEventKind {
+    pub fn name(&self) -> GitEventKindName {
+        match self {
+            GitEventKind::NewIssue => GitEventKindName::NewIssue,
+            GitEventKind::NewComment(_) => GitEventKindName::NewComment,
+            GitEventKind::IssueEdited(_) => GitEventKindName::IssueEdited,
+            GitEventKind::IssueReopened => GitEventKindName::IssueReopened,
+            GitEventKind::IssueClosed(_) => GitEventKindName::IssueClosed,
+            GitEventKind::IssueLabeled(_) => GitEventKindName::IssueLabeled,
+            GitEventKind::IssueUnlabeled(_) => GitEventKindName::IssueUnlabeled,
+            GitEventKind::IssueAssigned(_) => GitEventKindName::IssueAssigned,
+            GitEventKind::IssueTransferred => GitEventKindName::IssueTransferred,
+            GitEventKind::NewPullRequest => GitEventKindName::NewPullRequest,
+            GitEventKind::PullRequestSynchronized => GitEventKindName::PullRequestSynchronized,
+            GitEventKind::PullRequestReviewComment(_) => GitEventKindName::PullRequestReviewComment,
+        }
+    }
 }
@@ -2,7 +2,11 @@ use async_trait::async_trait;
 use mockall::automock;
 use non_empty_string::NonEmptyString;
 
-use super::model::{Comment, CommentId, Issue, IssueId, Label, Repo, RepoId, User, UserId};
+use super::model::{
+    ChangedFile, CloseReason, Comment, CommentId, HideReason, Issue, IssueId, IssueQuery,
+    IssuesPage, Label, LockReason, MilestoneId, PullRequest, Repo, RepoId, RepoName, Review, User,
+    UserId,
+};
 
 #[automock(type Error=();)]
 #[async_trait]
@@ -11,12 +15,62 @@ pub trait GitHost {
 
     fn get_self_name(&self) -> &NonEmptyString;
 
+    /// Perform a cheap request to check that the Git host is reachable and credentials are valid.
+    async fn check_connection(&self) -> Result<(), Self::Error>;
+
     async fn get_user(&self, id: UserId) -> Result<User, Self::Error>;
 
     async fn get_repo(&self, id: RepoId) -> Result<Repo, Self::Error>;
 
+    async fn find_repo(&self, name: &RepoName) -> Result<Repo, Self::Error>;
+
     async fn get_issue(&self, repo_id: RepoId, issue_id: IssueId) -> Result<Issue, Self::Error>;
 
+    /// Search issues of a repository. Pages are numbered starting from 1.
+    async fn search_issues(
+        &self,
+        repo_id: RepoId,
+        query: &IssueQuery,
+        page: u32,
+    ) -> Result<IssuesPage, Self::Error>;
+
+    async fn create_issue(
+        &self,
+        repo_id: RepoId,
+        title: NonEmptyString,
+        body: String,
+    ) -> Result<IssueId, Self::Error>;
+
+    /// Create a discussion in the category with the given name. Discussions share the numbering
+    /// with issues.
+    async fn create_discussion(
+        &self,
+        repo_id: RepoId,
+        category: NonEmptyString,
+        title: NonEmptyString,
+        body: String,
+    ) -> Result<IssueId, Self::Error>;
+
+    /// Content of a file in the default branch of the repository. Returns `None` if there is no
+    /// such file.
+    async fn get_file_content(
+        &self,
+        repo_id: RepoId,
+        path: String,
+    ) -> Result<Option<String>, Self::Error>;
+
+    /// Paths of the files (not subdirectories) in a directory of the default branch. Returns an
+    /// empty list if there is no such directory.
+    async fn list_directory(
+        &self,
+        repo_id: RepoId,
+        path: String,
+    ) -> Result<Vec<String>, Self::Error>;
+
+    /// Paths of all files in the default branch of the repository. The list can be incomplete for
+    /// a very big repository.
+    async fn list_files(&self, repo_id: RepoId) -> Result<Vec<String>, Self::Error>;
+
     async fn get_comment(
         &self,
         repo_id: RepoId,
@@ -29,6 +83,36 @@ pub trait GitHost {
         repo_id: RepoId,
         issue_id: IssueId,
         message: NonEmptyString,
+    ) -> Result<CommentId, Self::Error>;
+
+    /// All comments of the issue, from the oldest to the newest. Comments with empty body are
+    /// skipped.
+    async fn list_comments(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+    ) -> Result<Vec<Comment>, Self::Error>;
+
+    async fn edit_comment(
+        &self,
+        repo_id: RepoId,
+        comment_id: CommentId,
+        message: NonEmptyString,
+    ) -> Result<(), Self::Error>;
+
+    async fn delete_comment(
+        &self,
+        repo_id: RepoId,
+        comment_id: CommentId,
+    ) -> Result<(), Self::Error>;
+
+    /// Hide (minimize) the comment in the thread. The comment is still available to readers who
+    /// expand it.
+    async fn hide_comment(
+        &self,
+        repo_id: RepoId,
+        comment_id: CommentId,
+        reason: HideReason,
     ) -> Result<(), Self::Error>;
 
     async fn get_repo_labels(&self, repo_id: RepoId) -> Result<Vec<Label>, Self::Error>;
@@ -41,4 +125,122 @@ pub trait GitHost {
         issue_id: IssueId,
         label_name: NonEmptyString,
     ) -> Result<(), Self::Error>;
+
+    async fn remove_label(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+        label_name: NonEmptyString,
+    ) -> Result<(), Self::Error>;
+
+    /// Create a new label in the repository. Color is a hex code without the leading `#`.
+    async fn create_label(
+        &self,
+        repo_id: RepoId,
+        name: NonEmptyString,
+        color: NonEmptyString,
+        description: String,
+    ) -> Result<Label, Self::Error>;
+
+    /// Replace the assignees of the issue. An empty list removes all assignees.
+    async fn set_assignees(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+        assignees: Vec<NonEmptyString>,
+    ) -> Result<(), Self::Error>;
+
+    /// Set the milestone of the issue, or remove it with `None`.
+    async fn set_milestone(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+        milestone: Option<MilestoneId>,
+    ) -> Result<(), Self::Error>;
+
+    async fn close_issue(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+        reason: CloseReason,
+    ) -> Result<(), Self::Error>;
+
+    async fn reopen_issue(&self, repo_id: RepoId, issue_id: IssueId) -> Result<(), Self::Error>;
+
+    /// Replace the body of the issue.
+    async fn edit_issue_body(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+        body: String,
+    ) -> Result<(), Self::Error>;
+
+    async fn lock_issue(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+        reason: Option<LockReason>,
+    ) -> Result<(), Self::Error>;
+
+    async fn get_pull_request(
+        &self,
+        repo_id: RepoId,
+        pull_request_id: IssueId,
+    ) -> Result<PullRequest, Self::Error>;
+
+    async fn get_pull_request_files(
+        &self,
+        repo_id: RepoId,
+        pull_request_id: IssueId,
+    ) -> Result<Vec<ChangedFile>, Self::Error>;
+
+    /// Replace the description of the pull request.
+    async fn edit_pull_request_body(
+        &self,
+        repo_id: RepoId,
+        pull_request_id: IssueId,
+        body: String,
+    ) -> Result<(), Self::Error>;
+
+    /// Unified diff of the whole pull request.
+    async fn get_pull_request_diff(
+        &self,
+        repo_id: RepoId,
+        pull_request_id: IssueId,
+    ) -> Result<String, Self::Error>;
+
+    /// SHAs of the commits that are in `head` but not in `base`, from the oldest to the newest.
+    /// Refs can be branches, tags or commit SHAs.
+    async fn compare_refs(
+        &self,
+        repo_id: RepoId,
+        base: String,
+        head: String,
+    ) -> Result<Vec<String>, Self::Error>;
+
+    /// Merged pull requests that contain the commit.
+    async fn list_merged_pull_requests(
+        &self,
+        repo_id: RepoId,
+        commit_sha: String,
+    ) -> Result<Vec<IssueId>, Self::Error>;
+
+    /// Create a release for the tag. Draft releases are visible only to maintainers.
+    async fn create_release(
+        &self,
+        repo_id: RepoId,
+        tag: NonEmptyString,
+        name: String,
+        body: String,
+        draft: bool,
+    ) -> Result<(), Self::Error>;
+
+    // NOTE: Plain comments on pull requests are made with `make_comment`, as pull requests are
+    // issues too.
+    async fn make_pull_request_review(
+        &self,
+        repo_id: RepoId,
+        pull_request_id: IssueId,
+        review: Review,
+    ) -> Result<(), Self::Error>;
 }
@@ -18,6 +18,12 @@ pub enum GithubError {
     #[error("internal server error of webhook server")]
     WebhookServerError(#[source] std::io::Error),
 
+    #[error("GraphQL API returned an error: {0}")]
+    GraphQlError(String),
+
+    #[error("discussion category `{0}` does not exist")]
+    DiscussionCategoryNotFound(String),
+
     #[error("invalid format of the API response")]
     ApiResponseInvalidFormatError,
 
@@ -1,18 +1,30 @@
 use std::{path::PathBuf, str::FromStr};
 
 use async_trait::async_trait;
+use axum::http::{header::ACCEPT, HeaderMap, HeaderValue, StatusCode};
+use chrono::{DateTime, Utc};
 use jsonwebtoken::EncodingKey;
+use log::warn;
 use non_empty_string::NonEmptyString;
-use octocrab::{Octocrab, OctocrabBuilder};
+use octocrab::{
+    models::repos::{DiffEntry, DiffEntryStatus},
+    Octocrab, OctocrabBuilder,
+};
 use secrecy::{ExposeSecret, SecretVec};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 use tokio::fs::read;
 use url::Url;
 
 use crate::{
     githost::{
         host::GitHost,
-        model::{Comment, CommentId, Issue, IssueId, Label, LabelId, Repo, RepoId, User, UserId},
+        model::{
+            AuthorAssociation, ChangedFile, ChangedFileStatus, CloseReason, Comment, CommentId,
+            FoundIssue, HideReason, Issue, IssueId, IssueQuery, IssueState, IssueStateFilter,
+            IssuesPage, Label, LabelId, LockReason, Milestone, MilestoneId, PullRequest, Repo,
+            RepoId, RepoName, Review, ReviewVerdict, User, UserId, UserKind,
+        },
     },
     utils::clear_url::clear_url,
 };
@@ -27,6 +39,44 @@ pub struct GithubConfig {
     pub pem_rsa_key_path: PathBuf,
 }
 
+/// Part of the response of the compare API that is used.
+#[derive(Deserialize)]
+struct Comparison {
+    total_commits: usize,
+    commits: Vec<ComparisonCommit>,
+}
+
+#[derive(Deserialize)]
+struct ComparisonCommit {
+    sha: String,
+}
+
+/// An entry of a directory listing of the contents API, or of a Git tree.
+#[derive(Deserialize)]
+struct ContentEntry {
+    path: String,
+
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+/// A recursive listing of a Git tree.
+#[derive(Deserialize)]
+struct Tree {
+    tree: Vec<ContentEntry>,
+
+    /// The listing has a limit on the number of entries and the size.
+    #[serde(default)]
+    truncated: bool,
+}
+
+/// Part of the pull request model that is used for the pull requests of a commit.
+#[derive(Deserialize)]
+struct CommitPullRequest {
+    number: usize,
+    merged_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Clone)]
 pub struct GithubHost {
     octocrab: Octocrab,
@@ -35,6 +85,47 @@ pub struct GithubHost {
 
 const GITHUB_API_URL: &str = "https://api.github.com";
 
+const SEARCH_PAGE_SIZE: u8 = 100;
+
+/// GitHub returns only this many results of a search, the pages after them are an error.
+const SEARCH_RESULTS_LIMIT: u32 = 1000;
+
+const FILES_PAGE_SIZE: usize = 100;
+
+const COMMENTS_PAGE_SIZE: usize = 100;
+
+const COMMITS_PAGE_SIZE: usize = 100;
+
+const DIFF_MEDIA_TYPE: &str = "application/vnd.github.v3.diff";
+
+const RAW_MEDIA_TYPE: &str = "application/vnd.github.raw+json";
+
+const DISCUSSION_CATEGORIES_QUERY: &str = "
+query($owner: String!, $name: String!) {
+  repository(owner: $owner, name: $name) {
+    id
+    discussionCategories(first: 100) {
+      nodes { id name }
+    }
+  }
+}";
+
+const CREATE_DISCUSSION_MUTATION: &str = "
+mutation($repositoryId: ID!, $categoryId: ID!, $title: String!, $body: String!) {
+  createDiscussion(input: {
+    repositoryId: $repositoryId, categoryId: $categoryId, title: $title, body: $body
+  }) {
+    discussion { number }
+  }
+}";
+
+const MINIMIZE_COMMENT_MUTATION: &str = "
+mutation($subjectId: ID!, $classifier: ReportedContentClassifiers!) {
+  minimizeComment(input: { subjectId: $subjectId, classifier: $classifier }) {
+    minimizedComment { isMinimized }
+  }
+}";
+
 impl GithubHost {
     pub async fn build(config: GithubConfig) -> Result<Self, GithubError> {
         Self::build_raw(
@@ -70,6 +161,37 @@ impl GithubHost {
 
         Ok(Self { octocrab, bot_name })
     }
+
+    async fn update_issue(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+        body: serde_json::Value,
+    ) -> Result<(), GithubError> {
+        self.octocrab
+            .patch::<serde_json::Value, _, _>(issue_route(repo_id, issue_id), Some(&body))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Send a GraphQL request and return its `data`.
+    async fn graphql(
+        &self,
+        query: &str,
+        variables: serde_json::Value,
+    ) -> Result<serde_json::Value, GithubError> {
+        let mut response: serde_json::Value = self
+            .octocrab
+            .graphql(&json!({ "query": query, "variables": variables }))
+            .await?;
+
+        if let Some(errors) = response.get("errors") {
+            return Err(GithubError::GraphQlError(errors.to_string()));
+        }
+
+        Ok(response["data"].take())
+    }
 }
 
 #[async_trait]
@@ -80,6 +202,16 @@ impl GitHost for GithubHost {
         &self.bot_name
     }
 
+    async fn check_connection(&self) -> Result<(), Self::Error> {
+        // This endpoint is available for every installation token, so it checks both the
+        // connection and the credentials.
+        self.octocrab
+            .get::<serde_json::Value, _, ()>("/installation/repositories", None)
+            .await?;
+
+        Ok(())
+    }
+
     async fn get_user(&self, id: UserId) -> Result<User, Self::Error> {
         let profile = self
             .octocrab
@@ -93,6 +225,11 @@ impl GitHost for GithubHost {
                 .login
                 .try_into()
                 .map_err(|_| GithubError::ApiResponseInvalidFormatError)?,
+            kind: match profile.r#type.as_str() {
+                "Bot" => UserKind::Bot,
+                "Organization" => UserKind::Organization,
+                _ => UserKind::User,
+            },
         })
     }
 
@@ -113,6 +250,23 @@ impl GitHost for GithubHost {
         })
     }
 
+    async fn find_repo(&self, name: &RepoName) -> Result<Repo, Self::Error> {
+        let repo = self
+            .octocrab
+            .repos(name.owner.as_str(), name.name.as_str())
+            .get()
+            .await?;
+
+        Ok(Repo {
+            id: repo.id.into(),
+            owner: repo
+                .owner
+                .ok_or(GithubError::ApiResponseInvalidFormatError)?
+                .login,
+            name: repo.name,
+        })
+    }
+
     async fn get_issue(&self, repo_id: RepoId, issue_id: IssueId) -> Result<Issue, Self::Error> {
         let issue = self
             .octocrab
@@ -128,9 +282,235 @@ impl GitHost for GithubHost {
                 .map_err(|_| GithubError::ApiResponseInvalidFormatError)?,
             body: issue.body.unwrap_or(String::new()),
             author_user_id: issue.user.id.into(),
+            state: match issue.state {
+                octocrab::models::IssueState::Open => IssueState::Open,
+                _ => IssueState::Closed,
+            },
+            labels: issue
+                .labels
+                .into_iter()
+                .filter_map(|label| label.name.try_into().ok())
+                .collect(),
+            assignees: issue
+                .assignees
+                .into_iter()
+                .filter_map(|assignee| assignee.login.try_into().ok())
+                .collect(),
+            milestone: issue.milestone.and_then(|milestone| {
+                Some(Milestone {
+                    id: MilestoneId::from(milestone.number as usize),
+                    title: milestone.title.try_into().ok()?,
+                })
+            }),
+            created_at: issue.created_at,
+            updated_at: issue.updated_at,
+            author_association: make_author_association(&issue.author_association),
+        })
+    }
+
+    async fn search_issues(
+        &self,
+        repo_id: RepoId,
+        query: &IssueQuery,
+        page: u32,
+    ) -> Result<IssuesPage, Self::Error> {
+        let repo = self.get_repo(repo_id).await?;
+
+        let issues = self
+            .octocrab
+            .search()
+            .issues_and_pull_requests(&make_search_query(&repo, query))
+            .sort("created")
+            .order("asc")
+            .per_page(SEARCH_PAGE_SIZE)
+            .page(page)
+            .send()
+            .await?;
+
+        let has_next_page = page * u32::from(SEARCH_PAGE_SIZE) < SEARCH_RESULTS_LIMIT;
+
+        Ok(IssuesPage {
+            has_next_page: issues.next.is_some() && has_next_page,
+            capped: issues.next.is_some() && !has_next_page,
+            issues: issues
+                .items
+                .into_iter()
+                .map(|issue| FoundIssue {
+                    id: IssueId::from(issue.number),
+                    created_at: issue.created_at,
+                })
+                .collect(),
         })
     }
 
+    async fn create_issue(
+        &self,
+        repo_id: RepoId,
+        title: NonEmptyString,
+        body: String,
+    ) -> Result<IssueId, Self::Error> {
+        let issue: octocrab::models::issues::Issue = self
+            .octocrab
+            .post(
+                format!("/repositories/{}/issues", *repo_id),
+                Some(&json!({
+                    "title": title.as_str(),
+                    "body": body,
+                })),
+            )
+            .await?;
+
+        Ok(IssueId::from(issue.number))
+    }
+
+    async fn create_discussion(
+        &self,
+        repo_id: RepoId,
+        category: NonEmptyString,
+        title: NonEmptyString,
+        body: String,
+    ) -> Result<IssueId, Self::Error> {
+        let repo = self.get_repo(repo_id).await?;
+
+        let data = self
+            .graphql(
+                DISCUSSION_CATEGORIES_QUERY,
+                json!({ "owner": repo.owner, "name": repo.name }),
+            )
+            .await?;
+
+        let repository_id = data["repository"]["id"]
+            .as_str()
+            .ok_or(GithubError::ApiResponseInvalidFormatError)?;
+
+        let category_id = data["repository"]["discussionCategories"]["nodes"]
+            .as_array()
+            .ok_or(GithubError::ApiResponseInvalidFormatError)?
+            .iter()
+            .find(|node| node["name"].as_str() == Some(category.as_str()))
+            .and_then(|node| node["id"].as_str())
+            .ok_or_else(|| GithubError::DiscussionCategoryNotFound(category.to_string()))?;
+
+        let data = self
+            .graphql(
+                CREATE_DISCUSSION_MUTATION,
+                json!({
+                    "repositoryId": repository_id,
+                    "categoryId": category_id,
+                    "title": title.as_str(),
+                    "body": body,
+                }),
+            )
+            .await?;
+
+        let number = data["createDiscussion"]["discussion"]["number"]
+            .as_u64()
+            .ok_or(GithubError::ApiResponseInvalidFormatError)?;
+
+        Ok(IssueId::from(number as usize))
+    }
+
+    async fn get_file_content(
+        &self,
+        repo_id: RepoId,
+        path: String,
+    ) -> Result<Option<String>, Self::Error> {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static(RAW_MEDIA_TYPE));
+
+        let path = path
+            .split('/')
+            .map(encode_path_segment)
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let response = self
+            .octocrab
+            ._get_with_headers(
+                format!("/repositories/{}/contents/{}", *repo_id, path),
+                Some(headers),
+            )
+            .await?;
+
+        match octocrab::map_github_error(response).await {
+            Ok(response) => Ok(Some(self.octocrab.body_to_string(response).await?)),
+
+            Err(octocrab::Error::GitHub { source, .. })
+                if source.status_code == StatusCode::NOT_FOUND =>
+            {
+                Ok(None)
+            }
+
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn list_directory(
+        &self,
+        repo_id: RepoId,
+        path: String,
+    ) -> Result<Vec<String>, Self::Error> {
+        let path = path
+            .split('/')
+            .map(encode_path_segment)
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let response = self
+            .octocrab
+            ._get(format!("/repositories/{}/contents/{}", *repo_id, path))
+            .await?;
+
+        let response = match octocrab::map_github_error(response).await {
+            Ok(response) => response,
+
+            Err(octocrab::Error::GitHub { source, .. })
+                if source.status_code == StatusCode::NOT_FOUND =>
+            {
+                return Ok(Vec::new());
+            }
+
+            Err(e) => return Err(e.into()),
+        };
+
+        // The API returns an object instead of an array if the path is a file.
+        let entries: Vec<ContentEntry> =
+            serde_json::from_str(&self.octocrab.body_to_string(response).await?)
+                .map_err(|_| GithubError::ApiResponseInvalidFormatError)?;
+
+        Ok(entries
+            .into_iter()
+            .filter(|entry| entry.kind == "file")
+            .map(|entry| entry.path)
+            .collect())
+    }
+
+    async fn list_files(&self, repo_id: RepoId) -> Result<Vec<String>, Self::Error> {
+        let tree: Tree = self
+            .octocrab
+            .get(
+                format!("/repositories/{}/git/trees/HEAD", *repo_id),
+                Some(&[("recursive", "1")]),
+            )
+            .await?;
+
+        if tree.truncated {
+            warn!(
+                "The file tree of repository {} is too big, only {} entries were listed",
+                *repo_id,
+                tree.tree.len()
+            );
+        }
+
+        // Files are blobs in the Git terms.
+        Ok(tree
+            .tree
+            .into_iter()
+            .filter(|entry| entry.kind == "blob")
+            .map(|entry| entry.path)
+            .collect())
+    }
+
     async fn get_comment(
         &self,
         repo_id: RepoId,
@@ -151,6 +531,9 @@ impl GitHost for GithubHost {
                 .ok_or(GithubError::ApiResponseInvalidFormatError)?
                 .try_into()
                 .map_err(|_| GithubError::ApiResponseInvalidFormatError)?,
+            created_at: comment.created_at,
+            updated_at: comment.updated_at.unwrap_or(comment.created_at),
+            author_association: make_author_association(&comment.author_association),
         })
     }
 
@@ -159,12 +542,115 @@ impl GitHost for GithubHost {
         repo_id: RepoId,
         issue_id: IssueId,
         message: NonEmptyString,
+    ) -> Result<CommentId, Self::Error> {
+        let comment = self
+            .octocrab
+            .issues_by_id(octocrab::models::RepositoryId::from(*repo_id as u64))
+            .create_comment(*issue_id as u64, message)
+            .await?;
+
+        Ok(CommentId::from(*comment.id as usize))
+    }
+
+    async fn list_comments(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+    ) -> Result<Vec<Comment>, Self::Error> {
+        let mut comments = Vec::new();
+
+        for page in 1.. {
+            let page_comments: Vec<octocrab::models::issues::Comment> = self
+                .octocrab
+                .get(
+                    format!("/repositories/{}/issues/{}/comments", *repo_id, *issue_id),
+                    Some(&[("per_page", COMMENTS_PAGE_SIZE), ("page", page)]),
+                )
+                .await?;
+
+            let is_last_page = page_comments.len() < COMMENTS_PAGE_SIZE;
+
+            comments.extend(page_comments.into_iter().filter_map(|comment| {
+                Some(Comment {
+                    id: CommentId::from(*comment.id as usize),
+                    user_id: UserId::from(*comment.user.id as usize),
+                    created_at: comment.created_at,
+                    updated_at: comment.updated_at.unwrap_or(comment.created_at),
+                    author_association: make_author_association(&comment.author_association),
+                    body: comment.body?.try_into().ok()?,
+                })
+            }));
+
+            if is_last_page {
+                break;
+            }
+        }
+
+        Ok(comments)
+    }
+
+    async fn edit_comment(
+        &self,
+        repo_id: RepoId,
+        comment_id: CommentId,
+        message: NonEmptyString,
     ) -> Result<(), Self::Error> {
         self.octocrab
+            .patch::<serde_json::Value, _, _>(
+                format!("/repositories/{}/issues/comments/{}", *repo_id, *comment_id),
+                Some(&json!({ "body": message.as_str() })),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete_comment(
+        &self,
+        repo_id: RepoId,
+        comment_id: CommentId,
+    ) -> Result<(), Self::Error> {
+        let response = self
+            .octocrab
+            ._delete(
+                format!("/repositories/{}/issues/comments/{}", *repo_id, *comment_id),
+                None::<&()>,
+            )
+            .await?;
+
+        octocrab::map_github_error(response).await?;
+
+        Ok(())
+    }
+
+    async fn hide_comment(
+        &self,
+        repo_id: RepoId,
+        comment_id: CommentId,
+        reason: HideReason,
+    ) -> Result<(), Self::Error> {
+        // GraphQL needs the global node ID of the comment, which only the REST API knows.
+        let comment = self
+            .octocrab
             .issues_by_id(octocrab::models::RepositoryId::from(*repo_id as u64))
-            .create_comment(*issue_id as u64, message)
+            .get_comment(octocrab::models::CommentId::from(*comment_id as u64))
             .await?;
 
+        let classifier = match reason {
+            HideReason::Spam => "SPAM",
+            HideReason::Abuse => "ABUSE",
+            HideReason::OffTopic => "OFF_TOPIC",
+            HideReason::Outdated => "OUTDATED",
+            HideReason::Duplicate => "DUPLICATE",
+            HideReason::Resolved => "RESOLVED",
+        };
+
+        self.graphql(
+            MINIMIZE_COMMENT_MUTATION,
+            json!({ "subjectId": comment.node_id, "classifier": classifier }),
+        )
+        .await?;
+
         Ok(())
     }
 
@@ -215,55 +701,495 @@ impl GitHost for GithubHost {
 
         Ok(())
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use std::str::FromStr;
 
-    use secrecy::SecretVec;
-    use serde_json::json;
-    use url::Url;
-    use wiremock::{
-        matchers::{method, path},
-        Mock, MockServer, ResponseTemplate,
-    };
+    async fn remove_label(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+        label_name: NonEmptyString,
+    ) -> Result<(), Self::Error> {
+        let response = self
+            .octocrab
+            ._delete(
+                format!(
+                    "{}/labels/{}",
+                    issue_route(repo_id, issue_id),
+                    encode_path_segment(label_name.as_str())
+                ),
+                None::<&()>,
+            )
+            .await?;
 
-    use crate::githost::{
-        host::GitHost,
-        impls::github::github_host::GithubHost,
-        model::{CommentId, IssueId, Label, LabelId, RepoId, UserId},
-    };
+        octocrab::map_github_error(response).await?;
 
-    async fn setup() -> (MockServer, GithubHost) {
-        let mock_server = MockServer::start().await;
+        Ok(())
+    }
 
-        Mock::given(method("POST"))
-            .and(path("/app/installations/1/access_tokens"))
-            .respond_with(
-                ResponseTemplate::new(200).set_body_string(include_str!("access_token.json")),
+    async fn create_label(
+        &self,
+        repo_id: RepoId,
+        name: NonEmptyString,
+        color: NonEmptyString,
+        description: String,
+    ) -> Result<Label, Self::Error> {
+        let label: octocrab::models::Label = self
+            .octocrab
+            .post(
+                format!("/repositories/{}/labels", *repo_id),
+                Some(&json!({
+                    "name": name.as_str(),
+                    "color": color.as_str(),
+                    "description": description,
+                })),
             )
-            .mount(&mock_server)
-            .await;
+            .await?;
 
-        let github = GithubHost::build_raw(
-            "bot".try_into().unwrap(),
-            1,
-            1,
-            SecretVec::new(include_bytes!("test.pem").into()),
-            Url::from_str(&mock_server.uri().to_string()).unwrap(),
-        )
-        .await
-        .unwrap();
+        Ok(Label {
+            id: LabelId::from(*label.id as usize),
+            name: label
+                .name
+                .try_into()
+                .map_err(|_| GithubError::ApiResponseInvalidFormatError)?,
+            description: label.description.unwrap_or_default(),
+        })
+    }
 
-        (mock_server, github)
+    async fn set_assignees(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+        assignees: Vec<NonEmptyString>,
+    ) -> Result<(), Self::Error> {
+        let assignees: Vec<&str> = assignees.iter().map(|name| name.as_str()).collect();
+
+        self.update_issue(repo_id, issue_id, json!({ "assignees": assignees }))
+            .await
     }
 
-    #[tokio::test]
-    async fn get_user() {
-        let (mock_server, github) = setup().await;
+    async fn set_milestone(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+        milestone: Option<MilestoneId>,
+    ) -> Result<(), Self::Error> {
+        self.update_issue(
+            repo_id,
+            issue_id,
+            json!({ "milestone": milestone.map(|milestone| *milestone) }),
+        )
+        .await
+    }
 
-        Mock::given(method("GET"))
+    async fn close_issue(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+        reason: CloseReason,
+    ) -> Result<(), Self::Error> {
+        self.update_issue(
+            repo_id,
+            issue_id,
+            json!({ "state": "closed", "state_reason": reason }),
+        )
+        .await
+    }
+
+    async fn reopen_issue(&self, repo_id: RepoId, issue_id: IssueId) -> Result<(), Self::Error> {
+        self.update_issue(repo_id, issue_id, json!({ "state": "open" }))
+            .await
+    }
+
+    async fn edit_issue_body(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+        body: String,
+    ) -> Result<(), Self::Error> {
+        self.update_issue(repo_id, issue_id, json!({ "body": body }))
+            .await
+    }
+
+    async fn lock_issue(
+        &self,
+        repo_id: RepoId,
+        issue_id: IssueId,
+        reason: Option<LockReason>,
+    ) -> Result<(), Self::Error> {
+        let body = match reason {
+            Some(reason) => json!({ "lock_reason": reason }),
+            None => json!({}),
+        };
+
+        let response = self
+            .octocrab
+            ._put(
+                format!("{}/lock", issue_route(repo_id, issue_id)),
+                Some(&body),
+            )
+            .await?;
+
+        octocrab::map_github_error(response).await?;
+
+        Ok(())
+    }
+
+    // NOTE: octocrab has no handlers for pull requests by repository ID, so the routes are written
+    // by hand here.
+
+    async fn get_pull_request(
+        &self,
+        repo_id: RepoId,
+        pull_request_id: IssueId,
+    ) -> Result<PullRequest, Self::Error> {
+        let pull_request: octocrab::models::pulls::PullRequest = self
+            .octocrab
+            .get(pull_request_route(repo_id, pull_request_id), None::<&()>)
+            .await?;
+
+        Ok(PullRequest {
+            id: pull_request_id,
+            author_user_id: pull_request
+                .user
+                .ok_or(GithubError::ApiResponseInvalidFormatError)?
+                .id
+                .into(),
+            title: pull_request
+                .title
+                .ok_or(GithubError::ApiResponseInvalidFormatError)?
+                .try_into()
+                .map_err(|_| GithubError::ApiResponseInvalidFormatError)?,
+            body: pull_request.body.unwrap_or_default(),
+            base_ref: pull_request.base.ref_field,
+            head_ref: pull_request.head.ref_field,
+            head_sha: pull_request.head.sha,
+        })
+    }
+
+    async fn get_pull_request_files(
+        &self,
+        repo_id: RepoId,
+        pull_request_id: IssueId,
+    ) -> Result<Vec<ChangedFile>, Self::Error> {
+        let mut files = Vec::new();
+
+        for page in 1.. {
+            let entries: Vec<DiffEntry> = self
+                .octocrab
+                .get(
+                    format!("{}/files", pull_request_route(repo_id, pull_request_id)),
+                    Some(&[("per_page", FILES_PAGE_SIZE), ("page", page)]),
+                )
+                .await?;
+
+            let is_last_page = entries.len() < FILES_PAGE_SIZE;
+
+            files.extend(entries.into_iter().map(|entry| ChangedFile {
+                path: entry.filename,
+                status: match entry.status {
+                    DiffEntryStatus::Added => ChangedFileStatus::Added,
+                    DiffEntryStatus::Removed => ChangedFileStatus::Removed,
+                    DiffEntryStatus::Modified => ChangedFileStatus::Modified,
+                    DiffEntryStatus::Renamed => ChangedFileStatus::Renamed,
+                    _ => ChangedFileStatus::Other,
+                },
+                additions: entry.additions as usize,
+                deletions: entry.deletions as usize,
+                patch: entry.patch,
+            }));
+
+            if is_last_page {
+                break;
+            }
+        }
+
+        Ok(files)
+    }
+
+    async fn edit_pull_request_body(
+        &self,
+        repo_id: RepoId,
+        pull_request_id: IssueId,
+        body: String,
+    ) -> Result<(), Self::Error> {
+        self.octocrab
+            .patch::<serde_json::Value, _, _>(
+                pull_request_route(repo_id, pull_request_id),
+                Some(&json!({ "body": body })),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn compare_refs(
+        &self,
+        repo_id: RepoId,
+        base: String,
+        head: String,
+    ) -> Result<Vec<String>, Self::Error> {
+        let mut commits = Vec::new();
+
+        for page in 1.. {
+            let comparison: Comparison = self
+                .octocrab
+                .get(
+                    format!(
+                        "/repositories/{}/compare/{}...{}",
+                        *repo_id,
+                        encode_path_segment(&base),
+                        encode_path_segment(&head)
+                    ),
+                    Some(&[("per_page", COMMITS_PAGE_SIZE), ("page", page)]),
+                )
+                .await?;
+
+            let is_last_page = comparison.commits.len() < COMMITS_PAGE_SIZE;
+
+            commits.extend(comparison.commits.into_iter().map(|commit| commit.sha));
+
+            if is_last_page || commits.len() >= comparison.total_commits {
+                break;
+            }
+        }
+
+        Ok(commits)
+    }
+
+    async fn list_merged_pull_requests(
+        &self,
+        repo_id: RepoId,
+        commit_sha: String,
+    ) -> Result<Vec<IssueId>, Self::Error> {
+        let pull_requests: Vec<CommitPullRequest> = self
+            .octocrab
+            .get(
+                format!(
+                    "/repositories/{}/commits/{}/pulls",
+                    *repo_id,
+                    encode_path_segment(&commit_sha)
+                ),
+                None::<&()>,
+            )
+            .await?;
+
+        Ok(pull_requests
+            .into_iter()
+            .filter(|pull_request| pull_request.merged_at.is_some())
+            .map(|pull_request| IssueId::from(pull_request.number))
+            .collect())
+    }
+
+    async fn create_release(
+        &self,
+        repo_id: RepoId,
+        tag: NonEmptyString,
+        name: String,
+        body: String,
+        draft: bool,
+    ) -> Result<(), Self::Error> {
+        self.octocrab
+            .post::<_, serde_json::Value>(
+                format!("/repositories/{}/releases", *repo_id),
+                Some(&json!({
+                    "tag_name": tag.as_str(),
+                    "name": name,
+                    "body": body,
+                    "draft": draft,
+                })),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_pull_request_diff(
+        &self,
+        repo_id: RepoId,
+        pull_request_id: IssueId,
+    ) -> Result<String, Self::Error> {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static(DIFF_MEDIA_TYPE));
+
+        let response = self
+            .octocrab
+            ._get_with_headers(pull_request_route(repo_id, pull_request_id), Some(headers))
+            .await?;
+
+        let response = octocrab::map_github_error(response).await?;
+
+        Ok(self.octocrab.body_to_string(response).await?)
+    }
+
+    async fn make_pull_request_review(
+        &self,
+        repo_id: RepoId,
+        pull_request_id: IssueId,
+        review: Review,
+    ) -> Result<(), Self::Error> {
+        let body = json!({
+            "body": review.body,
+            "event": match review.verdict {
+                ReviewVerdict::Comment => "COMMENT",
+                ReviewVerdict::Approve => "APPROVE",
+                ReviewVerdict::RequestChanges => "REQUEST_CHANGES",
+            },
+            "comments": review
+                .comments
+                .into_iter()
+                .map(|comment| json!({
+                    "path": comment.path,
+                    "line": comment.line,
+                    "side": "RIGHT",
+                    "body": comment.body.as_str(),
+                }))
+                .collect::<Vec<_>>(),
+        });
+
+        self.octocrab
+            .post::<_, serde_json::Value>(
+                format!("{}/reviews", pull_request_route(repo_id, pull_request_id)),
+                Some(&body),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+// NOTE: The author association is serialized into a string first, as octocrab uses different types
+// for it in different models.
+fn make_author_association(value: &impl Serialize) -> AuthorAssociation {
+    let value = serde_json::to_value(value).unwrap_or_default();
+
+    match value.as_str() {
+        Some("OWNER") => AuthorAssociation::Owner,
+        Some("MEMBER") => AuthorAssociation::Member,
+        Some("COLLABORATOR") => AuthorAssociation::Collaborator,
+        Some("CONTRIBUTOR") => AuthorAssociation::Contributor,
+        Some("FIRST_TIME_CONTRIBUTOR") => AuthorAssociation::FirstTimeContributor,
+        Some("FIRST_TIMER") => AuthorAssociation::FirstTimer,
+        _ => AuthorAssociation::None,
+    }
+}
+
+fn issue_route(repo_id: RepoId, issue_id: IssueId) -> String {
+    format!("/repositories/{}/issues/{}", *repo_id, *issue_id)
+}
+
+/// Percent-encode a value (like a label name with spaces) to be used as a single URL path segment.
+fn encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn pull_request_route(repo_id: RepoId, pull_request_id: IssueId) -> String {
+    format!("/repositories/{}/pulls/{}", *repo_id, *pull_request_id)
+}
+
+fn make_search_query(repo: &Repo, query: &IssueQuery) -> String {
+    let mut parts = vec![
+        format!("repo:{}/{}", repo.owner, repo.name),
+        if query.pull_requests {
+            "is:pr".into()
+        } else {
+            "is:issue".into()
+        },
+    ];
+
+    match query.state {
+        IssueStateFilter::Open => parts.push("state:open".into()),
+        IssueStateFilter::Closed => parts.push("state:closed".into()),
+        IssueStateFilter::All => {}
+    }
+
+    for label in &query.labels {
+        parts.push(format!("label:\"{}\"", label));
+    }
+
+    if query.unlabeled {
+        parts.push("no:label".into());
+    }
+
+    if let Some(date) = query.created_after {
+        parts.push(format!("created:>={}", date.format("%Y-%m-%d")));
+    }
+
+    if let Some(date) = query.created_before {
+        parts.push(format!("created:<{}", date.format("%Y-%m-%d")));
+    }
+
+    if let Some(date) = query.closed_after {
+        parts.push(format!("closed:>={}", date.format("%Y-%m-%d")));
+    }
+
+    if let Some(date) = query.closed_before {
+        parts.push(format!("closed:<{}", date.format("%Y-%m-%d")));
+    }
+
+    parts.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chrono::{DateTime, NaiveDate};
+    use non_empty_string::NonEmptyString;
+    use secrecy::SecretVec;
+    use serde_json::json;
+    use url::Url;
+    use wiremock::{
+        matchers::{body_partial_json, body_string_contains, header, method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use crate::githost::{
+        host::GitHost,
+        impls::github::{errors::GithubError, github_host::GithubHost},
+        model::{
+            AuthorAssociation, ChangedFile, ChangedFileStatus, CloseReason, CommentId, FoundIssue,
+            HideReason, IssueId, IssueQuery, IssueState, IssueStateFilter, IssuesPage, Label,
+            LabelId, LockReason, Milestone, MilestoneId, RepoId, RepoName, Review, ReviewComment,
+            ReviewVerdict, UserId, UserKind,
+        },
+    };
+
+    async fn setup() -> (MockServer, GithubHost) {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/app/installations/1/access_tokens"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string(include_str!("access_token.json")),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let github = GithubHost::build_raw(
+            "bot".try_into().unwrap(),
+            1,
+            1,
+            SecretVec::new(include_bytes!("test.pem").into()),
+            Url::from_str(&mock_server.uri().to_string()).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        (mock_server, github)
+    }
+
+    #[tokio::test]
+    async fn get_user() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("GET"))
             .and(path("/user/1"))
             .respond_with(ResponseTemplate::new(200).set_body_json(json!({
               "login": "octocat",
@@ -302,120 +1228,1104 @@ mod tests {
             .mount(&mock_server)
             .await;
 
-        let user = github.get_user(UserId::from(1)).await.unwrap();
+        let user = github.get_user(UserId::from(1)).await.unwrap();
+
+        assert_eq!(user.id, UserId::from(1));
+        assert_eq!(user.nickname.as_str(), "octocat");
+        assert_eq!(user.kind, UserKind::User);
+    }
+
+    #[tokio::test]
+    async fn get_issue() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repositories/1/issues/1"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string(include_str!("issue_response.json")),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let issue = github
+            .get_issue(RepoId::from(1), IssueId::from(1 as usize))
+            .await
+            .unwrap();
+
+        assert_eq!(issue.id, IssueId::from(1 as usize));
+        assert_eq!(issue.title.as_str(), "Found a bug");
+        assert_eq!(issue.body.as_str(), "I'm having a problem with this.");
+        assert_eq!(issue.state, IssueState::Open);
+        assert_eq!(issue.labels, vec![NonEmptyString::from_str("bug").unwrap()]);
+        assert_eq!(
+            issue.assignees,
+            vec![NonEmptyString::from_str("octocat").unwrap()]
+        );
+        assert_eq!(
+            issue.milestone,
+            Some(Milestone {
+                id: MilestoneId::from(1),
+                title: "v1.0".try_into().unwrap(),
+            })
+        );
+        assert_eq!(issue.created_at.to_rfc3339(), "2011-04-22T13:33:48+00:00");
+        assert_eq!(issue.author_association, AuthorAssociation::Collaborator);
+    }
+
+    #[tokio::test]
+    async fn get_repo() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repositories/1"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(include_str!("repository_response.json")),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let repo = github.get_repo(RepoId::from(1)).await.unwrap();
+
+        assert_eq!(repo.id, RepoId::from(1));
+        assert_eq!(repo.owner.as_str(), "octocat");
+        assert_eq!(repo.name.as_str(), "Hello-World");
+    }
+
+    #[tokio::test]
+    async fn find_repo() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repos/octocat/Hello-World"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(include_str!("repository_response.json")),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let repo = github
+            .find_repo(&RepoName::from_str("octocat/Hello-World").unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(repo.id, RepoId::from(1296269));
+        assert_eq!(repo.owner.as_str(), "octocat");
+        assert_eq!(repo.name.as_str(), "Hello-World");
+    }
+
+    #[tokio::test]
+    async fn check_connection() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("GET"))
+            .and(path("/installation/repositories"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+              "total_count": 0,
+              "repositories": []
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        github.check_connection().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn search_issues() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repositories/1"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(include_str!("repository_response.json")),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let issue: serde_json::Value =
+            serde_json::from_str(include_str!("issue_response.json")).unwrap();
+
+        Mock::given(method("GET"))
+            .and(path("/search/issues"))
+            .and(query_param(
+                "q",
+                "repo:octocat/Hello-World is:issue state:open label:\"bug\" no:label",
+            ))
+            .and(query_param("page", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+              "total_count": 1,
+              "incomplete_results": false,
+              "items": [issue]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let page = github
+            .search_issues(
+                RepoId::from(1),
+                &IssueQuery {
+                    labels: vec!["bug".try_into().unwrap()],
+                    unlabeled: true,
+                    ..Default::default()
+                },
+                1,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            page,
+            IssuesPage {
+                issues: vec![FoundIssue {
+                    id: IssueId::from(1347 as usize),
+                    created_at: DateTime::parse_from_rfc3339("2011-04-22T13:33:48Z")
+                        .unwrap()
+                        .to_utc(),
+                }],
+                has_next_page: false,
+                capped: false,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn search_closed_pull_requests() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repositories/1"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(include_str!("repository_response.json")),
+            )
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/search/issues"))
+            .and(query_param(
+                "q",
+                "repo:octocat/Hello-World is:pr closed:>=2024-09-01 closed:<2024-09-08",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+              "total_count": 0,
+              "incomplete_results": false,
+              "items": []
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let page = github
+            .search_issues(
+                RepoId::from(1),
+                &IssueQuery {
+                    state: IssueStateFilter::All,
+                    pull_requests: true,
+                    closed_after: NaiveDate::from_ymd_opt(2024, 9, 1),
+                    closed_before: NaiveDate::from_ymd_opt(2024, 9, 8),
+                    ..Default::default()
+                },
+                1,
+            )
+            .await
+            .unwrap();
+
+        assert!(page.issues.is_empty());
+    }
+
+    #[tokio::test]
+    async fn create_issue() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("POST"))
+            .and(path("/repositories/1/issues"))
+            .and(body_partial_json(json!({
+              "title": "Found a bug",
+              "body": "I'm having a problem with this."
+            })))
+            .respond_with(
+                ResponseTemplate::new(201).set_body_string(include_str!("issue_response.json")),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let issue_id = github
+            .create_issue(
+                RepoId::from(1),
+                "Found a bug".try_into().unwrap(),
+                "I'm having a problem with this.".into(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(issue_id, IssueId::from(1347 as usize));
+    }
+
+    #[tokio::test]
+    async fn create_discussion() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repositories/1"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(include_str!("repository_response.json")),
+            )
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .and(body_string_contains("discussionCategories"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+              "data": {
+                "repository": {
+                  "id": "R_kgDOAAAAAQ",
+                  "discussionCategories": {
+                    "nodes": [
+                      { "id": "DIC_kwDOAAAAAc4AAAAA", "name": "General" },
+                      { "id": "DIC_kwDOAAAAAc4AAAAB", "name": "Announcements" }
+                    ]
+                  }
+                }
+              }
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .and(body_string_contains("createDiscussion"))
+            .and(body_partial_json(json!({
+              "variables": {
+                "repositoryId": "R_kgDOAAAAAQ",
+                "categoryId": "DIC_kwDOAAAAAc4AAAAB",
+                "title": "Weekly digest"
+              }
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+              "data": {
+                "createDiscussion": {
+                  "discussion": { "number": 42 }
+                }
+              }
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let discussion_id = github
+            .create_discussion(
+                RepoId::from(1),
+                "Announcements".try_into().unwrap(),
+                "Weekly digest".try_into().unwrap(),
+                "Nothing happened this week.".into(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(discussion_id, IssueId::from(42 as usize));
+    }
+
+    #[tokio::test]
+    async fn create_discussion_in_missing_category() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repositories/1"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(include_str!("repository_response.json")),
+            )
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+              "data": {
+                "repository": {
+                  "id": "R_kgDOAAAAAQ",
+                  "discussionCategories": { "nodes": [] }
+                }
+              }
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let res = github
+            .create_discussion(
+                RepoId::from(1),
+                "Announcements".try_into().unwrap(),
+                "Weekly digest".try_into().unwrap(),
+                "Nothing happened this week.".into(),
+            )
+            .await;
+
+        assert!(matches!(
+            res,
+            Err(GithubError::DiscussionCategoryNotFound(category)) if category == "Announcements"
+        ));
+    }
+
+    #[tokio::test]
+    async fn get_file_content() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repositories/1/contents/.github/CODEOWNERS"))
+            .and(header("Accept", "application/vnd.github.raw+json"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("* @octocat\n"))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/repositories/1/contents/MISSING.md"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+              "message": "Not Found",
+              "documentation_url": "https://docs.github.com/rest/repos/contents#get-repository-content"
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let content = github
+            .get_file_content(RepoId::from(1), ".github/CODEOWNERS".into())
+            .await
+            .unwrap();
+
+        assert_eq!(content.as_deref(), Some("* @octocat\n"));
+
+        let missing = github
+            .get_file_content(RepoId::from(1), "MISSING.md".into())
+            .await
+            .unwrap();
+
+        assert_eq!(missing, None);
+    }
+
+    #[tokio::test]
+    async fn list_directory() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repositories/1/contents/.github/ISSUE_TEMPLATE"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+              { "name": "bug_report.yml", "path": ".github/ISSUE_TEMPLATE/bug_report.yml", "type": "file" },
+              { "name": "config.yml", "path": ".github/ISSUE_TEMPLATE/config.yml", "type": "file" },
+              { "name": "old", "path": ".github/ISSUE_TEMPLATE/old", "type": "dir" }
+            ])))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/repositories/1/contents/missing"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+              "message": "Not Found",
+              "documentation_url": "https://docs.github.com/rest/repos/contents#get-repository-content"
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let files = github
+            .list_directory(RepoId::from(1), ".github/ISSUE_TEMPLATE".into())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            files,
+            vec![
+                ".github/ISSUE_TEMPLATE/bug_report.yml".to_string(),
+                ".github/ISSUE_TEMPLATE/config.yml".to_string()
+            ]
+        );
+
+        let missing = github
+            .list_directory(RepoId::from(1), "missing".into())
+            .await
+            .unwrap();
+
+        assert!(missing.is_empty());
+    }
+
+    #[tokio::test]
+    async fn list_files() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repositories/1/git/trees/HEAD"))
+            .and(query_param("recursive", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+              "sha": "9fb037999f264ba9a7fc6274d15fa3ae2ab98312",
+              "tree": [
+                { "path": "README.md", "mode": "100644", "type": "blob", "size": 30 },
+                { "path": "src", "mode": "040000", "type": "tree" },
+                { "path": "src/main.rs", "mode": "100644", "type": "blob", "size": 132 }
+              ],
+              "truncated": false
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let files = github.list_files(RepoId::from(1)).await.unwrap();
+
+        assert_eq!(
+            files,
+            vec!["README.md".to_string(), "src/main.rs".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn get_comment() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repositories/1/issues/comments/1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+              "id": 1,
+              "node_id": "MDEyOklzc3VlQ29tbWVudDE=",
+              "url": "https://api.github.com/repos/octocat/Hello-World/issues/comments/1",
+              "html_url": "https://github.com/octocat/Hello-World/issues/1347#issuecomment-1",
+              "body": "Me too",
+              "user": {
+                "login": "octocat",
+                "id": 1,
+                "node_id": "MDQ6VXNlcjE=",
+                "avatar_url": "https://github.com/images/error/octocat_happy.gif",
+                "gravatar_id": "",
+                "url": "https://api.github.com/users/octocat",
+                "html_url": "https://github.com/octocat",
+                "followers_url": "https://api.github.com/users/octocat/followers",
+                "following_url": "https://api.github.com/users/octocat/following{/other_user}",
+                "gists_url": "https://api.github.com/users/octocat/gists{/gist_id}",
+                "starred_url": "https://api.github.com/users/octocat/starred{/owner}{/repo}",
+                "subscriptions_url": "https://api.github.com/users/octocat/subscriptions",
+                "organizations_url": "https://api.github.com/users/octocat/orgs",
+                "repos_url": "https://api.github.com/users/octocat/repos",
+                "events_url": "https://api.github.com/users/octocat/events{/privacy}",
+                "received_events_url": "https://api.github.com/users/octocat/received_events",
+                "type": "User",
+                "site_admin": false
+              },
+              "created_at": "2011-04-14T16:00:49Z",
+              "updated_at": "2011-04-14T16:00:49Z",
+              "issue_url": "https://api.github.com/repos/octocat/Hello-World/issues/1347",
+              "author_association": "COLLABORATOR"
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let comment = github
+            .get_comment(
+                RepoId::from(1),
+                IssueId::from(1 as usize),
+                CommentId::from(1),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(comment.id, CommentId::from(1));
+        assert_eq!(comment.user_id, UserId::from(1));
+        assert_eq!(comment.body.as_str(), "Me too");
+    }
+
+    #[tokio::test]
+    async fn list_comments() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repositories/1/issues/1/comments"))
+            .and(query_param("page", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+              "id": 1,
+              "node_id": "MDEyOklzc3VlQ29tbWVudDE=",
+              "url": "https://api.github.com/repos/octocat/Hello-World/issues/comments/1",
+              "html_url": "https://github.com/octocat/Hello-World/issues/1347#issuecomment-1",
+              "body": "Me too",
+              "user": {
+                "login": "octocat",
+                "id": 1,
+                "node_id": "MDQ6VXNlcjE=",
+                "avatar_url": "https://github.com/images/error/octocat_happy.gif",
+                "gravatar_id": "",
+                "url": "https://api.github.com/users/octocat",
+                "html_url": "https://github.com/octocat",
+                "followers_url": "https://api.github.com/users/octocat/followers",
+                "following_url": "https://api.github.com/users/octocat/following{/other_user}",
+                "gists_url": "https://api.github.com/users/octocat/gists{/gist_id}",
+                "starred_url": "https://api.github.com/users/octocat/starred{/owner}{/repo}",
+                "subscriptions_url": "https://api.github.com/users/octocat/subscriptions",
+                "organizations_url": "https://api.github.com/users/octocat/orgs",
+                "repos_url": "https://api.github.com/users/octocat/repos",
+                "events_url": "https://api.github.com/users/octocat/events{/privacy}",
+                "received_events_url": "https://api.github.com/users/octocat/received_events",
+                "type": "User",
+                "site_admin": false
+              },
+              "created_at": "2011-04-14T16:00:49Z",
+              "updated_at": "2011-04-14T16:00:49Z",
+              "issue_url": "https://api.github.com/repos/octocat/Hello-World/issues/1347",
+              "author_association": "COLLABORATOR"
+            }])))
+            .mount(&mock_server)
+            .await;
+
+        let comments = github
+            .list_comments(RepoId::from(1), IssueId::from(1 as usize))
+            .await
+            .unwrap();
+
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0].id, CommentId::from(1));
+        assert_eq!(comments[0].body.as_str(), "Me too");
+    }
+
+    #[tokio::test]
+    async fn edit_comment() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("PATCH"))
+            .and(path("/repositories/1/issues/comments/1"))
+            .and(body_partial_json(json!({ "body": "Me too!" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        github
+            .edit_comment(
+                RepoId::from(1),
+                CommentId::from(1),
+                "Me too!".try_into().unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn make_comment() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("POST"))
+            .and(path("/repositories/1/issues/1/comments"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+              "id": 1,
+              "node_id": "MDEyOklzc3VlQ29tbWVudDE=",
+              "url": "https://api.github.com/repos/octocat/Hello-World/issues/comments/1",
+              "html_url": "https://github.com/octocat/Hello-World/issues/1347#issuecomment-1",
+              "body": "Me too",
+              "user": {
+                "login": "octocat",
+                "id": 1,
+                "node_id": "MDQ6VXNlcjE=",
+                "avatar_url": "https://github.com/images/error/octocat_happy.gif",
+                "gravatar_id": "",
+                "url": "https://api.github.com/users/octocat",
+                "html_url": "https://github.com/octocat",
+                "followers_url": "https://api.github.com/users/octocat/followers",
+                "following_url": "https://api.github.com/users/octocat/following{/other_user}",
+                "gists_url": "https://api.github.com/users/octocat/gists{/gist_id}",
+                "starred_url": "https://api.github.com/users/octocat/starred{/owner}{/repo}",
+                "subscriptions_url": "https://api.github.com/users/octocat/subscriptions",
+                "organizations_url": "https://api.github.com/users/octocat/orgs",
+                "repos_url": "https://api.github.com/users/octocat/repos",
+                "events_url": "https://api.github.com/users/octocat/events{/privacy}",
+                "received_events_url": "https://api.github.com/users/octocat/received_events",
+                "type": "User",
+                "site_admin": false
+              },
+              "created_at": "2011-04-14T16:00:49Z",
+              "updated_at": "2011-04-14T16:00:49Z",
+              "issue_url": "https://api.github.com/repos/octocat/Hello-World/issues/1347",
+              "author_association": "COLLABORATOR"
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let comment_id = github
+            .make_comment(
+                RepoId::from(1),
+                IssueId::from(1 as usize),
+                "message".try_into().unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(comment_id, CommentId::from(1));
+    }
+
+    #[tokio::test]
+    async fn delete_comment() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("DELETE"))
+            .and(path("/repositories/1/issues/comments/1"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        github
+            .delete_comment(RepoId::from(1), CommentId::from(1))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn hide_comment() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repositories/1/issues/comments/1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+              "id": 1,
+              "node_id": "MDEyOklzc3VlQ29tbWVudDE=",
+              "url": "https://api.github.com/repos/octocat/Hello-World/issues/comments/1",
+              "html_url": "https://github.com/octocat/Hello-World/issues/1347#issuecomment-1",
+              "body": "Me too",
+              "user": {
+                "login": "octocat",
+                "id": 1,
+                "node_id": "MDQ6VXNlcjE=",
+                "avatar_url": "https://github.com/images/error/octocat_happy.gif",
+                "gravatar_id": "",
+                "url": "https://api.github.com/users/octocat",
+                "html_url": "https://github.com/octocat",
+                "followers_url": "https://api.github.com/users/octocat/followers",
+                "following_url": "https://api.github.com/users/octocat/following{/other_user}",
+                "gists_url": "https://api.github.com/users/octocat/gists{/gist_id}",
+                "starred_url": "https://api.github.com/users/octocat/starred{/owner}{/repo}",
+                "subscriptions_url": "https://api.github.com/users/octocat/subscriptions",
+                "organizations_url": "https://api.github.com/users/octocat/orgs",
+                "repos_url": "https://api.github.com/users/octocat/repos",
+                "events_url": "https://api.github.com/users/octocat/events{/privacy}",
+                "received_events_url": "https://api.github.com/users/octocat/received_events",
+                "type": "User",
+                "site_admin": false
+              },
+              "created_at": "2011-04-14T16:00:49Z",
+              "updated_at": "2011-04-14T16:00:49Z",
+              "issue_url": "https://api.github.com/repos/octocat/Hello-World/issues/1347",
+              "author_association": "COLLABORATOR"
+            })))
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("/graphql"))
+            .and(body_string_contains("minimizeComment"))
+            .and(body_partial_json(json!({
+              "variables": {
+                "subjectId": "MDEyOklzc3VlQ29tbWVudDE=",
+                "classifier": "SPAM"
+              }
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+              "data": {
+                "minimizeComment": {
+                  "minimizedComment": { "isMinimized": true }
+                }
+              }
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        github
+            .hide_comment(RepoId::from(1), CommentId::from(1), HideReason::Spam)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn get_repo_labels() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repositories/1/labels"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+              {
+                "id": 1,
+                "node_id": "MDU6TGFiZWwyMDgwNDU5NDY=",
+                "url": "https://api.github.com/repos/octocat/Hello-World/labels/bug",
+                "name": "bug",
+                "description": "Something isn't working",
+                "color": "f29513",
+                "default": true
+              },
+              {
+                "id": 2,
+                "node_id": "MDU6TGFiZWwyMDgwNDU5NDc=",
+                "url": "https://api.github.com/repos/octocat/Hello-World/labels/enhancement",
+                "name": "enhancement",
+                "description": "New feature or request",
+                "color": "a2eeef",
+                "default": false
+              }
+            ])))
+            .mount(&mock_server)
+            .await;
+
+        let labels = github.get_repo_labels(RepoId::from(1)).await.unwrap();
+
+        assert_eq!(
+            labels,
+            vec![
+                Label {
+                    id: LabelId::from(1),
+                    name: "bug".try_into().unwrap(),
+                    description: "Something isn't working".to_owned()
+                },
+                Label {
+                    id: LabelId::from(2),
+                    name: "enhancement".try_into().unwrap(),
+                    description: "New feature or request".to_owned()
+                }
+            ]
+        )
+    }
+
+    #[tokio::test]
+    async fn assign_label() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("POST"))
+            .and(path("/repositories/1/issues/1/labels"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+            .mount(&mock_server)
+            .await;
+
+        github
+            .assign_label(
+                RepoId::from(1),
+                IssueId::from(1 as usize),
+                "bug".try_into().unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn remove_label() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("DELETE"))
+            .and(path("/repositories/1/issues/1/labels/good%20first%20issue"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        github
+            .remove_label(
+                RepoId::from(1),
+                IssueId::from(1 as usize),
+                "good first issue".try_into().unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn create_label() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("POST"))
+            .and(path("/repositories/1/labels"))
+            .and(body_partial_json(json!({
+              "name": "bug",
+              "color": "f29513",
+              "description": "Something isn't working"
+            })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+              "id": 208045946,
+              "node_id": "MDU6TGFiZWwyMDgwNDU5NDY=",
+              "url": "https://api.github.com/repos/octocat/Hello-World/labels/bug",
+              "name": "bug",
+              "description": "Something isn't working",
+              "color": "f29513",
+              "default": true
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let label = github
+            .create_label(
+                RepoId::from(1),
+                "bug".try_into().unwrap(),
+                "f29513".try_into().unwrap(),
+                "Something isn't working".into(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            label,
+            Label {
+                id: LabelId::from(208045946),
+                name: "bug".try_into().unwrap(),
+                description: "Something isn't working".into(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn set_assignees() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("PATCH"))
+            .and(path("/repositories/1/issues/1"))
+            .and(body_partial_json(json!({ "assignees": ["octocat"] })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        github
+            .set_assignees(
+                RepoId::from(1),
+                IssueId::from(1 as usize),
+                vec!["octocat".try_into().unwrap()],
+            )
+            .await
+            .unwrap();
+    }
 
-        assert_eq!(user.id, UserId::from(1));
-        assert_eq!(user.nickname.as_str(), "octocat");
+    #[tokio::test]
+    async fn set_milestone() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("PATCH"))
+            .and(path("/repositories/1/issues/1"))
+            .and(body_partial_json(json!({ "milestone": 3 })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        github
+            .set_milestone(
+                RepoId::from(1),
+                IssueId::from(1 as usize),
+                Some(MilestoneId::from(3)),
+            )
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
-    async fn get_issue() {
+    async fn close_issue() {
         let (mock_server, github) = setup().await;
 
-        Mock::given(method("GET"))
+        Mock::given(method("PATCH"))
             .and(path("/repositories/1/issues/1"))
-            .respond_with(
-                ResponseTemplate::new(200).set_body_string(include_str!("issue_response.json")),
+            .and(body_partial_json(json!({
+              "state": "closed",
+              "state_reason": "not_planned"
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        github
+            .close_issue(
+                RepoId::from(1),
+                IssueId::from(1 as usize),
+                CloseReason::NotPlanned,
             )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn reopen_issue() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("PATCH"))
+            .and(path("/repositories/1/issues/1"))
+            .and(body_partial_json(json!({ "state": "open" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
             .mount(&mock_server)
             .await;
 
-        let issue = github
-            .get_issue(RepoId::from(1), IssueId::from(1 as usize))
+        github
+            .reopen_issue(RepoId::from(1), IssueId::from(1 as usize))
             .await
             .unwrap();
+    }
 
-        assert_eq!(issue.id, IssueId::from(1 as usize));
-        assert_eq!(issue.title.as_str(), "Found a bug");
-        assert_eq!(issue.body.as_str(), "I'm having a problem with this.");
+    #[tokio::test]
+    async fn edit_issue_body() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("PATCH"))
+            .and(path("/repositories/1/issues/1"))
+            .and(body_partial_json(json!({ "body": "New body" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        github
+            .edit_issue_body(
+                RepoId::from(1),
+                IssueId::from(1 as usize),
+                "New body".into(),
+            )
+            .await
+            .unwrap();
     }
 
     #[tokio::test]
-    async fn get_repo() {
+    async fn lock_issue() {
         let (mock_server, github) = setup().await;
 
-        Mock::given(method("GET"))
-            .and(path("/repositories/1"))
-            .respond_with(
-                ResponseTemplate::new(200)
-                    .set_body_string(include_str!("repository_response.json")),
+        Mock::given(method("PUT"))
+            .and(path("/repositories/1/issues/1/lock"))
+            .and(body_partial_json(json!({ "lock_reason": "too heated" })))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        github
+            .lock_issue(
+                RepoId::from(1),
+                IssueId::from(1 as usize),
+                Some(LockReason::TooHeated),
             )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn compare_refs() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repositories/1/compare/v0.1.0...v0.2.0"))
+            .and(query_param("page", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+              "status": "ahead",
+              "ahead_by": 2,
+              "behind_by": 0,
+              "total_commits": 2,
+              "commits": [
+                { "sha": "6dcb09b5b57875f334f61aebed695e2e4193db5e" },
+                { "sha": "7638417db6d59f3c431d3e1f261cc637155684cd" }
+              ]
+            })))
+            .expect(1)
             .mount(&mock_server)
             .await;
 
-        let repo = github.get_repo(RepoId::from(1)).await.unwrap();
+        let commits = github
+            .compare_refs(RepoId::from(1), "v0.1.0".into(), "v0.2.0".into())
+            .await
+            .unwrap();
 
-        assert_eq!(repo.id, RepoId::from(1));
-        assert_eq!(repo.owner.as_str(), "octocat");
-        assert_eq!(repo.name.as_str(), "Hello-World");
+        assert_eq!(
+            commits,
+            vec![
+                "6dcb09b5b57875f334f61aebed695e2e4193db5e".to_string(),
+                "7638417db6d59f3c431d3e1f261cc637155684cd".to_string(),
+            ]
+        );
     }
 
     #[tokio::test]
-    async fn get_comment() {
+    async fn list_merged_pull_requests() {
         let (mock_server, github) = setup().await;
 
         Mock::given(method("GET"))
-            .and(path("/repositories/1/issues/comments/1"))
-            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
-              "id": 1,
-              "node_id": "MDEyOklzc3VlQ29tbWVudDE=",
-              "url": "https://api.github.com/repos/octocat/Hello-World/issues/comments/1",
-              "html_url": "https://github.com/octocat/Hello-World/issues/1347#issuecomment-1",
-              "body": "Me too",
-              "user": {
-                "login": "octocat",
-                "id": 1,
-                "node_id": "MDQ6VXNlcjE=",
-                "avatar_url": "https://github.com/images/error/octocat_happy.gif",
-                "gravatar_id": "",
-                "url": "https://api.github.com/users/octocat",
-                "html_url": "https://github.com/octocat",
-                "followers_url": "https://api.github.com/users/octocat/followers",
-                "following_url": "https://api.github.com/users/octocat/following{/other_user}",
-                "gists_url": "https://api.github.com/users/octocat/gists{/gist_id}",
-                "starred_url": "https://api.github.com/users/octocat/starred{/owner}{/repo}",
-                "subscriptions_url": "https://api.github.com/users/octocat/subscriptions",
-                "organizations_url": "https://api.github.com/users/octocat/orgs",
-                "repos_url": "https://api.github.com/users/octocat/repos",
-                "events_url": "https://api.github.com/users/octocat/events{/privacy}",
-                "received_events_url": "https://api.github.com/users/octocat/received_events",
-                "type": "User",
-                "site_admin": false
+            .and(path(
+                "/repositories/1/commits/6dcb09b5b57875f334f61aebed695e2e4193db5e/pulls",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+              {
+                "number": 1347,
+                "state": "closed",
+                "merged_at": "2011-01-26T19:01:12Z"
               },
-              "created_at": "2011-04-14T16:00:49Z",
-              "updated_at": "2011-04-14T16:00:49Z",
-              "issue_url": "https://api.github.com/repos/octocat/Hello-World/issues/1347",
-              "author_association": "COLLABORATOR"
-            })))
+              {
+                "number": 1348,
+                "state": "open",
+                "merged_at": null
+              }
+            ])))
+            .expect(1)
             .mount(&mock_server)
             .await;
 
-        let comment = github
-            .get_comment(
+        let pull_requests = github
+            .list_merged_pull_requests(
                 RepoId::from(1),
-                IssueId::from(1 as usize),
-                CommentId::from(1),
+                "6dcb09b5b57875f334f61aebed695e2e4193db5e".into(),
             )
             .await
             .unwrap();
 
-        assert_eq!(comment.id, CommentId::from(1));
-        assert_eq!(comment.user_id, UserId::from(1));
-        assert_eq!(comment.body.as_str(), "Me too");
+        assert_eq!(pull_requests, vec![IssueId::from(1347 as usize)]);
     }
 
     #[tokio::test]
-    async fn make_comment() {
+    async fn create_release() {
         let (mock_server, github) = setup().await;
 
         Mock::given(method("POST"))
-            .and(path("/repositories/1/issues/1/comments"))
+            .and(path("/repositories/1/releases"))
+            .and(body_partial_json(json!({
+              "tag_name": "v1.0.0",
+              "name": "v1.0.0",
+              "body": "Description of the release",
+              "draft": true
+            })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+              "id": 1,
+              "tag_name": "v1.0.0",
+              "name": "v1.0.0",
+              "body": "Description of the release",
+              "draft": true,
+              "prerelease": false
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        github
+            .create_release(
+                RepoId::from(1),
+                "v1.0.0".try_into().unwrap(),
+                "v1.0.0".into(),
+                "Description of the release".into(),
+                true,
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn get_pull_request() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repositories/1/pulls/2"))
             .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+              "url": "https://api.github.com/repos/octocat/Hello-World/pulls/2",
               "id": 1,
-              "node_id": "MDEyOklzc3VlQ29tbWVudDE=",
-              "url": "https://api.github.com/repos/octocat/Hello-World/issues/comments/1",
-              "html_url": "https://github.com/octocat/Hello-World/issues/1347#issuecomment-1",
-              "body": "Me too",
+              "number": 2,
+              "state": "open",
+              "title": "Amazing new feature",
+              "body": "Please pull these awesome changes in!",
               "user": {
                 "login": "octocat",
                 "id": 1,
@@ -436,87 +2346,153 @@ mod tests {
                 "type": "User",
                 "site_admin": false
               },
-              "created_at": "2011-04-14T16:00:49Z",
-              "updated_at": "2011-04-14T16:00:49Z",
-              "issue_url": "https://api.github.com/repos/octocat/Hello-World/issues/1347",
-              "author_association": "COLLABORATOR"
+              "head": {
+                "label": "octocat:new-topic",
+                "ref": "new-topic",
+                "sha": "6dcb09b5b57875f334f61aebed695e2e4193db5e"
+              },
+              "base": {
+                "label": "octocat:master",
+                "ref": "master",
+                "sha": "6dcb09b5b57875f334f61aebed695e2e4193db5e"
+              }
             })))
             .mount(&mock_server)
             .await;
 
-        github
-            .make_comment(
-                RepoId::from(1),
-                IssueId::from(1 as usize),
-                "message".try_into().unwrap(),
-            )
+        let pull_request = github
+            .get_pull_request(RepoId::from(1), IssueId::from(2 as usize))
             .await
             .unwrap();
+
+        assert_eq!(pull_request.id, IssueId::from(2 as usize));
+        assert_eq!(pull_request.author_user_id, UserId::from(1));
+        assert_eq!(pull_request.title.as_str(), "Amazing new feature");
+        assert_eq!(pull_request.base_ref, "master");
+        assert_eq!(pull_request.head_ref, "new-topic");
     }
 
     #[tokio::test]
-    async fn get_repo_labels() {
+    async fn get_pull_request_files() {
         let (mock_server, github) = setup().await;
 
         Mock::given(method("GET"))
-            .and(path("/repositories/1/labels"))
+            .and(path("/repositories/1/pulls/2/files"))
             .respond_with(ResponseTemplate::new(200).set_body_json(json!([
               {
-                "id": 1,
-                "node_id": "MDU6TGFiZWwyMDgwNDU5NDY=",
-                "url": "https://api.github.com/repos/octocat/Hello-World/labels/bug",
-                "name": "bug",
-                "description": "Something isn't working",
-                "color": "f29513",
-                "default": true
-              },
-              {
-                "id": 2,
-                "node_id": "MDU6TGFiZWwyMDgwNDU5NDc=",
-                "url": "https://api.github.com/repos/octocat/Hello-World/labels/enhancement",
-                "name": "enhancement",
-                "description": "New feature or request",
-                "color": "a2eeef",
-                "default": false
+                "sha": "bbcd538c8e72b8c175046e27cc8f907076331401",
+                "filename": "file1.txt",
+                "status": "added",
+                "additions": 103,
+                "deletions": 21,
+                "changes": 124,
+                "blob_url": "https://github.com/octocat/Hello-World/blob/6dcb09b5b57875f334f61aebed695e2e4193db5e/file1.txt",
+                "raw_url": "https://github.com/octocat/Hello-World/raw/6dcb09b5b57875f334f61aebed695e2e4193db5e/file1.txt",
+                "contents_url": "https://api.github.com/repos/octocat/Hello-World/contents/file1.txt?ref=6dcb09b5b57875f334f61aebed695e2e4193db5e",
+                "patch": "@@ -132,7 +132,7 @@ module Test @@ -1000,7 +1000,7 @@ module Test"
               }
             ])))
             .mount(&mock_server)
             .await;
 
-        let labels = github.get_repo_labels(RepoId::from(1)).await.unwrap();
+        let files = github
+            .get_pull_request_files(RepoId::from(1), IssueId::from(2 as usize))
+            .await
+            .unwrap();
 
         assert_eq!(
-            labels,
-            vec![
-                Label {
-                    id: LabelId::from(1),
-                    name: "bug".try_into().unwrap(),
-                    description: "Something isn't working".to_owned()
-                },
-                Label {
-                    id: LabelId::from(2),
-                    name: "enhancement".try_into().unwrap(),
-                    description: "New feature or request".to_owned()
-                }
-            ]
-        )
+            files,
+            vec![ChangedFile {
+                path: "file1.txt".into(),
+                status: ChangedFileStatus::Added,
+                additions: 103,
+                deletions: 21,
+                patch: Some(
+                    "@@ -132,7 +132,7 @@ module Test @@ -1000,7 +1000,7 @@ module Test".into()
+                ),
+            }]
+        );
     }
 
     #[tokio::test]
-    async fn assign_label() {
+    async fn get_pull_request_diff() {
+        let (mock_server, github) = setup().await;
+
+        let diff = "diff --git a/file1.txt b/file1.txt\n+Hello\n";
+
+        Mock::given(method("GET"))
+            .and(path("/repositories/1/pulls/2"))
+            .and(header("Accept", "application/vnd.github.v3.diff"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(diff))
+            .mount(&mock_server)
+            .await;
+
+        let result = github
+            .get_pull_request_diff(RepoId::from(1), IssueId::from(2 as usize))
+            .await
+            .unwrap();
+
+        assert_eq!(result, diff);
+    }
+
+    #[tokio::test]
+    async fn make_pull_request_review() {
         let (mock_server, github) = setup().await;
 
         Mock::given(method("POST"))
-            .and(path("/repositories/1/issues/1/labels"))
-            .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+            .and(path("/repositories/1/pulls/2/reviews"))
+            .and(body_partial_json(json!({
+              "body": "Looks good",
+              "event": "COMMENT",
+              "comments": [
+                {
+                  "path": "file1.txt",
+                  "line": 3,
+                  "side": "RIGHT",
+                  "body": "Typo"
+                }
+              ]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
             .mount(&mock_server)
             .await;
 
         github
-            .assign_label(
+            .make_pull_request_review(
                 RepoId::from(1),
-                IssueId::from(1 as usize),
-                "bug".try_into().unwrap(),
+                IssueId::from(2 as usize),
+                Review {
+                    body: "Looks good".into(),
+                    verdict: ReviewVerdict::Comment,
+                    comments: vec![ReviewComment {
+                        path: "file1.txt".into(),
+                        line: 3,
+                        body: "Typo".try_into().unwrap(),
+                    }],
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn edit_pull_request_body() {
+        let (mock_server, github) = setup().await;
+
+        Mock::given(method("PATCH"))
+            .and(path("/repositories/1/pulls/2"))
+            .and(body_partial_json(json!({ "body": "New description" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        github
+            .edit_pull_request_body(
+                RepoId::from(1),
+                IssueId::from(2 as usize),
+                "New description".into(),
             )
             .await
             .unwrap();
@@ -5,23 +5,33 @@ use axum::{
     routing::post,
     serve, Router,
 };
+use std::sync::Arc;
+
 use log::{error, info};
 use octocrab::models::{
+    issues::IssueStateReason,
     webhook_events::{
         payload::{
             IssueCommentWebhookEventAction, IssueCommentWebhookEventPayload,
             IssuesWebhookEventAction, IssuesWebhookEventPayload,
+            PullRequestReviewCommentWebhookEventAction,
+            PullRequestReviewCommentWebhookEventPayload, PullRequestWebhookEventAction,
+            PullRequestWebhookEventPayload,
         },
         WebhookEvent, WebhookEventPayload,
     },
-    Repository,
+    Author, Repository,
 };
 use tokio::{net::TcpListener, sync::mpsc::Sender};
 use tower_http::trace::TraceLayer;
 
 use crate::{
     config::WebhookServerConfig,
-    githost::events::{GitEvent, GitEventKind},
+    githost::{
+        events::{GitEvent, GitEventKind, IssueEdit, LabelChange},
+        model::CloseReason,
+    },
+    storage::storage_trait::Storage,
 };
 
 use super::errors::GithubError;
@@ -29,15 +39,24 @@ use super::errors::GithubError;
 pub struct GithubWebhookServer {
     sender: Sender<GitEvent>,
     config: WebhookServerConfig,
+    storage: Arc<dyn Storage + Send + Sync>,
 }
 
 impl GithubWebhookServer {
-    pub fn new(sender: Sender<GitEvent>, config: WebhookServerConfig) -> Self {
-        Self { sender, config }
+    pub fn new(
+        sender: Sender<GitEvent>,
+        config: WebhookServerConfig,
+        storage: Arc<dyn Storage + Send + Sync>,
+    ) -> Self {
+        Self {
+            sender,
+            config,
+            storage,
+        }
     }
 
     pub async fn serve(&self) -> Result<(), GithubError> {
-        let app = create_routes(self.sender.clone());
+        let app = create_routes(self.sender.clone(), self.storage.clone());
 
         let listener = TcpListener::bind((self.config.addr, self.config.port))
             .await
@@ -51,17 +70,70 @@ impl GithubWebhookServer {
     }
 }
 
-fn create_routes(sender: Sender<GitEvent>) -> Router {
+#[derive(Clone)]
+struct WebhookState {
+    sender: Sender<GitEvent>,
+    storage: Arc<dyn Storage + Send + Sync>,
+}
+
+fn create_routes(sender: Sender<GitEvent>, storage: Arc<dyn Storage + Send + Sync>) -> Router {
     Router::new()
         .route("/", post(webhook))
-        .with_state(sender)
+        .with_state(WebhookState { sender, storage })
         .layer(TraceLayer::new_for_http())
 }
 
 async fn webhook(
-    State(sender): State<Sender<GitEvent>>,
+    State(WebhookState { sender, storage }): State<WebhookState>,
     headers: HeaderMap,
     body: Bytes,
+) -> StatusCode {
+    // GitHub redelivers an event if the response was slow or failed, so the same delivery may
+    // come twice. The delivery is marked before processing, so that a redelivery that comes
+    // during the processing is ignored too.
+    let delivery_id = headers
+        .get("X-GitHub-Delivery")
+        .and_then(|delivery_id| delivery_id.to_str().ok());
+
+    if let Some(delivery_id) = delivery_id {
+        match storage.mark_delivery_processed(delivery_id).await {
+            Ok(true) => {}
+
+            Ok(false) => {
+                info!(
+                    "GitHub webhook delivery {} has been processed already. Ignoring",
+                    delivery_id
+                );
+                return StatusCode::OK;
+            }
+
+            // Better to process a delivery twice than to lose it.
+            Err(e) => error!(
+                "Unable to remember GitHub webhook delivery {}: {:?}",
+                delivery_id, e
+            ),
+        }
+    }
+
+    let status = handle_delivery(&headers, &body, sender).await;
+
+    // A failed delivery is not processed, so its redelivery shouldn't be ignored.
+    if let Some(delivery_id) = delivery_id.filter(|_| !status.is_success()) {
+        if let Err(e) = storage.forget_delivery(delivery_id).await {
+            error!(
+                "Unable to forget failed GitHub webhook delivery {}: {:?}",
+                delivery_id, e
+            );
+        }
+    }
+
+    status
+}
+
+async fn handle_delivery(
+    headers: &HeaderMap,
+    body: &Bytes,
+    sender: Sender<GitEvent>,
 ) -> StatusCode {
     if let Some(event_type) = headers.get("X-GitHub-Event") {
         match WebhookEvent::try_from_header_and_body(
@@ -72,7 +144,7 @@ async fn webhook(
                     return StatusCode::BAD_REQUEST;
                 }
             },
-            &body,
+            body,
         ) {
             Ok(event) => handle_webhook_event(event, sender).await,
             Err(err) => {
@@ -89,13 +161,21 @@ async fn handle_webhook_event(event: WebhookEvent, sender: Sender<GitEvent>) ->
     if let Some(repo) = event.repository {
         match event.specific {
             WebhookEventPayload::Issues(payload) => {
-                handle_issues_event(repo, *payload, sender).await
+                handle_issues_event(repo, event.sender, *payload, sender).await
             }
 
             WebhookEventPayload::IssueComment(payload) => {
                 handle_issue_comments_event(repo, *payload, sender).await
             }
 
+            WebhookEventPayload::PullRequest(payload) => {
+                handle_pull_request_event(repo, *payload, sender).await
+            }
+
+            WebhookEventPayload::PullRequestReviewComment(payload) => {
+                handle_pull_request_review_comment_event(repo, *payload, sender).await
+            }
+
             _ => {
                 error!("Unsupported GitHub webhook event: {:?}", event.kind);
                 StatusCode::NOT_IMPLEMENTED
@@ -109,15 +189,103 @@ async fn handle_webhook_event(event: WebhookEvent, sender: Sender<GitEvent>) ->
 
 async fn handle_issues_event(
     repo: Repository,
+    actor: Option<Author>,
     payload: IssuesWebhookEventPayload,
     sender: Sender<GitEvent>,
 ) -> StatusCode {
+    let kind = match make_issues_event_kind(actor, &payload) {
+        Some(kind) => kind,
+        None => {
+            error!("Unsupported issues action: {:?}. Ignoring", payload.action);
+            return StatusCode::NOT_IMPLEMENTED;
+        }
+    };
+
+    match sender
+        .send(GitEvent {
+            repo_id: repo.id.into(),
+            issue_id: payload.issue.number.into(),
+            kind,
+        })
+        .await
+    {
+        Ok(_) => {
+            info!("Received a GitEvent from webhook");
+            StatusCode::OK
+        }
+        Err(e) => {
+            error!("Unable to send GitEvent: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+/// Returns `None` if the action is not supported or the payload is malformed.
+fn make_issues_event_kind(
+    actor: Option<Author>,
+    payload: &IssuesWebhookEventPayload,
+) -> Option<GitEventKind> {
     match payload.action {
-        IssuesWebhookEventAction::Opened => match sender
+        IssuesWebhookEventAction::Opened => Some(GitEventKind::NewIssue),
+
+        IssuesWebhookEventAction::Edited => {
+            let changes = payload.changes.as_ref();
+
+            Some(GitEventKind::IssueEdited(IssueEdit {
+                old_title: changes
+                    .and_then(|changes| changes.title.as_ref())
+                    .and_then(|title| title.from.clone().try_into().ok()),
+                new_title: payload.issue.title.clone().try_into().ok()?,
+                old_body: changes
+                    .and_then(|changes| changes.body.as_ref())
+                    .map(|body| body.from.clone()),
+                new_body: payload.issue.body.clone().unwrap_or_default(),
+            }))
+        }
+
+        IssuesWebhookEventAction::Reopened => Some(GitEventKind::IssueReopened),
+
+        IssuesWebhookEventAction::Closed => Some(GitEventKind::IssueClosed(
+            match payload.issue.state_reason {
+                Some(IssueStateReason::Completed) => Some(CloseReason::Completed),
+                Some(IssueStateReason::NotPlanned) => Some(CloseReason::NotPlanned),
+                _ => None,
+            },
+        )),
+
+        IssuesWebhookEventAction::Labeled | IssuesWebhookEventAction::Unlabeled => {
+            let change = LabelChange {
+                label: payload.label.as_ref()?.name.clone().try_into().ok()?,
+                actor: actor?.id.into(),
+            };
+
+            Some(match payload.action {
+                IssuesWebhookEventAction::Labeled => GitEventKind::IssueLabeled(change),
+                _ => GitEventKind::IssueUnlabeled(change),
+            })
+        }
+
+        IssuesWebhookEventAction::Assigned => Some(GitEventKind::IssueAssigned(
+            payload.assignee.as_ref()?.id.into(),
+        )),
+
+        IssuesWebhookEventAction::Transferred => Some(GitEventKind::IssueTransferred),
+
+        _ => None,
+    }
+}
+
+async fn handle_issue_comments_event(
+    repo: Repository,
+    payload: IssueCommentWebhookEventPayload,
+    sender: Sender<GitEvent>,
+) -> StatusCode {
+    match payload.action {
+        IssueCommentWebhookEventAction::Created => match sender
             .send(GitEvent {
                 repo_id: repo.id.into(),
                 issue_id: payload.issue.number.into(),
-                kind: GitEventKind::NewIssue,
+                kind: GitEventKind::NewComment(payload.comment.id.into()),
             })
             .await
         {
@@ -138,17 +306,54 @@ async fn handle_issues_event(
     }
 }
 
-async fn handle_issue_comments_event(
+async fn handle_pull_request_event(
     repo: Repository,
-    payload: IssueCommentWebhookEventPayload,
+    payload: PullRequestWebhookEventPayload,
+    sender: Sender<GitEvent>,
+) -> StatusCode {
+    let kind = match payload.action {
+        PullRequestWebhookEventAction::Opened => GitEventKind::NewPullRequest,
+        PullRequestWebhookEventAction::Synchronize => GitEventKind::PullRequestSynchronized,
+
+        _ => {
+            error!(
+                "Unsupported pull request action: {:?}. Ignoring",
+                payload.action
+            );
+            return StatusCode::NOT_IMPLEMENTED;
+        }
+    };
+
+    match sender
+        .send(GitEvent {
+            repo_id: repo.id.into(),
+            issue_id: payload.number.into(),
+            kind,
+        })
+        .await
+    {
+        Ok(_) => {
+            info!("Received a GitEvent from webhook");
+            StatusCode::OK
+        }
+        Err(e) => {
+            error!("Unable to send GitEvent: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+async fn handle_pull_request_review_comment_event(
+    repo: Repository,
+    payload: PullRequestReviewCommentWebhookEventPayload,
     sender: Sender<GitEvent>,
 ) -> StatusCode {
     match payload.action {
-        IssueCommentWebhookEventAction::Created => match sender
+        PullRequestReviewCommentWebhookEventAction::Created => match sender
             .send(GitEvent {
                 repo_id: repo.id.into(),
-                issue_id: payload.issue.number.into(),
-                kind: GitEventKind::NewComment(payload.comment.id.into()),
+                issue_id: payload.pull_request.number.into(),
+                kind: GitEventKind::PullRequestReviewComment(payload.comment.id.into()),
             })
             .await
         {
@@ -163,7 +368,10 @@ async fn handle_issue_comments_event(
         },
 
         _ => {
-            error!("Unsupported issues action: {:?}. Ignoring", payload.action);
+            error!(
+                "Unsupported pull request review comment action: {:?}. Ignoring",
+                payload.action
+            );
             StatusCode::NOT_IMPLEMENTED
         }
     }
@@ -173,11 +381,17 @@ async fn handle_issue_comments_event(
 mod tests {
     use axum::http::StatusCode;
     use axum_test::TestServer;
-    use tokio::sync::mpsc::{channel, Receiver};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    use tokio::sync::mpsc::{channel, error::TryRecvError, Receiver};
 
-    use crate::githost::{
-        events::{GitEvent, GitEventKind},
-        model::{IssueId, RepoId},
+    use crate::{
+        githost::{
+            events::{GitEvent, GitEventKind, IssueEdit, LabelChange},
+            model::{CloseReason, IssueId, RepoId, UserId},
+        },
+        storage::{impls::sqlite_storage::SqliteStorage, storage_trait::Storage},
     };
 
     use super::create_routes;
@@ -208,10 +422,180 @@ mod tests {
         )
     }
 
+    #[tokio::test]
+    async fn sends_issue_edited_event() {
+        let mut payload: serde_json::Value =
+            serde_json::from_str(include_str!("issue_open_test.json")).unwrap();
+
+        payload["action"] = json!("edited");
+        payload["changes"] = json!({ "body": { "from": "Old body" } });
+
+        assert_eq!(
+            send_issues_event(payload).await,
+            GitEventKind::IssueEdited(IssueEdit {
+                old_title: None,
+                new_title: "Issue title".try_into().unwrap(),
+                old_body: Some("Old body".into()),
+                new_body: "This is the body of the issue".into(),
+            })
+        )
+    }
+
+    #[tokio::test]
+    async fn sends_issue_closed_event() {
+        let mut payload: serde_json::Value =
+            serde_json::from_str(include_str!("issue_open_test.json")).unwrap();
+
+        payload["action"] = json!("closed");
+        payload["issue"]["state"] = json!("closed");
+        payload["issue"]["state_reason"] = json!("not_planned");
+
+        assert_eq!(
+            send_issues_event(payload).await,
+            GitEventKind::IssueClosed(Some(CloseReason::NotPlanned))
+        )
+    }
+
+    #[tokio::test]
+    async fn sends_issue_labeled_event() {
+        let mut payload: serde_json::Value =
+            serde_json::from_str(include_str!("issue_open_test.json")).unwrap();
+
+        payload["action"] = json!("labeled");
+        payload["label"] = json!({
+          "id": 1,
+          "node_id": "MDU6TGFiZWwyMDgwNDU5NDY=",
+          "url": "https://api.github.com/repos/owner/repo/labels/bug",
+          "name": "bug",
+          "description": "Something isn't working",
+          "color": "f29513",
+          "default": true
+        });
+
+        assert_eq!(
+            send_issues_event(payload).await,
+            GitEventKind::IssueLabeled(LabelChange {
+                label: "bug".try_into().unwrap(),
+                actor: UserId::from(1234567),
+            })
+        )
+    }
+
+    #[tokio::test]
+    async fn sends_pull_request_synchronized_event() {
+        let issue_payload: serde_json::Value =
+            serde_json::from_str(include_str!("issue_open_test.json")).unwrap();
+
+        let payload = json!({
+          "action": "synchronize",
+          "number": 2,
+          "before": "6dcb09b5b57875f334f61aebed695e2e4193db5e",
+          "after": "bbcd538c8e72b8c175046e27cc8f907076331401",
+          "pull_request": {
+            "url": "https://api.github.com/repos/owner/repo/pulls/2",
+            "id": 1,
+            "number": 2,
+            "title": "Amazing new feature",
+            "user": issue_payload["issue"]["user"],
+            "head": {
+              "ref": "new-topic",
+              "sha": "bbcd538c8e72b8c175046e27cc8f907076331401"
+            },
+            "base": {
+              "ref": "master",
+              "sha": "6dcb09b5b57875f334f61aebed695e2e4193db5e"
+            }
+          },
+          "repository": issue_payload["repository"],
+          "sender": issue_payload["sender"]
+        });
+
+        let (mut receiver, server) = make_test_server();
+
+        let response = server
+            .post("/")
+            .add_header("X-GitHub-Event", "pull_request")
+            .text(payload.to_string())
+            .await;
+
+        assert_eq!(response.status_code(), StatusCode::OK);
+
+        assert_eq!(
+            receiver.recv().await.unwrap(),
+            GitEvent {
+                repo_id: RepoId::from(987654321),
+                issue_id: IssueId::from(2 as usize),
+                kind: GitEventKind::PullRequestSynchronized,
+            }
+        )
+    }
+
+    #[tokio::test]
+    async fn ignores_repeated_delivery() {
+        let (mut receiver, server) = make_test_server();
+
+        for _ in 0..2 {
+            let response = server
+                .post("/")
+                .add_header("X-GitHub-Event", "issues")
+                .add_header("X-GitHub-Delivery", "72d3162e-cc78-11e3-81ab-4c9367dc0958")
+                .text(include_str!("issue_open_test.json"))
+                .await;
+
+            assert_eq!(response.status_code(), StatusCode::OK);
+        }
+
+        assert_eq!(receiver.recv().await.unwrap().kind, GitEventKind::NewIssue);
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[tokio::test]
+    async fn forgets_failed_delivery() {
+        let (sender, receiver) = channel(42);
+        let storage = Arc::new(SqliteStorage::in_memory().unwrap());
+
+        let server = TestServer::new(create_routes(sender, storage.clone())).unwrap();
+
+        // The bot has stopped, so the event cannot be processed.
+        drop(receiver);
+
+        let response = server
+            .post("/")
+            .add_header("X-GitHub-Event", "issues")
+            .add_header("X-GitHub-Delivery", "72d3162e-cc78-11e3-81ab-4c9367dc0958")
+            .text(include_str!("issue_open_test.json"))
+            .await;
+
+        assert_eq!(response.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        assert!(storage
+            .mark_delivery_processed("72d3162e-cc78-11e3-81ab-4c9367dc0958")
+            .await
+            .unwrap());
+    }
+
+    async fn send_issues_event(payload: serde_json::Value) -> GitEventKind {
+        let (mut receiver, server) = make_test_server();
+
+        let response = server
+            .post("/")
+            .add_header("X-GitHub-Event", "issues")
+            .text(payload.to_string())
+            .await;
+
+        assert_eq!(response.status_code(), StatusCode::OK);
+
+        receiver.recv().await.unwrap().kind
+    }
+
     fn make_test_server() -> (Receiver<GitEvent>, TestServer) {
         let (sender, receiver) = channel(42);
 
-        let server = TestServer::new(create_routes(sender)).unwrap();
+        let server = TestServer::new(create_routes(
+            sender,
+            Arc::new(SqliteStorage::in_memory().unwrap()),
+        ))
+        .unwrap();
 
         (receiver, server)
     }
@@ -1,6 +1,9 @@
+use std::{fmt::Display, str::FromStr};
+
+use chrono::{DateTime, NaiveDate, Utc};
 use derive_more::derive::{AsRef, Deref, From};
 use non_empty_string::NonEmptyString;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Copy, From, AsRef, Deref, PartialEq, Debug)]
 pub struct UserId(usize);
@@ -9,9 +12,38 @@ pub struct UserId(usize);
 pub struct User {
     pub id: UserId,
     pub nickname: NonEmptyString,
+    pub kind: UserKind,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum UserKind {
+    User,
+    Bot,
+    Organization,
+}
+
+/// Relation of the author of an issue or a comment to the repository.
+#[derive(Clone, Copy, PartialEq, Serialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthorAssociation {
+    Owner,
+    Member,
+    Collaborator,
+
+    /// Has previously committed to the repository.
+    Contributor,
+
+    /// Has not previously committed to the repository.
+    FirstTimeContributor,
+
+    /// Has not previously committed to any repository on the Git host.
+    FirstTimer,
+
+    None,
 }
 
-#[derive(Clone, Copy, From, AsRef, Deref, Debug, PartialEq)]
+#[derive(Clone, Copy, From, AsRef, Deref, Debug, PartialEq, Eq, Hash)]
 pub struct RepoId(usize);
 
 pub struct Repo {
@@ -20,25 +52,242 @@ pub struct Repo {
     pub name: String,
 }
 
-#[derive(Serialize, Clone, Copy, From, AsRef, Deref, Debug, PartialEq)]
+/// Human-readable repository name in the `owner/name` format.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(try_from = "String")]
+pub struct RepoName {
+    pub owner: NonEmptyString,
+    pub name: NonEmptyString,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("repository name should be in the `owner/name` format")]
+pub struct RepoNameParseError;
+
+impl FromStr for RepoName {
+    type Err = RepoNameParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (owner, name) = s.split_once('/').ok_or(RepoNameParseError)?;
+
+        if name.contains('/') {
+            return Err(RepoNameParseError);
+        }
+
+        Ok(Self {
+            owner: NonEmptyString::from_str(owner).map_err(|_| RepoNameParseError)?,
+            name: NonEmptyString::from_str(name).map_err(|_| RepoNameParseError)?,
+        })
+    }
+}
+
+impl TryFrom<String> for RepoName {
+    type Error = RepoNameParseError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::from_str(&value)
+    }
+}
+
+impl Display for RepoName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.owner, self.name)
+    }
+}
+
+#[derive(Serialize, Clone, Copy, From, AsRef, Deref, Debug, PartialEq, Eq, Hash)]
 #[serde(transparent)]
 pub struct IssueId(usize);
 
+#[derive(Clone, Copy, PartialEq, Serialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueState {
+    Open,
+    Closed,
+}
+
 #[derive(Clone)]
 pub struct Issue {
     pub id: IssueId,
     pub author_user_id: UserId,
     pub title: NonEmptyString,
     pub body: String,
+    pub state: IssueState,
+
+    /// Names of the labels assigned to the issue.
+    pub labels: Vec<NonEmptyString>,
+
+    /// Nicknames of the assigned users.
+    pub assignees: Vec<NonEmptyString>,
+
+    pub milestone: Option<Milestone>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub author_association: AuthorAssociation,
+}
+
+/// Pull requests share the numbering with issues, so `IssueId` is used as their number.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PullRequest {
+    pub id: IssueId,
+    pub author_user_id: UserId,
+    pub title: NonEmptyString,
+    pub body: String,
+    pub base_ref: String,
+    pub head_ref: String,
+    pub head_sha: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangedFileStatus {
+    Added,
+    Removed,
+    Modified,
+    Renamed,
+    Other,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChangedFile {
+    pub path: String,
+    pub status: ChangedFileStatus,
+    pub additions: usize,
+    pub deletions: usize,
+
+    /// Unified diff of the file. Absent for binary files and very large diffs.
+    pub patch: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ReviewVerdict {
+    Comment,
+    Approve,
+    RequestChanges,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReviewComment {
+    pub path: String,
+
+    /// Line number in the new version of the file.
+    pub line: usize,
+
+    pub body: NonEmptyString,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Review {
+    pub body: String,
+    pub verdict: ReviewVerdict,
+    pub comments: Vec<ReviewComment>,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum CloseReason {
+    Completed,
+    NotPlanned,
+}
+
+// NOTE: Names are the same as in the GitHub API.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Debug)]
+pub enum LockReason {
+    #[serde(rename = "off-topic")]
+    OffTopic,
+
+    #[serde(rename = "too heated")]
+    TooHeated,
+
+    #[serde(rename = "resolved")]
+    Resolved,
+
+    #[serde(rename = "spam")]
+    Spam,
+}
+
+/// Why a comment is hidden.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum HideReason {
+    Spam,
+    Abuse,
+    OffTopic,
+    Outdated,
+    Duplicate,
+    Resolved,
+}
+
+/// Milestones are identified by their number in the repository.
+#[derive(Serialize, Clone, Copy, From, AsRef, Deref, Debug, PartialEq)]
+#[serde(transparent)]
+pub struct MilestoneId(usize);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Milestone {
+    pub id: MilestoneId,
+    pub title: NonEmptyString,
+}
+
+#[derive(Clone, Copy, PartialEq, Default, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueStateFilter {
+    #[default]
+    Open,
+    Closed,
+    All,
+}
+
+/// Filter for searching issues in a repository.
+#[derive(Clone, PartialEq, Default, Debug)]
+pub struct IssueQuery {
+    pub state: IssueStateFilter,
+
+    /// Search pull requests instead of issues.
+    pub pull_requests: bool,
+
+    /// Issue should have all of these labels.
+    pub labels: Vec<NonEmptyString>,
+
+    /// Issue should have no labels at all.
+    pub unlabeled: bool,
+
+    pub created_after: Option<NaiveDate>,
+    pub created_before: Option<NaiveDate>,
+    pub closed_after: Option<NaiveDate>,
+    pub closed_before: Option<NaiveDate>,
+}
+
+/// A page of issue search results. Issues are sorted from the oldest to the newest.
+///
+/// Git hosts may return only a limited number of results of a search. Then the last available
+/// page has no next page but is `capped`, and the rest should be searched with a narrower query.
+#[derive(Clone, PartialEq, Debug)]
+pub struct IssuesPage {
+    pub issues: Vec<FoundIssue>,
+    pub has_next_page: bool,
+
+    /// There are more results, but the Git host doesn't return them.
+    pub capped: bool,
 }
 
-#[derive(Clone, Copy, From, AsRef, Deref, Debug, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FoundIssue {
+    pub id: IssueId,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Copy, From, AsRef, Deref, Debug, PartialEq, Eq, Hash)]
 pub struct CommentId(usize);
 
+#[derive(Clone)]
 pub struct Comment {
     pub id: CommentId,
     pub user_id: UserId,
     pub body: NonEmptyString,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub author_association: AuthorAssociation,
 }
 
 #[derive(Clone, Copy, From, AsRef, Deref, PartialEq, Serialize, Debug)]
@@ -2,4 +2,8 @@ pub mod bot;
 pub mod config;
 pub mod githost;
 pub mod llm;
+pub mod notifier;
+pub mod retrieval;
+pub mod storage;
 pub mod utils;
+pub mod vector_store;
@@ -8,9 +8,10 @@ use tera::{Context, Tera};
 use tracing::instrument;
 
 use super::{
-    llm_trait::{CompletionParameters, Llm},
+    llm_trait::{CompletionParameters, Llm, ModerationScores},
     messages::UserMessage,
 };
+use crate::utils::redaction::{Placeholders, RedactionConfig, Redactor};
 
 #[derive(Debug, thiserror::Error)]
 pub enum LlmAgentError<LE> {
@@ -22,6 +23,12 @@ pub enum LlmAgentError<LE> {
 
     #[error("error from LLM")]
     LlmError(#[source] LE), // We cannot use `#[from]` here because of https://github.com/dtolnay/thiserror/issues/323
+
+    #[error("invalid redaction pattern")]
+    RedactionPatternError(#[from] regex::Error),
+
+    #[error("unable to convert template context to JSON")]
+    ContextSerializationError(#[from] serde_json::Error),
 }
 
 pub type Result<T, LE> = std::result::Result<T, LlmAgentError<LE>>;
@@ -31,12 +38,25 @@ pub struct LlmAgentConfig {
     system_message_template_path: PathBuf,
     user_message_template_path: PathBuf,
     completion_params: CompletionParameters,
+
+    /// Mask sensitive data in the template context before it is sent to the LLM.
+    #[serde(default)]
+    redaction: Option<RedactionConfig>,
+}
+
+/// System and user messages produced from the templates, before they are sent to an LLM.
+#[derive(Debug)]
+pub struct RenderedMessages {
+    pub system_message: NonEmptyString,
+    pub user_message: NonEmptyString,
 }
 
 pub struct LlmAgent<L, C> {
     llm: L,
     template_engine: Tera,
     completion_params: CompletionParameters,
+    redactor: Option<Redactor>,
+    restore_output: bool,
     _context: PhantomData<C>,
 }
 
@@ -60,11 +80,20 @@ impl<L: Llm, C: Serialize + Debug> LlmAgent<L, C> {
             ),
         ])?;
 
-        Ok(Self {
+        let agent = Self {
             llm,
             template_engine,
             completion_params: config.completion_params,
+            redactor: None,
+            restore_output: false,
             _context: PhantomData,
+        };
+
+        Ok(match config.redaction {
+            Some(redaction) => {
+                agent.with_redactor(Redactor::build(&redaction)?, redaction.restore_output)
+            }
+            None => agent,
         })
     }
 
@@ -88,14 +117,38 @@ impl<L: Llm, C: Serialize + Debug> LlmAgent<L, C> {
             llm,
             template_engine,
             completion_params,
+            redactor: None,
+            restore_output: false,
             _context: PhantomData,
         })
     }
 
-    #[instrument(skip(self))]
-    pub async fn process(&self, context: &C) -> Result<NonEmptyString, L::Error> {
+    /// Mask the matches of the redactor in every string of the context with placeholders.
+    pub fn with_redactor(mut self, redactor: Redactor, restore_output: bool) -> Self {
+        self.redactor = Some(redactor);
+        self.restore_output = restore_output;
+        self
+    }
+
+    pub fn render(&self, context: &C) -> Result<RenderedMessages, L::Error> {
+        self.render_redacted(context, &mut Placeholders::default())
+    }
+
+    fn render_redacted(
+        &self,
+        context: &C,
+        placeholders: &mut Placeholders,
+    ) -> Result<RenderedMessages, L::Error> {
         let mut tera_context = Context::new();
-        tera_context.insert(CONTEXT_VAR_NAME, &context);
+
+        match &self.redactor {
+            Some(redactor) => {
+                let mut value = serde_json::to_value(context)?;
+                redact_value(redactor, &mut value, placeholders);
+                tera_context.insert(CONTEXT_VAR_NAME, &value);
+            }
+            None => tera_context.insert(CONTEXT_VAR_NAME, &context),
+        }
 
         let system_message: NonEmptyString = self
             .template_engine
@@ -113,6 +166,21 @@ impl<L: Llm, C: Serialize + Debug> LlmAgent<L, C> {
 
         debug!("Rendered user message:\n{}", user_message);
 
+        Ok(RenderedMessages {
+            system_message,
+            user_message,
+        })
+    }
+
+    #[instrument(skip(self))]
+    pub async fn process(&self, context: &C) -> Result<NonEmptyString, L::Error> {
+        let mut placeholders = Placeholders::default();
+
+        let RenderedMessages {
+            system_message,
+            user_message,
+        } = self.render_redacted(context, &mut placeholders)?;
+
         let ai_message: NonEmptyString = self
             .llm
             .complete(
@@ -126,6 +194,138 @@ impl<L: Llm, C: Serialize + Debug> LlmAgent<L, C> {
 
         debug!("Resulting AI message:\n{}", ai_message);
 
-        Ok(ai_message)
+        if !self.restore_output {
+            return Ok(ai_message);
+        }
+
+        Ok(placeholders
+            .restore(ai_message.as_str())
+            .try_into()
+            .expect("restoring placeholders never makes text empty"))
+    }
+
+    /// Classify the text with the moderation endpoint of the LLM provider, bypassing the
+    /// templates.
+    pub async fn moderate(&self, text: &str) -> Result<Option<ModerationScores>, L::Error> {
+        self.llm
+            .moderate(&self.redact(text))
+            .await
+            .map_err(LlmAgentError::LlmError)
+    }
+
+    /// Mask the matches of the redactor in text that is sent to the LLM provider without the
+    /// templates, e.g. as a query for embeddings.
+    pub fn redact(&self, text: &str) -> String {
+        match &self.redactor {
+            Some(redactor) => redactor.redact(text, &mut Placeholders::default()),
+            None => text.to_string(),
+        }
+    }
+}
+
+fn redact_value(
+    redactor: &Redactor,
+    value: &mut serde_json::Value,
+    placeholders: &mut Placeholders,
+) {
+    match value {
+        serde_json::Value::String(text) => *text = redactor.redact(text, placeholders),
+
+        serde_json::Value::Array(values) => {
+            for value in values {
+                redact_value(redactor, value, placeholders);
+            }
+        }
+
+        serde_json::Value::Object(map) => {
+            for value in map.values_mut() {
+                redact_value(redactor, value, placeholders);
+            }
+        }
+
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use mockall::predicate;
+    use serde::Serialize;
+
+    use crate::{
+        llm::{
+            agent::LlmAgent,
+            llm_trait::{CompletionParameters, MockLlm},
+            messages::{AiMessage, ChatMessage, UserMessage},
+        },
+        utils::redaction::{RedactionConfig, Redactor},
+    };
+
+    #[derive(Serialize, Debug)]
+    struct TestContext {
+        body: String,
+    }
+
+    #[tokio::test]
+    async fn redacts_context_and_restores_output() {
+        let mut llm_mock = MockLlm::new();
+
+        llm_mock
+            .expect_complete()
+            .with(
+                predicate::always(),
+                predicate::eq(vec![ChatMessage::from(
+                    UserMessage::from_str("Reply to <EMAIL_1>").unwrap(),
+                )]),
+                predicate::always(),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(AiMessage::from_str("Hello, <EMAIL_1>!").unwrap()));
+
+        let redactor = Redactor::build(&RedactionConfig {
+            secrets: true,
+            emails: true,
+            ips: true,
+            custom: vec![],
+            restore_output: true,
+        })
+        .unwrap();
+
+        let agent = LlmAgent::build_raw(
+            llm_mock,
+            "You are a bot.".try_into().unwrap(),
+            "Reply to {{ context.body }}".try_into().unwrap(),
+            CompletionParameters::default(),
+        )
+        .unwrap()
+        .with_redactor(redactor, true);
+
+        let answer = agent
+            .process(&TestContext {
+                body: "bob@example.com".into(),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(answer.as_str(), "Hello, bob@example.com!");
+    }
+
+    #[test]
+    fn redacts_text_outside_templates() {
+        let agent = LlmAgent::<MockLlm, TestContext>::build_raw(
+            MockLlm::new(),
+            "You are a bot.".try_into().unwrap(),
+            "Reply to {{ context.body }}".try_into().unwrap(),
+            CompletionParameters::default(),
+        )
+        .unwrap()
+        .with_redactor(Redactor::secrets(), false);
+
+        assert_eq!(
+            agent.redact("Login fails with password: hunter2hunter2"),
+            "Login fails with <CREDENTIAL_1>"
+        );
     }
 }
@@ -1 +1,2 @@
 pub mod openai_llm;
+pub mod recording_llm;
@@ -6,7 +6,7 @@ use async_openai::{
     types::{
         ChatCompletionRequestAssistantMessageArgs, ChatCompletionRequestMessage,
         ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs,
-        CreateChatCompletionRequestArgs,
+        CreateChatCompletionRequestArgs, CreateEmbeddingRequestArgs, CreateModerationRequestArgs,
     },
     Client,
 };
@@ -18,7 +18,7 @@ use url::Url;
 
 use crate::{
     llm::{
-        llm_trait::{CompletionParameters, Llm},
+        llm_trait::{CompletionParameters, Embedding, Llm, ModerationScores},
         messages::{AiMessage, ChatMessage},
     },
     utils::clear_url::clear_url,
@@ -41,12 +41,17 @@ pub struct OpenAiLlmConfig {
     pub api_base_url: Url,
     pub model_name: NonEmptyString,
     pub api_key_env_var: NonEmptyString,
+
+    /// Model for the retrieval features, e.g. `text-embedding-3-small`.
+    #[serde(default)]
+    pub embedding_model_name: Option<NonEmptyString>,
 }
 
 #[derive(Clone)]
 pub struct OpenAiLlm {
     client: Client<OpenAIConfig>,
     model_name: NonEmptyString,
+    embedding_model_name: Option<NonEmptyString>,
 }
 
 impl OpenAiLlm {
@@ -54,11 +59,16 @@ impl OpenAiLlm {
         let api_key =
             std::env::var(config.api_key_env_var.as_str()).map_err(OpenAiLlmError::ApiKeyNotSet)?;
 
-        Self::build_raw(
+        let llm = Self::build_raw(
             config.api_base_url,
             config.model_name,
             SecretString::new(api_key),
-        )
+        )?;
+
+        Ok(match config.embedding_model_name {
+            Some(embedding_model_name) => llm.with_embedding_model(embedding_model_name),
+            None => llm,
+        })
     }
 
     pub fn build_raw(
@@ -72,7 +82,18 @@ impl OpenAiLlm {
                 .with_api_key(api_key.expose_secret()),
         );
 
-        Ok(Self { client, model_name })
+        Ok(Self {
+            client,
+            model_name,
+            embedding_model_name: None,
+        })
+    }
+
+    pub fn with_embedding_model(self, embedding_model_name: NonEmptyString) -> Self {
+        Self {
+            embedding_model_name: Some(embedding_model_name),
+            ..self
+        }
     }
 }
 
@@ -110,6 +131,46 @@ impl Llm for OpenAiLlm {
         )
         .map_err(|_| OpenAiLlmError::FormatError)?)
     }
+
+    async fn moderate(&self, text: &str) -> Result<Option<ModerationScores>, Self::Error> {
+        let request = CreateModerationRequestArgs::default().input(text).build()?;
+
+        let response = self.client.moderations().create(request).await?;
+
+        let result = response
+            .results
+            .first()
+            .ok_or(OpenAiLlmError::FormatError)?;
+
+        // Category names are taken from the serialized form, e.g. `harassment/threatening`.
+        let scores = serde_json::to_value(&result.category_scores)
+            .ok()
+            .and_then(|value| serde_json::from_value(value).ok())
+            .ok_or(OpenAiLlmError::FormatError)?;
+
+        Ok(Some(scores))
+    }
+
+    async fn embed(&self, texts: Vec<String>) -> Result<Option<Vec<Embedding>>, Self::Error> {
+        let Some(embedding_model_name) = &self.embedding_model_name else {
+            return Ok(None);
+        };
+
+        let request = CreateEmbeddingRequestArgs::default()
+            .model(embedding_model_name.as_str())
+            .input(texts)
+            .build()?;
+
+        let mut data = self.client.embeddings().create(request).await?.data;
+
+        data.sort_by_key(|embedding| embedding.index);
+
+        Ok(Some(
+            data.into_iter()
+                .map(|embedding| embedding.embedding)
+                .collect(),
+        ))
+    }
 }
 
 fn chat_message_to_openai(
@@ -207,4 +268,100 @@ mod tests {
 
         assert_eq!(response.as_str(), "assistant");
     }
+
+    #[tokio::test]
+    async fn openai_moderation() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/moderations"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+              "id": "modr-123",
+              "model": "text-moderation-007",
+              "results": [{
+                "flagged": true,
+                "categories": {
+                  "sexual": false,
+                  "hate": false,
+                  "harassment": true,
+                  "self-harm": false,
+                  "sexual/minors": false,
+                  "hate/threatening": false,
+                  "violence/graphic": false,
+                  "self-harm/intent": false,
+                  "self-harm/instructions": false,
+                  "harassment/threatening": false,
+                  "violence": false
+                },
+                "category_scores": {
+                  "sexual": 0.01,
+                  "hate": 0.02,
+                  "harassment": 0.9,
+                  "self-harm": 0.0,
+                  "sexual/minors": 0.0,
+                  "hate/threatening": 0.0,
+                  "violence/graphic": 0.0,
+                  "self-harm/intent": 0.0,
+                  "self-harm/instructions": 0.0,
+                  "harassment/threatening": 0.1,
+                  "violence": 0.0
+                }
+              }]
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let llm = OpenAiLlm::build_raw(
+            mock_server.uri().as_str().try_into().unwrap(),
+            "eliza".try_into().unwrap(),
+            SecretString::new("42".into()),
+        )
+        .unwrap();
+
+        let scores = llm.moderate("You are an idiot").await.unwrap().unwrap();
+
+        assert_eq!(scores.get("harassment"), Some(&0.9));
+        assert_eq!(scores.get("harassment/threatening"), Some(&0.1));
+    }
+
+    #[tokio::test]
+    async fn openai_embeddings() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/embeddings"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+              "object": "list",
+              "data": [
+                { "object": "embedding", "index": 1, "embedding": [0.0, 1.0] },
+                { "object": "embedding", "index": 0, "embedding": [1.0, 0.0] }
+              ],
+              "model": "text-embedding-3-small",
+              "usage": {
+                "prompt_tokens": 8,
+                "total_tokens": 8
+              }
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let llm = OpenAiLlm::build_raw(
+            mock_server.uri().as_str().try_into().unwrap(),
+            "eliza".try_into().unwrap(),
+            SecretString::new("42".into()),
+        )
+        .unwrap();
+
+        assert_eq!(llm.embed(vec!["first".into()]).await.unwrap(), None);
+
+        let llm = llm.with_embedding_model("text-embedding-3-small".try_into().unwrap());
+
+        let embeddings = llm
+            .embed(vec!["first".into(), "second".into()])
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(embeddings, vec![vec![1.0, 0.0], vec![0.0, 1.0]]);
+    }
 }
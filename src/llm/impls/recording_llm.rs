@@ -0,0 +1,425 @@
+use std::{collections::BTreeMap, future::Future, io::ErrorKind, path::PathBuf, sync::Arc};
+
+use async_trait::async_trait;
+use non_empty_string::NonEmptyString;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    fs::{read_to_string, write},
+    sync::Mutex,
+};
+
+use crate::llm::{
+    llm_trait::{CompletionParameters, Embedding, Llm, ModerationScores},
+    messages::{AiMessage, ChatMessage},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum RecordingLlmError<LE> {
+    #[error("error from the wrapped LLM")]
+    LlmError(#[source] LE),
+
+    #[error("unable to read cassette file")]
+    CassetteReadError(#[source] std::io::Error),
+
+    #[error("unable to write cassette file")]
+    CassetteWriteError(#[source] std::io::Error),
+
+    #[error("cassette file has an invalid format")]
+    CassetteFormatError(#[from] serde_json::Error),
+
+    #[error("no recorded response in the cassette for the request with key {0}")]
+    CassetteMiss(String),
+}
+
+pub type Result<T, LE> = std::result::Result<T, RecordingLlmError<LE>>;
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordingMode {
+    /// Forward every request to the wrapped LLM and save the answers into the cassette.
+    Record,
+
+    /// Serve answers only from the cassette. The wrapped LLM is never called.
+    Replay,
+}
+
+#[derive(Deserialize)]
+pub struct RecordingLlmConfig {
+    pub cassette_path: PathBuf,
+    pub mode: RecordingMode,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CassetteEntry<R, T> {
+    request: R,
+    response: T,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CompletionRequest {
+    system_message: NonEmptyString,
+    chat: Vec<ChatMessage>,
+    params: CompletionParameters,
+}
+
+// `BTreeMap` is used so that the cassette file has a stable order and produces small diffs.
+#[derive(Serialize, Deserialize, Default)]
+struct Cassette {
+    #[serde(default)]
+    completions: BTreeMap<String, CassetteEntry<CompletionRequest, AiMessage>>,
+
+    #[serde(default)]
+    moderations: BTreeMap<String, CassetteEntry<String, Option<BTreeMap<String, f32>>>>,
+
+    #[serde(default)]
+    embeddings: BTreeMap<String, CassetteEntry<Vec<String>, Option<Vec<Embedding>>>>,
+}
+
+#[derive(Clone)]
+struct Recording {
+    mode: RecordingMode,
+    cassette_path: PathBuf,
+    cassette: Arc<Mutex<Cassette>>,
+}
+
+/// An `Llm` wrapper that records completions, moderations and embeddings into a cassette file and
+/// replays them later.
+///
+/// Used for deterministic tests of templates and output parsing without network access. Without a
+/// cassette it only forwards the requests, so that the bot can always run with the wrapper.
+#[derive(Clone)]
+pub struct RecordingLlm<L> {
+    llm: L,
+    recording: Option<Recording>,
+}
+
+impl<L: Llm> RecordingLlm<L> {
+    pub async fn build(llm: L, config: Option<RecordingLlmConfig>) -> Result<Self, L::Error> {
+        match config {
+            Some(config) => Self::build_raw(llm, config.cassette_path, config.mode).await,
+            None => Ok(Self {
+                llm,
+                recording: None,
+            }),
+        }
+    }
+
+    pub async fn build_raw(
+        llm: L,
+        cassette_path: PathBuf,
+        mode: RecordingMode,
+    ) -> Result<Self, L::Error> {
+        let cassette = match read_to_string(&cassette_path).await {
+            Ok(content) => serde_json::from_str(&content)?,
+
+            // A new cassette is started when recording for the first time.
+            Err(e) if e.kind() == ErrorKind::NotFound && mode == RecordingMode::Record => {
+                Cassette::default()
+            }
+
+            Err(e) => return Err(RecordingLlmError::CassetteReadError(e)),
+        };
+
+        Ok(Self {
+            llm,
+            recording: Some(Recording {
+                mode,
+                cassette_path,
+                cassette: Arc::new(Mutex::new(cassette)),
+            }),
+        })
+    }
+
+    /// Answer the request from the cassette, or ask the wrapped LLM with `call` and save the
+    /// answer into the `entries` of the cassette.
+    async fn replay_or_record<R, T>(
+        &self,
+        entries: fn(&mut Cassette) -> &mut BTreeMap<String, CassetteEntry<R, T>>,
+        request: R,
+        call: impl Future<Output = std::result::Result<T, L::Error>>,
+    ) -> Result<T, L::Error>
+    where
+        R: Serialize,
+        T: Serialize + Clone,
+    {
+        let Some(recording) = &self.recording else {
+            return call.await.map_err(RecordingLlmError::LlmError);
+        };
+
+        let key = cassette_key(&request)?;
+
+        match recording.mode {
+            RecordingMode::Replay => {
+                let mut cassette = recording.cassette.lock().await;
+
+                match entries(&mut cassette).get(&key) {
+                    Some(entry) => Ok(entry.response.clone()),
+                    None => Err(RecordingLlmError::CassetteMiss(key)),
+                }
+            }
+
+            RecordingMode::Record => {
+                let response = call.await.map_err(RecordingLlmError::LlmError)?;
+
+                let mut cassette = recording.cassette.lock().await;
+
+                entries(&mut cassette).insert(
+                    key,
+                    CassetteEntry {
+                        request,
+                        response: response.clone(),
+                    },
+                );
+
+                write(
+                    &recording.cassette_path,
+                    serde_json::to_string_pretty(&*cassette)?,
+                )
+                .await
+                .map_err(RecordingLlmError::CassetteWriteError)?;
+
+                Ok(response)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<L> Llm for RecordingLlm<L>
+where
+    L: Llm + Send + Sync,
+    L::Error: Send,
+{
+    type Error = RecordingLlmError<L::Error>;
+
+    async fn complete(
+        &self,
+        system_message: &NonEmptyString,
+        chat: Vec<ChatMessage>,
+        params: &CompletionParameters,
+    ) -> std::result::Result<AiMessage, Self::Error> {
+        let request = CompletionRequest {
+            system_message: system_message.clone(),
+            chat: chat.clone(),
+            params: params.clone(),
+        };
+
+        self.replay_or_record(
+            |cassette| &mut cassette.completions,
+            request,
+            self.llm.complete(system_message, chat, params),
+        )
+        .await
+    }
+
+    async fn moderate(
+        &self,
+        text: &str,
+    ) -> std::result::Result<Option<ModerationScores>, Self::Error> {
+        // The scores are kept sorted in the cassette, so that it produces small diffs too.
+        let scores = self
+            .replay_or_record(
+                |cassette| &mut cassette.moderations,
+                text.to_string(),
+                async {
+                    self.llm
+                        .moderate(text)
+                        .await
+                        .map(|scores| scores.map(|scores| scores.into_iter().collect()))
+                },
+            )
+            .await?;
+
+        Ok(scores.map(|scores| scores.into_iter().collect()))
+    }
+
+    async fn embed(
+        &self,
+        texts: Vec<String>,
+    ) -> std::result::Result<Option<Vec<Embedding>>, Self::Error> {
+        self.replay_or_record(
+            |cassette| &mut cassette.embeddings,
+            texts.clone(),
+            self.llm.embed(texts),
+        )
+        .await
+    }
+}
+
+fn cassette_key(request: &impl Serialize) -> serde_json::Result<String> {
+    let request = serde_json::to_string(request)?;
+
+    Ok(format!("{:016x}", fnv1a_hash(request.as_bytes())))
+}
+
+// NOTE: `DefaultHasher` is not guaranteed to be stable between Rust releases, and cassettes are
+// committed to the repository, so a simple FNV-1a is used instead.
+fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, path::PathBuf, str::FromStr};
+
+    use non_empty_string::NonEmptyString;
+
+    use crate::llm::{
+        impls::recording_llm::{RecordingLlm, RecordingLlmError, RecordingMode},
+        llm_trait::{CompletionParameters, Llm, MockLlm},
+        messages::{AiMessage, UserMessage},
+    };
+
+    #[tokio::test]
+    async fn records_and_replays() {
+        let cassette_path = make_cassette_path("records_and_replays");
+
+        let mut llm_mock = MockLlm::new();
+
+        llm_mock
+            .expect_complete()
+            .times(1)
+            .returning(|_, _, _| Ok(AiMessage::from_str("recorded").unwrap()));
+
+        let recorder =
+            RecordingLlm::build_raw(llm_mock, cassette_path.clone(), RecordingMode::Record)
+                .await
+                .unwrap();
+
+        let recorded = complete(&recorder, "user").await.unwrap();
+
+        assert_eq!(recorded.as_str(), "recorded");
+
+        // The wrapped LLM has no expectations, so any call to it will panic.
+        let player = RecordingLlm::build_raw(MockLlm::new(), cassette_path, RecordingMode::Replay)
+            .await
+            .unwrap();
+
+        let replayed = complete(&player, "user").await.unwrap();
+
+        assert_eq!(replayed.as_str(), "recorded");
+    }
+
+    #[tokio::test]
+    async fn replay_fails_on_miss() {
+        let cassette_path = make_cassette_path("replay_fails_on_miss");
+
+        let mut llm_mock = MockLlm::new();
+
+        llm_mock
+            .expect_complete()
+            .returning(|_, _, _| Ok(AiMessage::from_str("recorded").unwrap()));
+
+        let recorder =
+            RecordingLlm::build_raw(llm_mock, cassette_path.clone(), RecordingMode::Record)
+                .await
+                .unwrap();
+
+        complete(&recorder, "user").await.unwrap();
+
+        let player = RecordingLlm::build_raw(MockLlm::new(), cassette_path, RecordingMode::Replay)
+            .await
+            .unwrap();
+
+        let result = complete(&player, "another user message").await;
+
+        assert!(matches!(result, Err(RecordingLlmError::CassetteMiss(_))));
+    }
+
+    #[tokio::test]
+    async fn replay_fails_without_cassette() {
+        let cassette_path = make_cassette_path("replay_fails_without_cassette");
+
+        let result =
+            RecordingLlm::build_raw(MockLlm::new(), cassette_path, RecordingMode::Replay).await;
+
+        assert!(matches!(
+            result,
+            Err(RecordingLlmError::CassetteReadError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn records_and_replays_moderations_and_embeddings() {
+        let cassette_path = make_cassette_path("records_and_replays_moderations_and_embeddings");
+
+        let mut llm_mock = MockLlm::new();
+
+        llm_mock
+            .expect_moderate()
+            .times(1)
+            .returning(|_| Ok(Some(HashMap::from([("spam".to_string(), 0.9)]))));
+
+        llm_mock
+            .expect_embed()
+            .times(1)
+            .returning(|_| Ok(Some(vec![vec![1.0, 0.0]])));
+
+        let recorder =
+            RecordingLlm::build_raw(llm_mock, cassette_path.clone(), RecordingMode::Record)
+                .await
+                .unwrap();
+
+        recorder.moderate("Buy now").await.unwrap();
+        recorder.embed(vec!["Buy now".into()]).await.unwrap();
+
+        let player = RecordingLlm::build_raw(MockLlm::new(), cassette_path, RecordingMode::Replay)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            player.moderate("Buy now").await.unwrap(),
+            Some(HashMap::from([("spam".to_string(), 0.9)]))
+        );
+
+        assert_eq!(
+            player.embed(vec!["Buy now".into()]).await.unwrap(),
+            Some(vec![vec![1.0, 0.0]])
+        );
+
+        assert!(matches!(
+            player.embed(vec!["Hello".into()]).await,
+            Err(RecordingLlmError::CassetteMiss(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn forwards_without_cassette() {
+        let mut llm_mock = MockLlm::new();
+
+        llm_mock
+            .expect_complete()
+            .times(2)
+            .returning(|_, _, _| Ok(AiMessage::from_str("answer").unwrap()));
+
+        let llm = RecordingLlm::build(llm_mock, None).await.unwrap();
+
+        complete(&llm, "user").await.unwrap();
+        complete(&llm, "user").await.unwrap();
+    }
+
+    async fn complete(
+        llm: &RecordingLlm<MockLlm>,
+        user_message: &str,
+    ) -> Result<AiMessage, RecordingLlmError<()>> {
+        llm.complete(
+            &NonEmptyString::from_str("system").unwrap(),
+            vec![UserMessage::from_str(user_message).unwrap().into()],
+            &CompletionParameters::default(),
+        )
+        .await
+    }
+
+    fn make_cassette_path(test_name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("gib_cassette_{}.json", test_name));
+
+        let _ = std::fs::remove_file(&path);
+
+        path
+    }
+}
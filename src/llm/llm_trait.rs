@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use async_trait::async_trait;
 use mockall::automock;
 use non_empty_string::NonEmptyString;
@@ -6,16 +8,24 @@ use smart_default::SmartDefault;
 
 use super::messages::{AiMessage, ChatMessage};
 
-#[derive(SmartDefault, Serialize, Deserialize)]
+#[derive(SmartDefault, Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(default)]
 pub struct CompletionParameters {
     #[default(1.0)]
     pub temperature: f32,
 }
 
+/// Scores of the moderation categories, from 0.0 to 1.0. Category names are the ones of the
+/// provider.
+pub type ModerationScores = HashMap<String, f32>;
+
+pub type Embedding = Vec<f32>;
+
+// NOTE: `async_trait` requires `Self: Sync` to call the default methods, so without the bound
+// `moderate` and `embed` can't be called on a generic `L: Llm`.
 #[automock(type Error = ();)]
 #[async_trait]
-pub trait Llm {
+pub trait Llm: Sync {
     type Error;
 
     async fn complete(
@@ -24,4 +34,16 @@ pub trait Llm {
         chat: Vec<ChatMessage>,
         params: &CompletionParameters,
     ) -> Result<AiMessage, Self::Error>;
+
+    /// Classify the text with the moderation endpoint of the provider. Returns `None` if the
+    /// provider has no such endpoint.
+    async fn moderate(&self, _text: &str) -> Result<Option<ModerationScores>, Self::Error> {
+        Ok(None)
+    }
+
+    /// Embed the texts with the embedding model of the provider, in the same order. Returns
+    /// `None` if no embedding model is available.
+    async fn embed(&self, _texts: Vec<String>) -> Result<Option<Vec<Embedding>>, Self::Error> {
+        Ok(None)
+    }
 }
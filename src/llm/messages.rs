@@ -2,7 +2,10 @@ use std::ops::Deref;
 
 use derive_more::derive::{AsRef, Deref, From, FromStr, Into};
 use non_empty_string::NonEmptyString;
+use serde::{Deserialize, Serialize};
 
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "role", content = "content", rename_all = "snake_case")]
 pub enum ChatMessage {
     UserMessage(UserMessage),
     AiMessage(AiMessage),
@@ -28,7 +31,8 @@ impl Deref for ChatMessage {
     }
 }
 
-#[derive(FromStr, AsRef, Deref, Into, From)]
+#[derive(FromStr, AsRef, Deref, Into, From, Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(transparent)]
 pub struct UserMessage(NonEmptyString);
 
 impl From<UserMessage> for ChatMessage {
@@ -37,7 +41,8 @@ impl From<UserMessage> for ChatMessage {
     }
 }
 
-#[derive(FromStr, AsRef, Deref, Into, From)]
+#[derive(FromStr, AsRef, Deref, Into, From, Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(transparent)]
 pub struct AiMessage(NonEmptyString);
 
 impl From<AiMessage> for ChatMessage {
@@ -1,19 +1,39 @@
-use std::{env::VarError, fmt::Debug};
+use std::{env::VarError, fmt::Debug, path::PathBuf, str::FromStr, time::Duration};
 
+use chrono::Utc;
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use gib::{
-    bot::{errors::GitBotError, gitbot::GitBot},
-    config::{Config, ConfigError, GitHostChoice, LlmChoice},
+    bot::{
+        backfill::{backfill, BackfillError, BackfillOptions},
+        bot_features::FeatureName,
+        errors::GitBotError,
+        features::release_notes_feature::format_changelog,
+        gitbot::GitBot,
+        scheduler::ScheduledEvent,
+    },
+    config::{Config, ConfigError, GitHostChoice, LlmChoice, WebhookServerConfig},
     githost::{
-        events::GitEvent,
+        events::{GitEvent, GitEventKind},
+        host::GitHost,
         impls::github::{
             errors::GithubError, github_host::GithubHost, webhook_server::GithubWebhookServer,
         },
+        model::{IssueId, IssueQuery, IssueStateFilter, RepoName},
+    },
+    llm::{
+        impls::{
+            openai_llm::{OpenAiLlm, OpenAiLlmError},
+            recording_llm::{RecordingLlm, RecordingLlmError},
+        },
+        llm_trait::{CompletionParameters, Llm},
+        messages::UserMessage,
     },
-    llm::impls::openai_llm::{OpenAiLlm, OpenAiLlmError},
     utils::display::display_error,
 };
-use log::error;
+use log::{error, info};
+use non_empty_string::NonEmptyString;
 use tokio::{
+    fs::write,
     sync::mpsc::{channel, Receiver, Sender},
     task::{JoinError, JoinHandle},
 };
@@ -21,6 +41,8 @@ use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, Env
 
 const GIT_EVENT_CHANNEL_BUFFER_SIZE: usize = 2;
 
+const SCHEDULED_EVENT_CHANNEL_BUFFER_SIZE: usize = 2;
+
 #[derive(Debug, thiserror::Error)]
 pub enum MainError<GE, LE> {
     #[error("unable to read config")]
@@ -41,27 +63,246 @@ pub enum MainError<GE, LE> {
     #[error("you must specify at least one feature")]
     NoFeaturesSelected,
 
+    #[error("feature `{0}` is not enabled in the configuration")]
+    FeatureNotEnabled(FeatureName),
+
     #[error("webhook server configuration should be present for the selected Git host")]
     NoWebhookConfiguration,
 
     #[error("error from Git bot")]
     GitBotError(#[from] GitBotError<GE, LE>),
 
+    #[error("features {0} have failed, see the log for the errors")]
+    FeaturesFailed(String),
+
+    #[error("unable to backfill issues")]
+    BackfillError(#[from] BackfillError<GE>),
+
+    #[error("features have failed on {0} issues, see the log for the errors")]
+    BackfillFailures(usize),
+
+    #[error("unable to write output file")]
+    OutputWriteError(#[source] std::io::Error),
+
     #[error("unable to join threads")]
     ThreadJoinError(#[from] JoinError),
 }
 
 type Result<T, GE, LE> = std::result::Result<T, MainError<GE, LE>>;
 
+/// The LLM of the configuration, recorded or replayed if `llm_recording` is set.
+type AppLlm = RecordingLlm<OpenAiLlm>;
+
+type AppLlmError = RecordingLlmError<OpenAiLlmError>;
+
+type AppResult<T> = Result<T, GithubError, AppLlmError>;
+
+#[derive(Parser)]
+#[command(version, about = "Git Intellectual Bot")]
+struct Cli {
+    /// What to do. Defaults to `serve`.
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Start the webhook server and process incoming events.
+    Serve,
+
+    /// Run all enabled features once on an existing issue.
+    Process {
+        /// Repository in the `owner/name` format.
+        #[arg(long)]
+        repo: RepoName,
+
+        /// Issue number.
+        #[arg(long)]
+        issue: usize,
+    },
+
+    /// Print the rendered system and user messages of a feature without calling the LLM.
+    Render {
+        /// Feature whose templates should be rendered.
+        #[arg(long)]
+        feature: FeatureName,
+
+        /// Repository in the `owner/name` format.
+        #[arg(long)]
+        repo: RepoName,
+
+        /// Issue number.
+        #[arg(long)]
+        issue: usize,
+    },
+
+    /// Run features on existing issues of a repository.
+    Backfill(BackfillArgs),
+
+    /// Write release notes for the pull requests merged between two refs.
+    ReleaseNotes(ReleaseNotesArgs),
+
+    /// Load the config, compile the templates and check connections to the Git host and the LLM.
+    CheckConfig,
+
+    /// Index the code and the docs of a repository again, for example after a release.
+    RebuildIndex {
+        /// Repository in the `owner/name` format.
+        #[arg(long)]
+        repo: RepoName,
+    },
+}
+
+#[derive(Args)]
+struct ReleaseNotesArgs {
+    /// Repository in the `owner/name` format.
+    #[arg(long)]
+    repo: RepoName,
+
+    /// Previous release: a tag, a branch or a commit SHA.
+    #[arg(long)]
+    from: String,
+
+    /// New release. Used as the version in the changelog and as the tag of the draft release.
+    #[arg(long)]
+    to: NonEmptyString,
+
+    /// Write the changelog section to this file instead of printing it.
+    #[arg(long)]
+    output: Option<PathBuf>,
+
+    /// Also create a draft release with the notes for the `to` tag.
+    #[arg(long)]
+    draft_release: bool,
+}
+
+#[derive(Args)]
+struct BackfillArgs {
+    /// Repository in the `owner/name` format.
+    #[arg(long)]
+    repo: RepoName,
+
+    /// State of the issues to process.
+    #[arg(long, value_enum, default_value_t = StateArg::Open)]
+    state: StateArg,
+
+    /// Process only issues with this label. Can be repeated.
+    #[arg(long = "label", value_parser = parse_label)]
+    labels: Vec<NonEmptyString>,
+
+    /// Process only issues without any labels.
+    #[arg(long)]
+    unlabeled: bool,
+
+    /// Process only issues created at least this many days ago.
+    #[arg(long)]
+    older_than_days: Option<i64>,
+
+    /// Process only issues created at most this many days ago.
+    #[arg(long)]
+    newer_than_days: Option<i64>,
+
+    /// Run only this feature. Can be repeated. All enabled features are run by default.
+    #[arg(long = "feature")]
+    features: Vec<FeatureName>,
+
+    /// Pause between two processed issues in milliseconds.
+    #[arg(long, default_value_t = 1000)]
+    delay_ms: u64,
+
+    /// File to store the last processed issue in, so that the backfill can be resumed.
+    #[arg(long)]
+    cursor_file: Option<PathBuf>,
+}
+
+impl BackfillArgs {
+    fn into_options(self) -> (RepoName, BackfillOptions) {
+        let today = Utc::now().date_naive();
+
+        let options = BackfillOptions {
+            query: IssueQuery {
+                state: self.state.into(),
+                labels: self.labels,
+                unlabeled: self.unlabeled,
+                created_after: self
+                    .newer_than_days
+                    .map(|days| today - chrono::Duration::days(days)),
+                created_before: self
+                    .older_than_days
+                    .map(|days| today - chrono::Duration::days(days)),
+                ..Default::default()
+            },
+            features: if self.features.is_empty() {
+                FeatureName::ALL.to_vec()
+            } else {
+                self.features
+            },
+            delay: Duration::from_millis(self.delay_ms),
+            cursor_path: self.cursor_file,
+        };
+
+        (self.repo, options)
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+enum StateArg {
+    Open,
+    Closed,
+    All,
+}
+
+impl From<StateArg> for IssueStateFilter {
+    fn from(value: StateArg) -> Self {
+        match value {
+            StateArg::Open => IssueStateFilter::Open,
+            StateArg::Closed => IssueStateFilter::Closed,
+            StateArg::All => IssueStateFilter::All,
+        }
+    }
+}
+
+fn parse_label(s: &str) -> std::result::Result<NonEmptyString, String> {
+    NonEmptyString::from_str(s).map_err(|_| "label name should not be empty".to_string())
+}
+
 #[tokio::main]
-async fn main() -> Result<(), GithubError, OpenAiLlmError> {
+async fn main() -> AppResult<()> {
     tracing_subscriber::registry()
         .with(fmt::layer())
         .with(EnvFilter::from_default_env())
         .init();
 
+    let cli = Cli::parse();
+
     let config = Config::build()?;
 
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => serve(config).await,
+        Command::Process { repo, issue } => process(config, repo, issue.into()).await,
+        Command::Render {
+            feature,
+            repo,
+            issue,
+        } => render(config, feature, repo, issue.into()).await,
+        Command::Backfill(args) => {
+            let (repo, options) = args.into_options();
+            run_backfill(config, repo, options).await
+        }
+        Command::ReleaseNotes(args) => release_notes(config, args).await,
+        Command::CheckConfig => check_config(config).await,
+        Command::RebuildIndex { repo } => rebuild_index(config, repo).await,
+    }
+}
+
+async fn build(
+    config: Config,
+) -> AppResult<(
+    GithubHost,
+    AppLlm,
+    GitBot<GithubHost, AppLlm>,
+    Option<WebhookServerConfig>,
+)> {
     let githost = match config.githost {
         GitHostChoice::Github(config) => GithubHost::build(config)
             .await
@@ -69,32 +310,63 @@ async fn main() -> Result<(), GithubError, OpenAiLlmError> {
     };
 
     let llm = match config.llm {
-        LlmChoice::OpenAi(config) => OpenAiLlm::build(config).map_err(MainError::LlmError)?,
+        LlmChoice::OpenAi(config) => OpenAiLlm::build(config)
+            .map_err(|e| MainError::LlmError(RecordingLlmError::LlmError(e)))?,
     };
 
-    let bot = GitBot::build(config.bot, githost, llm).await?;
+    let llm = RecordingLlm::build(llm, config.llm_recording)
+        .await
+        .map_err(MainError::LlmError)?;
+
+    let bot = GitBot::build(config.bot, githost.clone(), llm.clone()).await?;
+
+    Ok((githost, llm, bot, config.webhook_server))
+}
+
+async fn serve(config: Config) -> AppResult<()> {
+    let (githost, _, mut bot, webhook_config) = build(config).await?;
 
     let (events_send, mut events_receive): (Sender<GitEvent>, Receiver<GitEvent>) =
         channel(GIT_EVENT_CHANNEL_BUFFER_SIZE);
 
-    if let Some(webhook_config) = config.webhook_server {
-        let webhook_server = GithubWebhookServer::new(events_send, webhook_config);
+    let (scheduled_send, mut scheduled_receive): (
+        Sender<ScheduledEvent>,
+        Receiver<ScheduledEvent>,
+    ) = channel(SCHEDULED_EVENT_CHANNEL_BUFFER_SIZE);
 
-        let webhook_server_join = tokio::spawn(start_webhook_server(webhook_server));
+    // Without a scheduler the sender is dropped here and the bot only gets webhook events.
+    if let Some(scheduler) = bot.take_scheduler() {
+        tokio::spawn(async move {
+            if let Err(e) = scheduler.run(githost, scheduled_send).await {
+                error!("{}", display_error(e));
+            }
+        });
+    }
+
+    if let Some(webhook_config) = webhook_config {
+        let webhook_server = GithubWebhookServer::new(events_send, webhook_config, bot.storage());
 
-        let bot_join: JoinHandle<
-            std::result::Result<(), GitBotError<GithubError, OpenAiLlmError>>,
-        > = tokio::spawn(async move {
-            while let Some(event) = events_receive.recv().await {
-                let res = bot.process_event(&event).await;
+        let webhook_server_join = tokio::spawn(start_webhook_server(webhook_server));
 
-                if let Err(e) = res {
-                    error!("{}", display_error(e));
+        let bot_join: JoinHandle<std::result::Result<(), GitBotError<GithubError, AppLlmError>>> =
+            tokio::spawn(async move {
+                loop {
+                    tokio::select! {
+                        // Failures are logged by the bot.
+                        Some(event) = events_receive.recv() => {
+                            bot.process_event(&event).await;
+                        }
+                        Some(event) = scheduled_receive.recv() => {
+                            if let Err(e) = bot.process_scheduled_event(&event).await {
+                                error!("{}", display_error(e));
+                            }
+                        }
+                        else => break,
+                    };
                 }
-            }
 
-            Ok(())
-        });
+                Ok(())
+            });
 
         let (webhook_exit, bot_exit) = tokio::join!(webhook_server_join, bot_join);
         webhook_exit?.map_err(MainError::GitHostError)?;
@@ -106,6 +378,159 @@ async fn main() -> Result<(), GithubError, OpenAiLlmError> {
     }
 }
 
+async fn process(config: Config, repo: RepoName, issue_id: IssueId) -> AppResult<()> {
+    let (githost, _, bot, _) = build(config).await?;
+
+    let repo = githost
+        .find_repo(&repo)
+        .await
+        .map_err(MainError::GitHostError)?;
+
+    let failures = bot
+        .process_event(&GitEvent {
+            repo_id: repo.id,
+            issue_id,
+            kind: GitEventKind::NewIssue,
+        })
+        .await;
+
+    if !failures.is_empty() {
+        let features = failures
+            .iter()
+            .map(|(feature, _)| feature.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        return Err(MainError::FeaturesFailed(features));
+    }
+
+    Ok(())
+}
+
+async fn render(
+    config: Config,
+    feature: FeatureName,
+    repo: RepoName,
+    issue_id: IssueId,
+) -> AppResult<()> {
+    let (githost, _, bot, _) = build(config).await?;
+
+    let repo = githost
+        .find_repo(&repo)
+        .await
+        .map_err(MainError::GitHostError)?;
+
+    let messages = bot
+        .render(feature, repo.id, issue_id)
+        .await?
+        .ok_or(MainError::FeatureNotEnabled(feature))?;
+
+    println!("=== System message ===\n{}\n", messages.system_message);
+    println!("=== User message ===\n{}", messages.user_message);
+
+    Ok(())
+}
+
+async fn run_backfill(config: Config, repo: RepoName, options: BackfillOptions) -> AppResult<()> {
+    let (githost, _, bot, _) = build(config).await?;
+
+    let repo = githost
+        .find_repo(&repo)
+        .await
+        .map_err(MainError::GitHostError)?;
+
+    let summary = backfill(&githost, &bot, repo.id, &options).await?;
+
+    println!(
+        "Processed {} issues, features have failed on {} issues",
+        summary.processed,
+        summary.failed.len()
+    );
+
+    if !summary.failed.is_empty() {
+        return Err(MainError::BackfillFailures(summary.failed.len()));
+    }
+
+    Ok(())
+}
+
+async fn rebuild_index(config: Config, repo: RepoName) -> AppResult<()> {
+    let (githost, _, bot, _) = build(config).await?;
+
+    let repo = githost
+        .find_repo(&repo)
+        .await
+        .map_err(MainError::GitHostError)?;
+
+    bot.rebuild_indexes(repo.id).await?;
+
+    info!("Indexes of {}/{} are rebuilt", repo.owner, repo.name);
+
+    Ok(())
+}
+
+async fn release_notes(config: Config, args: ReleaseNotesArgs) -> AppResult<()> {
+    let (githost, _, bot, _) = build(config).await?;
+
+    let repo = githost
+        .find_repo(&args.repo)
+        .await
+        .map_err(MainError::GitHostError)?;
+
+    let notes = bot
+        .release_notes(repo.id, args.from, args.to.to_string())
+        .await?
+        .ok_or(MainError::FeatureNotEnabled(FeatureName::ReleaseNotes))?;
+
+    let changelog = format_changelog(args.to.as_str(), Utc::now().date_naive(), &notes);
+
+    match args.output {
+        Some(path) => write(path, changelog)
+            .await
+            .map_err(MainError::OutputWriteError)?,
+        None => println!("{}", changelog),
+    }
+
+    if args.draft_release {
+        githost
+            .create_release(repo.id, args.to.clone(), args.to.to_string(), notes, true)
+            .await
+            .map_err(MainError::GitHostError)?;
+
+        info!("Draft release {} is created", args.to);
+    }
+
+    Ok(())
+}
+
+async fn check_config(config: Config) -> AppResult<()> {
+    // Templates are compiled while building the bot.
+    let (githost, llm, _, _) = build(config).await?;
+
+    info!("Configuration is loaded and templates are compiled");
+
+    githost
+        .check_connection()
+        .await
+        .map_err(MainError::GitHostError)?;
+
+    info!("Connection to the Git host is working");
+
+    llm.complete(
+        &NonEmptyString::from_str("You are a health check. Answer with a single word.").unwrap(),
+        vec![UserMessage::from_str("ping").unwrap().into()],
+        &CompletionParameters::default(),
+    )
+    .await
+    .map_err(MainError::LlmError)?;
+
+    info!("Connection to the LLM is working");
+
+    println!("Configuration is OK");
+
+    Ok(())
+}
+
 async fn start_webhook_server(
     webhook_server: GithubWebhookServer,
 ) -> std::result::Result<(), GithubError> {
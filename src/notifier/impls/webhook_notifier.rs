@@ -0,0 +1,94 @@
+use async_trait::async_trait;
+use non_empty_string::NonEmptyString;
+use serde::Deserialize;
+use serde_json::json;
+use url::Url;
+
+use crate::notifier::notifier_trait::{Notifier, NotifierError};
+
+#[derive(Deserialize)]
+pub struct WebhookNotifierConfig {
+    /// The URL of an incoming webhook is a secret, so it is read from the environment.
+    pub url_env_var: NonEmptyString,
+}
+
+/// Posts notifications to an incoming webhook in the `{"text": ...}` format that Slack,
+/// Mattermost and Rocket.Chat understand.
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    url: Url,
+}
+
+impl WebhookNotifier {
+    pub fn build(config: WebhookNotifierConfig) -> Result<Self, NotifierError> {
+        let url = std::env::var(config.url_env_var.as_str()).map_err(NotifierError::UrlNotSet)?;
+
+        Ok(Self::build_raw(
+            Url::parse(&url).map_err(NotifierError::InvalidUrl)?,
+        ))
+    }
+
+    pub fn build_raw(url: Url) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    async fn notify(&self, message: &str) -> Result<(), NotifierError> {
+        self.client
+            .post(self.url.clone())
+            .json(&json!({ "text": message }))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_json, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use crate::notifier::{impls::webhook_notifier::WebhookNotifier, notifier_trait::Notifier};
+
+    #[tokio::test]
+    async fn posts_message() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/hooks/1"))
+            .and(body_json(json!({ "text": "Hello" })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let notifier =
+            WebhookNotifier::build_raw(format!("{}/hooks/1", mock_server.uri()).parse().unwrap());
+
+        notifier.notify("Hello").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn fails_on_error_status() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+
+        let notifier = WebhookNotifier::build_raw(mock_server.uri().parse().unwrap());
+
+        assert!(notifier.notify("Hello").await.is_err());
+    }
+}
@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use mockall::automock;
+use serde::Deserialize;
+
+use super::impls::webhook_notifier::{WebhookNotifier, WebhookNotifierConfig};
+
+#[derive(Debug, thiserror::Error)]
+pub enum NotifierError {
+    #[error("unable to send the notification")]
+    RequestError(#[from] reqwest::Error),
+
+    #[error("webhook URL environment variable is not set")]
+    UrlNotSet(#[source] std::env::VarError),
+
+    #[error("webhook URL is invalid")]
+    InvalidUrl(#[source] url::ParseError),
+}
+
+/// A channel to tell the maintainers about something that should not be discussed in public.
+#[automock]
+#[async_trait]
+pub trait Notifier {
+    async fn notify(&self, message: &str) -> Result<(), NotifierError>;
+}
+
+#[derive(Deserialize)]
+pub enum NotifierChoice {
+    Webhook(WebhookNotifierConfig),
+}
+
+pub fn build_notifier(
+    choice: NotifierChoice,
+) -> Result<Arc<dyn Notifier + Send + Sync>, NotifierError> {
+    Ok(match choice {
+        NotifierChoice::Webhook(config) => Arc::new(WebhookNotifier::build(config)?),
+    })
+}
@@ -0,0 +1,291 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// A piece of a source file. Lines are numbered from 1, the end is inclusive.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct LineChunk {
+    pub path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+}
+
+/// Split a file into chunks of `chunk_lines` lines. Chunks of only whitespace are skipped.
+pub fn chunk_by_lines(path: &str, text: &str, chunk_lines: usize) -> Vec<LineChunk> {
+    let lines: Vec<&str> = text.lines().collect();
+
+    lines
+        .chunks(chunk_lines.max(1))
+        .enumerate()
+        .filter(|(_, chunk)| chunk.iter().any(|line| !line.trim().is_empty()))
+        .map(|(i, chunk)| {
+            let start_line = i * chunk_lines.max(1) + 1;
+
+            LineChunk {
+                path: path.to_string(),
+                start_line,
+                end_line: start_line + chunk.len() - 1,
+                text: chunk.join("\n"),
+            }
+        })
+        .collect()
+}
+
+/// A part of a document under one heading.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Section {
+    /// Empty for the text before the first heading.
+    pub heading: String,
+
+    /// Fragment of the heading in the URL of the page, without `#`.
+    pub anchor: Option<String>,
+
+    pub text: String,
+}
+
+/// Split a Markdown document by its headings. Lines in code blocks are never headings.
+pub fn chunk_markdown(text: &str, max_chars: usize) -> Vec<Section> {
+    let mut sections = Vec::new();
+
+    let mut heading = String::new();
+    let mut lines: Vec<&str> = Vec::new();
+    let mut in_code_block = false;
+
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+
+        let title = line
+            .strip_prefix('#')
+            .map(|rest| rest.trim_start_matches('#'))
+            .filter(|rest| rest.starts_with(' '))
+            .map(|rest| rest.trim().trim_end_matches('#').trim());
+
+        match title {
+            Some(title) if !in_code_block => {
+                push_section(&mut sections, &heading, None, &lines.join("\n"), max_chars);
+
+                heading = title.to_string();
+                lines.clear();
+            }
+
+            _ => lines.push(line),
+        }
+    }
+
+    push_section(&mut sections, &heading, None, &lines.join("\n"), max_chars);
+
+    sections
+}
+
+/// Split an HTML page by its `<h1>`-`<h6>` headings. Tags are removed, the anchors are taken from
+/// the `id` attributes of the headings.
+pub fn chunk_html(html: &str, max_chars: usize) -> Vec<Section> {
+    let regex = |pattern: &str| Regex::new(pattern).expect("built-in patterns are valid");
+
+    let hidden = regex(r"(?is)<(script|style|head)\b.*?</(script|style|head)>");
+    let heading_tag = regex(r#"(?is)<h[1-6]\b([^>]*)>(.*?)</h[1-6]>"#);
+    let id_attribute = regex(r#"\bid\s*=\s*["']([^"']+)["']"#);
+
+    let html = hidden.replace_all(html, "");
+
+    let mut sections = Vec::new();
+
+    let mut heading = String::new();
+    let mut anchor = None;
+    let mut start = 0;
+
+    for captures in heading_tag.captures_iter(&html) {
+        let whole = captures.get(0).expect("there is always the whole match");
+
+        push_section(
+            &mut sections,
+            &heading,
+            anchor,
+            &html_to_text(&html[start..whole.start()]),
+            max_chars,
+        );
+
+        heading = html_to_text(&captures[2]);
+        anchor = id_attribute
+            .captures(&captures[1])
+            .map(|id| id[1].to_string());
+        start = whole.end();
+    }
+
+    push_section(
+        &mut sections,
+        &heading,
+        anchor,
+        &html_to_text(&html[start..]),
+        max_chars,
+    );
+
+    sections
+}
+
+fn html_to_text(html: &str) -> String {
+    let tag = Regex::new(r"<[^>]*>").expect("built-in patterns are valid");
+
+    let text = tag
+        .replace_all(html, " ")
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Add the section, split by paragraphs if it is longer than `max_chars`.
+fn push_section(
+    sections: &mut Vec<Section>,
+    heading: &str,
+    anchor: Option<String>,
+    text: &str,
+    max_chars: usize,
+) {
+    let mut part = String::new();
+
+    let mut push = |part: &str| {
+        if !part.trim().is_empty() {
+            sections.push(Section {
+                heading: heading.to_string(),
+                anchor: anchor.clone(),
+                text: part.trim().to_string(),
+            });
+        }
+    };
+
+    for paragraph in text.split("\n\n") {
+        if !part.is_empty() && part.len() + paragraph.len() > max_chars {
+            push(&part);
+            part.clear();
+        }
+
+        if !part.is_empty() {
+            part.push_str("\n\n");
+        }
+
+        part.push_str(paragraph);
+    }
+
+    push(&part);
+}
+
+/// Anchor of a Markdown heading the way GitHub makes it.
+pub fn heading_anchor(heading: &str) -> String {
+    heading
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::retrieval::chunking::{
+        chunk_by_lines, chunk_html, chunk_markdown, heading_anchor, LineChunk, Section,
+    };
+
+    #[test]
+    fn chunks_by_lines() {
+        let chunks = chunk_by_lines("src/main.rs", "fn main() {\n}\n\n\n\nfn f() {}", 2);
+
+        assert_eq!(
+            chunks,
+            vec![
+                LineChunk {
+                    path: "src/main.rs".into(),
+                    start_line: 1,
+                    end_line: 2,
+                    text: "fn main() {\n}".into(),
+                },
+                LineChunk {
+                    path: "src/main.rs".into(),
+                    start_line: 5,
+                    end_line: 6,
+                    text: "\nfn f() {}".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn chunks_markdown_by_headings() {
+        let sections = chunk_markdown(
+            "Intro.\n\n# Install\n\nRun `cargo install`.\n\n```sh\n# not a heading\n```\n\n\
+             ## Configure it ##\n\nFirst.\n\nSecond.",
+            10,
+        );
+
+        assert_eq!(
+            sections,
+            vec![
+                Section {
+                    heading: "".into(),
+                    anchor: None,
+                    text: "Intro.".into(),
+                },
+                Section {
+                    heading: "Install".into(),
+                    anchor: None,
+                    text: "Run `cargo install`.".into(),
+                },
+                Section {
+                    heading: "Install".into(),
+                    anchor: None,
+                    text: "```sh\n# not a heading\n```".into(),
+                },
+                Section {
+                    heading: "Configure it".into(),
+                    anchor: None,
+                    text: "First.".into(),
+                },
+                Section {
+                    heading: "Configure it".into(),
+                    anchor: None,
+                    text: "Second.".into(),
+                },
+            ]
+        );
+
+        assert_eq!(
+            heading_anchor("Configure it, quickly!"),
+            "configure-it-quickly"
+        );
+    }
+
+    #[test]
+    fn chunks_html_by_headings() {
+        let sections = chunk_html(
+            "<html><head><title>Docs</title></head><body>\
+             <h1 id=\"install\">Install</h1><p>Run <code>cargo install</code> &amp; wait.</p>\
+             <script>alert(1)</script><h2>Configure</h2><p>Edit the file.</p></body></html>",
+            1000,
+        );
+
+        assert_eq!(
+            sections,
+            vec![
+                Section {
+                    heading: "Install".into(),
+                    anchor: Some("install".into()),
+                    text: "Run cargo install & wait.".into(),
+                },
+                Section {
+                    heading: "Configure".into(),
+                    anchor: None,
+                    text: "Edit the file.".into(),
+                },
+            ]
+        );
+    }
+}
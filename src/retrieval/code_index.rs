@@ -0,0 +1,545 @@
+use std::{collections::HashSet, path::Path, sync::Arc};
+
+use glob::Pattern;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{
+    githost::{host::GitHost, model::RepoId},
+    llm::llm_trait::{Embedding, Llm},
+    vector_store::{
+        impls::file_vector_store::FileVectorStoreConfig,
+        vector_store_trait::{
+            build_vector_store, Metadata, VectorRecord, VectorStore, VectorStoreChoice,
+            VectorStoreError,
+        },
+    },
+};
+
+use super::chunking::{chunk_by_lines, LineChunk};
+
+/// Number of chunks in one embedding request.
+const EMBEDDING_BATCH_SIZE: usize = 64;
+
+/// Only the beginning of a long issue is used as the query.
+const MAX_QUERY_CHARS: usize = 8000;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CodeIndexError<GE, LE> {
+    #[error("unable to perform Git host action")]
+    GitHostError(#[from] GE),
+
+    #[error("unable to embed text")]
+    LlmError(#[source] LE),
+
+    #[error("invalid path pattern")]
+    GlobPatternError(#[source] glob::PatternError),
+
+    #[error("unable to access the vector store")]
+    VectorStoreError(#[source] VectorStoreError),
+
+    #[error("stored chunk has an invalid format")]
+    ChunkFormatError(#[source] serde_json::Error),
+
+    #[error("the LLM has no embedding model")]
+    NoEmbeddingModel,
+}
+
+pub type Result<T, GE, LE> = std::result::Result<T, CodeIndexError<GE, LE>>;
+
+#[derive(Deserialize)]
+pub struct CodeIndexConfig {
+    /// Where the embeddings are kept. By default they are saved to the `code_index` directory.
+    #[serde(default = "default_store")]
+    store: VectorStoreChoice,
+
+    /// Glob patterns of the files to index.
+    #[serde(default = "default_paths")]
+    paths: Vec<String>,
+
+    /// Extensions of the files to index.
+    #[serde(default = "default_file_types")]
+    file_types: Vec<String>,
+
+    #[serde(default = "default_chunk_lines")]
+    chunk_lines: usize,
+
+    /// Bigger files are skipped, they are usually generated.
+    #[serde(default = "default_max_file_bytes")]
+    max_file_bytes: usize,
+
+    /// How many snippets are given to a template at most.
+    #[serde(default = "default_max_snippets")]
+    max_snippets: usize,
+
+    /// How many characters of snippets are given to a template at most.
+    #[serde(default = "default_budget_chars")]
+    budget_chars: usize,
+}
+
+fn default_store() -> VectorStoreChoice {
+    VectorStoreChoice::File(FileVectorStoreConfig {
+        dir: "code_index".into(),
+    })
+}
+
+fn default_paths() -> Vec<String> {
+    vec!["**".into()]
+}
+
+fn default_file_types() -> Vec<String> {
+    vec!["rs".into(), "md".into(), "toml".into()]
+}
+
+fn default_chunk_lines() -> usize {
+    40
+}
+
+fn default_max_file_bytes() -> usize {
+    100_000
+}
+
+fn default_max_snippets() -> usize {
+    5
+}
+
+fn default_budget_chars() -> usize {
+    6000
+}
+
+pub struct CodeIndexOptions {
+    pub paths: Vec<Pattern>,
+    pub file_types: Vec<String>,
+    pub chunk_lines: usize,
+    pub max_file_bytes: usize,
+    pub max_snippets: usize,
+    pub budget_chars: usize,
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct CodeSnippet {
+    pub path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+
+    /// Cosine similarity to the issue, from -1.0 to 1.0.
+    pub score: f32,
+}
+
+/// An index of the source code and docs of the default branch of repositories, used to give the
+/// relevant parts of the code to the LLM.
+///
+/// The index of a repository is kept in the vector store, in the `code-<repo id>` namespace.
+/// Indexing takes a while, so it's never done while processing an issue. Run the `rebuild-index`
+/// command to build or update it.
+pub struct CodeIndex<G, L> {
+    githost: G,
+    llm: L,
+    store: Arc<dyn VectorStore + Send + Sync>,
+    options: CodeIndexOptions,
+
+    /// Repositories with an empty index that were already reported, so that the log is not
+    /// flooded.
+    reported_missing: Mutex<HashSet<RepoId>>,
+}
+
+impl<G: GitHost, L: Llm> CodeIndex<G, L> {
+    pub fn build_from_config(
+        config: CodeIndexConfig,
+        githost: G,
+        llm: L,
+    ) -> Result<Self, G::Error, L::Error> {
+        let paths = config
+            .paths
+            .iter()
+            .map(|pattern| Pattern::new(pattern).map_err(CodeIndexError::GlobPatternError))
+            .collect::<Result<Vec<_>, _, _>>()?;
+
+        let store = build_vector_store(config.store).map_err(CodeIndexError::VectorStoreError)?;
+
+        Ok(Self::new(
+            githost,
+            llm,
+            store,
+            CodeIndexOptions {
+                paths,
+                file_types: config.file_types,
+                chunk_lines: config.chunk_lines,
+                max_file_bytes: config.max_file_bytes,
+                max_snippets: config.max_snippets,
+                budget_chars: config.budget_chars,
+            },
+        ))
+    }
+
+    pub fn new(
+        githost: G,
+        llm: L,
+        store: Arc<dyn VectorStore + Send + Sync>,
+        options: CodeIndexOptions,
+    ) -> Self {
+        Self {
+            githost,
+            llm,
+            store,
+            options,
+            reported_missing: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Snippets that are the most similar to the text, within the budget. Returns nothing if the
+    /// LLM has no embedding model or the repository is not indexed.
+    pub async fn retrieve(
+        &self,
+        repo_id: RepoId,
+        text: &str,
+    ) -> Result<Vec<CodeSnippet>, G::Error, L::Error> {
+        let query: String = text.chars().take(MAX_QUERY_CHARS).collect();
+
+        let Some(query_embedding) = self
+            .embed(vec![query])
+            .await?
+            .and_then(|mut embeddings| embeddings.pop())
+        else {
+            return Ok(Vec::new());
+        };
+
+        let namespace = namespace(repo_id);
+
+        let count = self
+            .store
+            .count(&namespace)
+            .await
+            .map_err(CodeIndexError::VectorStoreError)?;
+
+        // An empty index is fine, e.g. when no file matches the paths.
+        if count == 0 {
+            if self.reported_missing.lock().await.insert(repo_id) {
+                warn!(
+                    "The code index of repository {} is empty, run `rebuild-index` if it is not \
+                     built yet",
+                    *repo_id
+                );
+            }
+
+            return Ok(Vec::new());
+        }
+
+        let found = self
+            .store
+            .query(
+                &namespace,
+                &query_embedding,
+                self.options.max_snippets,
+                &Metadata::new(),
+            )
+            .await
+            .map_err(CodeIndexError::VectorStoreError)?;
+
+        let mut snippets = Vec::new();
+        let mut used_chars = 0;
+
+        for found in found {
+            let chunk: LineChunk = serde_json::from_str(&found.record.document)
+                .map_err(CodeIndexError::ChunkFormatError)?;
+
+            if used_chars + chunk.text.len() > self.options.budget_chars {
+                break;
+            }
+
+            used_chars += chunk.text.len();
+
+            snippets.push(CodeSnippet {
+                path: chunk.path,
+                start_line: chunk.start_line,
+                end_line: chunk.end_line,
+                text: chunk.text,
+                score: found.score,
+            });
+        }
+
+        Ok(snippets)
+    }
+
+    /// Index the default branch of the repository again, replacing the old index.
+    pub async fn rebuild(&self, repo_id: RepoId) -> Result<(), G::Error, L::Error> {
+        let records = self.build_records(repo_id).await?;
+
+        let namespace = namespace(repo_id);
+
+        self.store
+            .clear(&namespace)
+            .await
+            .map_err(CodeIndexError::VectorStoreError)?;
+
+        self.store
+            .upsert(&namespace, records)
+            .await
+            .map_err(CodeIndexError::VectorStoreError)
+    }
+
+    async fn build_records(
+        &self,
+        repo_id: RepoId,
+    ) -> Result<Vec<VectorRecord>, G::Error, L::Error> {
+        info!("Indexing the code of repository {}", *repo_id);
+
+        let mut chunks = Vec::new();
+
+        for path in self.githost.list_files(repo_id).await? {
+            if !self.is_indexed(&path) {
+                continue;
+            }
+
+            let Some(content) = self.githost.get_file_content(repo_id, path.clone()).await? else {
+                continue;
+            };
+
+            if content.len() > self.options.max_file_bytes {
+                continue;
+            }
+
+            chunks.extend(chunk_by_lines(&path, &content, self.options.chunk_lines));
+        }
+
+        let mut records = Vec::new();
+
+        for batch in chunks.chunks(EMBEDDING_BATCH_SIZE) {
+            // The path helps to find the code by the names that are mentioned in the issue.
+            let texts = batch
+                .iter()
+                .map(|chunk| format!("{}\n{}", chunk.path, chunk.text))
+                .collect();
+
+            // Failing here keeps the existing index, which `rebuild` would clear otherwise.
+            let Some(embeddings) = self.embed(texts).await? else {
+                return Err(CodeIndexError::NoEmbeddingModel);
+            };
+
+            for (chunk, embedding) in batch.iter().zip(embeddings) {
+                records.push(VectorRecord {
+                    id: format!("{}:{}", chunk.path, chunk.start_line),
+                    embedding,
+                    metadata: Metadata::from([
+                        ("repo".to_string(), (*repo_id).to_string()),
+                        ("path".to_string(), chunk.path.clone()),
+                    ]),
+                    document: serde_json::to_string(chunk)
+                        .map_err(CodeIndexError::ChunkFormatError)?,
+                });
+            }
+        }
+
+        info!(
+            "Indexed {} chunks of repository {}",
+            records.len(),
+            *repo_id
+        );
+
+        Ok(records)
+    }
+
+    async fn embed(
+        &self,
+        texts: Vec<String>,
+    ) -> Result<Option<Vec<Embedding>>, G::Error, L::Error> {
+        self.llm
+            .embed(texts)
+            .await
+            .map_err(CodeIndexError::LlmError)
+    }
+
+    fn is_indexed(&self, path: &str) -> bool {
+        let has_type = Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| self.options.file_types.iter().any(|t| t == extension));
+
+        has_type
+            && self
+                .options
+                .paths
+                .iter()
+                .any(|pattern| pattern.matches(path))
+    }
+}
+
+fn namespace(repo_id: RepoId) -> String {
+    format!("code-{}", *repo_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use glob::Pattern;
+    use mockall::predicate;
+
+    use crate::{
+        githost::{host::MockGitHost, model::RepoId},
+        llm::llm_trait::MockLlm,
+        retrieval::code_index::{CodeIndex, CodeIndexError, CodeIndexOptions},
+        vector_store::{
+            impls::file_vector_store::FileVectorStore,
+            vector_store_trait::{Metadata, VectorRecord, VectorStore},
+        },
+    };
+
+    #[tokio::test]
+    async fn retrieves_relevant_code() {
+        let index_dir = std::env::temp_dir().join("gib_code_index_retrieves_relevant_code");
+
+        let _ = std::fs::remove_dir_all(&index_dir);
+
+        let mut githost_mock = MockGitHost::new();
+
+        githost_mock
+            .expect_list_files()
+            .with(predicate::eq(RepoId::from(1)))
+            .times(1)
+            .returning(|_| {
+                Ok(vec![
+                    "src/config.rs".into(),
+                    "src/server.rs".into(),
+                    "logo.png".into(),
+                ])
+            });
+
+        githost_mock
+            .expect_get_file_content()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq("src/config.rs".to_string()),
+            )
+            .returning(|_, _| Ok(Some("fn load_config() {}".into())));
+
+        githost_mock
+            .expect_get_file_content()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq("src/server.rs".to_string()),
+            )
+            .returning(|_, _| Ok(Some("fn serve() {}".into())));
+
+        let mut llm_mock = MockLlm::new();
+
+        // The embeddings of the chunks, and the one of the issue that is about the config.
+        llm_mock
+            .expect_embed()
+            .with(predicate::eq(vec![
+                "src/config.rs\nfn load_config() {}".to_string(),
+                "src/server.rs\nfn serve() {}".to_string(),
+            ]))
+            .times(1)
+            .returning(|_| Ok(Some(vec![vec![1.0, 0.0], vec![0.0, 1.0]])));
+
+        llm_mock
+            .expect_embed()
+            .with(predicate::eq(vec!["Config is not loaded".to_string()]))
+            .times(2)
+            .returning(|_| Ok(Some(vec![vec![0.9, 0.1]])));
+
+        let code_index = CodeIndex::new(
+            githost_mock,
+            llm_mock,
+            Arc::new(FileVectorStore::new(index_dir.clone())),
+            CodeIndexOptions {
+                paths: vec![Pattern::new("src/**").unwrap()],
+                file_types: vec!["rs".into()],
+                chunk_lines: 40,
+                max_file_bytes: 100_000,
+                max_snippets: 1,
+                budget_chars: 1000,
+            },
+        );
+
+        // The repository is not indexed while processing an issue.
+        let snippets = code_index
+            .retrieve(RepoId::from(1), "Config is not loaded")
+            .await
+            .unwrap();
+
+        assert!(snippets.is_empty());
+
+        code_index.rebuild(RepoId::from(1)).await.unwrap();
+
+        let snippets = code_index
+            .retrieve(RepoId::from(1), "Config is not loaded")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            snippets
+                .iter()
+                .map(|snippet| (
+                    snippet.path.as_str(),
+                    snippet.start_line,
+                    snippet.text.as_str()
+                ))
+                .collect::<Vec<_>>(),
+            vec![("src/config.rs", 1, "fn load_config() {}")]
+        );
+
+        assert!(index_dir.join("code-1.json").exists());
+    }
+
+    #[tokio::test]
+    async fn keeps_index_without_embedding_model() {
+        let index_dir =
+            std::env::temp_dir().join("gib_code_index_keeps_index_without_embedding_model");
+
+        let _ = std::fs::remove_dir_all(&index_dir);
+
+        let store = Arc::new(FileVectorStore::new(index_dir));
+
+        store
+            .upsert(
+                "code-1",
+                vec![VectorRecord {
+                    id: "src/config.rs:1".into(),
+                    embedding: vec![1.0, 0.0],
+                    metadata: Metadata::new(),
+                    document: "{}".into(),
+                }],
+            )
+            .await
+            .unwrap();
+
+        let mut githost_mock = MockGitHost::new();
+
+        githost_mock
+            .expect_list_files()
+            .returning(|_| Ok(vec!["src/config.rs".into()]));
+
+        githost_mock
+            .expect_get_file_content()
+            .returning(|_, _| Ok(Some("fn load_config() {}".into())));
+
+        let mut llm_mock = MockLlm::new();
+
+        llm_mock.expect_embed().times(1).returning(|_| Ok(None));
+
+        let code_index = CodeIndex::new(
+            githost_mock,
+            llm_mock,
+            store.clone(),
+            CodeIndexOptions {
+                paths: vec![Pattern::new("src/**").unwrap()],
+                file_types: vec!["rs".into()],
+                chunk_lines: 40,
+                max_file_bytes: 100_000,
+                max_snippets: 1,
+                budget_chars: 1000,
+            },
+        );
+
+        assert!(matches!(
+            code_index.rebuild(RepoId::from(1)).await,
+            Err(CodeIndexError::NoEmbeddingModel)
+        ));
+
+        assert_eq!(store.count("code-1").await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,564 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::{fs, sync::Mutex};
+use url::Url;
+
+use crate::{
+    githost::{host::GitHost, model::RepoId},
+    llm::llm_trait::{Embedding, Llm},
+    vector_store::{
+        impls::file_vector_store::FileVectorStoreConfig,
+        vector_store_trait::{
+            build_vector_store, Metadata, VectorRecord, VectorStore, VectorStoreChoice,
+            VectorStoreError,
+        },
+    },
+};
+
+use super::chunking::{chunk_html, chunk_markdown, heading_anchor, Section};
+
+/// Number of passages in one embedding request.
+const EMBEDDING_BATCH_SIZE: usize = 64;
+
+/// Only the beginning of a long issue is used as the query.
+const MAX_QUERY_CHARS: usize = 8000;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DocsIndexError<GE, LE> {
+    #[error("unable to perform Git host action")]
+    GitHostError(#[from] GE),
+
+    #[error("unable to embed text")]
+    LlmError(#[source] LE),
+
+    #[error("unable to read the docs directory")]
+    DocsReadError(#[source] std::io::Error),
+
+    #[error("unable to access the vector store")]
+    VectorStoreError(#[source] VectorStoreError),
+
+    #[error("stored passage has an invalid format")]
+    PassageFormatError(#[source] serde_json::Error),
+
+    #[error("the LLM has no embedding model")]
+    NoEmbeddingModel,
+}
+
+pub type Result<T, GE, LE> = std::result::Result<T, DocsIndexError<GE, LE>>;
+
+/// Where the docs are taken from.
+#[derive(Deserialize, Clone, Debug)]
+pub enum DocsSource {
+    /// A directory of Markdown files or of an HTML site export. The same docs are used for all
+    /// repositories.
+    Local {
+        path: PathBuf,
+
+        /// URL of the published docs that corresponds to `path`.
+        base_url: Url,
+    },
+
+    /// A directory in the default branch of each repository. Links point to the files on GitHub.
+    Repo {
+        #[serde(default = "default_repo_path")]
+        path: String,
+    },
+}
+
+fn default_repo_path() -> String {
+    "docs".into()
+}
+
+#[derive(Deserialize)]
+pub struct DocsIndexConfig {
+    source: DocsSource,
+
+    /// Where the embeddings are kept. By default they are saved to the `docs_index` directory.
+    #[serde(default = "default_store")]
+    store: VectorStoreChoice,
+
+    /// Longer sections are split by paragraphs.
+    #[serde(default = "default_max_chunk_chars")]
+    max_chunk_chars: usize,
+
+    #[serde(default = "default_max_passages")]
+    max_passages: usize,
+
+    /// Passages that are less similar to the issue are not used. If none is left, the bot doesn't
+    /// answer.
+    #[serde(default = "default_min_score")]
+    min_score: f32,
+}
+
+fn default_store() -> VectorStoreChoice {
+    VectorStoreChoice::File(FileVectorStoreConfig {
+        dir: "docs_index".into(),
+    })
+}
+
+fn default_max_chunk_chars() -> usize {
+    2000
+}
+
+fn default_max_passages() -> usize {
+    3
+}
+
+fn default_min_score() -> f32 {
+    0.5
+}
+
+pub struct DocsIndexOptions {
+    pub source: DocsSource,
+    pub max_chunk_chars: usize,
+    pub max_passages: usize,
+    pub min_score: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct DocPassage {
+    /// Heading of the section, empty for the text before the first heading.
+    pub title: String,
+    pub text: String,
+
+    /// Link to the section.
+    pub url: String,
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct ScoredDocPassage {
+    #[serde(flatten)]
+    pub passage: DocPassage,
+
+    /// Cosine similarity to the issue, from -1.0 to 1.0.
+    pub score: f32,
+}
+
+/// An index of documentation passages, used to answer questions with quotes from the docs.
+///
+/// The index is built by the `rebuild-index` command and kept in the vector store, in the
+/// `docs-<repo id>` namespace, or in `docs-local` for local docs, which are shared by all
+/// repositories.
+pub struct DocsIndex<G, L> {
+    githost: G,
+    llm: L,
+    store: Arc<dyn VectorStore + Send + Sync>,
+    options: DocsIndexOptions,
+
+    /// Namespaces whose empty index has been reported, so that the log is not flooded.
+    reported_missing: Mutex<HashSet<String>>,
+}
+
+impl<G: GitHost, L: Llm> DocsIndex<G, L> {
+    pub fn build_from_config(
+        config: DocsIndexConfig,
+        githost: G,
+        llm: L,
+    ) -> Result<Self, G::Error, L::Error> {
+        let store = build_vector_store(config.store).map_err(DocsIndexError::VectorStoreError)?;
+
+        Ok(Self::new(
+            githost,
+            llm,
+            store,
+            DocsIndexOptions {
+                source: config.source,
+                max_chunk_chars: config.max_chunk_chars,
+                max_passages: config.max_passages,
+                min_score: config.min_score,
+            },
+        ))
+    }
+
+    pub fn new(
+        githost: G,
+        llm: L,
+        store: Arc<dyn VectorStore + Send + Sync>,
+        options: DocsIndexOptions,
+    ) -> Self {
+        Self {
+            githost,
+            llm,
+            store,
+            options,
+            reported_missing: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Passages that are similar enough to the text, most similar first. Returns nothing if the
+    /// LLM has no embedding model or the docs are not indexed.
+    pub async fn retrieve(
+        &self,
+        repo_id: RepoId,
+        text: &str,
+    ) -> Result<Vec<ScoredDocPassage>, G::Error, L::Error> {
+        let query: String = text.chars().take(MAX_QUERY_CHARS).collect();
+
+        let Some(query_embedding) = self
+            .embed(vec![query])
+            .await?
+            .and_then(|mut embeddings| embeddings.pop())
+        else {
+            return Ok(Vec::new());
+        };
+
+        let namespace = self.namespace(repo_id);
+
+        let count = self
+            .store
+            .count(&namespace)
+            .await
+            .map_err(DocsIndexError::VectorStoreError)?;
+
+        // An empty index is fine, e.g. when there are no docs.
+        if count == 0 {
+            if self.reported_missing.lock().await.insert(namespace.clone()) {
+                warn!(
+                    "The docs index {} is empty, run `rebuild-index` if it is not built yet",
+                    namespace
+                );
+            }
+
+            return Ok(Vec::new());
+        }
+
+        let found = self
+            .store
+            .query(
+                &namespace,
+                &query_embedding,
+                self.options.max_passages,
+                &Metadata::new(),
+            )
+            .await
+            .map_err(DocsIndexError::VectorStoreError)?;
+
+        let mut passages = Vec::new();
+
+        for found in found {
+            if found.score < self.options.min_score {
+                continue;
+            }
+
+            passages.push(ScoredDocPassage {
+                passage: serde_json::from_str(&found.record.document)
+                    .map_err(DocsIndexError::PassageFormatError)?,
+                score: found.score,
+            });
+        }
+
+        Ok(passages)
+    }
+
+    /// Read and embed the docs again, replacing the old index.
+    pub async fn rebuild(&self, repo_id: RepoId) -> Result<(), G::Error, L::Error> {
+        let records = self.build_records(repo_id).await?;
+
+        let namespace = self.namespace(repo_id);
+
+        self.store
+            .clear(&namespace)
+            .await
+            .map_err(DocsIndexError::VectorStoreError)?;
+
+        self.store
+            .upsert(&namespace, records)
+            .await
+            .map_err(DocsIndexError::VectorStoreError)
+    }
+
+    async fn build_records(
+        &self,
+        repo_id: RepoId,
+    ) -> Result<Vec<VectorRecord>, G::Error, L::Error> {
+        let documents = match &self.options.source {
+            DocsSource::Local { path, base_url } => read_local_docs(path, base_url)
+                .await
+                .map_err(DocsIndexError::DocsReadError)?,
+
+            DocsSource::Repo { path } => self.read_repo_docs(repo_id, path).await?,
+        };
+
+        let passages: Vec<DocPassage> = documents
+            .into_iter()
+            .flat_map(|(url, path, content)| {
+                let sections = if is_html(&path) {
+                    chunk_html(&content, self.options.max_chunk_chars)
+                } else {
+                    chunk_markdown(&content, self.options.max_chunk_chars)
+                };
+
+                sections
+                    .into_iter()
+                    .map(move |section| make_passage(&url, &path, section))
+            })
+            .collect();
+
+        info!("Indexing {} passages of the docs", passages.len());
+
+        let mut records = Vec::new();
+
+        for batch in passages.chunks(EMBEDDING_BATCH_SIZE) {
+            let texts = batch
+                .iter()
+                .map(|passage| format!("{}\n{}", passage.title, passage.text))
+                .collect();
+
+            // Failing here keeps the existing index, which `rebuild` would clear otherwise.
+            let Some(embeddings) = self.embed(texts).await? else {
+                return Err(DocsIndexError::NoEmbeddingModel);
+            };
+
+            for (passage, embedding) in batch.iter().zip(embeddings) {
+                // A long section is split into several passages with the same URL.
+                records.push(VectorRecord {
+                    id: format!("{}:{}", passage.url, records.len()),
+                    embedding,
+                    metadata: Metadata::from([("url".to_string(), passage.url.clone())]),
+                    document: serde_json::to_string(passage)
+                        .map_err(DocsIndexError::PassageFormatError)?,
+                });
+            }
+        }
+
+        Ok(records)
+    }
+
+    /// Docs files of the repository as `(url, path, content)`.
+    async fn read_repo_docs(
+        &self,
+        repo_id: RepoId,
+        docs_path: &str,
+    ) -> Result<Vec<(String, String, String)>, G::Error, L::Error> {
+        let repo = self.githost.get_repo(repo_id).await?;
+
+        let prefix = format!("{}/", docs_path.trim_end_matches('/'));
+
+        let mut documents = Vec::new();
+
+        for path in self.githost.list_files(repo_id).await? {
+            if !path.starts_with(&prefix) || !is_docs_file(&path) {
+                continue;
+            }
+
+            if let Some(content) = self.githost.get_file_content(repo_id, path.clone()).await? {
+                let url = format!(
+                    "https://github.com/{}/{}/blob/HEAD/{}",
+                    repo.owner, repo.name, path
+                );
+
+                documents.push((url, path, content));
+            }
+        }
+
+        Ok(documents)
+    }
+
+    async fn embed(
+        &self,
+        texts: Vec<String>,
+    ) -> Result<Option<Vec<Embedding>>, G::Error, L::Error> {
+        self.llm
+            .embed(texts)
+            .await
+            .map_err(DocsIndexError::LlmError)
+    }
+
+    fn namespace(&self, repo_id: RepoId) -> String {
+        match self.options.source {
+            DocsSource::Local { .. } => "docs-local".into(),
+            DocsSource::Repo { .. } => format!("docs-{}", *repo_id),
+        }
+    }
+}
+
+/// Docs files of a local directory as `(url, path, content)`.
+async fn read_local_docs(
+    root: &Path,
+    base_url: &Url,
+) -> std::io::Result<Vec<(String, String, String)>> {
+    let mut documents = Vec::new();
+    let mut directories = vec![root.to_path_buf()];
+
+    while let Some(directory) = directories.pop() {
+        let mut entries = fs::read_dir(&directory).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+
+            if entry.file_type().await?.is_dir() {
+                directories.push(path);
+                continue;
+            }
+
+            let relative = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+
+            if !is_docs_file(&relative) {
+                continue;
+            }
+
+            let url = match base_url.join(&relative) {
+                Ok(url) => url.to_string(),
+                Err(_) => continue,
+            };
+
+            documents.push((url, relative, fs::read_to_string(&path).await?));
+        }
+    }
+
+    // Directory listings have no stable order.
+    documents.sort();
+
+    Ok(documents)
+}
+
+fn make_passage(url: &str, path: &str, section: Section) -> DocPassage {
+    // Markdown renderers make the anchors from the headings, HTML pages have them in `id`.
+    let anchor = match section.anchor {
+        Some(anchor) => Some(anchor),
+        None if !is_html(path) && !section.heading.is_empty() => {
+            Some(heading_anchor(&section.heading))
+        }
+        None => None,
+    };
+
+    DocPassage {
+        title: section.heading,
+        text: section.text,
+        url: match anchor {
+            Some(anchor) => format!("{}#{}", url, anchor),
+            None => url.to_string(),
+        },
+    }
+}
+
+fn is_docs_file(path: &str) -> bool {
+    is_html(path) || path.ends_with(".md") || path.ends_with(".markdown")
+}
+
+fn is_html(path: &str) -> bool {
+    path.ends_with(".html") || path.ends_with(".htm")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use mockall::predicate;
+
+    use crate::{
+        githost::{
+            host::MockGitHost,
+            model::{Repo, RepoId},
+        },
+        llm::llm_trait::MockLlm,
+        retrieval::docs_index::{DocPassage, DocsIndex, DocsIndexOptions, DocsSource},
+        vector_store::impls::file_vector_store::FileVectorStore,
+    };
+
+    #[tokio::test]
+    async fn retrieves_repo_docs_with_links() {
+        let index_dir = std::env::temp_dir().join("gib_docs_index_retrieves_repo_docs_with_links");
+
+        let _ = std::fs::remove_dir_all(&index_dir);
+
+        let mut githost_mock = MockGitHost::new();
+
+        githost_mock.expect_get_repo().returning(|id| {
+            Ok(Repo {
+                id,
+                owner: "InAnYan".into(),
+                name: "rust-gib".into(),
+            })
+        });
+
+        githost_mock
+            .expect_list_files()
+            .times(1)
+            .returning(|_| Ok(vec!["docs/setup.md".into(), "src/main.rs".into()]));
+
+        githost_mock
+            .expect_get_file_content()
+            .with(
+                predicate::eq(RepoId::from(1)),
+                predicate::eq("docs/setup.md".to_string()),
+            )
+            .returning(|_, _| {
+                Ok(Some(
+                    "# Setup\n\nRun the bot.\n\n## Private key\n\nDownload the key.".into(),
+                ))
+            });
+
+        let mut llm_mock = MockLlm::new();
+
+        llm_mock
+            .expect_embed()
+            .with(predicate::eq(vec![
+                "Setup\nRun the bot.".to_string(),
+                "Private key\nDownload the key.".to_string(),
+            ]))
+            .times(1)
+            .returning(|_| Ok(Some(vec![vec![1.0, 0.0], vec![0.0, 1.0]])));
+
+        llm_mock
+            .expect_embed()
+            .with(predicate::eq(vec!["Where do I get the key?".to_string()]))
+            .times(2)
+            .returning(|_| Ok(Some(vec![vec![0.2, 1.0]])));
+
+        let docs_index = DocsIndex::new(
+            githost_mock,
+            llm_mock,
+            Arc::new(FileVectorStore::new(index_dir.clone())),
+            DocsIndexOptions {
+                source: DocsSource::Repo {
+                    path: "docs".into(),
+                },
+                max_chunk_chars: 2000,
+                max_passages: 3,
+                min_score: 0.5,
+            },
+        );
+
+        // The docs are not indexed while processing an issue.
+        let passages = docs_index
+            .retrieve(RepoId::from(1), "Where do I get the key?")
+            .await
+            .unwrap();
+
+        assert!(passages.is_empty());
+
+        docs_index.rebuild(RepoId::from(1)).await.unwrap();
+
+        let passages = docs_index
+            .retrieve(RepoId::from(1), "Where do I get the key?")
+            .await
+            .unwrap();
+
+        // The setup section is not similar enough.
+        assert_eq!(
+            passages
+                .into_iter()
+                .map(|passage| passage.passage)
+                .collect::<Vec<_>>(),
+            vec![DocPassage {
+                title: "Private key".into(),
+                text: "Download the key.".into(),
+                url: "https://github.com/InAnYan/rust-gib/blob/HEAD/docs/setup.md#private-key"
+                    .into(),
+            }]
+        );
+
+        assert!(index_dir.join("docs-1.json").exists());
+    }
+}
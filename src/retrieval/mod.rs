@@ -0,0 +1,3 @@
+pub mod chunking;
+pub mod code_index;
+pub mod docs_index;
@@ -0,0 +1,28 @@
+CREATE TABLE processed_deliveries (
+    delivery_id TEXT PRIMARY KEY,
+    processed_at TEXT NOT NULL
+);
+
+CREATE TABLE comments (
+    repo_id INTEGER NOT NULL,
+    issue_id INTEGER NOT NULL,
+    feature TEXT NOT NULL,
+    comment_id INTEGER NOT NULL,
+    PRIMARY KEY (repo_id, issue_id, feature)
+);
+
+CREATE TABLE assignments (
+    nickname TEXT PRIMARY KEY,
+    assigned_at TEXT NOT NULL
+);
+
+CREATE TABLE scheduler_runs (
+    job TEXT PRIMARY KEY,
+    last_run_at TEXT NOT NULL
+);
+
+CREATE TABLE repo_settings (
+    repo_id INTEGER PRIMARY KEY,
+    settings TEXT NOT NULL,
+    fetched_at TEXT NOT NULL
+);
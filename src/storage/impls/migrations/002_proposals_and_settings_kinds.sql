@@ -0,0 +1,27 @@
+CREATE TABLE proposals (
+    repo_id INTEGER NOT NULL,
+    issue_id INTEGER NOT NULL,
+    feature TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (repo_id, issue_id, feature)
+);
+
+CREATE TABLE conversation_turns (
+    repo_id INTEGER NOT NULL,
+    issue_id INTEGER NOT NULL,
+    feature TEXT NOT NULL,
+    turns INTEGER NOT NULL,
+    PRIMARY KEY (repo_id, issue_id, feature)
+);
+
+-- Only a cache, so it is not worth copying.
+DROP TABLE repo_settings;
+
+CREATE TABLE repo_settings (
+    repo_id INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    settings TEXT NOT NULL,
+    fetched_at TEXT NOT NULL,
+    PRIMARY KEY (repo_id, kind)
+);
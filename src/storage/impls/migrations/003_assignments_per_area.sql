@@ -0,0 +1,9 @@
+-- The turns were shared by all areas before, so the round-robin starts over.
+DROP TABLE assignments;
+
+CREATE TABLE assignments (
+    area TEXT NOT NULL,
+    nickname TEXT NOT NULL,
+    assigned_at TEXT NOT NULL,
+    PRIMARY KEY (area, nickname)
+);
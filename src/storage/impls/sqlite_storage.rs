@@ -0,0 +1,642 @@
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Deserialize;
+
+use crate::{
+    githost::model::{CommentId, RepoId},
+    storage::storage_trait::{
+        Assignment, CachedRepoSettings, IssueKey, PendingProposal, Storage, StorageError,
+    },
+};
+
+/// Applied in order, the number of applied ones is kept in `PRAGMA user_version`. Released
+/// migrations must not be changed, add a new one instead.
+const MIGRATIONS: &[&str] = &[
+    include_str!("migrations/001_initial.sql"),
+    include_str!("migrations/002_proposals_and_settings_kinds.sql"),
+    include_str!("migrations/003_assignments_per_area.sql"),
+];
+
+/// GitHub redelivers webhooks only for a few days, older deliveries are forgotten.
+const DELIVERY_RETENTION_DAYS: i64 = 7;
+
+#[derive(Deserialize)]
+pub struct SqliteStorageConfig {
+    /// Path of the database file. It is created if it doesn't exist.
+    pub path: PathBuf,
+}
+
+pub struct SqliteStorage {
+    // `rusqlite` is blocking, so the connection is used from `spawn_blocking`.
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStorage {
+    pub fn build(config: SqliteStorageConfig) -> Result<Self, StorageError> {
+        if let Some(parent) = config.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        Self::new(Connection::open(config.path)?)
+    }
+
+    /// A database that is lost when the bot stops.
+    pub fn in_memory() -> Result<Self, StorageError> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
+    pub fn new(mut connection: Connection) -> Result<Self, StorageError> {
+        migrate(&mut connection)?;
+
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    async fn with_connection<T: Send + 'static>(
+        &self,
+        action: impl FnOnce(&mut Connection) -> Result<T, StorageError> + Send + 'static,
+    ) -> Result<T, StorageError> {
+        let connection = self.connection.clone();
+
+        tokio::task::spawn_blocking(move || {
+            // A panic in another query doesn't leave the database in a broken state.
+            let mut connection = connection
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+            action(&mut connection)
+        })
+        .await?
+    }
+}
+
+fn migrate(connection: &mut Connection) -> Result<(), StorageError> {
+    let version: i64 = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    let version = version as usize;
+
+    if version > MIGRATIONS.len() {
+        return Err(StorageError::UnknownVersion(version, MIGRATIONS.len()));
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let transaction = connection.transaction()?;
+
+        transaction.execute_batch(migration)?;
+        transaction.pragma_update(None, "user_version", (index + 1) as i64)?;
+
+        transaction.commit()?;
+
+        info!("Applied storage migration {}", index + 1);
+    }
+
+    Ok(())
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    async fn mark_delivery_processed(&self, delivery_id: &str) -> Result<bool, StorageError> {
+        let delivery_id = delivery_id.to_string();
+        let now = Utc::now();
+
+        self.with_connection(move |connection| {
+            connection.execute(
+                "DELETE FROM processed_deliveries WHERE processed_at < ?1",
+                params![format_time(now - Duration::days(DELIVERY_RETENTION_DAYS))],
+            )?;
+
+            let inserted = connection.execute(
+                "INSERT OR IGNORE INTO processed_deliveries (delivery_id, processed_at)
+                 VALUES (?1, ?2)",
+                params![delivery_id, format_time(now)],
+            )?;
+
+            Ok(inserted == 1)
+        })
+        .await
+    }
+
+    async fn forget_delivery(&self, delivery_id: &str) -> Result<(), StorageError> {
+        let delivery_id = delivery_id.to_string();
+
+        self.with_connection(move |connection| {
+            connection.execute(
+                "DELETE FROM processed_deliveries WHERE delivery_id = ?1",
+                params![delivery_id],
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_comment(&self, key: &IssueKey) -> Result<Option<CommentId>, StorageError> {
+        let (repo_id, issue_id, feature) = key_params(key);
+
+        self.with_connection(move |connection| {
+            let comment_id: Option<i64> = connection
+                .query_row(
+                    "SELECT comment_id FROM comments
+                     WHERE repo_id = ?1 AND issue_id = ?2 AND feature = ?3",
+                    params![repo_id, issue_id, feature],
+                    |row| row.get(0),
+                )
+                .optional()?;
+
+            Ok(comment_id.map(|comment_id| CommentId::from(comment_id as usize)))
+        })
+        .await
+    }
+
+    async fn set_comment(&self, key: &IssueKey, comment_id: CommentId) -> Result<(), StorageError> {
+        let (repo_id, issue_id, feature) = key_params(key);
+
+        self.with_connection(move |connection| {
+            connection.execute(
+                "INSERT OR REPLACE INTO comments (repo_id, issue_id, feature, comment_id)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![repo_id, issue_id, feature, *comment_id as i64],
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn remove_comment(&self, key: &IssueKey) -> Result<(), StorageError> {
+        let (repo_id, issue_id, feature) = key_params(key);
+
+        self.with_connection(move |connection| {
+            connection.execute(
+                "DELETE FROM comments WHERE repo_id = ?1 AND issue_id = ?2 AND feature = ?3",
+                params![repo_id, issue_id, feature],
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_proposal(&self, key: &IssueKey) -> Result<Option<PendingProposal>, StorageError> {
+        let (repo_id, issue_id, feature) = key_params(key);
+
+        self.with_connection(move |connection| {
+            let row: Option<(String, String)> = connection
+                .query_row(
+                    "SELECT payload, created_at FROM proposals
+                     WHERE repo_id = ?1 AND issue_id = ?2 AND feature = ?3",
+                    params![repo_id, issue_id, feature],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?;
+
+            match row {
+                Some((payload, created_at)) => Ok(Some(PendingProposal {
+                    payload,
+                    created_at: parse_time(created_at)?,
+                })),
+                None => Ok(None),
+            }
+        })
+        .await
+    }
+
+    async fn set_proposal(
+        &self,
+        key: &IssueKey,
+        proposal: PendingProposal,
+    ) -> Result<(), StorageError> {
+        let (repo_id, issue_id, feature) = key_params(key);
+
+        self.with_connection(move |connection| {
+            connection.execute(
+                "INSERT OR REPLACE INTO proposals (repo_id, issue_id, feature, payload, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    repo_id,
+                    issue_id,
+                    feature,
+                    proposal.payload,
+                    format_time(proposal.created_at)
+                ],
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn remove_proposal(&self, key: &IssueKey) -> Result<(), StorageError> {
+        let (repo_id, issue_id, feature) = key_params(key);
+
+        self.with_connection(move |connection| {
+            connection.execute(
+                "DELETE FROM proposals WHERE repo_id = ?1 AND issue_id = ?2 AND feature = ?3",
+                params![repo_id, issue_id, feature],
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn increment_turns(&self, key: &IssueKey) -> Result<u32, StorageError> {
+        let (repo_id, issue_id, feature) = key_params(key);
+
+        self.with_connection(move |connection| {
+            let turns: i64 = connection.query_row(
+                "INSERT INTO conversation_turns (repo_id, issue_id, feature, turns)
+                 VALUES (?1, ?2, ?3, 1)
+                 ON CONFLICT (repo_id, issue_id, feature) DO UPDATE SET turns = turns + 1
+                 RETURNING turns",
+                params![repo_id, issue_id, feature],
+                |row| row.get(0),
+            )?;
+
+            Ok(turns as u32)
+        })
+        .await
+    }
+
+    async fn reset_turns(&self, key: &IssueKey) -> Result<(), StorageError> {
+        let (repo_id, issue_id, feature) = key_params(key);
+
+        self.with_connection(move |connection| {
+            connection.execute(
+                "DELETE FROM conversation_turns
+                 WHERE repo_id = ?1 AND issue_id = ?2 AND feature = ?3",
+                params![repo_id, issue_id, feature],
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn last_assignment(
+        &self,
+        area: &str,
+        nickname: &str,
+    ) -> Result<Option<DateTime<Utc>>, StorageError> {
+        let area = area.to_string();
+        let nickname = nickname.to_string();
+
+        self.with_connection(move |connection| {
+            let assigned_at: Option<String> = connection
+                .query_row(
+                    "SELECT assigned_at FROM assignments WHERE area = ?1 AND nickname = ?2",
+                    params![area, nickname],
+                    |row| row.get(0),
+                )
+                .optional()?;
+
+            assigned_at.map(parse_time).transpose()
+        })
+        .await
+    }
+
+    async fn set_assignment(
+        &self,
+        area: &str,
+        nickname: &str,
+        time: DateTime<Utc>,
+    ) -> Result<(), StorageError> {
+        let area = area.to_string();
+        let nickname = nickname.to_string();
+
+        self.with_connection(move |connection| {
+            connection.execute(
+                "INSERT OR REPLACE INTO assignments (area, nickname, assigned_at)
+                 VALUES (?1, ?2, ?3)",
+                params![area, nickname, format_time(time)],
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn recent_assignments(&self, limit: usize) -> Result<Vec<Assignment>, StorageError> {
+        self.with_connection(move |connection| {
+            let mut statement = connection.prepare(
+                "SELECT area, nickname, assigned_at FROM assignments
+                 ORDER BY assigned_at DESC LIMIT ?1",
+            )?;
+
+            let rows = statement
+                .query_map(params![limit as i64], |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+                })?
+                .collect::<Result<Vec<(String, String, String)>, _>>()?;
+
+            rows.into_iter()
+                .map(|(area, nickname, assigned_at)| {
+                    Ok(Assignment {
+                        area,
+                        nickname,
+                        assigned_at: parse_time(assigned_at)?,
+                    })
+                })
+                .collect()
+        })
+        .await
+    }
+
+    async fn last_scheduler_run(&self, job: &str) -> Result<Option<DateTime<Utc>>, StorageError> {
+        let job = job.to_string();
+
+        self.with_connection(move |connection| {
+            let last_run_at: Option<String> = connection
+                .query_row(
+                    "SELECT last_run_at FROM scheduler_runs WHERE job = ?1",
+                    params![job],
+                    |row| row.get(0),
+                )
+                .optional()?;
+
+            last_run_at.map(parse_time).transpose()
+        })
+        .await
+    }
+
+    async fn set_scheduler_run(&self, job: &str, time: DateTime<Utc>) -> Result<(), StorageError> {
+        let job = job.to_string();
+
+        self.with_connection(move |connection| {
+            connection.execute(
+                "INSERT OR REPLACE INTO scheduler_runs (job, last_run_at) VALUES (?1, ?2)",
+                params![job, format_time(time)],
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_repo_settings(
+        &self,
+        repo_id: RepoId,
+        kind: &str,
+    ) -> Result<Option<CachedRepoSettings>, StorageError> {
+        let kind = kind.to_string();
+
+        self.with_connection(move |connection| {
+            let row: Option<(String, String)> = connection
+                .query_row(
+                    "SELECT settings, fetched_at FROM repo_settings
+                     WHERE repo_id = ?1 AND kind = ?2",
+                    params![*repo_id as i64, kind],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?;
+
+            match row {
+                Some((settings, fetched_at)) => Ok(Some(CachedRepoSettings {
+                    settings,
+                    fetched_at: parse_time(fetched_at)?,
+                })),
+                None => Ok(None),
+            }
+        })
+        .await
+    }
+
+    async fn set_repo_settings(
+        &self,
+        repo_id: RepoId,
+        kind: &str,
+        settings: CachedRepoSettings,
+    ) -> Result<(), StorageError> {
+        let kind = kind.to_string();
+
+        self.with_connection(move |connection| {
+            connection.execute(
+                "INSERT OR REPLACE INTO repo_settings (repo_id, kind, settings, fetched_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    *repo_id as i64,
+                    kind,
+                    settings.settings,
+                    format_time(settings.fetched_at)
+                ],
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+}
+
+fn key_params(key: &IssueKey) -> (i64, i64, String) {
+    (
+        *key.repo_id as i64,
+        *key.issue_id as i64,
+        key.feature.clone(),
+    )
+}
+
+/// Times are stored as text with a fixed number of digits, so that they can be compared as text.
+fn format_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+fn parse_time(text: String) -> Result<DateTime<Utc>, StorageError> {
+    match DateTime::parse_from_rfc3339(&text) {
+        Ok(time) => Ok(time.to_utc()),
+        Err(_) => Err(StorageError::InvalidValue(text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+    use rusqlite::Connection;
+
+    use crate::{
+        githost::model::{CommentId, IssueId, RepoId},
+        storage::{
+            impls::sqlite_storage::{SqliteStorage, SqliteStorageConfig, MIGRATIONS},
+            storage_trait::{
+                Assignment, CachedRepoSettings, IssueKey, PendingProposal, Storage, StorageError,
+            },
+        },
+    };
+
+    #[tokio::test]
+    async fn remembers_state_across_restarts() {
+        let dir = std::env::temp_dir().join("gib_sqlite_storage_remembers_state_across_restarts");
+
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let make_storage = || {
+            SqliteStorage::build(SqliteStorageConfig {
+                path: dir.join("gib.db"),
+            })
+            .unwrap()
+        };
+
+        let key = IssueKey {
+            repo_id: RepoId::from(1),
+            issue_id: IssueId::from(2 as usize),
+            feature: "improve".into(),
+        };
+
+        let proposal = PendingProposal {
+            payload: r#"{"labels": ["bug"]}"#.into(),
+            created_at: make_time("2024-10-01T12:00:00Z"),
+        };
+
+        let settings = CachedRepoSettings {
+            settings: "{}".into(),
+            fetched_at: make_time("2024-10-01T13:00:00.5Z"),
+        };
+
+        let storage = make_storage();
+
+        assert!(storage.mark_delivery_processed("delivery-1").await.unwrap());
+        assert!(!storage.mark_delivery_processed("delivery-1").await.unwrap());
+
+        assert!(storage.mark_delivery_processed("delivery-2").await.unwrap());
+        storage.forget_delivery("delivery-2").await.unwrap();
+        assert!(storage.mark_delivery_processed("delivery-2").await.unwrap());
+
+        storage.set_comment(&key, CommentId::from(3)).await.unwrap();
+        storage.set_proposal(&key, proposal.clone()).await.unwrap();
+        storage.increment_turns(&key).await.unwrap();
+        storage
+            .set_assignment("webhooks", "alice", make_time("2024-10-01T12:00:00Z"))
+            .await
+            .unwrap();
+        storage
+            .set_assignment("llm", "alice", make_time("2024-10-01T12:30:00Z"))
+            .await
+            .unwrap();
+        storage
+            .set_scheduler_run("stale daily", make_time("2024-10-01T14:00:00Z"))
+            .await
+            .unwrap();
+        storage
+            .set_repo_settings(RepoId::from(1), "issue_forms", settings.clone())
+            .await
+            .unwrap();
+
+        drop(storage);
+
+        // The migrations are not applied again to the existing database.
+        let storage = make_storage();
+
+        assert!(!storage.mark_delivery_processed("delivery-1").await.unwrap());
+        assert_eq!(
+            storage.get_comment(&key).await.unwrap(),
+            Some(CommentId::from(3))
+        );
+        assert_eq!(storage.get_proposal(&key).await.unwrap(), Some(proposal));
+        assert_eq!(storage.increment_turns(&key).await.unwrap(), 2);
+        assert_eq!(
+            storage.last_assignment("webhooks", "alice").await.unwrap(),
+            Some(make_time("2024-10-01T12:00:00Z"))
+        );
+        assert_eq!(
+            storage.last_assignment("webhooks", "bob").await.unwrap(),
+            None
+        );
+        assert_eq!(
+            storage.recent_assignments(1).await.unwrap(),
+            vec![Assignment {
+                area: "llm".into(),
+                nickname: "alice".into(),
+                assigned_at: make_time("2024-10-01T12:30:00Z"),
+            }]
+        );
+        assert_eq!(
+            storage.last_scheduler_run("stale daily").await.unwrap(),
+            Some(make_time("2024-10-01T14:00:00Z"))
+        );
+        assert_eq!(
+            storage
+                .get_repo_settings(RepoId::from(1), "issue_forms")
+                .await
+                .unwrap(),
+            Some(settings)
+        );
+
+        // Other kinds of settings have their own cache.
+        assert_eq!(
+            storage
+                .get_repo_settings(RepoId::from(1), "labels")
+                .await
+                .unwrap(),
+            None
+        );
+
+        // Other issues and features have their own state.
+        let other_key = IssueKey {
+            feature: "summary".into(),
+            ..key.clone()
+        };
+
+        assert_eq!(storage.get_comment(&other_key).await.unwrap(), None);
+        assert_eq!(storage.increment_turns(&other_key).await.unwrap(), 1);
+
+        storage.remove_comment(&key).await.unwrap();
+        storage.remove_proposal(&key).await.unwrap();
+        storage.reset_turns(&key).await.unwrap();
+
+        assert_eq!(storage.get_comment(&key).await.unwrap(), None);
+        assert_eq!(storage.get_proposal(&key).await.unwrap(), None);
+        assert_eq!(storage.increment_turns(&key).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn migrates_database_of_first_version() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection.execute_batch(MIGRATIONS[0]).unwrap();
+        connection.pragma_update(None, "user_version", 1).unwrap();
+        connection
+            .execute(
+                "INSERT INTO comments (repo_id, issue_id, feature, comment_id) VALUES (1, 2, 'improve', 3)",
+                [],
+            )
+            .unwrap();
+
+        let storage = SqliteStorage::new(connection).unwrap();
+
+        let key = IssueKey {
+            repo_id: RepoId::from(1),
+            issue_id: IssueId::from(2 as usize),
+            feature: "improve".into(),
+        };
+
+        assert_eq!(
+            storage.get_comment(&key).await.unwrap(),
+            Some(CommentId::from(3))
+        );
+        assert_eq!(storage.increment_turns(&key).await.unwrap(), 1);
+    }
+
+    #[test]
+    fn rejects_database_of_newer_version() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection
+            .pragma_update(None, "user_version", 1000)
+            .unwrap();
+
+        assert!(matches!(
+            SqliteStorage::new(connection),
+            Err(StorageError::UnknownVersion(1000, _))
+        ));
+    }
+
+    fn make_time(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().to_utc()
+    }
+}
@@ -0,0 +1,152 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mockall::automock;
+use serde::Deserialize;
+
+use crate::githost::model::{CommentId, IssueId, RepoId};
+
+use super::impls::sqlite_storage::{SqliteStorage, SqliteStorageConfig};
+
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    #[error("unable to create the database directory")]
+    IoError(#[from] std::io::Error),
+
+    #[error("SQLite error")]
+    SqliteError(#[from] rusqlite::Error),
+
+    #[error("storage task has failed")]
+    TaskError(#[from] tokio::task::JoinError),
+
+    #[error("stored value `{0}` is invalid")]
+    InvalidValue(String),
+
+    #[error("database has version {0}, but this version of the bot knows only {1} migrations")]
+    UnknownVersion(usize, usize),
+}
+
+/// Identifies what a feature keeps about an issue.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct IssueKey {
+    pub repo_id: RepoId,
+    pub issue_id: IssueId,
+
+    /// Name of the feature, as in the configuration.
+    pub feature: String,
+}
+
+/// Something that a feature has suggested and that waits for a confirmation, e.g. by a
+/// maintainer's command.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PendingProposal {
+    /// Defined by the feature, usually JSON.
+    pub payload: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Assignment {
+    pub area: String,
+    pub nickname: String,
+    pub assigned_at: DateTime<Utc>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct CachedRepoSettings {
+    /// Defined by the user of the cache, usually JSON.
+    pub settings: String,
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// The memory of the bot that should survive restarts.
+///
+/// Comments, proposals and conversation turns are kept per issue and feature.
+#[automock]
+#[async_trait]
+pub trait Storage {
+    /// Remember the webhook delivery. Returns `false` if it has been remembered before, so that a
+    /// redelivery is not processed twice.
+    async fn mark_delivery_processed(&self, delivery_id: &str) -> Result<bool, StorageError>;
+
+    /// Forget the webhook delivery, e.g. because it has failed and should be processed when it
+    /// is redelivered.
+    async fn forget_delivery(&self, delivery_id: &str) -> Result<(), StorageError>;
+
+    async fn get_comment(&self, key: &IssueKey) -> Result<Option<CommentId>, StorageError>;
+
+    async fn set_comment(&self, key: &IssueKey, comment_id: CommentId) -> Result<(), StorageError>;
+
+    async fn remove_comment(&self, key: &IssueKey) -> Result<(), StorageError>;
+
+    async fn get_proposal(&self, key: &IssueKey) -> Result<Option<PendingProposal>, StorageError>;
+
+    /// Replaces the previous proposal of the feature on the issue.
+    async fn set_proposal(
+        &self,
+        key: &IssueKey,
+        proposal: PendingProposal,
+    ) -> Result<(), StorageError>;
+
+    async fn remove_proposal(&self, key: &IssueKey) -> Result<(), StorageError>;
+
+    /// Count one more turn of the conversation of the feature on the issue. Returns the number
+    /// of turns so far.
+    async fn increment_turns(&self, key: &IssueKey) -> Result<u32, StorageError>;
+
+    async fn reset_turns(&self, key: &IssueKey) -> Result<(), StorageError>;
+
+    /// When the user was last assigned to an issue of the area by the triage.
+    async fn last_assignment(
+        &self,
+        area: &str,
+        nickname: &str,
+    ) -> Result<Option<DateTime<Utc>>, StorageError>;
+
+    async fn set_assignment(
+        &self,
+        area: &str,
+        nickname: &str,
+        time: DateTime<Utc>,
+    ) -> Result<(), StorageError>;
+
+    /// The last assignment of every user in every area, the newest first.
+    async fn recent_assignments(&self, limit: usize) -> Result<Vec<Assignment>, StorageError>;
+
+    /// The last run of the scheduled job, identified by a key that the scheduler makes.
+    async fn last_scheduler_run(&self, job: &str) -> Result<Option<DateTime<Utc>>, StorageError>;
+
+    async fn set_scheduler_run(&self, job: &str, time: DateTime<Utc>) -> Result<(), StorageError>;
+
+    /// Every repository has a separate cache for every `kind` of settings, e.g. `issue_forms`.
+    /// Settings are never expired by the storage, the caller decides by `fetched_at`.
+    async fn get_repo_settings(
+        &self,
+        repo_id: RepoId,
+        kind: &str,
+    ) -> Result<Option<CachedRepoSettings>, StorageError>;
+
+    async fn set_repo_settings(
+        &self,
+        repo_id: RepoId,
+        kind: &str,
+        settings: CachedRepoSettings,
+    ) -> Result<(), StorageError>;
+}
+
+#[derive(Deserialize)]
+pub enum StorageChoice {
+    Sqlite(SqliteStorageConfig),
+}
+
+/// Build the configured storage. Without a configuration the bot remembers things only until it
+/// is restarted.
+pub fn build_storage(
+    choice: Option<StorageChoice>,
+) -> Result<Arc<dyn Storage + Send + Sync>, StorageError> {
+    Ok(match choice {
+        Some(StorageChoice::Sqlite(config)) => Arc::new(SqliteStorage::build(config)?),
+        None => Arc::new(SqliteStorage::in_memory()?),
+    })
+}
@@ -0,0 +1,443 @@
+use regex::Regex;
+use serde::Serialize;
+
+/// Panics, backtraces, error lines and versions found in a pasted log.
+#[derive(Serialize, Clone, PartialEq, Default, Debug)]
+pub struct LogReport {
+    pub panics: Vec<Panic>,
+    pub backtraces: Vec<Backtrace>,
+    pub errors: Vec<ErrorLine>,
+    pub versions: Vec<Version>,
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Panic {
+    pub thread: String,
+    pub message: String,
+    pub location: Option<SourceLocation>,
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Backtrace {
+    /// Innermost frame first, as printed by Rust.
+    pub frames: Vec<Frame>,
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Frame {
+    /// Without the symbol hash.
+    pub function: String,
+    pub location: Option<SourceLocation>,
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: u32,
+    pub column: Option<u32>,
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct ErrorLine {
+    /// Module that has logged the error, if the log format has it.
+    pub target: Option<String>,
+    pub message: String,
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Version {
+    pub name: String,
+    pub version: String,
+}
+
+/// A place in the project's own code that a panic or a backtrace goes through.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct Suspect {
+    pub file: String,
+    pub line: u32,
+    pub function: Option<String>,
+}
+
+impl LogReport {
+    /// Whether the logs show that something has failed. The versions alone are not worth
+    /// analyzing, as they are often given in the issue template.
+    pub fn has_failures(&self) -> bool {
+        !self.panics.is_empty() || !self.backtraces.is_empty() || !self.errors.is_empty()
+    }
+
+    pub fn extend(&mut self, other: LogReport) {
+        self.panics.extend(other.panics);
+        self.backtraces.extend(other.backtraces);
+        self.errors.extend(other.errors);
+
+        for version in other.versions {
+            if !self.versions.contains(&version) {
+                self.versions.push(version);
+            }
+        }
+    }
+
+    /// Files of the project where the problem probably is, most likely first: panic locations,
+    /// then the frames of the backtraces from the innermost one. Only relative paths are
+    /// considered to be of the project, as the standard library and the dependencies are printed
+    /// with absolute ones.
+    pub fn suspects(&self) -> Vec<Suspect> {
+        let panics = self.panics.iter().filter_map(|panic| {
+            panic.location.as_ref().map(|location| Suspect {
+                file: location.file.clone(),
+                line: location.line,
+                function: None,
+            })
+        });
+
+        let frames = self.backtraces.iter().flat_map(|backtrace| {
+            backtrace.frames.iter().filter_map(|frame| {
+                frame.location.as_ref().map(|location| Suspect {
+                    file: location.file.clone(),
+                    line: location.line,
+                    function: Some(frame.function.clone()),
+                })
+            })
+        });
+
+        let mut suspects: Vec<Suspect> = Vec::new();
+
+        for suspect in panics.chain(frames) {
+            if !is_project_file(&suspect.file) {
+                continue;
+            }
+
+            match suspects.iter_mut().find(|known| known.file == suspect.file) {
+                // A panic location has no function, but a frame of the same file may have it.
+                Some(known) => {
+                    if known.function.is_none() {
+                        known.function = suspect.function;
+                    }
+                }
+
+                None => suspects.push(suspect),
+            }
+        }
+
+        suspects
+    }
+}
+
+fn is_project_file(file: &str) -> bool {
+    !file.starts_with('/') && !file.starts_with('\\') && !file.contains(":\\")
+}
+
+/// Finds panics, backtraces, error lines and versions in logs. Understands Rust panic messages
+/// (both the current and the pre-1.73 format), `RUST_BACKTRACE` output, and the default formats
+/// of `tracing-subscriber` and `env_logger`. Terminal colors are ignored.
+pub struct LogExtractor {
+    ansi: Regex,
+    panic: Regex,
+    old_panic: Regex,
+    backtrace_start: Regex,
+    frame: Regex,
+    frame_location: Regex,
+    symbol_hash: Regex,
+    log_error: Regex,
+    plain_error: Regex,
+    tool_version: Regex,
+    named_version: Regex,
+}
+
+impl Default for LogExtractor {
+    fn default() -> Self {
+        let regex = |pattern: &str| Regex::new(pattern).expect("built-in patterns are valid");
+
+        Self {
+            ansi: regex(r"\x1b\[[0-9;]*m"),
+            panic: regex(r"^thread '([^']*)'(?: \(\d+\))? panicked at (\S+?):(\d+):(\d+):$"),
+            old_panic: regex(
+                r"^thread '([^']*)'(?: \(\d+\))? panicked at '(.*)', (\S+?):(\d+):(\d+)$",
+            ),
+            backtrace_start: regex(r"^(?i)stack backtrace:$"),
+            frame: regex(r"^\s*\d+:\s+(?:0x[0-9a-fA-F]+ - )?(\S.*?)\s*$"),
+            frame_location: regex(r"^\s+at (.+?):(\d+)(?::(\d+))?\s*$"),
+            symbol_hash: regex(r"::h[0-9a-f]{16}$"),
+            log_error: regex(
+                r"^\[?(?:\S*\d{2}:\d{2}:\d{2}\S*\s+)?ERROR\s+(?:([A-Za-z_][\w:]*)(?:\]|:)\s+)?(.*)$",
+            ),
+            plain_error: regex(r"^(?:Error|error(?:\[E\d+\])?): (.+)$"),
+            tool_version: regex(r"\b(rustc|cargo|rustup) (\d+\.\d+\.\d+(?:-[0-9A-Za-z.]+)?)"),
+            named_version: regex(
+                r"(?i)(?:\b([A-Za-z][\w-]*)\s+)?\bversion:?\s*v?(\d+\.\d+(?:\.\d+)?(?:-[0-9A-Za-z.]+)?)\b",
+            ),
+        }
+    }
+}
+
+impl LogExtractor {
+    pub fn extract(&self, text: &str) -> LogReport {
+        let text = self.ansi.replace_all(text, "");
+        let lines: Vec<&str> = text.lines().collect();
+
+        let mut report = LogReport::default();
+
+        let mut i = 0;
+
+        while i < lines.len() {
+            let line = lines[i].trim_end();
+
+            if let Some(captures) = self.panic.captures(line) {
+                // The message is on the next line since Rust 1.73.
+                let message = lines.get(i + 1).map(|line| line.trim()).unwrap_or("");
+
+                report.panics.push(Panic {
+                    thread: captures[1].to_string(),
+                    message: message.to_string(),
+                    location: Some(SourceLocation {
+                        file: normalize_path(&captures[2]),
+                        line: captures[3].parse().unwrap_or(0),
+                        column: captures[4].parse().ok(),
+                    }),
+                });
+
+                i += 2;
+                continue;
+            }
+
+            if let Some(captures) = self.old_panic.captures(line) {
+                report.panics.push(Panic {
+                    thread: captures[1].to_string(),
+                    message: captures[2].to_string(),
+                    location: Some(SourceLocation {
+                        file: normalize_path(&captures[3]),
+                        line: captures[4].parse().unwrap_or(0),
+                        column: captures[5].parse().ok(),
+                    }),
+                });
+
+                i += 1;
+                continue;
+            }
+
+            if self.backtrace_start.is_match(line.trim()) {
+                let (backtrace, next) = self.extract_backtrace(&lines, i + 1);
+
+                if !backtrace.frames.is_empty() {
+                    report.backtraces.push(backtrace);
+                }
+
+                i = next;
+                continue;
+            }
+
+            if let Some(captures) = self.log_error.captures(line.trim()) {
+                report.errors.push(ErrorLine {
+                    target: captures.get(1).map(|target| target.as_str().to_string()),
+                    message: captures[2].trim().to_string(),
+                });
+            } else if let Some(captures) = self.plain_error.captures(line.trim()) {
+                report.errors.push(ErrorLine {
+                    target: None,
+                    message: captures[1].trim().to_string(),
+                });
+            }
+
+            i += 1;
+        }
+
+        let tool_versions = self
+            .tool_version
+            .captures_iter(&text)
+            .map(|captures| Version {
+                name: captures[1].to_string(),
+                version: captures[2].to_string(),
+            });
+
+        let named_versions = self
+            .named_version
+            .captures_iter(&text)
+            .map(|captures| Version {
+                name: captures
+                    .get(1)
+                    .map(|name| name.as_str())
+                    .unwrap_or("version")
+                    .to_string(),
+                version: captures[2].to_string(),
+            });
+
+        for version in tool_versions.chain(named_versions) {
+            if !report.versions.contains(&version) {
+                report.versions.push(version);
+            }
+        }
+
+        report
+    }
+
+    /// Read the frames that start at line `start`. Returns the backtrace and the first line after
+    /// it.
+    fn extract_backtrace(&self, lines: &[&str], start: usize) -> (Backtrace, usize) {
+        let mut frames: Vec<Frame> = Vec::new();
+
+        let mut i = start;
+
+        while i < lines.len() {
+            let line = lines[i];
+
+            if let Some(captures) = self.frame_location.captures(line) {
+                if let Some(frame) = frames.last_mut() {
+                    frame.location = Some(SourceLocation {
+                        file: normalize_path(&captures[1]),
+                        line: captures[2].parse().unwrap_or(0),
+                        column: captures
+                            .get(3)
+                            .and_then(|column| column.as_str().parse().ok()),
+                    });
+                }
+            } else if let Some(captures) = self.frame.captures(line) {
+                frames.push(Frame {
+                    function: self.symbol_hash.replace(&captures[1], "").into_owned(),
+                    location: None,
+                });
+            } else {
+                break;
+            }
+
+            i += 1;
+        }
+
+        (Backtrace { frames }, i)
+    }
+}
+
+fn normalize_path(path: &str) -> String {
+    path.trim_start_matches("./").replace('\\', "/")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::log_extraction::{
+        ErrorLine, Frame, LogExtractor, Panic, SourceLocation, Suspect, Version,
+    };
+
+    const LOG: &str = "\
+rustc 1.81.0 (eeb90cda1 2024-09-04)
+GIB version: 0.1.0
+\x1b[31m2024-10-01T12:00:00.123456Z ERROR gib::server: unable to process event\x1b[0m
+thread 'tokio-runtime-worker' panicked at src/bot/bot.rs:42:9:
+called `Option::unwrap()` on a `None` value
+stack backtrace:
+   0: rust_begin_unwind
+             at /rustc/eeb90cda1/library/std/src/panicking.rs:665:5
+   1: core::option::unwrap_failed::h0123456789abcdef
+             at /rustc/eeb90cda1/library/core/src/option.rs:2015:5
+   2: gib::bot::features::label_feature::LabelFeature::process_event
+             at ./src/bot/features/label_feature.rs:120:13
+   3: gib::bot::bot::Bot::process_event
+             at ./src/bot/bot.rs:42:9
+note: Some details are omitted, run with `RUST_BACKTRACE=full` for a verbose backtrace.
+";
+
+    #[test]
+    fn extracts_rust_logs() {
+        let report = LogExtractor::default().extract(LOG);
+
+        assert_eq!(
+            report.panics,
+            vec![Panic {
+                thread: "tokio-runtime-worker".into(),
+                message: "called `Option::unwrap()` on a `None` value".into(),
+                location: Some(SourceLocation {
+                    file: "src/bot/bot.rs".into(),
+                    line: 42,
+                    column: Some(9),
+                }),
+            }]
+        );
+
+        assert_eq!(report.backtraces.len(), 1);
+        assert_eq!(report.backtraces[0].frames.len(), 4);
+
+        assert_eq!(
+            report.backtraces[0].frames[1],
+            Frame {
+                function: "core::option::unwrap_failed".into(),
+                location: Some(SourceLocation {
+                    file: "/rustc/eeb90cda1/library/core/src/option.rs".into(),
+                    line: 2015,
+                    column: Some(5),
+                }),
+            }
+        );
+
+        assert_eq!(
+            report.errors,
+            vec![ErrorLine {
+                target: Some("gib::server".into()),
+                message: "unable to process event".into(),
+            }]
+        );
+
+        assert_eq!(
+            report.versions,
+            vec![
+                Version {
+                    name: "rustc".into(),
+                    version: "1.81.0".into(),
+                },
+                Version {
+                    name: "GIB".into(),
+                    version: "0.1.0".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_suspects() {
+        let report = LogExtractor::default().extract(LOG);
+
+        assert_eq!(
+            report.suspects(),
+            vec![
+                Suspect {
+                    file: "src/bot/bot.rs".into(),
+                    line: 42,
+                    function: Some("gib::bot::bot::Bot::process_event".into()),
+                },
+                Suspect {
+                    file: "src/bot/features/label_feature.rs".into(),
+                    line: 120,
+                    function: Some(
+                        "gib::bot::features::label_feature::LabelFeature::process_event".into()
+                    ),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn extracts_old_panics_and_env_logger_errors() {
+        let report = LogExtractor::default().extract(
+            "[2024-10-01T12:00:00Z ERROR gib::githost] request failed\n\
+             thread 'main' panicked at 'index out of bounds', src/main.rs:5:5\n\
+             Error: missing configuration",
+        );
+
+        assert_eq!(report.panics[0].message, "index out of bounds");
+        assert_eq!(
+            report.panics[0].location.as_ref().unwrap().file,
+            "src/main.rs"
+        );
+
+        assert_eq!(
+            report.errors,
+            vec![
+                ErrorLine {
+                    target: Some("gib::githost".into()),
+                    message: "request failed".into(),
+                },
+                ErrorLine {
+                    target: None,
+                    message: "missing configuration".into(),
+                },
+            ]
+        );
+    }
+}
@@ -1,2 +1,4 @@
 pub mod clear_url;
 pub mod display;
+pub mod log_extraction;
+pub mod redaction;
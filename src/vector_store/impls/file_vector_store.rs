@@ -0,0 +1,242 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::ErrorKind,
+    path::PathBuf,
+};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use tokio::{fs, sync::Mutex};
+
+use crate::vector_store::vector_store_trait::{
+    top_k, Metadata, VectorMatch, VectorRecord, VectorStore, VectorStoreError,
+};
+
+#[derive(Deserialize)]
+pub struct FileVectorStoreConfig {
+    /// Directory with one JSON file per namespace.
+    pub dir: PathBuf,
+}
+
+type Records = BTreeMap<String, VectorRecord>;
+
+/// Keeps the records in memory and saves a namespace to its file after every change. Queries are
+/// brute force, which is fast enough for the size of a single repository.
+pub struct FileVectorStore {
+    dir: PathBuf,
+
+    // Namespaces are read from the files on the first use.
+    namespaces: Mutex<HashMap<String, Records>>,
+}
+
+impl FileVectorStore {
+    pub fn build(config: FileVectorStoreConfig) -> Self {
+        Self::new(config.dir)
+    }
+
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            namespaces: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn records<'a>(
+        &self,
+        namespaces: &'a mut HashMap<String, Records>,
+        namespace: &str,
+    ) -> Result<&'a mut Records, VectorStoreError> {
+        if !namespaces.contains_key(namespace) {
+            let records = self.read(namespace).await?;
+
+            namespaces.insert(namespace.to_string(), records);
+        }
+
+        Ok(namespaces
+            .get_mut(namespace)
+            .expect("the namespace was just loaded"))
+    }
+
+    async fn read(&self, namespace: &str) -> Result<Records, VectorStoreError> {
+        match fs::read_to_string(self.path(namespace)).await {
+            Ok(content) => {
+                let records: Vec<VectorRecord> = serde_json::from_str(&content)?;
+
+                Ok(records
+                    .into_iter()
+                    .map(|record| (record.id.clone(), record))
+                    .collect())
+            }
+
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Records::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn write(&self, namespace: &str, records: &Records) -> Result<(), VectorStoreError> {
+        fs::create_dir_all(&self.dir).await?;
+
+        let records: Vec<&VectorRecord> = records.values().collect();
+
+        fs::write(self.path(namespace), serde_json::to_string(&records)?).await?;
+
+        Ok(())
+    }
+
+    fn path(&self, namespace: &str) -> PathBuf {
+        // Namespaces may have characters that are not allowed in file names.
+        let name: String = namespace
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+
+        self.dir.join(format!("{}.json", name))
+    }
+}
+
+#[async_trait]
+impl VectorStore for FileVectorStore {
+    async fn upsert(
+        &self,
+        namespace: &str,
+        records: Vec<VectorRecord>,
+    ) -> Result<(), VectorStoreError> {
+        let mut namespaces = self.namespaces.lock().await;
+        let stored = self.records(&mut namespaces, namespace).await?;
+
+        stored.extend(
+            records
+                .into_iter()
+                .map(|record| (record.id.clone(), record)),
+        );
+
+        self.write(namespace, stored).await
+    }
+
+    async fn delete(&self, namespace: &str, ids: Vec<String>) -> Result<(), VectorStoreError> {
+        let mut namespaces = self.namespaces.lock().await;
+        let stored = self.records(&mut namespaces, namespace).await?;
+
+        for id in ids {
+            stored.remove(&id);
+        }
+
+        self.write(namespace, stored).await
+    }
+
+    async fn clear(&self, namespace: &str) -> Result<(), VectorStoreError> {
+        let mut namespaces = self.namespaces.lock().await;
+
+        match fs::remove_file(self.path(namespace)).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        namespaces.insert(namespace.to_string(), Records::new());
+
+        Ok(())
+    }
+
+    async fn query(
+        &self,
+        namespace: &str,
+        embedding: &[f32],
+        k: usize,
+        filter: &Metadata,
+    ) -> Result<Vec<VectorMatch>, VectorStoreError> {
+        let mut namespaces = self.namespaces.lock().await;
+        let stored = self.records(&mut namespaces, namespace).await?;
+
+        Ok(top_k(stored.values(), embedding, k, filter))
+    }
+
+    async fn count(&self, namespace: &str) -> Result<usize, VectorStoreError> {
+        let mut namespaces = self.namespaces.lock().await;
+
+        Ok(self.records(&mut namespaces, namespace).await?.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vector_store::{
+        impls::file_vector_store::FileVectorStore,
+        vector_store_trait::{Metadata, VectorRecord, VectorStore},
+    };
+
+    #[tokio::test]
+    async fn upserts_queries_and_persists() {
+        let dir = std::env::temp_dir().join("gib_file_vector_store_upserts_queries_and_persists");
+
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let store = FileVectorStore::new(dir.clone());
+
+        store
+            .upsert(
+                "issues/1",
+                vec![
+                    make_record("1", vec![1.0, 0.0], "open"),
+                    make_record("2", vec![0.9, 0.1], "closed"),
+                    make_record("3", vec![0.0, 1.0], "open"),
+                ],
+            )
+            .await
+            .unwrap();
+
+        store
+            .upsert("issues/2", vec![make_record("1", vec![1.0, 0.0], "open")])
+            .await
+            .unwrap();
+
+        // Replaces the record with the same ID.
+        store
+            .upsert("issues/1", vec![make_record("3", vec![0.8, 0.2], "open")])
+            .await
+            .unwrap();
+
+        store.delete("issues/1", vec!["1".into()]).await.unwrap();
+
+        let open = Metadata::from([("state".to_string(), "open".to_string())]);
+
+        // A new store reads what the first one has saved.
+        let store = FileVectorStore::new(dir.clone());
+
+        let found: Vec<String> = store
+            .query("issues/1", &[1.0, 0.0], 5, &open)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|found| found.record.id)
+            .collect();
+
+        assert_eq!(found, vec!["3".to_string()]);
+
+        assert_eq!(store.count("issues/1").await.unwrap(), 2);
+        assert_eq!(store.count("issues/2").await.unwrap(), 1);
+
+        store.clear("issues/1").await.unwrap();
+
+        assert_eq!(store.count("issues/1").await.unwrap(), 0);
+        assert_eq!(
+            FileVectorStore::new(dir).count("issues/2").await.unwrap(),
+            1
+        );
+    }
+
+    fn make_record(id: &str, embedding: Vec<f32>, state: &str) -> VectorRecord {
+        VectorRecord {
+            id: id.into(),
+            embedding,
+            metadata: Metadata::from([("state".to_string(), state.to_string())]),
+            document: format!("Issue {}", id),
+        }
+    }
+}
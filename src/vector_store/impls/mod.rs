@@ -0,0 +1,2 @@
+pub mod file_vector_store;
+pub mod sqlite_vector_store;
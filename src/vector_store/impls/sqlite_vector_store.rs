@@ -0,0 +1,291 @@
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use rusqlite::{params, Connection};
+use serde::Deserialize;
+
+use crate::vector_store::vector_store_trait::{
+    top_k, Metadata, VectorMatch, VectorRecord, VectorStore, VectorStoreError,
+};
+
+#[derive(Deserialize)]
+pub struct SqliteVectorStoreConfig {
+    /// Path of the database file. It is created if it doesn't exist.
+    pub path: PathBuf,
+}
+
+/// Keeps the records in an SQLite database, so that a store can be shared by several indexes and
+/// is not held in memory. Queries read the namespace and compare the vectors by brute force.
+pub struct SqliteVectorStore {
+    // `rusqlite` is blocking, so the connection is used from `spawn_blocking`.
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteVectorStore {
+    pub fn build(config: SqliteVectorStoreConfig) -> Result<Self, VectorStoreError> {
+        if let Some(parent) = config.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        Self::new(Connection::open(config.path)?)
+    }
+
+    pub fn new(connection: Connection) -> Result<Self, VectorStoreError> {
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS vectors (
+                namespace TEXT NOT NULL,
+                id TEXT NOT NULL,
+                embedding BLOB NOT NULL,
+                metadata TEXT NOT NULL,
+                document TEXT NOT NULL,
+                PRIMARY KEY (namespace, id)
+            );",
+        )?;
+
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    async fn with_connection<T: Send + 'static>(
+        &self,
+        action: impl FnOnce(&mut Connection) -> Result<T, VectorStoreError> + Send + 'static,
+    ) -> Result<T, VectorStoreError> {
+        let connection = self.connection.clone();
+
+        tokio::task::spawn_blocking(move || {
+            // A panic in another query doesn't leave the database in a broken state.
+            let mut connection = connection
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+            action(&mut connection)
+        })
+        .await?
+    }
+}
+
+#[async_trait]
+impl VectorStore for SqliteVectorStore {
+    async fn upsert(
+        &self,
+        namespace: &str,
+        records: Vec<VectorRecord>,
+    ) -> Result<(), VectorStoreError> {
+        let namespace = namespace.to_string();
+
+        self.with_connection(move |connection| {
+            let transaction = connection.transaction()?;
+
+            {
+                let mut statement = transaction.prepare(
+                    "INSERT OR REPLACE INTO vectors (namespace, id, embedding, metadata, document)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                )?;
+
+                for record in records {
+                    statement.execute(params![
+                        namespace,
+                        record.id,
+                        embedding_to_bytes(&record.embedding),
+                        serde_json::to_string(&record.metadata)?,
+                        record.document,
+                    ])?;
+                }
+            }
+
+            transaction.commit()?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn delete(&self, namespace: &str, ids: Vec<String>) -> Result<(), VectorStoreError> {
+        let namespace = namespace.to_string();
+
+        self.with_connection(move |connection| {
+            let transaction = connection.transaction()?;
+
+            {
+                let mut statement =
+                    transaction.prepare("DELETE FROM vectors WHERE namespace = ?1 AND id = ?2")?;
+
+                for id in ids {
+                    statement.execute(params![namespace, id])?;
+                }
+            }
+
+            transaction.commit()?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn clear(&self, namespace: &str) -> Result<(), VectorStoreError> {
+        let namespace = namespace.to_string();
+
+        self.with_connection(move |connection| {
+            connection.execute(
+                "DELETE FROM vectors WHERE namespace = ?1",
+                params![namespace],
+            )?;
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn query(
+        &self,
+        namespace: &str,
+        embedding: &[f32],
+        k: usize,
+        filter: &Metadata,
+    ) -> Result<Vec<VectorMatch>, VectorStoreError> {
+        let namespace = namespace.to_string();
+
+        let records = self
+            .with_connection(move |connection| {
+                let mut statement = connection.prepare(
+                    "SELECT id, embedding, metadata, document FROM vectors WHERE namespace = ?1",
+                )?;
+
+                let rows = statement.query_map(params![namespace], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, Vec<u8>>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, String>(3)?,
+                    ))
+                })?;
+
+                let mut records = Vec::new();
+
+                for row in rows {
+                    let (id, embedding, metadata, document) = row?;
+
+                    records.push(VectorRecord {
+                        id,
+                        embedding: bytes_to_embedding(&embedding),
+                        metadata: serde_json::from_str(&metadata)?,
+                        document,
+                    });
+                }
+
+                Ok(records)
+            })
+            .await?;
+
+        Ok(top_k(&records, embedding, k, filter))
+    }
+
+    async fn count(&self, namespace: &str) -> Result<usize, VectorStoreError> {
+        let namespace = namespace.to_string();
+
+        self.with_connection(move |connection| {
+            let count: i64 = connection.query_row(
+                "SELECT COUNT(*) FROM vectors WHERE namespace = ?1",
+                params![namespace],
+                |row| row.get(0),
+            )?;
+
+            Ok(count as usize)
+        })
+        .await
+    }
+}
+
+fn embedding_to_bytes(embedding: &[f32]) -> Vec<u8> {
+    embedding.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+fn bytes_to_embedding(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vector_store::{
+        impls::sqlite_vector_store::{SqliteVectorStore, SqliteVectorStoreConfig},
+        vector_store_trait::{Metadata, VectorRecord, VectorStore},
+    };
+
+    #[tokio::test]
+    async fn upserts_queries_and_persists() {
+        let dir = std::env::temp_dir().join("gib_sqlite_vector_store_upserts_queries_and_persists");
+
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let make_store = || {
+            SqliteVectorStore::build(SqliteVectorStoreConfig {
+                path: dir.join("vectors.db"),
+            })
+            .unwrap()
+        };
+
+        let store = make_store();
+
+        store
+            .upsert(
+                "issues/1",
+                vec![
+                    make_record("1", vec![1.0, 0.0], "open"),
+                    make_record("2", vec![0.9, 0.1], "closed"),
+                    make_record("3", vec![0.0, 1.0], "open"),
+                ],
+            )
+            .await
+            .unwrap();
+
+        store
+            .upsert("issues/2", vec![make_record("1", vec![1.0, 0.0], "open")])
+            .await
+            .unwrap();
+
+        // Replaces the record with the same ID.
+        store
+            .upsert("issues/1", vec![make_record("3", vec![0.8, 0.2], "open")])
+            .await
+            .unwrap();
+
+        store.delete("issues/1", vec!["1".into()]).await.unwrap();
+
+        let open = Metadata::from([("state".to_string(), "open".to_string())]);
+
+        // A new connection sees what the first one has written.
+        let store = make_store();
+
+        let found = store
+            .query("issues/1", &[1.0, 0.0], 5, &open)
+            .await
+            .unwrap();
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].record, make_record("3", vec![0.8, 0.2], "open"));
+
+        assert_eq!(store.count("issues/1").await.unwrap(), 2);
+        assert_eq!(store.count("issues/2").await.unwrap(), 1);
+
+        store.clear("issues/1").await.unwrap();
+
+        assert_eq!(store.count("issues/1").await.unwrap(), 0);
+        assert_eq!(store.count("issues/2").await.unwrap(), 1);
+    }
+
+    fn make_record(id: &str, embedding: Vec<f32>, state: &str) -> VectorRecord {
+        VectorRecord {
+            id: id.into(),
+            embedding,
+            metadata: Metadata::from([("state".to_string(), state.to_string())]),
+            document: format!("Issue {}", id),
+        }
+    }
+}
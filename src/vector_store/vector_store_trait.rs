@@ -0,0 +1,136 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use async_trait::async_trait;
+use mockall::automock;
+use serde::{Deserialize, Serialize};
+
+use super::impls::{
+    file_vector_store::{FileVectorStore, FileVectorStoreConfig},
+    sqlite_vector_store::{SqliteVectorStore, SqliteVectorStoreConfig},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum VectorStoreError {
+    #[error("unable to read or write the store file")]
+    IoError(#[from] std::io::Error),
+
+    #[error("store file has an invalid format")]
+    FormatError(#[from] serde_json::Error),
+
+    #[error("SQLite error")]
+    SqliteError(#[from] rusqlite::Error),
+
+    #[error("store task has failed")]
+    TaskError(#[from] tokio::task::JoinError),
+}
+
+/// Key-value pairs attached to a vector, like `repo` or `state`.
+pub type Metadata = BTreeMap<String, String>;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct VectorRecord {
+    /// Unique in the namespace. Upserting a record with the same ID replaces it.
+    pub id: String,
+    pub embedding: Vec<f32>,
+    pub metadata: Metadata,
+
+    /// What the vector was made from, usually serialized as JSON.
+    pub document: String,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct VectorMatch {
+    pub record: VectorRecord,
+
+    /// Cosine similarity to the query, from -1.0 to 1.0.
+    pub score: f32,
+}
+
+/// A storage of embeddings that can be searched by similarity.
+///
+/// Records are separated by namespaces, usually one per repository and kind of index, so that
+/// several indexes can share a store.
+#[automock]
+#[async_trait]
+pub trait VectorStore {
+    async fn upsert(
+        &self,
+        namespace: &str,
+        records: Vec<VectorRecord>,
+    ) -> Result<(), VectorStoreError>;
+
+    async fn delete(&self, namespace: &str, ids: Vec<String>) -> Result<(), VectorStoreError>;
+
+    /// Delete all records of the namespace.
+    async fn clear(&self, namespace: &str) -> Result<(), VectorStoreError>;
+
+    /// The `k` records that are the most similar to the embedding, most similar first. Only the
+    /// records that have all the key-value pairs of `filter` in their metadata are considered.
+    async fn query(
+        &self,
+        namespace: &str,
+        embedding: &[f32],
+        k: usize,
+        filter: &Metadata,
+    ) -> Result<Vec<VectorMatch>, VectorStoreError>;
+
+    async fn count(&self, namespace: &str) -> Result<usize, VectorStoreError>;
+}
+
+#[derive(Deserialize)]
+pub enum VectorStoreChoice {
+    File(FileVectorStoreConfig),
+    Sqlite(SqliteVectorStoreConfig),
+}
+
+pub fn build_vector_store(
+    choice: VectorStoreChoice,
+) -> Result<Arc<dyn VectorStore + Send + Sync>, VectorStoreError> {
+    Ok(match choice {
+        VectorStoreChoice::File(config) => Arc::new(FileVectorStore::build(config)),
+        VectorStoreChoice::Sqlite(config) => Arc::new(SqliteVectorStore::build(config)?),
+    })
+}
+
+/// Brute-force search used by the stores that keep no index of the vectors.
+pub fn top_k<'a>(
+    records: impl IntoIterator<Item = &'a VectorRecord>,
+    embedding: &[f32],
+    k: usize,
+    filter: &Metadata,
+) -> Vec<VectorMatch> {
+    let mut scored: Vec<(f32, &VectorRecord)> = records
+        .into_iter()
+        .filter(|record| matches_filter(&record.metadata, filter))
+        .map(|record| (cosine_similarity(embedding, &record.embedding), record))
+        .collect();
+
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.truncate(k);
+
+    scored
+        .into_iter()
+        .map(|(score, record)| VectorMatch {
+            record: record.clone(),
+            score,
+        })
+        .collect()
+}
+
+pub fn matches_filter(metadata: &Metadata, filter: &Metadata) -> bool {
+    filter
+        .iter()
+        .all(|(key, value)| metadata.get(key) == Some(value))
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}